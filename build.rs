@@ -141,9 +141,11 @@ fn write_tables() {
 
     write_table!(1D => f, log_table,      "LOG_TABLE",      "u8");
     write_table!(1D => f, exp_table,      "EXP_TABLE",      "u8");
-    write_table!(2D => f, mul_table,      "MUL_TABLE",      "u8");
+    if !cfg!(feature = "small-tables") {
+        write_table!(2D => f, mul_table,      "MUL_TABLE",      "u8");
+    }
 
-    if cfg!(feature = "simd-accel") {
+    if (cfg!(feature = "simd-accel") && simd_c_supported()) || cfg!(feature = "static-simd") {
         let (mul_table_low, mul_table_high) = gen_mul_table_half(&log_table, &exp_table);
 
         write_table!(2D => f, mul_table_low,  "MUL_TABLE_LOW",  "u8");
@@ -151,25 +153,46 @@ fn write_tables() {
     }
 }
 
-#[cfg(all(
-    feature = "simd-accel",
-    any(target_arch = "x86_64", target_arch = "aarch64"),
-    not(any(target_os = "android", target_os = "ios"))
-))]
+/// Whether the SIMD C code supports the target, as opposed to the host the
+/// build script runs on, e.g. when cross-compiling to `wasm32`.
+fn simd_c_supported() -> bool {
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+
+    let endian = env::var("CARGO_CFG_TARGET_ENDIAN").unwrap_or_default();
+
+    (arch == "x86_64" || arch == "aarch64" || (arch == "powerpc64" && endian == "little"))
+        && !(os == "android" || os == "ios")
+}
+
+#[cfg(feature = "simd-accel")]
 fn compile_simd_c() {
+    if !simd_c_supported() {
+        return;
+    }
+
+    // cc emits rerun-if-env-changed lines, which turn off the rerun on any
+    // file change of the package
+    println!("cargo:rerun-if-changed=simd_c/reedsolomon.c");
+    println!("cargo:rerun-if-changed=simd_c/reedsolomon.h");
+
+    // GCC has no -march on POWER, where -mcpu=native also enables VSX
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let cpu_flag = if arch == "powerpc64" {
+        "-mcpu=native"
+    } else {
+        "-march=native"
+    };
+
     cc::Build::new()
         .opt_level(3)
-        .flag("-march=native")
+        .flag(cpu_flag)
         .flag("-std=c11")
         .file("simd_c/reedsolomon.c")
         .compile("reedsolomon");
 }
 
-#[cfg(not(all(
-    feature = "simd-accel",
-    any(target_arch = "x86_64", target_arch = "aarch64"),
-    not(any(target_os = "android", target_os = "ios"))
-)))]
+#[cfg(not(feature = "simd-accel"))]
 fn compile_simd_c() {}
 
 fn main() {
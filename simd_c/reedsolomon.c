@@ -87,6 +87,13 @@
 # define USE_ALTIVEC 0
 #endif
 
+/* VSX (POWER7 and later) adds unaligned vector loads and stores */
+#if USE_ALTIVEC && defined(__VSX__) && __VSX__
+# define USE_VSX 1
+#else
+# define USE_VSX 0
+#endif
+
 #ifndef VECTOR_SIZE
 /* 'Generic' code */
 # define VECTOR_SIZE 16
@@ -174,6 +181,8 @@ typedef v256 v;
 static ALWAYS_INLINE UNALIGNED_ACCESS v128 loadu_v128(const uint8_t *in) {
 #if USE_SSE2
         const v128 result = { .m128i = _mm_loadu_si128((const __m128i *)in) };
+#elif USE_VSX
+        const v128 result = { .uint8x16 = vec_xl(0, in) };
 #else
         v128 result;
         memcpy(&result.u64, in, sizeof(result.u64));
@@ -333,6 +342,8 @@ static ALWAYS_INLINE UNALIGNED_ACCESS void storeu_v(uint8_t *out, const v vec) {
         _mm256_storeu_si256((__m256i *)out, vec.m256i);
 #elif USE_SSE2
         _mm_storeu_si128((__m128i *)out, vec.m128i);
+#elif USE_VSX
+        vec_xst(vec.uint8x16, 0, out);
 #else
         memcpy(out, &vec.u64, sizeof(vec.u64));
 #endif
@@ -524,3 +535,21 @@ HOT_FUNCTION
 FORCE_ALIGN_ARG_POINTER PROTO(reedsolomon_gal_mul_xor) {
         return reedsolomon_gal_mul_impl(low, high, in, out, len, xor_v);
 }
+
+reedsolomon_cpu_support reedsolomon_determine_cpu_support(void) {
+#if USE_AVX2
+        return REEDSOLOMON_CPU_AVX2;
+#elif USE_SSSE3
+        return REEDSOLOMON_CPU_SSSE3;
+#elif USE_SSE2
+        return REEDSOLOMON_CPU_SSE2;
+#elif USE_ARM_NEON
+        return REEDSOLOMON_CPU_NEON;
+#elif USE_VSX
+        return REEDSOLOMON_CPU_VSX;
+#elif USE_ALTIVEC
+        return REEDSOLOMON_CPU_ALTIVEC;
+#else
+        return REEDSOLOMON_CPU_GENERIC;
+#endif
+}
@@ -51,6 +51,7 @@ typedef enum {
         REEDSOLOMON_CPU_AVX2 = 4,
         REEDSOLOMON_CPU_NEON = 5,
         REEDSOLOMON_CPU_ALTIVEC = 6,
+        REEDSOLOMON_CPU_VSX = 7,
 } reedsolomon_cpu_support;
 
 reedsolomon_cpu_support reedsolomon_determine_cpu_support(void);
@@ -0,0 +1,240 @@
+//! Group commit of records into encoded stripes.
+//!
+//! A `StripeAccumulator` packs variable-size records, e.g. the entries of a
+//! write-ahead log, one after the other into the data shards of a stripe.
+//! The stripe is encoded and handed out as a `Commit` once the next record
+//! does not fit in it, or it is full, or its oldest record has waited for
+//! the maximum delay, or on an explicit `flush`. A commit lists where each
+//! of its records is, so that the records can be acknowledged once the
+//! shards are stored, and read back later.
+//!
+//! A record is stored in one piece in the data shards of its stripe taken
+//! as a whole, so it may start in one data shard and carry on into the
+//! next ones. Records are not split across stripes. The space left at the
+//! end of a stripe is zeros.
+//!
+//! The accumulator works with fields whose elements are bytes,
+//! i.e. `galois_8::Field`.
+
+use std::time::{Duration, Instant};
+
+use crate::stream::Stripe;
+use crate::{Error, Field, ReedSolomon};
+
+/// Where a record is stored.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct RecordLocation {
+    /// The sequence number of the record, counting from 0 in push order.
+    pub record: u64,
+    /// The index of the stripe, counting from 0 in commit order.
+    pub stripe: usize,
+    /// The data shard the record starts in.
+    pub shard: usize,
+    /// The offset of the record in `shard`.
+    pub offset: usize,
+    /// The length of the record in bytes.
+    pub len: usize,
+}
+
+impl RecordLocation {
+    /// Reads the record back from the data shards of its stripe.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data shards are too short to hold the record.
+    pub fn read<T: AsRef<[u8]>>(&self, data_shards: &[T]) -> Vec<u8> {
+        let mut record = Vec::with_capacity(self.len);
+        let mut offset = self.offset;
+        for shard in &data_shards[self.shard..] {
+            if record.len() == self.len {
+                break;
+            }
+            let shard = shard.as_ref();
+            let end = shard.len().min(offset + self.len - record.len());
+            record.extend_from_slice(&shard[offset..end]);
+            offset = 0;
+        }
+        assert_eq!(
+            self.len,
+            record.len(),
+            "the data shards are too short to hold the record"
+        );
+
+        record
+    }
+}
+
+/// An encoded stripe and the records it holds.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Commit {
+    /// The stripe, `Stripe::data_len` being the number of bytes of records.
+    pub stripe: Stripe,
+    /// The records of the stripe, in push order.
+    pub records: Vec<RecordLocation>,
+}
+
+/// Packs records into data shards, committing encoded stripes.
+///
+/// See the module documentation.
+#[derive(Debug)]
+pub struct StripeAccumulator<F: Field> {
+    codec: ReedSolomon<F>,
+    shard_len: usize,
+    max_delay: Option<Duration>,
+    next_record: u64,
+    next_stripe: usize,
+    // the data shards of the pending stripe, allocated on its first record
+    shards: Vec<Vec<u8>>,
+    // the number of bytes of the pending stripe taken by its records
+    used: usize,
+    records: Vec<RecordLocation>,
+    // when the first record of the pending stripe was pushed
+    first_push: Option<Instant>,
+}
+
+impl<F: Field<Elem = u8>> StripeAccumulator<F> {
+    /// Creates an accumulator packing records into stripes of `codec` with
+    /// shards of `shard_len` bytes.
+    ///
+    /// Returns `Error::EmptyShard` if `shard_len` is 0.
+    pub fn new(codec: ReedSolomon<F>, shard_len: usize) -> Result<StripeAccumulator<F>, Error> {
+        if shard_len == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        Ok(StripeAccumulator {
+            codec,
+            shard_len,
+            max_delay: None,
+            next_record: 0,
+            next_stripe: 0,
+            shards: Vec::new(),
+            used: 0,
+            records: Vec::new(),
+            first_push: None,
+        })
+    }
+
+    /// Sets how long the first record of a stripe may wait before `poll`
+    /// commits the stripe. `None`, the default, never commits on a delay.
+    pub fn set_max_delay(&mut self, max_delay: Option<Duration>) {
+        self.max_delay = max_delay;
+    }
+
+    pub fn codec(&self) -> &ReedSolomon<F> {
+        &self.codec
+    }
+
+    pub fn shard_len(&self) -> usize {
+        self.shard_len
+    }
+
+    /// Returns the number of bytes of records a stripe holds.
+    pub fn capacity(&self) -> usize {
+        self.shard_len * self.codec.data_shard_count
+    }
+
+    /// Returns the number of bytes of records waiting to be committed.
+    pub fn pending_len(&self) -> usize {
+        self.used
+    }
+
+    /// Returns the sequence number the next record pushed gets.
+    pub fn next_record(&self) -> u64 {
+        self.next_record
+    }
+
+    /// Appends a record to the pending stripe.
+    ///
+    /// Returns the stripes committed by the push, in order: the pending
+    /// stripe if the record does not fit in it, and the stripe of the
+    /// record if the record fills it up. Most pushes commit none.
+    ///
+    /// Returns `Error::RecordTooLarge` if `record` is longer than
+    /// `capacity`, and then nothing is pushed.
+    pub fn push(&mut self, record: &[u8]) -> Result<Vec<Commit>, Error> {
+        if record.len() > self.capacity() {
+            return Err(Error::RecordTooLarge);
+        }
+
+        let mut commits = Vec::new();
+        if self.used + record.len() > self.capacity() {
+            commits.extend(self.flush());
+        }
+
+        if self.shards.is_empty() {
+            self.shards = vec![vec![0u8; self.shard_len]; self.codec.total_shard_count];
+            self.first_push = Some(Instant::now());
+        }
+        self.records.push(RecordLocation {
+            record: self.next_record,
+            stripe: self.next_stripe,
+            shard: self.used / self.shard_len,
+            offset: self.used % self.shard_len,
+            len: record.len(),
+        });
+        self.next_record += 1;
+
+        let mut rest = record;
+        while !rest.is_empty() {
+            let shard = &mut self.shards[self.used / self.shard_len];
+            let offset = self.used % self.shard_len;
+            let len = rest.len().min(self.shard_len - offset);
+            shard[offset..offset + len].copy_from_slice(&rest[..len]);
+            rest = &rest[len..];
+            self.used += len;
+        }
+
+        if self.used == self.capacity() {
+            commits.extend(self.flush());
+        }
+
+        Ok(commits)
+    }
+
+    /// Commits the pending stripe if its first record has waited for the
+    /// maximum delay.
+    pub fn poll(&mut self) -> Option<Commit> {
+        match (self.max_delay, self.first_push) {
+            (Some(max_delay), Some(first_push)) if first_push.elapsed() >= max_delay => {
+                self.flush()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns how long until `poll` commits the pending stripe, `None` if
+    /// there is no pending record or no maximum delay.
+    pub fn time_to_deadline(&self) -> Option<Duration> {
+        let first_push = self.first_push?;
+        let max_delay = self.max_delay?;
+        Some(max_delay.saturating_sub(first_push.elapsed()))
+    }
+
+    /// Commits the pending stripe, `None` if it has no records.
+    pub fn flush(&mut self) -> Option<Commit> {
+        if self.records.is_empty() {
+            return None;
+        }
+
+        let mut shards = std::mem::take(&mut self.shards);
+        self.codec
+            .encode(&mut shards)
+            .expect("the shards are built for the codec; qed");
+        let stripe = Stripe::new(
+            self.next_stripe,
+            self.codec.data_shard_count,
+            self.used,
+            shards,
+        );
+
+        self.next_stripe += 1;
+        self.used = 0;
+        self.first_push = None;
+
+        Some(Commit {
+            stripe,
+            records: std::mem::take(&mut self.records),
+        })
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+
+use crate::{Field, ReedSolomon};
+
+/// Alignment of the buffer of an `AlignedShard`, and the multiple its
+/// capacity is padded to.
+pub const SHARD_ALIGN: usize = 64;
+
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+struct Block([u8; SHARD_ALIGN]);
+
+/// A shard of bytes starting on a 64-byte boundary, with its buffer padded
+/// with zeros to a multiple of 64 bytes.
+///
+/// SIMD kernels can then use aligned loads, and process the padded buffers
+/// of all shards of a stripe without a tail. The padding is not part of the
+/// shard, and stays zero unless changed through `padded_mut`.
+#[derive(Clone)]
+pub struct AlignedShard {
+    blocks: Vec<Block>,
+    len: usize,
+}
+
+impl AlignedShard {
+    /// Creates a shard of `len` zero bytes.
+    pub fn zeroed(len: usize) -> AlignedShard {
+        AlignedShard {
+            blocks: vec![Block([0; SHARD_ALIGN]); len.div_ceil(SHARD_ALIGN)],
+            len,
+        }
+    }
+
+    /// Creates a shard holding a copy of `data`.
+    pub fn from_slice(data: &[u8]) -> AlignedShard {
+        let mut shard = AlignedShard::zeroed(data.len());
+        shard.copy_from_slice(data);
+        shard
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bytes of the shard followed by the padding.
+    pub fn padded(&self) -> &[u8] {
+        // Safe as blocks are 64 bytes without padding between them.
+        unsafe {
+            std::slice::from_raw_parts(
+                self.blocks.as_ptr() as *const u8,
+                self.blocks.len() * SHARD_ALIGN,
+            )
+        }
+    }
+
+    /// Returns the bytes of the shard followed by the padding, mutably.
+    ///
+    /// Coding the padded buffers of all shards of a stripe keeps the
+    /// padding of the parity shards zero, as long as the padding of the
+    /// data shards is.
+    pub fn padded_mut(&mut self) -> &mut [u8] {
+        // Safe as blocks are 64 bytes without padding between them.
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.blocks.as_mut_ptr() as *mut u8,
+                self.blocks.len() * SHARD_ALIGN,
+            )
+        }
+    }
+}
+
+impl Deref for AlignedShard {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.padded()[..self.len]
+    }
+}
+
+impl DerefMut for AlignedShard {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len;
+        &mut self.padded_mut()[..len]
+    }
+}
+
+impl AsRef<[u8]> for AlignedShard {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl AsMut<[u8]> for AlignedShard {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+impl FromIterator<u8> for AlignedShard {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> AlignedShard {
+        let bytes: Vec<u8> = iter.into_iter().collect();
+        AlignedShard::from_slice(&bytes)
+    }
+}
+
+impl PartialEq for AlignedShard {
+    fn eq(&self, rhs: &AlignedShard) -> bool {
+        **self == **rhs
+    }
+}
+
+impl Eq for AlignedShard {}
+
+impl fmt::Debug for AlignedShard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<F: Field<Elem = u8>> ReedSolomon<F> {
+    /// Allocates `total_shard_count` zeroed aligned shards of `shard_len`
+    /// bytes.
+    pub fn alloc_aligned_shards(&self, shard_len: usize) -> Vec<AlignedShard> {
+        vec![AlignedShard::zeroed(shard_len); self.total_shard_count]
+    }
+}
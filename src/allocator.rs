@@ -0,0 +1,83 @@
+//! Coding with shards held in a custom allocator.
+//!
+//! The methods in this module take an `Allocator`, e.g. an arena or a bump
+//! allocator, and allocate every shard they produce in it instead of the
+//! global heap.
+//!
+//! Shards held in a custom allocator can also be encoded and verified with
+//! the usual methods, which take any `AsRef<[F::Elem]>`. To keep the
+//! verification buffers out of the global heap as well, pass buffers from
+//! the allocator to `ReedSolomon::verify_with_buffer`.
+//!
+//! This module is only available with the `allocator-api` feature, which
+//! requires a nightly compiler.
+
+use std::alloc::Allocator;
+
+use crate::{Error, Field, ReedSolomon};
+
+/// Allocates a zeroed shard of `len` elements in `alloc`.
+fn zeroed_in<F: Field, A: Allocator>(len: usize, alloc: A) -> Vec<F::Elem, A> {
+    let mut shard = Vec::with_capacity_in(len, alloc);
+    shard.resize(len, F::zero());
+    shard
+}
+
+impl<F: Field> ReedSolomon<F> {
+    /// Constructs the parity shards into buffers allocated in `alloc`.
+    ///
+    /// Returns the parity shards, in a list also allocated in `alloc`.
+    ///
+    /// The errors are those of `encode_owned`.
+    pub fn encode_owned_in<T, A>(
+        &self,
+        data: &[T],
+        alloc: A,
+    ) -> Result<Vec<Vec<F::Elem, A>, A>, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        A: Allocator + Clone,
+    {
+        check_piece_count!(data => self, data);
+        check_slices!(multi => data);
+
+        let shard_len = data[0].as_ref().len();
+        let mut parity = Vec::with_capacity_in(self.parity_shard_count, alloc.clone());
+        for _ in 0..self.parity_shard_count {
+            parity.push(zeroed_in::<F, A>(shard_len, alloc.clone()));
+        }
+
+        self.encode_sep(data, &mut parity)?;
+
+        Ok(parity)
+    }
+
+    /// Reconstructs all shards, allocating the missing shards in `alloc`.
+    ///
+    /// The errors are those of `reconstruct`.
+    pub fn reconstruct_in<A>(
+        &self,
+        shards: &mut [Option<Vec<F::Elem, A>>],
+        alloc: A,
+    ) -> Result<(), Error>
+    where
+        A: Allocator + Clone,
+    {
+        self.reconstruct_alloc(shards, |len| zeroed_in::<F, A>(len, alloc.clone()))
+    }
+
+    /// Reconstructs only the data shards, allocating the missing data shards
+    /// in `alloc`.
+    ///
+    /// The errors are those of `reconstruct_data`.
+    pub fn reconstruct_data_in<A>(
+        &self,
+        shards: &mut [Option<Vec<F::Elem, A>>],
+        alloc: A,
+    ) -> Result<(), Error>
+    where
+        A: Allocator + Clone,
+    {
+        self.reconstruct_data_alloc(shards, |len| zeroed_in::<F, A>(len, alloc.clone()))
+    }
+}
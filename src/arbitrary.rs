@@ -0,0 +1,284 @@
+//! Generators for property testing code built on top of this crate:
+//! `quickcheck::Arbitrary` implementations, enabled by the `quickcheck`
+//! feature, and `proptest` strategies, enabled by the `proptest` feature.
+//!
+//! `Shape` generates valid codec shapes, `Stripe` generates encoded stripes
+//! of a shape, and `LossPattern` generates the shards present in a stripe
+//! of a shape, losing no more shards than can be reconstructed. All of
+//! them are over GF(2^8) and shrink towards smaller cases.
+//!
+//! With `proptest`, the types implement `proptest::arbitrary::Arbitrary`,
+//! so `any::<Stripe>()` works, and the strategies are also returned by
+//! `shape_strategy`, `stripe_strategy` and `loss_pattern_strategy`.
+
+#[cfg(feature = "quickcheck")]
+use quickcheck::{Arbitrary, Gen};
+
+#[cfg(feature = "proptest")]
+use proptest::prelude::{any, BoxedStrategy, Just, Strategy};
+
+use crate::galois_8::ReedSolomon;
+use crate::ShardSet;
+
+/// The largest number of data or parity shards generated, before
+/// limiting by the size of the generator.
+const MAX_SHARDS: usize = 64;
+
+/// The longest shards generated by the `proptest` strategies, matching the
+/// default size of `quickcheck`.
+#[cfg(feature = "proptest")]
+const MAX_SHARD_LEN: usize = 100;
+
+/// Returns a random number in `low..=high`.
+#[cfg(feature = "quickcheck")]
+fn gen_inclusive<G: Gen>(g: &mut G, low: usize, high: usize) -> usize {
+    low + g.next_u32() as usize % (high - low + 1)
+}
+
+/// The shape of a codec, i.e. its numbers of data and parity shards.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Shape {
+    pub data_shards: usize,
+    pub parity_shards: usize,
+}
+
+impl Shape {
+    pub fn total_shards(&self) -> usize {
+        self.data_shards + self.parity_shards
+    }
+
+    /// Creates a codec of the shape.
+    pub fn codec(&self) -> ReedSolomon {
+        ReedSolomon::new(self.data_shards, self.parity_shards)
+            .expect("generated shapes are valid; qed")
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+impl Arbitrary for Shape {
+    fn arbitrary<G: Gen>(g: &mut G) -> Shape {
+        let max = g.size().clamp(1, MAX_SHARDS);
+
+        Shape {
+            data_shards: gen_inclusive(g, 1, max),
+            parity_shards: gen_inclusive(g, 1, max),
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Shape>> {
+        let shape = *self;
+
+        let fewer_data = (1..shape.data_shards).rev().map(move |data_shards| Shape {
+            data_shards,
+            ..shape
+        });
+        let fewer_parity = (1..shape.parity_shards)
+            .rev()
+            .map(move |parity_shards| Shape {
+                parity_shards,
+                ..shape
+            });
+
+        Box::new(fewer_data.chain(fewer_parity))
+    }
+}
+
+/// An encoded stripe, data shards first.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Stripe {
+    pub shape: Shape,
+    pub shards: Vec<Vec<u8>>,
+}
+
+impl Stripe {
+    /// Encodes the data shards `data` into a stripe of shape `shape`.
+    ///
+    /// # Panics
+    /// Panics if the number of data shards does not match the shape, or
+    /// the data shards are empty or of different lengths.
+    pub fn encode(shape: Shape, data: Vec<Vec<u8>>) -> Stripe {
+        let shard_len = data[0].len();
+
+        let mut shards = data;
+        shards.resize(shape.total_shards(), vec![0; shard_len]);
+        shape
+            .codec()
+            .encode(&mut shards)
+            .expect("data shards match the shape; qed");
+
+        Stripe { shape, shards }
+    }
+
+    pub fn shard_len(&self) -> usize {
+        self.shards[0].len()
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+impl Arbitrary for Stripe {
+    fn arbitrary<G: Gen>(g: &mut G) -> Stripe {
+        let shape = Shape::arbitrary(g);
+        let shard_len = gen_inclusive(g, 1, g.size().max(1));
+
+        let data = (0..shape.data_shards)
+            .map(|_| {
+                let mut shard = vec![0; shard_len];
+                g.fill_bytes(&mut shard);
+                shard
+            })
+            .collect();
+
+        Stripe::encode(shape, data)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Stripe>> {
+        let stripe = self.clone();
+
+        // keep the data of the remaining shards, and re-encode
+        let smaller_shapes = self.shape.shrink().map(move |shape| {
+            let data = stripe.shards[..shape.data_shards].to_vec();
+            Stripe::encode(shape, data)
+        });
+
+        let stripe = self.clone();
+        let shorter = (1..self.shard_len()).rev().map(move |len| {
+            let data = stripe.shards[..stripe.shape.data_shards]
+                .iter()
+                .map(|shard| shard[..len].to_vec())
+                .collect();
+            Stripe::encode(stripe.shape, data)
+        });
+
+        Box::new(smaller_shapes.chain(shorter))
+    }
+}
+
+/// The shards present in a stripe, with at most `parity_shards`
+/// shards lost, so that the lost shards can be reconstructed.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct LossPattern {
+    pub shape: Shape,
+    pub present: ShardSet,
+}
+
+impl LossPattern {
+    /// Marks the lost shards of `shards` as not present.
+    ///
+    /// # Panics
+    /// Panics if the number of shards does not match the shape.
+    pub fn apply<T>(&self, shards: Vec<T>) -> Vec<Option<T>> {
+        assert_eq!(self.shape.total_shards(), shards.len());
+
+        shards
+            .into_iter()
+            .enumerate()
+            .map(|(i, shard)| Some(shard).filter(|_| self.present.contains(i)))
+            .collect()
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+impl Arbitrary for LossPattern {
+    fn arbitrary<G: Gen>(g: &mut G) -> LossPattern {
+        let shape = Shape::arbitrary(g);
+        let total_shards = shape.total_shards();
+
+        let mut present = ShardSet::full(total_shards);
+        let lost = gen_inclusive(g, 0, shape.parity_shards);
+        while present.count() > total_shards - lost {
+            present.remove(gen_inclusive(g, 0, total_shards - 1));
+        }
+
+        LossPattern { shape, present }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = LossPattern>> {
+        let pattern = self.clone();
+
+        // bring back one lost shard at a time
+        let lost: Vec<usize> = self.present.complement().iter().collect();
+        Box::new(lost.into_iter().map(move |i| {
+            let mut pattern = pattern.clone();
+            pattern.present.insert(i);
+            pattern
+        }))
+    }
+}
+
+/// Returns a strategy generating valid codec shapes, shrinking towards
+/// fewer shards.
+#[cfg(feature = "proptest")]
+pub fn shape_strategy() -> BoxedStrategy<Shape> {
+    (1..=MAX_SHARDS, 1..=MAX_SHARDS)
+        .prop_map(|(data_shards, parity_shards)| Shape {
+            data_shards,
+            parity_shards,
+        })
+        .boxed()
+}
+
+/// Returns a strategy generating encoded stripes, shrinking towards fewer
+/// and shorter shards.
+#[cfg(feature = "proptest")]
+pub fn stripe_strategy() -> BoxedStrategy<Stripe> {
+    (shape_strategy(), 1..=MAX_SHARD_LEN)
+        .prop_flat_map(|(shape, shard_len)| {
+            let data = proptest::collection::vec(
+                proptest::collection::vec(any::<u8>(), shard_len),
+                shape.data_shards,
+            );
+            (Just(shape), data)
+        })
+        .prop_map(|(shape, data)| Stripe::encode(shape, data))
+        .boxed()
+}
+
+/// Returns a strategy generating loss patterns, shrinking towards fewer
+/// shards and fewer lost shards.
+#[cfg(feature = "proptest")]
+pub fn loss_pattern_strategy() -> BoxedStrategy<LossPattern> {
+    shape_strategy()
+        .prop_flat_map(|shape| {
+            let indices: Vec<usize> = (0..shape.total_shards()).collect();
+            let lost = proptest::sample::subsequence(indices, 0..=shape.parity_shards);
+            (Just(shape), lost)
+        })
+        .prop_map(|(shape, lost)| {
+            let mut present = ShardSet::full(shape.total_shards());
+            for i in lost {
+                present.remove(i);
+            }
+            LossPattern { shape, present }
+        })
+        .boxed()
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Shape {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Shape>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<Shape> {
+        shape_strategy()
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Stripe {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Stripe>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<Stripe> {
+        stripe_strategy()
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for LossPattern {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<LossPattern>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<LossPattern> {
+        loss_pattern_strategy()
+    }
+}
@@ -0,0 +1,187 @@
+//! Zero-copy serialization with `rkyv`.
+//!
+//! The configuration of a codec, shards with their place in a stripe, and
+//! the decode matrices a codec has cached can be archived, e.g. with
+//! `rkyv::to_bytes`, then written to a file or sent to another process.
+//! There the bytes are checked and read in place with
+//! `rkyv::check_archived_root`, without deserializing: the shard of an
+//! `ArchivedShardEnvelope` is a slice of the archive, and
+//! `ReedSolomon::import_archived_decode_matrices` fills the cache of a
+//! codec straight from an `ArchivedDecodeMatrixCache`, sparing the new
+//! process the matrix inversions of a warmed up one.
+//!
+//! Archives are in the byte order of the machine which wrote them.
+//!
+//! This module is only available with the `rkyv` feature.
+
+use std::sync::Arc;
+
+use rkyv::{Archive, Deserialize, Infallible, Serialize};
+
+use crate::matrix::Matrix;
+use crate::{Error, Field, MatrixKind, ReedSolomon};
+
+/// The parameters a codec is created from.
+#[derive(Archive, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[archive(check_bytes)]
+pub struct CodecConfig {
+    pub data_shard_count: usize,
+    pub parity_shard_count: usize,
+    pub matrix_kind: MatrixKind,
+}
+
+impl CodecConfig {
+    /// Returns the configuration of `codec`.
+    pub fn of<F: Field>(codec: &ReedSolomon<F>) -> CodecConfig {
+        CodecConfig {
+            data_shard_count: codec.data_shard_count(),
+            parity_shard_count: codec.parity_shard_count(),
+            matrix_kind: codec.matrix_kind(),
+        }
+    }
+
+    /// Creates a codec of this configuration.
+    ///
+    /// Returns the errors of `ReedSolomon::new`.
+    pub fn build<F: Field>(&self) -> Result<ReedSolomon<F>, Error> {
+        match self.matrix_kind {
+            MatrixKind::Vandermonde => {
+                ReedSolomon::new(self.data_shard_count, self.parity_shard_count)
+            }
+            MatrixKind::Cauchy => {
+                ReedSolomon::new_cauchy(self.data_shard_count, self.parity_shard_count)
+            }
+        }
+    }
+}
+
+/// A shard with its place in a stripe.
+#[derive(Archive, Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[archive(check_bytes)]
+pub struct ShardEnvelope {
+    /// The configuration of the codec which encoded the stripe.
+    pub config: CodecConfig,
+    /// The index of the stripe.
+    pub stripe: u64,
+    /// The index of the shard within the stripe.
+    pub index: usize,
+    pub shard: Vec<u8>,
+}
+
+/// The decode matrices cached by a codec, see
+/// `ReedSolomon::export_decode_matrices`.
+#[derive(Archive, Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[archive(check_bytes)]
+pub struct DecodeMatrixCache<E> {
+    /// The configuration of the codec the matrices belong to.
+    pub config: CodecConfig,
+    pub matrices: Vec<CachedDecodeMatrix<E>>,
+}
+
+/// The decode matrix of the missing shards `invalid_indices`.
+#[derive(Archive, Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[archive(check_bytes)]
+pub struct CachedDecodeMatrix<E> {
+    /// The indices of the missing shards, in ascending order.
+    pub invalid_indices: Vec<usize>,
+    /// The `data_shard_count` rows of the square matrix, one after another.
+    pub elems: Vec<E>,
+}
+
+impl<F: Field> ReedSolomon<F> {
+    /// Returns the decode matrices the codec has built so far, to be
+    /// imported by another codec of the same configuration.
+    pub fn export_decode_matrices(&self) -> DecodeMatrixCache<F::Elem> {
+        let matrices = self
+            .tree
+            .entries()
+            .into_iter()
+            // the identity matrix of no missing shard is always there
+            .filter(|(invalid_indices, _)| !invalid_indices.is_empty())
+            .map(|(invalid_indices, matrix)| CachedDecodeMatrix {
+                invalid_indices,
+                elems: (0..matrix.row_count())
+                    .flat_map(|r| matrix.get_row(r).iter().cloned())
+                    .collect(),
+            })
+            .collect();
+
+        DecodeMatrixCache {
+            config: CodecConfig::of(self),
+            matrices,
+        }
+    }
+
+    /// Adds the decode matrices of `cache`, e.g. exported by another
+    /// process, to the matrices cached by the codec.
+    ///
+    /// The matrices are trusted to be those of the codec: only their
+    /// shape is checked, not their coefficients.
+    ///
+    /// Returns `Error::InvalidDecodeMatrix`, adding no matrix, if `cache`
+    /// was exported by a codec of another configuration, or holds a
+    /// matrix of the wrong size or an invalid erasure pattern.
+    pub fn import_decode_matrices(&self, cache: &DecodeMatrixCache<F::Elem>) -> Result<(), Error> {
+        self.import_matrices(
+            cache.config,
+            cache
+                .matrices
+                .iter()
+                .map(|m| (m.invalid_indices.clone(), &m.elems[..])),
+        )
+    }
+
+    /// Adds the decode matrices of an archived `DecodeMatrixCache`, read in
+    /// place, to the matrices cached by the codec.
+    ///
+    /// Behaves as `import_decode_matrices`.
+    pub fn import_archived_decode_matrices(
+        &self,
+        cache: &ArchivedDecodeMatrixCache<F::Elem>,
+    ) -> Result<(), Error>
+    where
+        F::Elem: Archive<Archived = F::Elem>,
+    {
+        let config: CodecConfig = cache.config.deserialize(&mut Infallible).unwrap();
+        self.import_matrices(
+            config,
+            cache.matrices.iter().map(|m| {
+                let invalid_indices = m.invalid_indices.iter().map(|&i| i as usize).collect();
+                (invalid_indices, &m.elems[..])
+            }),
+        )
+    }
+
+    fn import_matrices<'a, I>(&self, config: CodecConfig, matrices: I) -> Result<(), Error>
+    where
+        I: Iterator<Item = (Vec<usize>, &'a [F::Elem])>,
+        F::Elem: 'a,
+    {
+        if config != CodecConfig::of(self) {
+            return Err(Error::InvalidDecodeMatrix);
+        }
+
+        let k = self.data_shard_count;
+        let mut checked = Vec::new();
+        for (invalid_indices, elems) in matrices {
+            if invalid_indices.is_empty()
+                || invalid_indices.len() > self.parity_shard_count
+                || invalid_indices.windows(2).any(|w| w[0] >= w[1])
+                || invalid_indices.iter().any(|&i| i >= self.total_shard_count)
+                || elems.len() != k * k
+            {
+                return Err(Error::InvalidDecodeMatrix);
+            }
+            let matrix = Matrix::new_with_data(elems.chunks(k).map(|row| row.to_vec()).collect());
+            checked.push((invalid_indices, Arc::new(matrix)));
+        }
+
+        for (invalid_indices, matrix) in checked {
+            self.tree
+                .insert_inverted_matrix(&invalid_indices, &matrix)
+                .expect("the pattern is not empty and the matrix is square; qed");
+        }
+
+        Ok(())
+    }
+}
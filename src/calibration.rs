@@ -0,0 +1,264 @@
+//! Picking the fastest kernel and chunk size on the machine at hand.
+//!
+//! Which GF(2^8) backend and which `ReedSolomon::cache_chunk_len` code the
+//! fastest depends on the CPU, its caches, and the shape of the codec.
+//! `ReedSolomon::calibrate` times encoding with every combination of the
+//! available backends and a few chunk sizes, and keeps the fastest one.
+//! Codecs over other fields do not use the GF(2^8) backends, and only have
+//! their chunk size calibrated.
+//!
+//! A `Calibration` is written as a few lines of text, so that services can
+//! store it and apply it on the next start instead of timing again, see
+//! `ReedSolomon::calibrate_cached`.
+
+use std::any::TypeId;
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::path::Path;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::{Duration, Instant};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::{fs, io};
+
+use crate::galois_8::{self, Backend};
+use crate::{Error, Field, ReedSolomon};
+
+/// Chunk sizes tried by `ReedSolomon::calibrate`, `0` coding whole shards.
+const CHUNK_LENS: [usize; 5] = [1024, 4096, 16384, 65536, 0];
+
+/// Maximum number of elements of a shard coded while calibrating.
+const MAX_SHARD_LEN: usize = 65536;
+
+/// Maximum number of bytes of all shards coded while calibrating.
+const MAX_STRIPE_BYTES: usize = 16 << 20;
+
+/// Number of times every combination is timed, the fastest time counting.
+const ROUNDS: usize = 3;
+
+/// The backend and chunk size picked by `ReedSolomon::calibrate`.
+///
+/// It displays as, and parses from, lines of `key=value`:
+///
+/// ```text
+/// backend=avx2
+/// cache_chunk_len=4096
+/// ```
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Calibration {
+    /// The GF(2^8) backend, used by all GF(2^8) codecs of the process.
+    ///
+    /// For codecs over other fields, the backend in use when calibrating,
+    /// which calibrating and applying leave alone.
+    pub backend: Backend,
+    /// The chunk size, see `ReedSolomon::cache_chunk_len`.
+    pub cache_chunk_len: usize,
+}
+
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "backend={}", self.backend.name())?;
+        writeln!(f, "cache_chunk_len={}", self.cache_chunk_len)
+    }
+}
+
+impl FromStr for Calibration {
+    type Err = Error;
+
+    /// Parses the lines written by `Display`, in any order. Blank lines are
+    /// ignored.
+    ///
+    /// Returns `Error::InvalidCalibration` for unknown keys, values which
+    /// do not parse, and missing keys. A backend unavailable in this build
+    /// still parses, `ReedSolomon::apply_calibration` rejects it.
+    fn from_str(s: &str) -> Result<Calibration, Error> {
+        let mut backend = None;
+        let mut cache_chunk_len = None;
+
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().ok_or(Error::InvalidCalibration)?.trim();
+
+            match key {
+                "backend" => backend = Backend::from_name(value),
+                "cache_chunk_len" => cache_chunk_len = value.parse().ok(),
+                _ => return Err(Error::InvalidCalibration),
+            }
+        }
+
+        match (backend, cache_chunk_len) {
+            (Some(backend), Some(cache_chunk_len)) => Ok(Calibration {
+                backend,
+                cache_chunk_len,
+            }),
+            _ => Err(Error::InvalidCalibration),
+        }
+    }
+}
+
+impl<F: 'static + Field> ReedSolomon<F> {
+    /// Times encoding with every available GF(2^8) backend and a few chunk
+    /// sizes, then applies the fastest combination as `apply_calibration`
+    /// and returns it.
+    ///
+    /// This encodes stripes of up to 16 MB a few dozen times, and switches
+    /// the backend of all codecs of the process meanwhile. The results are
+    /// the same with every backend, only their speed differs. The encoding
+    /// done counts in `stats`.
+    ///
+    /// Codecs over fields other than GF(2^8) only time the chunk sizes, and
+    /// keep the backend in use.
+    ///
+    /// The chunk size best for a codec may not be best for codecs of other
+    /// shapes.
+    ///
+    /// Not available on `wasm32-unknown-unknown`, which has no clock.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn calibrate(&self) -> Calibration {
+        let backends = Self::calibration_backends();
+        let stripe_len =
+            MAX_STRIPE_BYTES / (self.total_shard_count * std::mem::size_of::<F::Elem>());
+        let shard_len = stripe_len.clamp(1, MAX_SHARD_LEN);
+
+        self.calibrate_among(&backends, shard_len, ROUNDS)
+    }
+
+    /// Applies the calibration stored at `path`, or calibrates and stores
+    /// the result there if the file is missing, does not parse, or names a
+    /// backend this build does not have.
+    ///
+    /// Codecs of different shapes should use different files.
+    ///
+    /// Returns the errors of writing the file. The calibration is applied
+    /// even then.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn calibrate_cached<P: AsRef<Path>>(&self, path: P) -> io::Result<Calibration> {
+        let path = path.as_ref();
+
+        if let Ok(text) = fs::read_to_string(path) {
+            if let Ok(calibration) = text.parse::<Calibration>() {
+                if self.apply_calibration(&calibration).is_ok() {
+                    return Ok(calibration);
+                }
+            }
+        }
+
+        let calibration = self.calibrate();
+        fs::write(path, calibration.to_string())?;
+        Ok(calibration)
+    }
+
+    /// Sets the GF(2^8) backend of the process and the chunk size of this
+    /// codec to those of `calibration`. Codecs over other fields only set
+    /// their chunk size.
+    ///
+    /// Returns `Error::UnsupportedBackend` if a GF(2^8) codec is given a
+    /// backend which is not available, in which case nothing is changed.
+    pub fn apply_calibration(&self, calibration: &Calibration) -> Result<(), Error> {
+        // leaves the backend alone when it already is the one picked
+        if Self::uses_galois_8_backend() && galois_8::backend() != calibration.backend {
+            galois_8::set_backend(Some(calibration.backend))?;
+        }
+        self.set_cache_chunk_len(calibration.cache_chunk_len);
+        Ok(())
+    }
+
+    /// Whether the codec codes with the GF(2^8) kernels, whose backend
+    /// calibrating picks.
+    fn uses_galois_8_backend() -> bool {
+        TypeId::of::<F>() == TypeId::of::<galois_8::Field>()
+    }
+
+    /// The backends `calibrate` tries: all available ones for GF(2^8)
+    /// codecs, and only the one in use for the others.
+    pub(crate) fn calibration_backends() -> Vec<Backend> {
+        if !Self::uses_galois_8_backend() {
+            return vec![galois_8::backend()];
+        }
+
+        Backend::ALL
+            .iter()
+            .cloned()
+            .filter(|backend| backend.is_available())
+            .collect()
+    }
+
+    /// Calibrates as `calibrate`, only trying `backends`, which are
+    /// available, on shards of `shard_len` elements.
+    ///
+    /// The backend is not touched when there is a single one to try.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub(crate) fn calibrate_among(
+        &self,
+        backends: &[Backend],
+        shard_len: usize,
+        rounds: usize,
+    ) -> Calibration {
+        // pseudo-random data, as coding runs of equal elements may be cheaper
+        let mut state = 0x2545_f491_usize;
+        let data: Vec<Vec<F::Elem>> = (0..self.data_shard_count)
+            .map(|_| {
+                (0..shard_len)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        F::nth(state % F::ORDER)
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut parity = vec![vec![F::zero(); shard_len]; self.parity_shard_count];
+
+        let mut best: Option<(Duration, Calibration)> = None;
+        for &backend in backends {
+            if backends.len() > 1 {
+                galois_8::set_backend(Some(backend)).expect("backends to try are available; qed");
+            }
+
+            for &cache_chunk_len in CHUNK_LENS.iter() {
+                self.set_cache_chunk_len(cache_chunk_len);
+
+                let elapsed = (0..rounds.max(1))
+                    .map(|_| {
+                        let start = Instant::now();
+                        self.encode_sep(&data, &mut parity)
+                            .expect("shards are allocated for the codec; qed");
+                        start.elapsed()
+                    })
+                    .min()
+                    .expect("at least one round; qed");
+
+                let faster = match best {
+                    Some((fastest, _)) => elapsed < fastest,
+                    None => true,
+                };
+                if faster {
+                    best = Some((
+                        elapsed,
+                        Calibration {
+                            backend,
+                            cache_chunk_len,
+                        },
+                    ));
+                }
+            }
+        }
+
+        let calibration = match best {
+            Some((_, calibration)) => calibration,
+            None => Calibration {
+                backend: galois_8::backend(),
+                cache_chunk_len: self.cache_chunk_len(),
+            },
+        };
+        if backends.len() > 1 {
+            galois_8::set_backend(Some(calibration.backend))
+                .expect("backends to try are available; qed");
+        }
+        self.set_cache_chunk_len(calibration.cache_chunk_len);
+        calibration
+    }
+}
@@ -0,0 +1,306 @@
+//! Classic Reed-Solomon error correction inside shards.
+//!
+//! The erasure code of `ReedSolomon` recovers whole shards which are known
+//! to be missing, but cannot tell which shard is corrupt. `CodewordCodec`
+//! complements it by protecting the bytes of each shard with the
+//! RS(255, 255 - 2t) code over GF(2^8), which locates and corrects up to
+//! `t` corrupted bytes in every codeword of 255 bytes, without knowing
+//! where they are.
+//!
+//! A shard is cut into blocks of `255 - 2t` bytes, and each block is
+//! stored followed by its `2t` parity bytes. The final block may be
+//! shorter, giving a shortened codeword.
+//!
+//! `CodewordCodec::reconstruct` combines both codes: it corrects the bytes
+//! of each present shard, treats the shards which cannot be corrected
+//! as missing, then reconstructs the missing shards.
+
+use smallvec::SmallVec;
+
+use crate::galois_8::{div, exp, mul};
+use crate::{Error, Field, ReedSolomon};
+
+/// The length of a full codeword.
+const CODEWORD_LEN: usize = 255;
+
+/// The primitive element generating the roots of the code.
+const GENERATOR: u8 = 2;
+
+/// Evaluates `poly`, with the highest degree coefficient first, at `x`.
+fn poly_eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter().fold(0, |acc, &c| mul(acc, x) ^ c)
+}
+
+/// Evaluates `poly`, with the lowest degree coefficient first, at `x`.
+fn poly_eval_rev(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c)
+}
+
+/// Encoder/decoder of RS(255, 255 - 2t) codewords inside shards.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CodewordCodec {
+    parity_len: usize,
+    // highest degree coefficient first
+    generator: Vec<u8>,
+}
+
+impl CodewordCodec {
+    /// Creates a codec correcting up to `parity_len / 2` bytes in each
+    /// codeword, using `parity_len` parity bytes per codeword.
+    ///
+    /// Returns `Error::TooFewParityShards` if `parity_len == 0`.
+    ///
+    /// Returns `Error::TooManyParityShards` if `parity_len >= 255`.
+    pub fn new(parity_len: usize) -> Result<CodewordCodec, Error> {
+        if parity_len == 0 {
+            return Err(Error::TooFewParityShards);
+        }
+        if parity_len >= CODEWORD_LEN {
+            return Err(Error::TooManyParityShards);
+        }
+
+        // g(x) = (x - a^0)(x - a^1)...(x - a^(parity_len - 1))
+        let mut generator = vec![1u8];
+        for i in 0..parity_len {
+            let root = exp(GENERATOR, i);
+            let mut next = vec![0u8; generator.len() + 1];
+            for (j, &g) in generator.iter().enumerate() {
+                next[j] ^= g;
+                next[j + 1] ^= mul(g, root);
+            }
+            generator = next;
+        }
+
+        Ok(CodewordCodec {
+            parity_len,
+            generator,
+        })
+    }
+
+    /// Returns the number of parity bytes per codeword.
+    pub fn parity_len(&self) -> usize {
+        self.parity_len
+    }
+
+    /// Returns the number of corrupted bytes which can be corrected
+    /// in each codeword.
+    pub fn correctable_errors(&self) -> usize {
+        self.parity_len / 2
+    }
+
+    /// Returns the number of data bytes in a full codeword.
+    pub fn block_len(&self) -> usize {
+        CODEWORD_LEN - self.parity_len
+    }
+
+    /// Returns the length of a shard of `data_len` bytes once encoded.
+    pub fn encoded_len(&self, data_len: usize) -> usize {
+        let blocks = data_len.div_ceil(self.block_len());
+        data_len + blocks * self.parity_len
+    }
+
+    /// Returns the length of the data in an encoded shard of `encoded_len`
+    /// bytes, or `None` if no shard encodes to that length.
+    pub fn decoded_len(&self, encoded_len: usize) -> Option<usize> {
+        let full = encoded_len / CODEWORD_LEN;
+        match encoded_len % CODEWORD_LEN {
+            0 => Some(full * self.block_len()),
+            rem if rem > self.parity_len => Some(full * self.block_len() + rem - self.parity_len),
+            _ => None,
+        }
+    }
+
+    /// Encodes `shard`, appending parity bytes to each block.
+    pub fn encode_shard(&self, shard: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(self.encoded_len(shard.len()));
+
+        for block in shard.chunks(self.block_len()) {
+            let start = encoded.len();
+            encoded.extend_from_slice(block);
+            encoded.resize(start + block.len() + self.parity_len, 0);
+
+            // polynomial long division by the generator, leaving the
+            // remainder in the parity bytes
+            let codeword = &mut encoded[start..];
+            for i in 0..block.len() {
+                let coef = codeword[i];
+                if coef != 0 {
+                    for (j, &g) in self.generator.iter().enumerate().skip(1) {
+                        codeword[i + j] ^= mul(g, coef);
+                    }
+                }
+            }
+            codeword[..block.len()].copy_from_slice(block);
+        }
+
+        encoded
+    }
+
+    /// Decodes an encoded shard, correcting corrupted bytes.
+    ///
+    /// Returns the shard data along with the number of bytes corrected.
+    ///
+    /// Returns `Error::IncorrectShardSize` if no shard encodes to the length
+    /// of `encoded`, or `Error::UncorrectableCodeword` if a codeword has more
+    /// corrupted bytes than can be corrected.
+    pub fn decode_shard(&self, encoded: &[u8]) -> Result<(Vec<u8>, usize), Error> {
+        let data_len = self
+            .decoded_len(encoded.len())
+            .ok_or(Error::IncorrectShardSize)?;
+
+        let mut data = Vec::with_capacity(data_len);
+        let mut corrected = 0;
+
+        let mut codeword: SmallVec<[u8; 256]> = SmallVec::new();
+        for chunk in encoded.chunks(CODEWORD_LEN) {
+            codeword.clear();
+            codeword.extend_from_slice(chunk);
+
+            corrected += self.correct_codeword(&mut codeword)?;
+
+            data.extend_from_slice(&codeword[..codeword.len() - self.parity_len]);
+        }
+
+        Ok((data, corrected))
+    }
+
+    /// Corrects every present shard, marks the shards which cannot be
+    /// corrected as missing, then reconstructs all missing shards with
+    /// `codec`.
+    ///
+    /// Present shards are expected to be encoded with `encode_shard`,
+    /// and are replaced by their decoded data.
+    ///
+    /// Returns the total number of bytes corrected.
+    ///
+    /// Returns the errors of `ReedSolomon::reconstruct`, e.g.
+    /// `Error::TooFewShardsPresent` if too many shards are uncorrectable.
+    pub fn reconstruct<F: Field<Elem = u8>>(
+        &self,
+        codec: &ReedSolomon<F>,
+        shards: &mut [Option<Vec<u8>>],
+    ) -> Result<usize, Error> {
+        check_piece_count!(all => codec, shards);
+
+        let mut corrected = 0;
+        for shard in shards.iter_mut() {
+            let decoded = match *shard {
+                Some(ref encoded) => self.decode_shard(encoded).ok(),
+                None => continue,
+            };
+
+            *shard = decoded.map(|(data, n)| {
+                corrected += n;
+                data
+            });
+        }
+
+        codec.reconstruct(shards)?;
+
+        Ok(corrected)
+    }
+
+    /// Corrects a single (possibly shortened) codeword in place,
+    /// returning the number of bytes corrected.
+    fn correct_codeword(&self, codeword: &mut [u8]) -> Result<usize, Error> {
+        let syndromes: SmallVec<[u8; 256]> = (0..self.parity_len)
+            .map(|i| poly_eval(codeword, exp(GENERATOR, i)))
+            .collect();
+
+        if syndromes.iter().all(|&s| s == 0) {
+            return Ok(0);
+        }
+
+        let locator = Self::error_locator(&syndromes);
+        let error_count = locator.len() - 1;
+        if error_count * 2 > self.parity_len {
+            return Err(Error::UncorrectableCodeword);
+        }
+
+        // Chien search: the error at power `e` of the codeword polynomial
+        // is a root of the locator at a^-e.
+        let n = codeword.len();
+        let error_powers: SmallVec<[usize; 256]> = (0..n)
+            .filter(|&e| {
+                poly_eval_rev(&locator, exp(GENERATOR, (CODEWORD_LEN - e) % CODEWORD_LEN)) == 0
+            })
+            .collect();
+        if error_powers.len() != error_count {
+            return Err(Error::UncorrectableCodeword);
+        }
+
+        // Forney: the error evaluator is S(x) * L(x) mod x^(2t).
+        let mut evaluator: SmallVec<[u8; 256]> = SmallVec::from_elem(0, self.parity_len);
+        for (i, &s) in syndromes.iter().enumerate() {
+            for (j, &l) in locator.iter().enumerate().take(self.parity_len - i) {
+                evaluator[i + j] ^= mul(s, l);
+            }
+        }
+
+        for &e in error_powers.iter() {
+            let x = exp(GENERATOR, e);
+            let x_inv = div(1, x);
+
+            // formal derivative of the locator, only odd terms remain
+            let derivative = locator
+                .iter()
+                .enumerate()
+                .skip(1)
+                .step_by(2)
+                .fold(0, |acc, (i, &l)| acc ^ mul(l, exp(x_inv, i - 1)));
+
+            let magnitude = div(mul(x, poly_eval_rev(&evaluator, x_inv)), derivative);
+
+            codeword[n - 1 - e] ^= magnitude;
+        }
+
+        // guard against miscorrection beyond the capability of the code
+        if (0..self.parity_len).any(|i| poly_eval(codeword, exp(GENERATOR, i)) != 0) {
+            return Err(Error::UncorrectableCodeword);
+        }
+
+        Ok(error_count)
+    }
+
+    /// Berlekamp-Massey, returning the error locator polynomial
+    /// with the lowest degree coefficient first.
+    fn error_locator(syndromes: &[u8]) -> SmallVec<[u8; 256]> {
+        let mut locator: SmallVec<[u8; 256]> = SmallVec::from_elem(0, syndromes.len() + 1);
+        let mut prev: SmallVec<[u8; 256]> = SmallVec::from_elem(0, syndromes.len() + 1);
+        locator[0] = 1;
+        prev[0] = 1;
+
+        let mut len = 0;
+        let mut shift = 1;
+        let mut prev_discrepancy = 1;
+
+        for n in 0..syndromes.len() {
+            let discrepancy = (1..=len).fold(syndromes[n], |acc, i| {
+                acc ^ mul(locator[i], syndromes[n - i])
+            });
+
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+
+            let scale = div(discrepancy, prev_discrepancy);
+            let old = locator.clone();
+            for i in 0..locator.len() - shift {
+                locator[i + shift] ^= mul(scale, prev[i]);
+            }
+
+            if 2 * len <= n {
+                len = n + 1 - len;
+                prev = old;
+                prev_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                shift += 1;
+            }
+        }
+
+        locator.truncate(len + 1);
+        locator
+    }
+}
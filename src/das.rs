@@ -0,0 +1,236 @@
+//! Grids of cells for data-availability sampling.
+//!
+//! A blob is cut into `rows` rows of `k` cells of `cell_len` elements,
+//! `k` being the number of data shards of the codec. Every row is extended
+//! with the parity cells of the codec, giving a grid of `rows` rows and
+//! `k + m` columns in which every cell is one shard of the stripe of its
+//! row.
+//!
+//! Light clients sample random cells of the grid. Once the sampled cells
+//! hold `k` distinct cells of every row, the whole grid, and with it the
+//! blob, can be rebuilt from them.
+
+use std::collections::HashSet;
+
+use crate::{Error, Field, ReedSolomon};
+
+/// The position of a cell in a `CellGrid`.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone, Copy)]
+pub struct CellIndex {
+    pub row: usize,
+    /// The index of the shard of the cell in the stripe of its row, the
+    /// data shards coming first.
+    pub column: usize,
+}
+
+impl CellIndex {
+    pub fn new(row: usize, column: usize) -> CellIndex {
+        CellIndex { row, column }
+    }
+}
+
+/// Encodes blobs as grids of cells, and rebuilds them from sampled cells.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CellGrid<F: Field> {
+    codec: ReedSolomon<F>,
+    rows: usize,
+    cell_len: usize,
+}
+
+impl<F: Field> CellGrid<F> {
+    /// Creates grids of `rows` rows of cells of `cell_len` elements, the
+    /// rows being encoded with `codec`.
+    ///
+    /// Returns `Error::EmptyShard` if `rows` or `cell_len` is `0`.
+    pub fn new(codec: ReedSolomon<F>, rows: usize, cell_len: usize) -> Result<CellGrid<F>, Error> {
+        if rows == 0 || cell_len == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        Ok(CellGrid {
+            codec,
+            rows,
+            cell_len,
+        })
+    }
+
+    /// Returns the codec of the rows.
+    pub fn codec(&self) -> &ReedSolomon<F> {
+        &self.codec
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of cells per row, data and parity.
+    pub fn columns(&self) -> usize {
+        self.codec.total_shard_count()
+    }
+
+    pub fn cell_len(&self) -> usize {
+        self.cell_len
+    }
+
+    /// Returns the number of cells of the grid.
+    pub fn cell_count(&self) -> usize {
+        self.rows * self.columns()
+    }
+
+    /// Returns the maximum number of elements of a blob.
+    pub fn blob_len(&self) -> usize {
+        self.rows * self.codec.data_shard_count() * self.cell_len
+    }
+
+    /// Returns the position of the cell at `index` in the row-major order
+    /// of the grid, as returned by `encode`.
+    pub fn cell_index(&self, index: usize) -> CellIndex {
+        CellIndex::new(index / self.columns(), index % self.columns())
+    }
+
+    /// Returns the index of `cell` in the row-major order of the grid.
+    pub fn cell_position(&self, cell: CellIndex) -> usize {
+        cell.row * self.columns() + cell.column
+    }
+
+    /// Encodes `blob` as the cells of a grid, in row-major order.
+    ///
+    /// A blob shorter than `blob_len` is padded with zeros.
+    ///
+    /// Returns `Error::BlobTooLarge` if `blob` is longer than `blob_len`.
+    pub fn encode(&self, blob: &[F::Elem]) -> Result<Vec<Vec<F::Elem>>, Error> {
+        if blob.len() > self.blob_len() {
+            return Err(Error::BlobTooLarge);
+        }
+
+        let data_shards = self.codec.data_shard_count();
+        let mut cells = Vec::with_capacity(self.cell_count());
+
+        let mut rest = blob;
+        for _ in 0..self.rows {
+            let start = cells.len();
+            for _ in 0..data_shards {
+                let (head, tail) = rest.split_at(self.cell_len.min(rest.len()));
+                let mut cell = head.to_vec();
+                cell.resize(self.cell_len, F::zero());
+                cells.push(cell);
+                rest = tail;
+            }
+            cells.resize(start + self.columns(), vec![F::zero(); self.cell_len]);
+
+            self.codec.encode(&mut cells[start..])?;
+        }
+
+        Ok(cells)
+    }
+
+    /// Checks if the cells at `samples` suffice to rebuild the grid, i.e.
+    /// if they hold as many distinct cells of every row as there are data
+    /// shards.
+    ///
+    /// Positions outside of the grid are ignored, and repeated positions
+    /// count once.
+    pub fn is_sufficient<I: IntoIterator<Item = CellIndex>>(&self, samples: I) -> bool {
+        self.missing_cells(samples) == 0
+    }
+
+    /// Returns the number of distinct cells which must still be sampled, at
+    /// the least, before the grid can be rebuilt from the cells at
+    /// `samples`. See `is_sufficient`.
+    pub fn missing_cells<I: IntoIterator<Item = CellIndex>>(&self, samples: I) -> usize {
+        let mut seen = HashSet::new();
+        let mut per_row = vec![0usize; self.rows];
+
+        for cell in samples {
+            if cell.row < self.rows && cell.column < self.columns() && seen.insert(cell) {
+                per_row[cell.row] += 1;
+            }
+        }
+
+        let data_shards = self.codec.data_shard_count();
+        per_row
+            .iter()
+            .map(|&count| data_shards.saturating_sub(count))
+            .sum()
+    }
+
+    /// Rebuilds all the cells of the grid, in row-major order, from the
+    /// sampled `cells`.
+    ///
+    /// The cells of a position repeated in `cells` are assumed equal, the
+    /// first one is used.
+    ///
+    /// Returns `Error::InvalidIndex` for a position outside of the grid,
+    /// `Error::IncorrectShardSize` for a cell not of `cell_len` elements,
+    /// and `Error::TooFewShardsPresent` if the cells are not sufficient,
+    /// see `is_sufficient`.
+    pub fn reconstruct<T: AsRef<[F::Elem]>>(
+        &self,
+        cells: &[(CellIndex, T)],
+    ) -> Result<Vec<Vec<F::Elem>>, Error> {
+        let mut grid = self.lay_out(cells)?;
+        for row in grid.chunks_mut(self.columns()) {
+            if row.iter().any(Option::is_none) {
+                self.codec.reconstruct(row)?;
+            }
+        }
+
+        Ok(grid
+            .into_iter()
+            .map(|cell| cell.expect("all rows were reconstructed; qed"))
+            .collect())
+    }
+
+    /// Rebuilds the blob from the sampled `cells`, as `reconstruct`, only
+    /// rebuilding data cells.
+    ///
+    /// The blob is returned with the padding of `encode`, `blob_len`
+    /// elements long.
+    pub fn reconstruct_blob<T: AsRef<[F::Elem]>>(
+        &self,
+        cells: &[(CellIndex, T)],
+    ) -> Result<Vec<F::Elem>, Error> {
+        let data_shards = self.codec.data_shard_count();
+
+        let mut grid = self.lay_out(cells)?;
+        let mut blob = Vec::with_capacity(self.blob_len());
+        for row in grid.chunks_mut(self.columns()) {
+            if row[..data_shards].iter().any(Option::is_none) {
+                self.codec.reconstruct_data(row)?;
+            }
+            for cell in &row[..data_shards] {
+                blob.extend_from_slice(cell.as_ref().expect("data cells were reconstructed; qed"));
+            }
+        }
+
+        Ok(blob)
+    }
+
+    /// Checks the sampled `cells` as `reconstruct`, and copies them into
+    /// the grid, the cells not sampled being `None`.
+    fn lay_out<T: AsRef<[F::Elem]>>(
+        &self,
+        cells: &[(CellIndex, T)],
+    ) -> Result<Vec<Option<Vec<F::Elem>>>, Error> {
+        let mut grid: Vec<Option<Vec<F::Elem>>> = vec![None; self.cell_count()];
+        for (cell, elems) in cells {
+            if cell.row >= self.rows || cell.column >= self.columns() {
+                return Err(Error::InvalidIndex);
+            }
+            if elems.as_ref().len() != self.cell_len {
+                return Err(Error::IncorrectShardSize);
+            }
+
+            let slot = &mut grid[self.cell_position(*cell)];
+            if slot.is_none() {
+                *slot = Some(elems.as_ref().to_vec());
+            }
+        }
+
+        if !self.is_sufficient(cells.iter().map(|&(cell, _)| cell)) {
+            return Err(Error::TooFewShardsPresent);
+        }
+
+        Ok(grid)
+    }
+}
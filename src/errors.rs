@@ -1,5 +1,6 @@
 use std;
 use std::fmt::Formatter;
+use std::io;
 
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum Error {
@@ -16,6 +17,20 @@ pub enum Error {
     EmptyShard,
     InvalidShardFlags,
     InvalidIndex,
+    UncorrectableCodeword,
+    InvalidDecodeMatrix,
+    InvalidLossModel,
+    UnsupportedBackend,
+    ChecksumMismatch,
+    ErasurePatternNotPrecomputed,
+    InvalidCalibration,
+    BlobTooLarge,
+    InvalidRate,
+    InvalidManifest,
+    InvalidCheckpoint,
+    InconsistentShards,
+    RecordTooLarge,
+    InvalidPlacement,
 }
 
 impl Error {
@@ -34,6 +49,20 @@ impl Error {
             Error::EmptyShard => "The first shard provided is of zero length",
             Error::InvalidShardFlags => "The number of flags does not match the total number of shards",
             Error::InvalidIndex => "The data shard index provided is greater or equal to the number of data shards in codec",
+            Error::UncorrectableCodeword => "A codeword contains more corrupted bytes than can be corrected",
+            Error::InvalidDecodeMatrix => "The matrix is not the decode matrix of the missing shards",
+            Error::InvalidLossModel => "A probability of the loss model is not within [0, 1], or a node holds no shards",
+            Error::UnsupportedBackend => "The backend is not available in this build of the library",
+            Error::ChecksumMismatch => "A reconstructed shard does not match its expected checksum",
+            Error::ErasurePatternNotPrecomputed => "The decode matrix of the missing shards was not precomputed",
+            Error::InvalidCalibration => "The calibration is not made of the lines of a backend and a chunk size",
+            Error::BlobTooLarge => "The blob is larger than the grid of cells holds",
+            Error::InvalidRate => "The rate is neither a positive number of bytes per second nor a duty cycle within (0, 1]",
+            Error::InvalidManifest => "The manifest misses shards, or does not match the shards or the codec",
+            Error::InvalidCheckpoint => "The checkpoint was taken with another codec or for other missing shards",
+            Error::InconsistentShards => "The reconstructed shards do not match the parity, a shard present is corrupt",
+            Error::RecordTooLarge => "The record is larger than the data shards of a stripe hold",
+            Error::InvalidPlacement => "The nodes cannot hold the shards of a stripe within the limit of shards per domain",
         }
     }
 }
@@ -50,37 +79,114 @@ impl std::error::Error for Error {
     }
 }
 
+impl Error {
+    /// Returns the kind of the `io::Error` the error converts to.
+    ///
+    /// The errors of shards failing a check of their contents are
+    /// `InvalidData`, and `UnsupportedBackend` is `Unsupported`. All other
+    /// errors are about the arguments of a call, and are `InvalidInput`.
+    pub fn io_kind(&self) -> io::ErrorKind {
+        match *self {
+            Error::UncorrectableCodeword
+            | Error::ChecksumMismatch
+            | Error::InvalidCalibration
+            | Error::InvalidManifest
+            | Error::InconsistentShards => io::ErrorKind::InvalidData,
+            Error::UnsupportedBackend => io::ErrorKind::Unsupported,
+            _ => io::ErrorKind::InvalidInput,
+        }
+    }
+}
+
+/// Converts to an `io::Error` of kind `Error::io_kind`, holding the error.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        io::Error::new(e.io_kind(), e)
+    }
+}
+
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum SBSError {
-    TooManyCalls,
-    LeftoverShards,
-    RSError(Error),
+    /// All `data_shard_count` data shards have already been encoded.
+    TooManyCalls { data_shard_count: usize },
+    /// Only `cur_input` of the `data_shard_count` data shards have been
+    /// encoded.
+    LeftoverShards {
+        cur_input: usize,
+        data_shard_count: usize,
+    },
+    /// The shards provided while at input shard `cur_input` do not fit
+    /// the codec.
+    RSError { cur_input: usize, error: Error },
+    /// A background job panicked while `cur_input` data shards were
+    /// encoded.
+    JobPanicked { cur_input: usize },
 }
 
 impl SBSError {
-    fn to_string(&self) -> &str {
+    /// Returns the input shard index the bookkeeping was at when the
+    /// error occurred.
+    pub fn cur_input(&self) -> usize {
         match *self {
-            SBSError::TooManyCalls => "Too many calls",
-            SBSError::LeftoverShards => "Leftover shards",
-            SBSError::RSError(ref e) => e.to_string(),
+            SBSError::TooManyCalls { data_shard_count } => data_shard_count,
+            SBSError::LeftoverShards { cur_input, .. } => cur_input,
+            SBSError::RSError { cur_input, .. } => cur_input,
+            SBSError::JobPanicked { cur_input } => cur_input,
         }
     }
 }
 
 impl std::fmt::Display for SBSError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "{}", self.to_string())
+        match *self {
+            SBSError::TooManyCalls { data_shard_count } => write!(
+                f,
+                "Too many calls, all {} data shards are already encoded",
+                data_shard_count
+            ),
+            SBSError::LeftoverShards {
+                cur_input,
+                data_shard_count,
+            } => write!(
+                f,
+                "Leftover shards, {} of {} data shards are encoded",
+                cur_input, data_shard_count
+            ),
+            SBSError::RSError {
+                cur_input,
+                ref error,
+            } => {
+                write!(f, "At input shard {}: {}", cur_input, error)
+            }
+            SBSError::JobPanicked { cur_input } => write!(
+                f,
+                "A background job panicked, {} data shards are encoded",
+                cur_input
+            ),
+        }
     }
 }
 
-impl std::error::Error for SBSError {
-    fn description(&self) -> &str {
-        self.to_string()
+impl std::error::Error for SBSError {}
+
+/// Converts to an `io::Error` holding the error, of the kind of the codec
+/// error for `SBSError::RSError`, of kind `Other` for
+/// `SBSError::JobPanicked`, and of kind `InvalidInput` otherwise.
+impl From<SBSError> for io::Error {
+    fn from(e: SBSError) -> io::Error {
+        let kind = match e {
+            SBSError::RSError { error, .. } => error.io_kind(),
+            SBSError::JobPanicked { .. } => io::ErrorKind::Other,
+            _ => io::ErrorKind::InvalidInput,
+        };
+        io::Error::new(kind, e)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io;
+
     use crate::errors::Error;
     use crate::errors::SBSError;
 
@@ -135,12 +241,119 @@ mod tests {
             Error::InvalidIndex.to_string(),
             "The data shard index provided is greater or equal to the number of data shards in codec"
         );
+        assert_eq!(
+            Error::UncorrectableCodeword.to_string(),
+            "A codeword contains more corrupted bytes than can be corrected"
+        );
+        assert_eq!(
+            Error::InvalidDecodeMatrix.to_string(),
+            "The matrix is not the decode matrix of the missing shards"
+        );
+        assert_eq!(
+            Error::InvalidLossModel.to_string(),
+            "A probability of the loss model is not within [0, 1], or a node holds no shards"
+        );
+        assert_eq!(
+            Error::UnsupportedBackend.to_string(),
+            "The backend is not available in this build of the library"
+        );
+        assert_eq!(
+            Error::ChecksumMismatch.to_string(),
+            "A reconstructed shard does not match its expected checksum"
+        );
+        assert_eq!(
+            Error::InvalidCalibration.to_string(),
+            "The calibration is not made of the lines of a backend and a chunk size"
+        );
+        assert_eq!(
+            Error::BlobTooLarge.to_string(),
+            "The blob is larger than the grid of cells holds"
+        );
+        assert_eq!(
+            Error::InvalidRate.to_string(),
+            "The rate is neither a positive number of bytes per second nor a duty cycle within (0, 1]"
+        );
+        assert_eq!(
+            Error::InvalidManifest.to_string(),
+            "The manifest misses shards, or does not match the shards or the codec"
+        );
+        assert_eq!(
+            Error::InvalidCheckpoint.to_string(),
+            "The checkpoint was taken with another codec or for other missing shards"
+        );
+        assert_eq!(
+            Error::InconsistentShards.to_string(),
+            "The reconstructed shards do not match the parity, a shard present is corrupt"
+        );
+        assert_eq!(
+            Error::RecordTooLarge.to_string(),
+            "The record is larger than the data shards of a stripe hold"
+        );
+        assert_eq!(
+            Error::InvalidPlacement.to_string(),
+            "The nodes cannot hold the shards of a stripe within the limit of shards per domain"
+        );
     }
 
     #[test]
     fn test_sbserror_to_string_is_okay() {
-        assert_eq!(SBSError::TooManyCalls.to_string(), "Too many calls");
-        assert_eq!(SBSError::LeftoverShards.to_string(), "Leftover shards");
+        assert_eq!(
+            SBSError::TooManyCalls {
+                data_shard_count: 10
+            }
+            .to_string(),
+            "Too many calls, all 10 data shards are already encoded"
+        );
+        assert_eq!(
+            SBSError::LeftoverShards {
+                cur_input: 3,
+                data_shard_count: 10
+            }
+            .to_string(),
+            "Leftover shards, 3 of 10 data shards are encoded"
+        );
+        assert_eq!(
+            SBSError::RSError {
+                cur_input: 3,
+                error: Error::EmptyShard
+            }
+            .to_string(),
+            "At input shard 3: The first shard provided is of zero length"
+        );
+        assert_eq!(
+            SBSError::JobPanicked { cur_input: 3 }.to_string(),
+            "A background job panicked, 3 data shards are encoded"
+        );
+    }
+
+    #[test]
+    fn test_error_into_io_error() {
+        let e = io::Error::from(Error::TooFewShardsPresent);
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        assert_eq!(
+            Error::TooFewShardsPresent,
+            *e.into_inner().unwrap().downcast::<Error>().unwrap()
+        );
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            io::Error::from(Error::ChecksumMismatch).kind()
+        );
+        assert_eq!(
+            io::ErrorKind::Unsupported,
+            io::Error::from(Error::UnsupportedBackend).kind()
+        );
+
+        let e = io::Error::from(SBSError::RSError {
+            cur_input: 3,
+            error: Error::InconsistentShards,
+        });
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+        let e = io::Error::from(SBSError::TooManyCalls {
+            data_shard_count: 10,
+        });
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        let e = io::Error::from(SBSError::JobPanicked { cur_input: 3 });
+        assert_eq!(io::ErrorKind::Other, e.kind());
     }
 
     #[test]
@@ -150,6 +363,11 @@ mod tests {
 
     #[test]
     fn test_sbserror_display_does_not_panic() {
-        println!("{}", SBSError::TooManyCalls);
+        println!(
+            "{}",
+            SBSError::TooManyCalls {
+                data_shard_count: 10
+            }
+        );
     }
 }
@@ -0,0 +1,176 @@
+//! Deterministic damage to shards, for reproducible recovery tests,
+//! enabled by the `test-support` feature.
+//!
+//! A `FaultInjector` draws the damage from its own seeded pseudo-random
+//! generator, as `sim::Simulation` does, so the same seed damages the same
+//! shards in the same way. Every method returns the `Fault`s it applied,
+//! which `Fault::apply` replays, e.g. to reproduce a failure from the log of
+//! a test.
+//!
+//! Bit flips and swaps leave wrong shards in place, as silent corruption
+//! does. Truncation models torn writes, and dropped shards lost nodes. The
+//! shards are those taken by `ReedSolomon::reconstruct`, with `None` for a
+//! missing shard.
+
+use crate::sim::next_random;
+
+/// A fault of a stripe of shards.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Fault {
+    /// Bit `bit`, from 0 to 7, of byte `offset` of shard `shard` is flipped.
+    BitFlip {
+        shard: usize,
+        offset: usize,
+        bit: u8,
+    },
+    /// Shard `shard` is cut down to `len` bytes.
+    Truncate { shard: usize, len: usize },
+    /// Shards `a` and `b` are swapped, as by a misdirected write.
+    Swap { a: usize, b: usize },
+    /// Shard `shard` is lost.
+    Drop { shard: usize },
+}
+
+impl Fault {
+    /// Applies the fault to `shards`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fault does not fit the shards, e.g. if it flips a bit
+    /// of a missing shard.
+    pub fn apply(&self, shards: &mut [Option<Vec<u8>>]) {
+        match *self {
+            Fault::BitFlip { shard, offset, bit } => {
+                let shard = shards[shard]
+                    .as_mut()
+                    .expect("no fault applies to a missing shard");
+                shard[offset] ^= 1 << bit;
+            }
+            Fault::Truncate { shard, len } => {
+                let shard = shards[shard]
+                    .as_mut()
+                    .expect("no fault applies to a missing shard");
+                assert!(
+                    len <= shard.len(),
+                    "the shard is already shorter than the fault"
+                );
+                shard.truncate(len);
+            }
+            Fault::Swap { a, b } => shards.swap(a, b),
+            Fault::Drop { shard } => shards[shard] = None,
+        }
+    }
+}
+
+/// Damages shards from a seeded pseudo-random generator.
+///
+/// See the module documentation.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct FaultInjector {
+    state: u64,
+}
+
+impl FaultInjector {
+    /// Creates an injector seeded with `seed`.
+    pub fn new(seed: u64) -> FaultInjector {
+        FaultInjector {
+            // xorshift needs a non-zero state
+            state: (seed ^ 0x853C_49E6_748F_EA9B).max(1),
+        }
+    }
+
+    /// Returns a number in `0..n`, for `n > 0`.
+    fn below(&mut self, n: usize) -> usize {
+        (next_random(&mut self.state) % n as u64) as usize
+    }
+
+    /// Picks one of `candidates`, if any.
+    fn pick(&mut self, candidates: &[usize]) -> Option<usize> {
+        match candidates.len() {
+            0 => None,
+            len => Some(candidates[self.below(len)]),
+        }
+    }
+
+    /// Flips `count` bits, each of a random byte of a random present shard.
+    ///
+    /// The same bit may be flipped twice, restoring it. No bit is flipped
+    /// if all shards are missing or empty.
+    pub fn flip_bits(&mut self, shards: &mut [Option<Vec<u8>>], count: usize) -> Vec<Fault> {
+        let candidates = non_empty(shards);
+        let mut faults = Vec::with_capacity(count);
+        for _ in 0..count {
+            let shard = match self.pick(&candidates) {
+                Some(shard) => shard,
+                None => break,
+            };
+            let len = shards[shard].as_ref().map_or(0, Vec::len);
+            let fault = Fault::BitFlip {
+                shard,
+                offset: self.below(len),
+                bit: self.below(8) as u8,
+            };
+            fault.apply(shards);
+            faults.push(fault);
+        }
+        faults
+    }
+
+    /// Cuts a random present shard down to a random shorter length, maybe
+    /// 0, or returns `None` if all shards are missing or empty.
+    pub fn truncate(&mut self, shards: &mut [Option<Vec<u8>>]) -> Option<Fault> {
+        let shard = self.pick(&non_empty(shards))?;
+        let len = shards[shard].as_ref().map_or(0, Vec::len);
+        let fault = Fault::Truncate {
+            shard,
+            len: self.below(len),
+        };
+        fault.apply(shards);
+        Some(fault)
+    }
+
+    /// Swaps two random shards, or returns `None` if there are fewer than
+    /// two shards.
+    pub fn swap(&mut self, shards: &mut [Option<Vec<u8>>]) -> Option<Fault> {
+        if shards.len() < 2 {
+            return None;
+        }
+        let a = self.below(shards.len());
+        // any other shard
+        let b = (a + 1 + self.below(shards.len() - 1)) % shards.len();
+        let fault = Fault::Swap { a, b };
+        fault.apply(shards);
+        Some(fault)
+    }
+
+    /// Drops `count` random present shards, or all present shards if there
+    /// are fewer.
+    pub fn drop_shards(&mut self, shards: &mut [Option<Vec<u8>>], count: usize) -> Vec<Fault> {
+        let mut candidates: Vec<usize> = shards
+            .iter()
+            .enumerate()
+            .filter(|(_, shard)| shard.is_some())
+            .map(|(i, _)| i)
+            .collect();
+        let mut faults = Vec::with_capacity(count.min(candidates.len()));
+        while faults.len() < count && !candidates.is_empty() {
+            let i = self.below(candidates.len());
+            let fault = Fault::Drop {
+                shard: candidates.swap_remove(i),
+            };
+            fault.apply(shards);
+            faults.push(fault);
+        }
+        faults
+    }
+}
+
+/// Returns the indices of the present shards holding at least a byte.
+fn non_empty(shards: &[Option<Vec<u8>>]) -> Vec<usize> {
+    shards
+        .iter()
+        .enumerate()
+        .filter(|(_, shard)| shard.as_ref().is_some_and(|shard| !shard.is_empty()))
+        .map(|(i, _)| i)
+        .collect()
+}
@@ -0,0 +1,126 @@
+//! Reconstruction from shards fetched on demand.
+//!
+//! Instead of prefetching every shard before calling `reconstruct`,
+//! the drivers in this module ask a `ShardSource` for only as many shards
+//! as the decoder needs, falling back to further shards when a fetch fails.
+//!
+//! Data shards are requested first, so an intact stripe is read without
+//! any decoding at all.
+//!
+//! This module is only available with the `async` feature.
+
+use bytes::Bytes;
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use crate::{Error, Field, ReedSolomon};
+
+/// Something which can fetch the shards of a stripe by index.
+#[allow(async_fn_in_trait)]
+pub trait ShardSource {
+    /// Fetches the shard at `index`, returning `None` if it is unavailable.
+    async fn fetch(&self, index: usize) -> Option<Bytes>;
+}
+
+impl<F: Field<Elem = u8>> ReedSolomon<F> {
+    /// Fetches just enough shards from `source` to recover the data shards,
+    /// then reconstructs the data shards.
+    ///
+    /// At most `max_concurrent` fetches are in flight at any time (a value
+    /// of `0` is treated as `1`). Whenever a fetch fails, the next shard
+    /// which has not been requested yet is fetched in its place.
+    ///
+    /// A fetched shard which is empty, or of a different length than the
+    /// first shard fetched, is treated as a failed fetch.
+    ///
+    /// Returns `Error::TooFewShardsPresent` when fewer than
+    /// `data_shard_count` shards could be fetched.
+    pub async fn reconstruct_data_from<S: ShardSource>(
+        &self,
+        source: &S,
+        max_concurrent: usize,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut shards = self.fetch_shards(source, max_concurrent).await?;
+
+        self.reconstruct_data(&mut shards)?;
+
+        Ok(shards
+            .into_iter()
+            .take(self.data_shard_count)
+            .map(|shard| shard.expect("data shards are reconstructed; qed"))
+            .collect())
+    }
+
+    /// Fetches just enough shards from `source` to recover the stripe,
+    /// then reconstructs all shards, data shards first.
+    ///
+    /// Fetching behaves as in `reconstruct_data_from`.
+    pub async fn reconstruct_from<S: ShardSource>(
+        &self,
+        source: &S,
+        max_concurrent: usize,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut shards = self.fetch_shards(source, max_concurrent).await?;
+
+        self.reconstruct(&mut shards)?;
+
+        Ok(shards
+            .into_iter()
+            .map(|shard| shard.expect("all shards are reconstructed; qed"))
+            .collect())
+    }
+
+    async fn fetch_shards<S: ShardSource>(
+        &self,
+        source: &S,
+        max_concurrent: usize,
+    ) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        let max_concurrent = max_concurrent.max(1);
+
+        let mut shards: Vec<Option<Vec<u8>>> = vec![None; self.total_shard_count];
+        let mut shard_len = None;
+        let mut number_present = 0;
+        let mut next_index = 0;
+
+        let mut in_flight = FuturesUnordered::new();
+
+        loop {
+            // Keep enough fetches going to reach the data shard count,
+            // within the concurrency limit.
+            while next_index < self.total_shard_count
+                && in_flight.len() < max_concurrent
+                && number_present + in_flight.len() < self.data_shard_count
+            {
+                let index = next_index;
+                in_flight.push(async move { (index, source.fetch(index).await) });
+                next_index += 1;
+            }
+
+            let (index, fetched) = match in_flight.next().await {
+                Some(x) => x,
+                None => break,
+            };
+
+            let shard = match fetched {
+                Some(shard) => shard,
+                None => continue,
+            };
+            if shard.is_empty() || shard_len.is_some_and(|len| len != shard.len()) {
+                continue;
+            }
+
+            shard_len = Some(shard.len());
+            shards[index] = Some(shard.to_vec());
+            number_present += 1;
+
+            if number_present == self.data_shard_count {
+                break;
+            }
+        }
+
+        if number_present < self.data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+
+        Ok(shards)
+    }
+}
@@ -59,6 +59,18 @@ pub type ReedSolomon = crate::ReedSolomon<Field>;
 /// Type alias of ShardByShard over GF(2^8).
 pub type ShardByShard<'a> = crate::ShardByShard<'a, Field>;
 
+/// Type alias of OwnedShardByShard over GF(2^16).
+pub type OwnedShardByShard = crate::OwnedShardByShard<Field>;
+
+/// Type alias of SyncShardByShard over GF(2^16).
+pub type SyncShardByShard<'a> = crate::SyncShardByShard<'a, Field>;
+
+/// Type alias of BackgroundShardByShard over GF(2^16).
+pub type BackgroundShardByShard = crate::BackgroundShardByShard<Field>;
+
+/// Type alias of ReconstructScratch over GF(2^16).
+pub type ReconstructScratch = crate::ReconstructScratch<Field>;
+
 /// An element of `GF(2^16)`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 struct Element(pub [u8; 2]);
@@ -1,5 +1,10 @@
 //! Implementation of GF(2^8): the finite field with 2^8 elements.
 
+use std::convert::TryInto;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::Error;
+
 include!(concat!(env!("OUT_DIR"), "/table.rs"));
 
 /// The field GF(2^8).
@@ -47,6 +52,10 @@ impl crate::Field for Field {
     fn mul_slice_add(c: u8, input: &[u8], out: &mut [u8]) {
         mul_slice_xor(c, input, out)
     }
+
+    fn mul_slices_add(elems: &[u8], inputs: &[&[u8]], out: &mut [u8]) {
+        mul_slices_xor(elems, inputs, out)
+    }
 }
 
 /// Type alias of ReedSolomon over GF(2^8).
@@ -55,6 +64,18 @@ pub type ReedSolomon = crate::ReedSolomon<Field>;
 /// Type alias of ShardByShard over GF(2^8).
 pub type ShardByShard<'a> = crate::ShardByShard<'a, Field>;
 
+/// Type alias of OwnedShardByShard over GF(2^8).
+pub type OwnedShardByShard = crate::OwnedShardByShard<Field>;
+
+/// Type alias of SyncShardByShard over GF(2^8).
+pub type SyncShardByShard<'a> = crate::SyncShardByShard<'a, Field>;
+
+/// Type alias of BackgroundShardByShard over GF(2^8).
+pub type BackgroundShardByShard = crate::BackgroundShardByShard<Field>;
+
+/// Type alias of ReconstructScratch over GF(2^8).
+pub type ReconstructScratch = crate::ReconstructScratch<Field>;
+
 /// Add two elements.
 pub fn add(a: u8, b: u8) -> u8 {
     a ^ b
@@ -67,10 +88,35 @@ pub fn sub(a: u8, b: u8) -> u8 {
 }
 
 /// Multiply two elements.
+///
+/// This is a single lookup in the full 256×256 product table, rather
+/// than the log/exp lookups used by `div` and `exp`.
+///
+/// With the `small-tables` feature, the product table is left out and
+/// the log/exp tables are used instead.
+#[cfg(not(feature = "small-tables"))]
 pub fn mul(a: u8, b: u8) -> u8 {
     MUL_TABLE[a as usize][b as usize]
 }
 
+/// Multiply two elements.
+///
+/// This uses the log/exp tables, as the `small-tables` feature leaves
+/// out the full 256×256 product table.
+#[cfg(feature = "small-tables")]
+pub fn mul(a: u8, b: u8) -> u8 {
+    mul_log_exp(a, b)
+}
+
+fn mul_log_exp(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        let log_result = LOG_TABLE[a as usize] as usize + LOG_TABLE[b as usize] as usize;
+        EXP_TABLE[log_result]
+    }
+}
+
 /// Divide one element by another. `b`, the divisor, may not be 0.
 pub fn div(a: u8, b: u8) -> u8 {
     if a == 0 {
@@ -116,24 +162,385 @@ macro_rules! return_if_empty {
     };
 }
 
+/// A kernel for the slice operations of GF(2^8).
+///
+/// The SIMD kernel is chosen for the target CPU when the crate is built
+/// with the `simd-accel` feature, so the only backends available are
+/// `Scalar` and the one of the build.
+///
+/// With the `static-simd` feature, the SIMD kernel is instead chosen at
+/// compile time from the target features, e.g. with
+/// `-C target-feature=+avx2`, and written in Rust so that it can be inlined
+/// into its callers. That kernel is then the only backend available, and is
+/// used without any check at runtime. Without target features of a SIMD
+/// kernel, the feature has no effect.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Backend {
+    /// Portable Rust, working on 8 bytes at a time.
+    Scalar,
+    /// 16 bytes at a time with SSSE3 shuffles, on `x86_64`.
+    Ssse3,
+    /// 32 bytes at a time with AVX2 shuffles, on `x86_64`.
+    Avx2,
+    /// 16 bytes at a time with NEON table lookups, on `aarch64`.
+    Neon,
+    /// The POWER8 and later vector unit, on `powerpc64le`.
+    Vsx,
+}
+
+/// Environment variable overriding the backend, holding the name of one of
+/// the available backends, e.g. `REED_SOLOMON_BACKEND=scalar`.
+pub const BACKEND_ENV: &str = "REED_SOLOMON_BACKEND";
+
+// 0 until the backend is first used
+static BACKEND: AtomicU8 = AtomicU8::new(0);
+
+impl Backend {
+    pub(crate) const ALL: [Backend; 5] = [
+        Backend::Scalar,
+        Backend::Ssse3,
+        Backend::Avx2,
+        Backend::Neon,
+        Backend::Vsx,
+    ];
+
+    /// Returns the lowercase name of the backend, as used in `BACKEND_ENV`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Scalar => "scalar",
+            Backend::Ssse3 => "ssse3",
+            Backend::Avx2 => "avx2",
+            Backend::Neon => "neon",
+            Backend::Vsx => "vsx",
+        }
+    }
+
+    /// Checks if the backend can be used on this machine, i.e. if it is
+    /// the backend picked by `detected_backend`, or `Scalar` unless a
+    /// kernel is chosen at compile time by the `static-simd` feature.
+    pub fn is_available(self) -> bool {
+        match STATIC_BACKEND {
+            Some(backend) => self == backend,
+            None => self == Backend::Scalar || self == detected_backend(),
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Backend> {
+        Backend::ALL
+            .iter()
+            .cloned()
+            .find(|b| b.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn from_env() -> Option<Backend> {
+        let name = std::env::var(BACKEND_ENV).ok()?;
+        Backend::from_name(&name).filter(|b| b.is_available())
+    }
+}
+
+/// Returns the backend picked by auto-detection.
 #[cfg(not(all(
     feature = "simd-accel",
-    any(target_arch = "x86_64", target_arch = "aarch64"),
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
     not(any(target_os = "android", target_os = "ios"))
 )))]
+pub fn detected_backend() -> Backend {
+    STATIC_BACKEND.unwrap_or(Backend::Scalar)
+}
+
+/// Returns the backend picked by auto-detection.
+#[cfg(all(
+    feature = "simd-accel",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
+    not(any(target_os = "android", target_os = "ios"))
+))]
+pub fn detected_backend() -> Backend {
+    if let Some(backend) = STATIC_BACKEND {
+        return backend;
+    }
+
+    // values of reedsolomon_cpu_support
+    match unsafe { reedsolomon_determine_cpu_support() } {
+        2 | 3 => Backend::Ssse3,
+        4 => Backend::Avx2,
+        5 => Backend::Neon,
+        7 => Backend::Vsx,
+        // the generic C kernels are no faster than the scalar one
+        _ => Backend::Scalar,
+    }
+}
+
+/// Returns the backend in use.
+///
+/// On first use, this is the backend named by the `REED_SOLOMON_BACKEND`
+/// environment variable if it is available, and the detected backend
+/// otherwise. It is always the backend selected at compile time with the
+/// `static-simd` feature.
+pub fn backend() -> Backend {
+    if let Some(backend) = STATIC_BACKEND {
+        return backend;
+    }
+
+    match BACKEND.load(Ordering::Relaxed) {
+        0 => {
+            let backend = Backend::from_env().unwrap_or_else(detected_backend);
+            BACKEND.store(backend as u8 + 1, Ordering::Relaxed);
+            backend
+        }
+        n => Backend::ALL[n as usize - 1],
+    }
+}
+
+/// Forces all codecs of the process to use `backend`, or goes back to the
+/// detected backend if `backend` is `None`.
+///
+/// This is meant for isolating performance regressions or suspected bugs
+/// of a kernel, as all backends compute the same results.
+///
+/// Returns `Error::UnsupportedBackend` if `backend` is not available.
+pub fn set_backend(backend: Option<Backend>) -> Result<(), Error> {
+    let backend = backend.unwrap_or_else(detected_backend);
+    if !backend.is_available() {
+        return Err(Error::UnsupportedBackend);
+    }
+
+    BACKEND.store(backend as u8 + 1, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(not(all(
+    feature = "simd-accel",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
+    not(any(target_os = "android", target_os = "ios"))
+)))]
+#[inline]
 pub fn mul_slice(c: u8, input: &[u8], out: &mut [u8]) {
-    mul_slice_pure_rust(c, input, out);
+    if mul_slice_trivial(c, input, out) {
+        return;
+    }
+    if STATIC_BACKEND.is_some() {
+        return mul_slice_static(c, input, out);
+    }
+
+    mul_slice_swar(c, input, out);
 }
 
 #[cfg(not(all(
     feature = "simd-accel",
-    any(target_arch = "x86_64", target_arch = "aarch64"),
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
     not(any(target_os = "android", target_os = "ios"))
 )))]
+#[inline]
 pub fn mul_slice_xor(c: u8, input: &[u8], out: &mut [u8]) {
-    mul_slice_xor_pure_rust(c, input, out);
+    if mul_slice_xor_trivial(c, input, out) {
+        return;
+    }
+    if STATIC_BACKEND.is_some() {
+        return mul_slice_xor_static(c, input, out);
+    }
+
+    mul_slice_xor_swar(c, input, out);
 }
 
+/// XORs `input` into `out`, i.e. adds the slices in the field.
+///
+/// Uses the same SIMD kernels as `mul_slice_xor` when the `simd-accel`
+/// feature is enabled.
+///
+/// # Panics
+/// Panics if the output slice does not have equal length to the input.
+#[cfg(not(all(
+    feature = "simd-accel",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
+    not(any(target_os = "android", target_os = "ios"))
+)))]
+#[inline]
+pub fn xor_slice(input: &[u8], out: &mut [u8]) {
+    if STATIC_BACKEND.is_some() {
+        // multiplying by one leaves the input as is
+        return mul_slice_xor_static(1, input, out);
+    }
+
+    xor_slice_pure_rust(input, out);
+}
+
+/// Maximum number of inputs `mul_slices_xor` adds in one pass over the
+/// output.
+const FUSED_INPUTS: usize = 4;
+
+/// Multiplies every slice of `inputs` by the element of `elems` at the same
+/// index, XORing the products into `out`.
+///
+/// Unlike `mul_slice_xor` on every input, this reads and writes `out` once
+/// per 4 inputs, except with the SIMD kernels of the `simd-accel` feature,
+/// which take a single input.
+///
+/// # Panics
+/// Panics if `elems` and `inputs` differ in length, or if an input does not
+/// have equal length to the output.
+pub fn mul_slices_xor(elems: &[u8], inputs: &[&[u8]], out: &mut [u8]) {
+    assert_eq!(elems.len(), inputs.len());
+    for input in inputs {
+        assert_eq!(input.len(), out.len());
+    }
+    return_if_empty!(out.len());
+
+    if STATIC_BACKEND.is_none() && backend() != Backend::Scalar {
+        for (&c, input) in elems.iter().zip(inputs) {
+            mul_slice_xor(c, input, out);
+        }
+        return;
+    }
+
+    for (elems, inputs) in elems.chunks(FUSED_INPUTS).zip(inputs.chunks(FUSED_INPUTS)) {
+        let bytes_done = static_mul_chunks(elems, inputs, out, true);
+
+        let mut tails: [&[u8]; FUSED_INPUTS] = [&[]; FUSED_INPUTS];
+        for (tail, input) in tails.iter_mut().zip(inputs) {
+            *tail = &input[bytes_done..];
+        }
+        mul_slices_xor_swar(elems, &tails[..inputs.len()], &mut out[bytes_done..]);
+    }
+}
+
+/// Handles the coefficients 0 and 1 of `mul_slice`, returning whether `c`
+/// was one of them.
+fn mul_slice_trivial(c: u8, input: &[u8], out: &mut [u8]) -> bool {
+    assert_eq!(input.len(), out.len());
+
+    match c {
+        0 => out.iter_mut().for_each(|x| *x = 0),
+        1 => out.copy_from_slice(input),
+        _ => return false,
+    }
+    true
+}
+
+/// Handles the coefficients 0 and 1 of `mul_slice_xor`, returning whether
+/// `c` was one of them.
+fn mul_slice_xor_trivial(c: u8, input: &[u8], out: &mut [u8]) -> bool {
+    assert_eq!(input.len(), out.len());
+
+    match c {
+        0 => {}
+        1 => xor_slice(input, out),
+        _ => return false,
+    }
+    true
+}
+
+const SWAR_LANES: usize = 8;
+
+/// Lowest bit of every byte lane of a `u64`.
+const SWAR_LOW_BITS: u64 = 0x0101_0101_0101_0101;
+
+/// Returns `c * 2^j` broadcast to every byte lane, for `j` in `0..8`.
+///
+/// Multiplication by `c` is linear over GF(2), so `c * x` is the XOR of
+/// the entries for the bits set in `x`.
+fn swar_table(c: u8) -> [u64; 8] {
+    let mut table = [0u64; 8];
+    for (j, lanes) in table.iter_mut().enumerate() {
+        *lanes = u64::from(mul(c, 1 << j)) * SWAR_LOW_BITS;
+    }
+    table
+}
+
+/// Multiplies the 8 byte lanes of `x` by the constant of `table`.
+#[inline(always)]
+fn swar_mul(table: &[u64; 8], x: u64) -> u64 {
+    let mut acc = 0;
+    for (j, &lanes) in table.iter().enumerate() {
+        // all ones in the lanes where bit `j` is set
+        let mask = ((x >> j) & SWAR_LOW_BITS) * 0xFF;
+        acc ^= mask & lanes;
+    }
+    acc
+}
+
+/// Scalar `mul_slice` processing 8 bytes per iteration in the lanes
+/// of a `u64`, without table lookups.
+fn mul_slice_swar(c: u8, input: &[u8], out: &mut [u8]) {
+    assert_eq!(input.len(), out.len());
+
+    let table = swar_table(c);
+
+    let mut input_chunks = input.chunks_exact(SWAR_LANES);
+    let mut out_chunks = out.chunks_exact_mut(SWAR_LANES);
+    for (i, o) in (&mut input_chunks).zip(&mut out_chunks) {
+        let x = u64::from_le_bytes(i.try_into().expect("chunk of 8 bytes; qed"));
+        o.copy_from_slice(&swar_mul(&table, x).to_le_bytes());
+    }
+
+    mul_slice_pure_rust(c, input_chunks.remainder(), out_chunks.into_remainder());
+}
+
+/// Scalar `mul_slice_xor` processing 8 bytes per iteration in the lanes
+/// of a `u64`, without table lookups.
+fn mul_slice_xor_swar(c: u8, input: &[u8], out: &mut [u8]) {
+    assert_eq!(input.len(), out.len());
+
+    let table = swar_table(c);
+
+    let mut input_chunks = input.chunks_exact(SWAR_LANES);
+    let mut out_chunks = out.chunks_exact_mut(SWAR_LANES);
+    for (i, o) in (&mut input_chunks).zip(&mut out_chunks) {
+        let x = u64::from_le_bytes(i.try_into().expect("chunk of 8 bytes; qed"));
+        let y = u64::from_le_bytes((&*o).try_into().expect("chunk of 8 bytes; qed"));
+        o.copy_from_slice(&(y ^ swar_mul(&table, x)).to_le_bytes());
+    }
+
+    mul_slice_xor_pure_rust(c, input_chunks.remainder(), out_chunks.into_remainder());
+}
+
+/// Scalar `mul_slices_xor` of at most `FUSED_INPUTS` inputs, processing 8
+/// bytes of all inputs per iteration.
+fn mul_slices_xor_swar(elems: &[u8], inputs: &[&[u8]], out: &mut [u8]) {
+    let mut tables = [[0u64; 8]; FUSED_INPUTS];
+    for (table, &c) in tables.iter_mut().zip(elems) {
+        *table = swar_table(c);
+    }
+
+    let len = out.len() - out.len() % SWAR_LANES;
+    for (i_chunk, o) in out[..len].chunks_exact_mut(SWAR_LANES).enumerate() {
+        let start = i_chunk * SWAR_LANES;
+        let mut y = u64::from_le_bytes((&*o).try_into().expect("chunk of 8 bytes; qed"));
+        for (table, input) in tables.iter().zip(inputs) {
+            let i = &input[start..start + SWAR_LANES];
+            y ^= swar_mul(
+                table,
+                u64::from_le_bytes(i.try_into().expect("chunk of 8 bytes; qed")),
+            );
+        }
+        o.copy_from_slice(&y.to_le_bytes());
+    }
+
+    for (&c, input) in elems.iter().zip(inputs) {
+        mul_slice_xor_pure_rust(c, &input[len..], &mut out[len..]);
+    }
+}
+
+#[cfg(not(feature = "small-tables"))]
 fn mul_slice_pure_rust(c: u8, input: &[u8], out: &mut [u8]) {
     let mt = &MUL_TABLE[c as usize];
     let mt_ptr: *const u8 = &mt[0];
@@ -176,6 +583,7 @@ fn mul_slice_pure_rust(c: u8, input: &[u8], out: &mut [u8]) {
      */
 }
 
+#[cfg(not(feature = "small-tables"))]
 fn mul_slice_xor_pure_rust(c: u8, input: &[u8], out: &mut [u8]) {
     let mt = &MUL_TABLE[c as usize];
     let mt_ptr: *const u8 = &mt[0];
@@ -218,8 +626,25 @@ fn mul_slice_xor_pure_rust(c: u8, input: &[u8], out: &mut [u8]) {
      */
 }
 
-#[cfg(test)]
-fn slice_xor(input: &[u8], out: &mut [u8]) {
+#[cfg(feature = "small-tables")]
+fn mul_slice_pure_rust(c: u8, input: &[u8], out: &mut [u8]) {
+    assert_eq!(input.len(), out.len());
+
+    for (o, &i) in out.iter_mut().zip(input.iter()) {
+        *o = mul(c, i);
+    }
+}
+
+#[cfg(feature = "small-tables")]
+fn mul_slice_xor_pure_rust(c: u8, input: &[u8], out: &mut [u8]) {
+    assert_eq!(input.len(), out.len());
+
+    for (o, &i) in out.iter_mut().zip(input.iter()) {
+        *o ^= mul(c, i);
+    }
+}
+
+fn xor_slice_pure_rust(input: &[u8], out: &mut [u8]) {
     assert_eq!(input.len(), out.len());
 
     let len: isize = input.len() as isize;
@@ -260,7 +685,11 @@ fn slice_xor(input: &[u8], out: &mut [u8]) {
 
 #[cfg(all(
     feature = "simd-accel",
-    any(target_arch = "x86_64", target_arch = "aarch64"),
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
     not(any(target_os = "android", target_os = "ios"))
 ))]
 extern "C" {
@@ -279,14 +708,30 @@ extern "C" {
         out: *mut libc::uint8_t,
         len: libc::size_t,
     ) -> libc::size_t;
+
+    fn reedsolomon_determine_cpu_support() -> libc::c_int;
 }
 
 #[cfg(all(
     feature = "simd-accel",
-    any(target_arch = "x86_64", target_arch = "aarch64"),
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
     not(any(target_os = "android", target_os = "ios"))
 ))]
 pub fn mul_slice(c: u8, input: &[u8], out: &mut [u8]) {
+    if mul_slice_trivial(c, input, out) {
+        return;
+    }
+    if STATIC_BACKEND.is_some() {
+        return mul_slice_static(c, input, out);
+    }
+    if backend() == Backend::Scalar {
+        return mul_slice_swar(c, input, out);
+    }
+
     let low: *const libc::uint8_t = &MUL_TABLE_LOW[c as usize][0];
     let high: *const libc::uint8_t = &MUL_TABLE_HIGH[c as usize][0];
 
@@ -299,15 +744,29 @@ pub fn mul_slice(c: u8, input: &[u8], out: &mut [u8]) {
     let bytes_done: usize =
         unsafe { reedsolomon_gal_mul(low, high, input_ptr, out_ptr, size) as usize };
 
-    mul_slice_pure_rust(c, &input[bytes_done..], &mut out[bytes_done..]);
+    mul_slice_swar(c, &input[bytes_done..], &mut out[bytes_done..]);
 }
 
 #[cfg(all(
     feature = "simd-accel",
-    any(target_arch = "x86_64", target_arch = "aarch64"),
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
     not(any(target_os = "android", target_os = "ios"))
 ))]
 pub fn mul_slice_xor(c: u8, input: &[u8], out: &mut [u8]) {
+    if mul_slice_xor_trivial(c, input, out) {
+        return;
+    }
+    if STATIC_BACKEND.is_some() {
+        return mul_slice_xor_static(c, input, out);
+    }
+    if backend() == Backend::Scalar {
+        return mul_slice_xor_swar(c, input, out);
+    }
+
     let low: *const libc::uint8_t = &MUL_TABLE_LOW[c as usize][0];
     let high: *const libc::uint8_t = &MUL_TABLE_HIGH[c as usize][0];
 
@@ -320,7 +779,277 @@ pub fn mul_slice_xor(c: u8, input: &[u8], out: &mut [u8]) {
     let bytes_done: usize =
         unsafe { reedsolomon_gal_mul_xor(low, high, input_ptr, out_ptr, size) as usize };
 
-    mul_slice_xor_pure_rust(c, &input[bytes_done..], &mut out[bytes_done..]);
+    mul_slice_xor_swar(c, &input[bytes_done..], &mut out[bytes_done..]);
+}
+
+/// XORs `input` into `out`, i.e. adds the slices in the field.
+///
+/// Uses the same SIMD kernels as `mul_slice_xor` when the `simd-accel`
+/// feature is enabled.
+///
+/// # Panics
+/// Panics if the output slice does not have equal length to the input.
+#[cfg(all(
+    feature = "simd-accel",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "powerpc64", target_endian = "little")
+    ),
+    not(any(target_os = "android", target_os = "ios"))
+))]
+pub fn xor_slice(input: &[u8], out: &mut [u8]) {
+    if STATIC_BACKEND.is_some() {
+        // multiplying by one leaves the input as is
+        return mul_slice_xor_static(1, input, out);
+    }
+    if backend() == Backend::Scalar {
+        return xor_slice_pure_rust(input, out);
+    }
+
+    // multiplying by one leaves the input as is
+    let low: *const u8 = &MUL_TABLE_LOW[1][0];
+    let high: *const u8 = &MUL_TABLE_HIGH[1][0];
+
+    assert_eq!(input.len(), out.len());
+
+    if input.is_empty() {
+        return;
+    }
+
+    let input_ptr: *const u8 = &input[0];
+    let out_ptr: *mut u8 = &mut out[0];
+    let size: libc::size_t = input.len();
+
+    let bytes_done: usize =
+        unsafe { reedsolomon_gal_mul_xor(low, high, input_ptr, out_ptr, size) as usize };
+
+    xor_slice_pure_rust(&input[bytes_done..], &mut out[bytes_done..]);
+}
+
+// `static-simd` kernels: the multiplication by `c` of every byte is the sum
+// of the products of its low and high nibbles, looked up 16 or 32 bytes at a
+// time with byte shuffles. The products of up to `FUSED_INPUTS` inputs are
+// summed before the output is written.
+
+/// The backend selected at compile time by the `static-simd` feature, from
+/// the target features the crate is built with.
+#[cfg(all(
+    feature = "static-simd",
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "avx2"
+))]
+const STATIC_BACKEND: Option<Backend> = Some(Backend::Avx2);
+
+#[cfg(all(
+    feature = "static-simd",
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "ssse3",
+    not(target_feature = "avx2")
+))]
+const STATIC_BACKEND: Option<Backend> = Some(Backend::Ssse3);
+
+#[cfg(all(
+    feature = "static-simd",
+    target_arch = "aarch64",
+    target_feature = "neon"
+))]
+const STATIC_BACKEND: Option<Backend> = Some(Backend::Neon);
+
+#[cfg(not(all(
+    feature = "static-simd",
+    any(
+        all(
+            any(target_arch = "x86", target_arch = "x86_64"),
+            target_feature = "ssse3"
+        ),
+        all(target_arch = "aarch64", target_feature = "neon")
+    )
+)))]
+const STATIC_BACKEND: Option<Backend> = None;
+
+/// Multiplies the leading multiple of 32 bytes of each of `inputs` by the
+/// element of `elems` at the same index, at most `FUSED_INPUTS` of them, and
+/// writes the sum of the products to `out`, or adds it to `out` if `xor` is
+/// set. Returns the number of bytes done.
+#[cfg(all(
+    feature = "static-simd",
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "avx2"
+))]
+#[inline]
+fn static_mul_chunks(elems: &[u8], inputs: &[&[u8]], out: &mut [u8], xor: bool) -> usize {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    let len = out.len() - out.len() % 32;
+
+    // the target features are enabled for the whole build, and the loads and
+    // stores stay within the chunks
+    unsafe {
+        let mut tables = [(_mm256_setzero_si256(), _mm256_setzero_si256()); FUSED_INPUTS];
+        for (table, &c) in tables.iter_mut().zip(elems) {
+            let low = _mm_loadu_si128(MUL_TABLE_LOW[c as usize].as_ptr() as *const __m128i);
+            let high = _mm_loadu_si128(MUL_TABLE_HIGH[c as usize].as_ptr() as *const __m128i);
+            *table = (
+                _mm256_broadcastsi128_si256(low),
+                _mm256_broadcastsi128_si256(high),
+            );
+        }
+        let mask = _mm256_set1_epi8(0x0F);
+
+        for (i_chunk, o) in out[..len].chunks_exact_mut(32).enumerate() {
+            let start = i_chunk * 32;
+            let mut y = if xor {
+                _mm256_loadu_si256(o.as_ptr() as *const __m256i)
+            } else {
+                _mm256_setzero_si256()
+            };
+            for (&(low, high), input) in tables.iter().zip(inputs) {
+                let x = _mm256_loadu_si256(input[start..].as_ptr() as *const __m256i);
+                let lo = _mm256_and_si256(x, mask);
+                let hi = _mm256_and_si256(_mm256_srli_epi64(x, 4), mask);
+                y = _mm256_xor_si256(y, _mm256_shuffle_epi8(low, lo));
+                y = _mm256_xor_si256(y, _mm256_shuffle_epi8(high, hi));
+            }
+            _mm256_storeu_si256(o.as_mut_ptr() as *mut __m256i, y);
+        }
+    }
+
+    len
+}
+
+/// Multiplies the leading multiple of 16 bytes of each of `inputs` by the
+/// element of `elems` at the same index, at most `FUSED_INPUTS` of them, and
+/// writes the sum of the products to `out`, or adds it to `out` if `xor` is
+/// set. Returns the number of bytes done.
+#[cfg(all(
+    feature = "static-simd",
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "ssse3",
+    not(target_feature = "avx2")
+))]
+#[inline]
+fn static_mul_chunks(elems: &[u8], inputs: &[&[u8]], out: &mut [u8], xor: bool) -> usize {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    let len = out.len() - out.len() % 16;
+
+    // the target features are enabled for the whole build, and the loads and
+    // stores stay within the chunks
+    unsafe {
+        let mut tables = [(_mm_setzero_si128(), _mm_setzero_si128()); FUSED_INPUTS];
+        for (table, &c) in tables.iter_mut().zip(elems) {
+            let low = _mm_loadu_si128(MUL_TABLE_LOW[c as usize].as_ptr() as *const __m128i);
+            let high = _mm_loadu_si128(MUL_TABLE_HIGH[c as usize].as_ptr() as *const __m128i);
+            *table = (low, high);
+        }
+        let mask = _mm_set1_epi8(0x0F);
+
+        for (i_chunk, o) in out[..len].chunks_exact_mut(16).enumerate() {
+            let start = i_chunk * 16;
+            let mut y = if xor {
+                _mm_loadu_si128(o.as_ptr() as *const __m128i)
+            } else {
+                _mm_setzero_si128()
+            };
+            for (&(low, high), input) in tables.iter().zip(inputs) {
+                let x = _mm_loadu_si128(input[start..].as_ptr() as *const __m128i);
+                let lo = _mm_and_si128(x, mask);
+                let hi = _mm_and_si128(_mm_srli_epi64(x, 4), mask);
+                y = _mm_xor_si128(y, _mm_shuffle_epi8(low, lo));
+                y = _mm_xor_si128(y, _mm_shuffle_epi8(high, hi));
+            }
+            _mm_storeu_si128(o.as_mut_ptr() as *mut __m128i, y);
+        }
+    }
+
+    len
+}
+
+/// Multiplies the leading multiple of 16 bytes of each of `inputs` by the
+/// element of `elems` at the same index, at most `FUSED_INPUTS` of them, and
+/// writes the sum of the products to `out`, or adds it to `out` if `xor` is
+/// set. Returns the number of bytes done.
+#[cfg(all(
+    feature = "static-simd",
+    target_arch = "aarch64",
+    target_feature = "neon"
+))]
+#[inline]
+fn static_mul_chunks(elems: &[u8], inputs: &[&[u8]], out: &mut [u8], xor: bool) -> usize {
+    use std::arch::aarch64::*;
+
+    let len = out.len() - out.len() % 16;
+
+    // the target features are enabled for the whole build, and the loads and
+    // stores stay within the chunks
+    unsafe {
+        let mut tables = [(vdupq_n_u8(0), vdupq_n_u8(0)); FUSED_INPUTS];
+        for (table, &c) in tables.iter_mut().zip(elems) {
+            let low = vld1q_u8(MUL_TABLE_LOW[c as usize].as_ptr());
+            let high = vld1q_u8(MUL_TABLE_HIGH[c as usize].as_ptr());
+            *table = (low, high);
+        }
+        let mask = vdupq_n_u8(0x0F);
+
+        for (i_chunk, o) in out[..len].chunks_exact_mut(16).enumerate() {
+            let start = i_chunk * 16;
+            let mut y = if xor {
+                vld1q_u8(o.as_ptr())
+            } else {
+                vdupq_n_u8(0)
+            };
+            for (&(low, high), input) in tables.iter().zip(inputs) {
+                let x = vld1q_u8(input[start..].as_ptr());
+                let lo = vandq_u8(x, mask);
+                let hi = vshrq_n_u8(x, 4);
+                y = veorq_u8(y, vqtbl1q_u8(low, lo));
+                y = veorq_u8(y, vqtbl1q_u8(high, hi));
+            }
+            vst1q_u8(o.as_mut_ptr(), y);
+        }
+    }
+
+    len
+}
+
+#[cfg(not(all(
+    feature = "static-simd",
+    any(
+        all(
+            any(target_arch = "x86", target_arch = "x86_64"),
+            target_feature = "ssse3"
+        ),
+        all(target_arch = "aarch64", target_feature = "neon")
+    )
+)))]
+#[inline]
+fn static_mul_chunks(_elems: &[u8], _inputs: &[&[u8]], _out: &mut [u8], _xor: bool) -> usize {
+    0
+}
+
+/// `mul_slice` with the kernel of `STATIC_BACKEND`.
+#[inline]
+fn mul_slice_static(c: u8, input: &[u8], out: &mut [u8]) {
+    assert_eq!(input.len(), out.len());
+
+    let bytes_done = static_mul_chunks(&[c], &[input], out, false);
+    mul_slice_swar(c, &input[bytes_done..], &mut out[bytes_done..]);
+}
+
+/// `mul_slice_xor` with the kernel of `STATIC_BACKEND`.
+#[inline]
+fn mul_slice_xor_static(c: u8, input: &[u8], out: &mut [u8]) {
+    assert_eq!(input.len(), out.len());
+
+    let bytes_done = static_mul_chunks(&[c], &[input], out, true);
+    mul_slice_xor_swar(c, &input[bytes_done..], &mut out[bytes_done..]);
 }
 
 #[cfg(test)]
@@ -355,6 +1084,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mul_same_as_log_exp() {
+        for a in 0..256 {
+            for b in 0..256 {
+                assert_eq!(mul_log_exp(a as u8, b as u8), mul(a as u8, b as u8));
+            }
+        }
+    }
+
     #[test]
     fn test_associativity() {
         for a in 0..256 {
@@ -556,7 +1294,7 @@ mod tests {
             for i in 0..expect.len() {
                 expect[i] = input[i] ^ output[i];
             }
-            slice_xor(&input, &mut output);
+            xor_slice(&input, &mut output);
             for i in 0..expect.len() {
                 assert_eq!(expect[i], output[i]);
             }
@@ -564,13 +1302,198 @@ mod tests {
             for i in 0..expect.len() {
                 expect[i] = input[i] ^ output[i];
             }
-            slice_xor(&input, &mut output);
+            xor_slice(&input, &mut output);
             for i in 0..expect.len() {
                 assert_eq!(expect[i], output[i]);
             }
         }
     }
 
+    #[test]
+    fn test_xor_slice_any_length() {
+        for len in 0..100 {
+            let mut input = vec![0; len];
+            fill_random(&mut input);
+            let mut output = vec![0; len];
+            fill_random(&mut output);
+
+            let expect: Vec<u8> = input
+                .iter()
+                .zip(output.iter())
+                .map(|(a, b)| a ^ b)
+                .collect();
+
+            xor_slice(&input, &mut output);
+            assert_eq!(expect, output);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_xor_slice_length_mismatch() {
+        xor_slice(&[1, 2, 3], &mut [0; 2]);
+    }
+
+    #[test]
+    fn test_swar_same_as_pure_rust() {
+        for len in (0..40).chain(Some(10_003)) {
+            let c = rand::random::<u8>();
+            let mut input = vec![0; len];
+            fill_random(&mut input);
+            let mut output = vec![0; len];
+            fill_random(&mut output);
+
+            let mut expect = output.clone();
+            let mut actual = output.clone();
+            mul_slice_pure_rust(c, &input, &mut expect);
+            mul_slice_swar(c, &input, &mut actual);
+            assert_eq!(expect, actual);
+
+            let mut expect = output.clone();
+            let mut actual = output;
+            mul_slice_xor_pure_rust(c, &input, &mut expect);
+            mul_slice_xor_swar(c, &input, &mut actual);
+            assert_eq!(expect, actual);
+        }
+    }
+
+    #[test]
+    fn test_trivial_coefficients() {
+        for &c in [0, 1].iter() {
+            let mut input = vec![0; 1003];
+            fill_random(&mut input);
+            let mut output = vec![0; 1003];
+            fill_random(&mut output);
+
+            let mut expect = output.clone();
+            let mut actual = output.clone();
+            mul_slice_pure_rust(c, &input, &mut expect);
+            mul_slice(c, &input, &mut actual);
+            assert_eq!(expect, actual);
+
+            let mut expect = output.clone();
+            let mut actual = output;
+            mul_slice_xor_pure_rust(c, &input, &mut expect);
+            mul_slice_xor(c, &input, &mut actual);
+            assert_eq!(expect, actual);
+        }
+    }
+
+    #[test]
+    fn test_backend_override() {
+        if STATIC_BACKEND.is_some() {
+            // no backend but the static one is available, see
+            // `test_static_backend`
+            return;
+        }
+
+        let detected = detected_backend();
+        assert!(detected.is_available());
+        assert!(Backend::Scalar.is_available());
+        for &b in Backend::ALL.iter() {
+            assert_eq!(Some(b), Backend::from_name(&b.name().to_uppercase()));
+            if b != Backend::Scalar && b != detected {
+                assert!(!b.is_available());
+                assert_eq!(Err(Error::UnsupportedBackend), set_backend(Some(b)));
+            }
+        }
+        assert_eq!(None, Backend::from_name("avx512"));
+
+        let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut expect = vec![0; 1000];
+        mul_slice(7, &input, &mut expect);
+        xor_slice(&input, &mut expect);
+
+        set_backend(Some(Backend::Scalar)).unwrap();
+        assert_eq!(Backend::Scalar, backend());
+        let mut actual = vec![0; 1000];
+        mul_slice(7, &input, &mut actual);
+        xor_slice(&input, &mut actual);
+        assert_eq!(expect, actual);
+
+        set_backend(None).unwrap();
+        assert_eq!(detected, backend());
+    }
+
+    #[test]
+    fn test_mul_slices_xor() {
+        for &len in [0, 1, 7, 8, 9, 31, 32, 33, 1000].iter() {
+            let inputs: Vec<Vec<u8>> = (0..6)
+                .map(|_| {
+                    let mut input = vec![0u8; len];
+                    fill_random(&mut input);
+                    input
+                })
+                .collect();
+            let inputs: Vec<&[u8]> = inputs.iter().map(|input| &input[..]).collect();
+            let elems = [0u8, 1, 2, 0x53, 0xFF, 7];
+
+            for count in 0..=inputs.len() {
+                let mut expect = vec![0u8; len];
+                fill_random(&mut expect);
+                let mut actual = expect.clone();
+
+                for (&c, input) in elems.iter().zip(&inputs[..count]) {
+                    mul_slice_xor_pure_rust(c, input, &mut expect);
+                }
+                mul_slices_xor(&elems[..count], &inputs[..count], &mut actual);
+                assert_eq!(expect, actual);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mul_slices_xor_panics_on_mismatched_lengths() {
+        mul_slices_xor(&[2, 3], &[&[1, 2], &[3]], &mut [0, 0]);
+    }
+
+    #[test]
+    fn test_static_backend() {
+        let expected = match STATIC_BACKEND {
+            Some(b) => b,
+            None => return,
+        };
+
+        assert_eq!(expected, detected_backend());
+        assert_eq!(expected, backend());
+        assert!(!Backend::Scalar.is_available());
+        assert_eq!(
+            Err(Error::UnsupportedBackend),
+            set_backend(Some(Backend::Scalar))
+        );
+        set_backend(None).unwrap();
+        assert_eq!(expected, backend());
+
+        // lengths around the chunks of the kernels, leaving tails for the
+        // scalar kernel
+        for &len in [0, 1, 15, 16, 17, 31, 32, 33, 100, 1000].iter() {
+            let mut input = vec![0u8; len];
+            fill_random(&mut input);
+
+            for &c in [2u8, 7, 0x53, 0xFF].iter() {
+                let mut expect = vec![0u8; len];
+                fill_random(&mut expect);
+                let mut actual = expect.clone();
+
+                mul_slice_xor_swar(c, &input, &mut expect);
+                mul_slice_xor(c, &input, &mut actual);
+                assert_eq!(expect, actual);
+
+                mul_slice_swar(c, &input, &mut expect);
+                mul_slice(c, &input, &mut actual);
+                assert_eq!(expect, actual);
+            }
+
+            let mut expect = vec![0u8; len];
+            fill_random(&mut expect);
+            let mut actual = expect.clone();
+            xor_slice_pure_rust(&input, &mut expect);
+            xor_slice(&input, &mut actual);
+            assert_eq!(expect, actual);
+        }
+    }
+
     #[test]
     fn test_div_a_is_0() {
         assert_eq!(0, div(0, 100));
@@ -0,0 +1,363 @@
+//! The generator matrix of a codec, for auditing the code and for use by
+//! other tools.
+//!
+//! `ReedSolomon::generator_matrix` returns the `(k + m) x k` matrix whose
+//! row `i` holds the coefficients of the data shards in shard `i`, the
+//! first `k` rows being the identity. The matrix can be exported as JSON,
+//! CSV or a binary form, and read back from the binary form, e.g. to check
+//! that a codec still matches a matrix persisted in a manifest.
+//!
+//! `ReedSolomon::decode_matrix_for` likewise returns the decode matrix of
+//! an erasure pattern, and `ReedSolomon::insert_decode_matrix` puts one
+//! built elsewhere into the cache of a codec, e.g. to warm up the codecs of
+//! a fleet with the matrices persisted by another process.
+//!
+//! # Binary form
+//!
+//! All integers are little-endian.
+//!
+//! | Field               | Size                               |
+//! |---------------------|------------------------------------|
+//! | magic `b"RSGM"`     | 4                                  |
+//! | version, `1`        | 1                                  |
+//! | element size `e`    | 1, `1` for GF(2^8), `2` for GF(2^16) |
+//! | matrix kind         | 1, `0` Vandermonde, `1` Cauchy     |
+//! | data shards `k`     | 2                                  |
+//! | parity shards `m`   | 2                                  |
+//! | rows                | `(k + m) * k * e`, row-major       |
+
+use std::collections::hash_map::RandomState;
+use std::convert::TryInto;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::Arc;
+
+use crate::matrix::Matrix;
+use crate::sim::next_random;
+use crate::{galois_16, galois_8, Error, Field, MatrixKind, ReedSolomon};
+
+const MAGIC: &[u8; 4] = b"RSGM";
+
+const VERSION: u8 = 1;
+
+/// Fields whose elements can be exported as integers.
+pub trait ExportField: Field {
+    /// Name of the field, e.g. `"GF(2^8)"`.
+    const NAME: &'static str;
+    /// Size of an element in the binary form.
+    const ELEM_LEN: usize;
+
+    /// Returns the integer representing `elem`.
+    fn to_value(elem: Self::Elem) -> u32;
+
+    /// Returns the element represented by `value`, or `None` if `value`
+    /// is not an element of the field.
+    fn from_value(value: u32) -> Option<Self::Elem>;
+}
+
+impl ExportField for galois_8::Field {
+    const NAME: &'static str = "GF(2^8)";
+    const ELEM_LEN: usize = 1;
+
+    fn to_value(elem: u8) -> u32 {
+        u32::from(elem)
+    }
+
+    fn from_value(value: u32) -> Option<u8> {
+        value.try_into().ok()
+    }
+}
+
+impl ExportField for galois_16::Field {
+    const NAME: &'static str = "GF(2^16)";
+    const ELEM_LEN: usize = 2;
+
+    fn to_value(elem: [u8; 2]) -> u32 {
+        u32::from(u16::from_be_bytes(elem))
+    }
+
+    fn from_value(value: u32) -> Option<[u8; 2]> {
+        let value: u16 = value.try_into().ok()?;
+        Some(value.to_be_bytes())
+    }
+}
+
+/// The generator matrix of a codec.
+#[derive(PartialEq, Debug, Clone)]
+pub struct GeneratorMatrix<F: Field> {
+    matrix_kind: MatrixKind,
+    data_shards: usize,
+    rows: Vec<Vec<F::Elem>>,
+}
+
+impl<F: Field> GeneratorMatrix<F> {
+    pub fn matrix_kind(&self) -> MatrixKind {
+        self.matrix_kind
+    }
+
+    pub fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    pub fn parity_shards(&self) -> usize {
+        self.rows.len() - self.data_shards
+    }
+
+    /// Returns all rows, data rows first.
+    pub fn rows(&self) -> &[Vec<F::Elem>] {
+        &self.rows
+    }
+
+    /// Returns the rows of the parity shards.
+    pub fn parity_rows(&self) -> &[Vec<F::Elem>] {
+        &self.rows[self.data_shards..]
+    }
+}
+
+impl<F: ExportField> GeneratorMatrix<F> {
+    fn kind_name(&self) -> &'static str {
+        match self.matrix_kind {
+            MatrixKind::Vandermonde => "vandermonde",
+            MatrixKind::Cauchy => "cauchy",
+        }
+    }
+
+    fn row_values(row: &[F::Elem]) -> Vec<String> {
+        row.iter().map(|&e| F::to_value(e).to_string()).collect()
+    }
+
+    /// Returns the matrix as a JSON object with the keys `field`,
+    /// `matrix_kind`, `data_shards`, `parity_shards` and `rows`, the rows
+    /// being arrays of integers.
+    pub fn to_json(&self) -> String {
+        let rows: Vec<_> = self
+            .rows
+            .iter()
+            .map(|row| format!("[{}]", Self::row_values(row).join(", ")))
+            .collect();
+
+        format!(
+            concat!(
+                "{{\"field\": \"{}\", \"matrix_kind\": \"{}\", \"data_shards\": {}, ",
+                "\"parity_shards\": {}, \"rows\": [{}]}}"
+            ),
+            F::NAME,
+            self.kind_name(),
+            self.data_shards,
+            self.parity_shards(),
+            rows.join(", ")
+        )
+    }
+
+    /// Returns the rows as CSV, one line of comma-separated integers per row.
+    pub fn to_csv(&self) -> String {
+        self.rows
+            .iter()
+            .map(|row| Self::row_values(row).join(",") + "\n")
+            .collect()
+    }
+
+    /// Returns the binary form of the matrix.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(F::ELEM_LEN as u8);
+        bytes.push(match self.matrix_kind {
+            MatrixKind::Vandermonde => 0,
+            MatrixKind::Cauchy => 1,
+        });
+        bytes.extend_from_slice(&(self.data_shards as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.parity_shards() as u16).to_le_bytes());
+        for &elem in self.rows.iter().flatten() {
+            let value = F::to_value(elem).to_le_bytes();
+            bytes.extend_from_slice(&value[..F::ELEM_LEN]);
+        }
+
+        bytes
+    }
+
+    /// Parses the binary form of a matrix.
+    ///
+    /// Returns an error of kind `InvalidData` if `bytes` is not a matrix
+    /// over `F` of a supported version.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<GeneratorMatrix<F>> {
+        let invalid = || io::Error::from(io::ErrorKind::InvalidData);
+
+        let header_len = MAGIC.len() + 3 + 4;
+        if bytes.len() < header_len
+            || &bytes[..4] != MAGIC
+            || bytes[4] != VERSION
+            || bytes[5] as usize != F::ELEM_LEN
+        {
+            return Err(invalid());
+        }
+        let matrix_kind = match bytes[6] {
+            0 => MatrixKind::Vandermonde,
+            1 => MatrixKind::Cauchy,
+            _ => return Err(invalid()),
+        };
+        let data_shards = u16::from_le_bytes([bytes[7], bytes[8]]) as usize;
+        let parity_shards = u16::from_le_bytes([bytes[9], bytes[10]]) as usize;
+
+        let body = &bytes[header_len..];
+        if data_shards == 0
+            || body.len() != (data_shards + parity_shards) * data_shards * F::ELEM_LEN
+        {
+            return Err(invalid());
+        }
+
+        let mut rows = Vec::with_capacity(data_shards + parity_shards);
+        for row in body.chunks(data_shards * F::ELEM_LEN) {
+            let row = row
+                .chunks(F::ELEM_LEN)
+                .map(|elem| {
+                    let mut value = [0; 4];
+                    value[..F::ELEM_LEN].copy_from_slice(elem);
+                    F::from_value(u32::from_le_bytes(value)).ok_or_else(invalid)
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            rows.push(row);
+        }
+
+        Ok(GeneratorMatrix {
+            matrix_kind,
+            data_shards,
+            rows,
+        })
+    }
+}
+
+impl<F: Field> ReedSolomon<F> {
+    /// Returns the generator matrix of the codec.
+    pub fn generator_matrix(&self) -> GeneratorMatrix<F> {
+        GeneratorMatrix {
+            matrix_kind: self.matrix_kind,
+            data_shards: self.data_shard_count,
+            rows: (0..self.total_shard_count)
+                .map(|r| self.matrix.get_row(r).to_vec())
+                .collect(),
+        }
+    }
+
+    /// Returns the row of the generator matrix of the shard at
+    /// `shard_index`: the coefficients the data shards are multiplied by
+    /// and summed to get the shard. Returns `None` if `shard_index` is not
+    /// a valid shard index.
+    ///
+    /// The row of data shard `i` has a `1` at column `i`, and `0`
+    /// elsewhere.
+    pub fn coefficients(&self, shard_index: usize) -> Option<&[F::Elem]> {
+        if shard_index < self.total_shard_count {
+            Some(self.matrix.get_row(shard_index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the decode matrix of the erasure pattern of the missing
+    /// shards `invalid_indices`, building it and caching it in the codec if
+    /// it is not cached yet.
+    ///
+    /// Row `r` of the `data_shard_count` square matrix holds the
+    /// coefficients of data shard `r` in the first `data_shard_count`
+    /// shards not missing, in index order. This is the matrix reconstruction
+    /// uses, and the matrix `insert_decode_matrix` takes.
+    ///
+    /// Returns `Error::InvalidIndex` if `invalid_indices` is not in strictly
+    /// ascending order, or holds an index not less than
+    /// `total_shard_count`.
+    ///
+    /// Returns `Error::TooFewShardsPresent` if more than
+    /// `parity_shard_count` shards are missing.
+    pub fn decode_matrix_for(&self, invalid_indices: &[usize]) -> Result<Vec<Vec<F::Elem>>, Error> {
+        let valid_indices = self.decode_sources(invalid_indices)?;
+
+        let matrix = self.get_data_decode_matrix(&valid_indices, invalid_indices);
+
+        Ok((0..self.data_shard_count)
+            .map(|r| matrix.get_row(r).to_vec())
+            .collect())
+    }
+
+    /// Caches `rows`, e.g. from `decode_matrix_for` on another codec of the
+    /// same generator matrix, as the decode matrix of the missing shards
+    /// `invalid_indices`, replacing any cached one.
+    ///
+    /// The matrix is checked against the codec with a randomized test, in
+    /// `O(data_shard_count^2)` rather than the `O(data_shard_count^3)` of
+    /// building it. A matrix which does not decode the pattern passes with
+    /// probability at most `1 / F::ORDER^2`.
+    ///
+    /// The identity matrix of no missing shards is kept as it is.
+    ///
+    /// Returns `Error::InvalidDecodeMatrix` if `rows` is not the decode
+    /// matrix of the pattern, and the errors of `decode_matrix_for`.
+    pub fn insert_decode_matrix<T: AsRef<[F::Elem]>>(
+        &self,
+        invalid_indices: &[usize],
+        rows: &[T],
+    ) -> Result<(), Error> {
+        let valid_indices = self.decode_sources(invalid_indices)?;
+
+        let k = self.data_shard_count;
+        if rows.len() != k || rows.iter().any(|row| row.as_ref().len() != k) {
+            return Err(Error::InvalidDecodeMatrix);
+        }
+
+        // Freivalds' test: the decode matrix undoes the encoding rows of
+        // the shards it decodes from, for random vectors
+        let mut state = RandomState::new().build_hasher().finish() | 1;
+        for _ in 0..2 {
+            let x: Vec<F::Elem> = (0..k)
+                .map(|_| F::nth((next_random(&mut state) % F::ORDER as u64) as usize))
+                .collect();
+            let encoded: Vec<F::Elem> = valid_indices
+                .iter()
+                .map(|&i| dot::<F>(self.matrix.get_row(i), &x))
+                .collect();
+            if rows
+                .iter()
+                .zip(x.iter())
+                .any(|(row, &x)| dot::<F>(row.as_ref(), &encoded) != x)
+            {
+                return Err(Error::InvalidDecodeMatrix);
+            }
+        }
+
+        if invalid_indices.is_empty() {
+            return Ok(());
+        }
+        let matrix = Matrix::new_with_data(rows.iter().map(|row| row.as_ref().to_vec()).collect());
+        self.tree
+            .insert_inverted_matrix(invalid_indices, &Arc::new(matrix))
+            .expect("the pattern is not empty and the matrix is square; qed");
+
+        Ok(())
+    }
+
+    /// Returns the shards the decode matrix of the missing shards
+    /// `invalid_indices` decodes from.
+    fn decode_sources(&self, invalid_indices: &[usize]) -> Result<Vec<usize>, Error> {
+        if invalid_indices.windows(2).any(|w| w[0] >= w[1])
+            || invalid_indices.iter().any(|&i| i >= self.total_shard_count)
+        {
+            return Err(Error::InvalidIndex);
+        }
+        if invalid_indices.len() > self.parity_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+
+        Ok((0..self.total_shard_count)
+            .filter(|i| !invalid_indices.contains(i))
+            .take(self.data_shard_count)
+            .collect())
+    }
+}
+
+fn dot<F: Field>(a: &[F::Elem], b: &[F::Elem]) -> F::Elem {
+    a.iter()
+        .zip(b.iter())
+        .fold(F::zero(), |acc, (&a, &b)| F::add(acc, F::mul(a, b)))
+}
@@ -0,0 +1,159 @@
+//! Mapping of objects onto stripes, in the style of object stores such as
+//! MinIO and Ceph.
+//!
+//! An object is cut into blocks of `block_size` bytes, and each block is
+//! encoded as one stripe. The block is split contiguously over the data
+//! shards of the stripe, each shard holding `shard_size` bytes, the final
+//! data shard being padded with zeros. The last block of an object may be
+//! shorter than `block_size`, giving a stripe with shorter shards.
+//!
+//! The shards at the same index of all stripes are stored one after
+//! another in a shard file, so a gateway can translate a range read of the
+//! object into reads of the shard files using `StripeGeometry`.
+
+use std::ops::Range;
+
+use crate::Error;
+
+/// Layout of an object of a given size over stripes.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct StripeGeometry {
+    data_shard_count: usize,
+    block_size: usize,
+    object_size: u64,
+}
+
+impl StripeGeometry {
+    /// Creates the layout of an object of `object_size` bytes, cut into
+    /// blocks of `block_size` bytes encoded over `data_shards` data shards.
+    ///
+    /// Returns `Error::TooFewDataShards` if `data_shards == 0`.
+    ///
+    /// Returns `Error::EmptyShard` if `block_size == 0`.
+    pub fn new(
+        data_shards: usize,
+        block_size: usize,
+        object_size: u64,
+    ) -> Result<StripeGeometry, Error> {
+        if data_shards == 0 {
+            return Err(Error::TooFewDataShards);
+        }
+        if block_size == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        Ok(StripeGeometry {
+            data_shard_count: data_shards,
+            block_size,
+            object_size,
+        })
+    }
+
+    pub fn data_shard_count(&self) -> usize {
+        self.data_shard_count
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn object_size(&self) -> u64 {
+        self.object_size
+    }
+
+    /// Returns the size of the shards of a full stripe.
+    pub fn shard_size(&self) -> usize {
+        self.block_size.div_ceil(self.data_shard_count)
+    }
+
+    /// Returns the number of stripes of the object.
+    pub fn stripe_count(&self) -> usize {
+        self.object_size.div_ceil(self.block_size as u64) as usize
+    }
+
+    /// Returns the number of object bytes held by stripe `stripe`,
+    /// or `None` if the object has no such stripe.
+    pub fn stripe_len(&self, stripe: usize) -> Option<usize> {
+        if stripe >= self.stripe_count() {
+            return None;
+        }
+
+        let start = self.stripe_offset(stripe);
+        Some((self.object_size - start).min(self.block_size as u64) as usize)
+    }
+
+    /// Returns the size of the shards of stripe `stripe`, or `None` if the
+    /// object has no such stripe.
+    ///
+    /// This is `shard_size` for every stripe but possibly the last one.
+    pub fn stripe_shard_size(&self, stripe: usize) -> Option<usize> {
+        self.stripe_len(stripe)
+            .map(|len| len.div_ceil(self.data_shard_count))
+    }
+
+    /// Returns the offset of the first byte of stripe `stripe`
+    /// within the object.
+    pub fn stripe_offset(&self, stripe: usize) -> u64 {
+        stripe as u64 * self.block_size as u64
+    }
+
+    /// Returns the offset of the shards of stripe `stripe` within
+    /// their shard files.
+    pub fn shard_file_offset(&self, stripe: usize) -> u64 {
+        stripe as u64 * self.shard_size() as u64
+    }
+
+    /// Returns the size of every shard file of the object.
+    pub fn shard_file_size(&self) -> u64 {
+        match self.stripe_count() {
+            0 => 0,
+            count => {
+                let last = self
+                    .stripe_shard_size(count - 1)
+                    .expect("the last stripe exists; qed");
+                self.shard_file_offset(count - 1) + last as u64
+            }
+        }
+    }
+
+    /// Translates an offset within the object into the index of the stripe,
+    /// the index of the data shard within the stripe, and the offset within
+    /// that shard.
+    ///
+    /// Returns `Error::InvalidIndex` if `offset` is not below the size of
+    /// the object.
+    pub fn locate(&self, offset: u64) -> Result<(usize, usize, usize), Error> {
+        if offset >= self.object_size {
+            return Err(Error::InvalidIndex);
+        }
+
+        let stripe = (offset / self.block_size as u64) as usize;
+        let in_stripe = (offset % self.block_size as u64) as usize;
+        let shard_size = self
+            .stripe_shard_size(stripe)
+            .expect("offset is within the object; qed");
+
+        Ok((stripe, in_stripe / shard_size, in_stripe % shard_size))
+    }
+
+    /// Returns the stripes holding the object bytes in `offset..offset + len`.
+    ///
+    /// An empty range maps to no stripes.
+    ///
+    /// Returns `Error::InvalidIndex` if the range extends beyond the end
+    /// of the object.
+    pub fn stripes_for_range(&self, offset: u64, len: u64) -> Result<Range<usize>, Error> {
+        let end = offset.checked_add(len).ok_or(Error::InvalidIndex)?;
+        if end > self.object_size {
+            return Err(Error::InvalidIndex);
+        }
+        if len == 0 {
+            return Ok(0..0);
+        }
+
+        let first = (offset / self.block_size as u64) as usize;
+        let last = ((end - 1) / self.block_size as u64) as usize;
+
+        Ok(first..last + 1)
+    }
+}
@@ -0,0 +1,277 @@
+//! Helpers for the erasure coding policies of HDFS.
+//!
+//! HDFS stores a file in block groups. The data of a block group is cut into
+//! cells of `cell_size` bytes, and the cells are laid out round-robin over
+//! `data_units` internal data blocks. Each stripe of `data_units` cells is
+//! encoded into `parity_units` parity cells, which are appended to the
+//! internal parity blocks. The cells of a short final stripe are padded with
+//! zeros for encoding, but the padding is not stored.
+//!
+//! The RS codec of HDFS uses the Cauchy matrix of `ReedSolomon::new_cauchy`
+//! over GF(2^8), so internal blocks produced and repaired with `EcPolicy`
+//! are byte-compatible with the ones stored by HDFS. The `RS-LEGACY`
+//! policies are not supported.
+
+use crate::galois_8::ReedSolomon;
+use crate::Error;
+
+/// An HDFS erasure coding policy of the RS codec.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct EcPolicy {
+    data_units: usize,
+    parity_units: usize,
+    cell_size: usize,
+}
+
+impl EcPolicy {
+    /// The `RS-3-2-64k` policy.
+    pub const RS_3_2_64K: EcPolicy = EcPolicy::new_unchecked(3, 2, 64 * 1024);
+    /// The `RS-6-3-64k` policy.
+    pub const RS_6_3_64K: EcPolicy = EcPolicy::new_unchecked(6, 3, 64 * 1024);
+    /// The `RS-10-4-64k` policy.
+    pub const RS_10_4_64K: EcPolicy = EcPolicy::new_unchecked(10, 4, 64 * 1024);
+    /// The `RS-3-2-1024k` policy.
+    pub const RS_3_2_1024K: EcPolicy = EcPolicy::new_unchecked(3, 2, 1024 * 1024);
+    /// The `RS-6-3-1024k` policy, the default policy of HDFS.
+    pub const RS_6_3_1024K: EcPolicy = EcPolicy::new_unchecked(6, 3, 1024 * 1024);
+    /// The `RS-10-4-1024k` policy.
+    pub const RS_10_4_1024K: EcPolicy = EcPolicy::new_unchecked(10, 4, 1024 * 1024);
+
+    const fn new_unchecked(data_units: usize, parity_units: usize, cell_size: usize) -> EcPolicy {
+        EcPolicy {
+            data_units,
+            parity_units,
+            cell_size,
+        }
+    }
+
+    /// Creates a custom RS policy.
+    ///
+    /// Returns `Error::EmptyShard` if `cell_size == 0`, and the errors
+    /// of `ReedSolomon::new` for invalid unit counts.
+    pub fn new(
+        data_units: usize,
+        parity_units: usize,
+        cell_size: usize,
+    ) -> Result<EcPolicy, Error> {
+        if cell_size == 0 {
+            return Err(Error::EmptyShard);
+        }
+        ReedSolomon::check_shard_counts(data_units, parity_units)?;
+
+        Ok(EcPolicy::new_unchecked(data_units, parity_units, cell_size))
+    }
+
+    /// Returns the name of the policy as used by HDFS, e.g. `RS-6-3-1024k`.
+    pub fn name(&self) -> String {
+        if self.cell_size.is_multiple_of(1024) {
+            format!(
+                "RS-{}-{}-{}k",
+                self.data_units,
+                self.parity_units,
+                self.cell_size / 1024
+            )
+        } else {
+            format!(
+                "RS-{}-{}-{}",
+                self.data_units, self.parity_units, self.cell_size
+            )
+        }
+    }
+
+    /// Returns the number of internal data blocks.
+    pub fn data_units(&self) -> usize {
+        self.data_units
+    }
+
+    /// Returns the number of internal parity blocks.
+    pub fn parity_units(&self) -> usize {
+        self.parity_units
+    }
+
+    /// Returns the size of a cell in bytes.
+    pub fn cell_size(&self) -> usize {
+        self.cell_size
+    }
+
+    /// Creates the codec of the policy.
+    pub fn codec(&self) -> ReedSolomon {
+        ReedSolomon::new_cauchy(self.data_units, self.parity_units)
+            .expect("unit counts are checked on construction; qed")
+    }
+
+    /// Maps an offset within a block group to the index of the internal
+    /// data block holding it, and the offset within that block.
+    pub fn locate(&self, offset: u64) -> (usize, u64) {
+        let cell_size = self.cell_size as u64;
+        let cell = offset / cell_size;
+
+        let block = (cell % self.data_units as u64) as usize;
+        let block_offset = (cell / self.data_units as u64) * cell_size + offset % cell_size;
+
+        (block, block_offset)
+    }
+
+    /// Returns the length of internal block `index` of a block group
+    /// holding `group_len` bytes of data.
+    ///
+    /// Parity blocks are as long as the first data block.
+    ///
+    /// Returns `Error::InvalidIndex` if `index` is not below the total
+    /// number of units.
+    pub fn internal_block_len(&self, group_len: u64, index: usize) -> Result<u64, Error> {
+        if index >= self.data_units + self.parity_units {
+            return Err(Error::InvalidIndex);
+        }
+        let index = if index < self.data_units {
+            index as u64
+        } else {
+            0
+        };
+
+        let cell_size = self.cell_size as u64;
+        let stripe_size = cell_size * self.data_units as u64;
+
+        let full_stripes = group_len / stripe_size;
+        let last_stripe = group_len % stripe_size;
+        let last_cell = last_stripe.saturating_sub(index * cell_size).min(cell_size);
+
+        Ok(full_stripes * cell_size + last_cell)
+    }
+
+    /// Lays out the data of a block group over the internal data blocks,
+    /// and computes the internal parity blocks.
+    ///
+    /// Returns all internal blocks, data blocks first.
+    ///
+    /// Returns `Error::EmptyShard` if `group` is empty, and the errors of
+    /// `check_codec` if `codec` does not match the policy.
+    pub fn encode(&self, codec: &ReedSolomon, group: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        self.check_codec(codec)?;
+        if group.is_empty() {
+            return Err(Error::EmptyShard);
+        }
+
+        let group_len = group.len() as u64;
+        let padded_len = self.internal_block_len(group_len, 0)? as usize;
+
+        let mut blocks = vec![vec![0u8; padded_len]; self.data_units + self.parity_units];
+        for (i, cell) in group.chunks(self.cell_size).enumerate() {
+            let (block, offset) = self.locate((i * self.cell_size) as u64);
+            let offset = offset as usize;
+            blocks[block][offset..offset + cell.len()].copy_from_slice(cell);
+        }
+
+        codec.encode(&mut blocks)?;
+
+        for (i, block) in blocks.iter_mut().enumerate().take(self.data_units) {
+            block.truncate(self.internal_block_len(group_len, i)? as usize);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Reconstructs the missing internal blocks of a block group holding
+    /// `group_len` bytes of data.
+    ///
+    /// Blocks marked `None` are missing. Present blocks must have the
+    /// lengths given by `internal_block_len`, otherwise
+    /// `Error::IncorrectShardSize` is returned.
+    ///
+    /// Returns the errors of `ReedSolomon::reconstruct`, and of `check_codec`
+    /// if `codec` does not match the policy. Nothing is touched on error.
+    pub fn reconstruct(
+        &self,
+        codec: &ReedSolomon,
+        blocks: &mut [Option<Vec<u8>>],
+        group_len: u64,
+    ) -> Result<(), Error> {
+        self.check_codec(codec)?;
+        check_piece_count!(all => codec, blocks);
+        if group_len == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        for (i, block) in blocks.iter().enumerate() {
+            if let Some(ref block) = *block {
+                if block.len() as u64 != self.internal_block_len(group_len, i)? {
+                    return Err(Error::IncorrectShardSize);
+                }
+            }
+        }
+
+        // Short data blocks are padded with zeros, as when encoding.
+        let padded_len = self.internal_block_len(group_len, 0)? as usize;
+        for block in blocks.iter_mut().take(self.data_units).flatten() {
+            block.resize(padded_len, 0);
+        }
+
+        let result = codec.reconstruct(blocks);
+
+        for (i, block) in blocks.iter_mut().enumerate().take(self.data_units) {
+            if let Some(ref mut block) = *block {
+                block.truncate(self.internal_block_len(group_len, i)? as usize);
+            }
+        }
+
+        result
+    }
+
+    /// Reassembles the data of a block group holding `group_len` bytes
+    /// from its internal data blocks.
+    ///
+    /// Returns `Error::TooFewDataShards` or `Error::TooManyDataShards` when
+    /// the number of blocks does not match the number of data units, and
+    /// `Error::IncorrectShardSize` if a block is not of the length given by
+    /// `internal_block_len`.
+    pub fn join<T: AsRef<[u8]>>(&self, blocks: &[T], group_len: u64) -> Result<Vec<u8>, Error> {
+        if blocks.len() < self.data_units {
+            return Err(Error::TooFewDataShards);
+        }
+        if blocks.len() > self.data_units {
+            return Err(Error::TooManyDataShards);
+        }
+        for (i, block) in blocks.iter().enumerate() {
+            if block.as_ref().len() as u64 != self.internal_block_len(group_len, i)? {
+                return Err(Error::IncorrectShardSize);
+            }
+        }
+
+        let mut group = Vec::with_capacity(group_len as usize);
+        let mut offset = 0;
+        while offset < group_len {
+            let (block, block_offset) = self.locate(offset);
+            let block_offset = block_offset as usize;
+            let len = (self.cell_size as u64).min(group_len - offset) as usize;
+
+            group.extend_from_slice(&blocks[block].as_ref()[block_offset..block_offset + len]);
+            offset += len as u64;
+        }
+
+        Ok(group)
+    }
+
+    /// Checks that `codec` has the shape of the policy.
+    ///
+    /// Returns `Error::TooFewDataShards`, `Error::TooManyDataShards`,
+    /// `Error::TooFewParityShards` or `Error::TooManyParityShards` for a
+    /// mismatching shape.
+    ///
+    /// Note that only codecs created with `codec` (or `ReedSolomon::new_cauchy`)
+    /// are compatible with HDFS.
+    pub fn check_codec(&self, codec: &ReedSolomon) -> Result<(), Error> {
+        if codec.data_shard_count() < self.data_units {
+            return Err(Error::TooFewDataShards);
+        }
+        if codec.data_shard_count() > self.data_units {
+            return Err(Error::TooManyDataShards);
+        }
+        if codec.parity_shard_count() < self.parity_units {
+            return Err(Error::TooFewParityShards);
+        }
+        if codec.parity_shard_count() > self.parity_units {
+            return Err(Error::TooManyParityShards);
+        }
+        Ok(())
+    }
+}
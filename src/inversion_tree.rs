@@ -1,3 +1,29 @@
+//! A cache of values keyed by erasure pattern.
+//!
+//! `ReedSolomon` caches the decode matrix of every erasure pattern it has
+//! reconstructed from in an `InversionTree`, so that it is only inverted
+//! once. The tree is usable on its own by decode pipelines built outside
+//! the codec, e.g. to cache the plans of `repair`, or the decode matrices
+//! of a custom code, by the indices of the missing shards.
+//!
+//! An erasure pattern is given as the indices of the missing shards, in
+//! ascending order. The tree has a node per pattern looked up, and a node
+//! only holds the nodes of the patterns it is a prefix of, so a lookup
+//! takes a step per missing shard.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::sync::Arc;
+//! use reed_solomon_erasure::inversion_tree::InversionTree;
+//!
+//! let tree: InversionTree<Vec<u8>> = InversionTree::new(6);
+//! assert_eq!(None, tree.get(&[1, 4]));
+//!
+//! tree.insert(&[1, 4], Arc::new(vec![1, 2, 3])).unwrap();
+//! assert_eq!(Some(Arc::new(vec![1, 2, 3])), tree.get(&[1, 4]));
+//! ```
+
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -5,25 +31,108 @@ use crate::matrix::Matrix;
 use crate::Field;
 
 #[derive(PartialEq, Copy, Clone, Debug)]
-pub enum Error {
+pub(crate) enum Error {
     AlreadySet,
     NotSquare,
 }
 
+/// A cache of values keyed by the erasure patterns of a code of
+/// `total_shard_count` shards, safe to share between threads.
 #[derive(Debug)]
-pub struct InversionTree<F: Field> {
-    pub root: Mutex<InversionNode<F>>,
+pub struct InversionTree<T> {
+    root: Mutex<InversionNode<T>>,
     total_shards: usize,
 }
 
 #[derive(Debug)]
-pub struct InversionNode<F: Field> {
-    pub matrix: Option<Arc<Matrix<F>>>,
-    pub children: Vec<Option<InversionNode<F>>>,
+struct InversionNode<T> {
+    matrix: Option<Arc<T>>,
+    children: Vec<Option<InversionNode<T>>>,
+}
+
+impl<T> InversionTree<T> {
+    /// Creates an empty cache for a code of `total_shards` shards.
+    pub fn new(total_shards: usize) -> InversionTree<T> {
+        InversionTree {
+            root: Mutex::new(InversionNode::new(None, total_shards)),
+            total_shards,
+        }
+    }
+
+    /// Returns the number of shards of the code the cache is for.
+    pub fn total_shard_count(&self) -> usize {
+        self.total_shards
+    }
+
+    /// Returns the value cached for the pattern of the missing shards
+    /// `invalid_indices`, or `None` if there is none.
+    ///
+    /// No value is cached for an invalid pattern, see `insert`.
+    pub fn get(&self, invalid_indices: &[usize]) -> Option<Arc<T>> {
+        if !self.is_pattern(invalid_indices) {
+            return None;
+        }
+
+        self.root
+            .lock()
+            .unwrap()
+            .get_inverted_matrix(invalid_indices, self.total_shards, 0)
+    }
+
+    /// Caches `value` for the pattern of the missing shards
+    /// `invalid_indices`, replacing any value cached for it.
+    ///
+    /// Returns `Error::InvalidIndex` if `invalid_indices` is not in
+    /// strictly ascending order, or holds an index not less than
+    /// `total_shard_count`.
+    pub fn insert(&self, invalid_indices: &[usize], value: Arc<T>) -> Result<(), crate::Error> {
+        if !self.is_pattern(invalid_indices) {
+            return Err(crate::Error::InvalidIndex);
+        }
+
+        // Lock the tree for writing and reading before accessing the tree.
+        // Recursively create nodes for the value in the tree until we reach
+        // the node to insert the value to.  We start by passing in 0 as the
+        // parent index as we start at the root of the tree.
+        self.root.lock().unwrap().insert_inverted_matrix(
+            &value,
+            invalid_indices,
+            self.total_shards,
+            0,
+        );
+
+        Ok(())
+    }
+
+    /// Removes all the cached values.
+    pub fn clear(&self) {
+        *self.root.lock().unwrap() = InversionNode::new(None, self.total_shards);
+    }
+
+    /// Returns the values cached, each with its pattern, the patterns in
+    /// lexicographic order.
+    pub(crate) fn entries(&self) -> Vec<(Vec<usize>, Arc<T>)> {
+        let mut entries = Vec::new();
+        self.root
+            .lock()
+            .unwrap()
+            .collect(0, &mut Vec::new(), &mut entries);
+        entries
+    }
+
+    fn is_pattern(&self, invalid_indices: &[usize]) -> bool {
+        invalid_indices.windows(2).all(|w| w[0] < w[1])
+            && invalid_indices.iter().all(|&i| i < self.total_shards)
+    }
 }
 
-impl<F: Field> InversionTree<F> {
-    pub fn new(data_shards: usize, parity_shards: usize) -> InversionTree<F> {
+impl<F: Field> InversionTree<Matrix<F>> {
+    /// Creates the tree of a codec, caching the identity matrix for the
+    /// pattern with no missing shards.
+    pub(crate) fn with_identity(
+        data_shards: usize,
+        parity_shards: usize,
+    ) -> InversionTree<Matrix<F>> {
         InversionTree {
             root: Mutex::new(InversionNode::new(
                 Some(Arc::new(Matrix::identity(data_shards))),
@@ -33,7 +142,7 @@ impl<F: Field> InversionTree<F> {
         }
     }
 
-    pub fn get_inverted_matrix(&self, invalid_indices: &[usize]) -> Option<Arc<Matrix<F>>> {
+    pub(crate) fn get_inverted_matrix(&self, invalid_indices: &[usize]) -> Option<Arc<Matrix<F>>> {
         if invalid_indices.len() == 0 {
             match self.root.lock().unwrap().matrix {
                 None => panic!(),
@@ -47,7 +156,7 @@ impl<F: Field> InversionTree<F> {
             .get_inverted_matrix(invalid_indices, self.total_shards, 0)
     }
 
-    pub fn insert_inverted_matrix(
+    pub(crate) fn insert_inverted_matrix(
         &self,
         invalid_indices: &[usize],
         matrix: &Arc<Matrix<F>>,
@@ -62,10 +171,6 @@ impl<F: Field> InversionTree<F> {
             return Err(Error::NotSquare);
         }
 
-        // Lock the tree for writing and reading before accessing the tree.
-        // Recursively create nodes for the inverted matrix in the tree until
-        // we reach the node to insert the matrix to.  We start by passing in
-        // 0 as the parent index as we start at the root of the tree.
         self.root.lock().unwrap().insert_inverted_matrix(
             matrix,
             invalid_indices,
@@ -77,8 +182,8 @@ impl<F: Field> InversionTree<F> {
     }
 }
 
-impl<F: Field> InversionNode<F> {
-    pub fn new(matrix: Option<Arc<Matrix<F>>>, children_count: usize) -> InversionNode<F> {
+impl<T> InversionNode<T> {
+    fn new(matrix: Option<Arc<T>>, children_count: usize) -> InversionNode<T> {
         let mut children = Vec::with_capacity(children_count);
         for _ in 0..children_count {
             children.push(None);
@@ -91,7 +196,7 @@ impl<F: Field> InversionNode<F> {
         offset: usize,
         requested_index: usize,
         total_shards: usize,
-    ) -> &'a mut InversionNode<F> {
+    ) -> &'a mut InversionNode<T> {
         let node_index = requested_index - offset;
         {
             let node = &mut self.children[node_index];
@@ -108,12 +213,31 @@ impl<F: Field> InversionNode<F> {
         }
     }
 
-    pub fn get_inverted_matrix(
+    fn collect(
+        &self,
+        offset: usize,
+        invalid_indices: &mut Vec<usize>,
+        entries: &mut Vec<(Vec<usize>, Arc<T>)>,
+    ) {
+        if let Some(ref value) = self.matrix {
+            entries.push((invalid_indices.clone(), Arc::clone(value)));
+        }
+        // child `i` is for the missing shard `offset + i`
+        for (i, child) in self.children.iter().enumerate() {
+            if let Some(ref child) = *child {
+                invalid_indices.push(offset + i);
+                child.collect(offset + i + 1, invalid_indices, entries);
+                invalid_indices.pop();
+            }
+        }
+    }
+
+    fn get_inverted_matrix(
         &mut self,
         invalid_indices: &[usize],
         total_shards: usize,
         offset: usize,
-    ) -> Option<Arc<Matrix<F>>> {
+    ) -> Option<Arc<T>> {
         if invalid_indices.len() == 0 {
             match self.matrix {
                 None => None,
@@ -127,9 +251,9 @@ impl<F: Field> InversionNode<F> {
         }
     }
 
-    pub fn insert_inverted_matrix(
+    fn insert_inverted_matrix(
         &mut self,
-        matrix: &Arc<Matrix<F>>,
+        matrix: &Arc<T>,
         invalid_indices: &[usize],
         total_shards: usize,
         offset: usize,
@@ -176,7 +300,7 @@ mod tests {
 
     #[test]
     fn test_new_inversion_tree() {
-        let tree: InversionTree<galois_8::Field> = InversionTree::new(3, 2);
+        let tree: InversionTree<Matrix<galois_8::Field>> = InversionTree::with_identity(3, 2);
 
         let children = tree.root.lock().unwrap().children.len();
         assert_eq!(5, children);
@@ -187,7 +311,7 @@ mod tests {
 
     #[test]
     fn test_get_inverted_matrix() {
-        let tree: InversionTree<galois_8::Field> = InversionTree::new(3, 2);
+        let tree: InversionTree<Matrix<galois_8::Field>> = InversionTree::with_identity(3, 2);
 
         let matrix = &*tree.get_inverted_matrix(&[]).unwrap();
 
@@ -212,7 +336,7 @@ mod tests {
 
     #[test]
     fn test_insert_inverted_matrix() {
-        let tree: InversionTree<galois_8::Field> = InversionTree::new(3, 2);
+        let tree: InversionTree<Matrix<galois_8::Field>> = InversionTree::with_identity(3, 2);
 
         let matrix = Matrix::new(3, 3);
         let matrix_copy = matrix.clone();
@@ -233,7 +357,7 @@ mod tests {
 
     #[test]
     fn test_double_insert_inverted_matrix() {
-        let tree: InversionTree<galois_8::Field> = InversionTree::new(3, 2);
+        let tree: InversionTree<Matrix<galois_8::Field>> = InversionTree::with_identity(3, 2);
 
         let matrix1 = Matrix::make_random(3);
         let matrix2 = Matrix::make_random(3);
@@ -252,7 +376,7 @@ mod tests {
 
     #[test]
     fn test_extended_inverted_matrix() {
-        let tree: InversionTree<galois_8::Field> = InversionTree::new(10, 3);
+        let tree: InversionTree<Matrix<galois_8::Field>> = InversionTree::with_identity(10, 3);
         let matrix = Matrix::new(10, 10);
         let matrix_copy = matrix.clone();
         let matrix2 = matrix!(
@@ -299,6 +423,38 @@ mod tests {
         assert_eq!(matrix3_copy, *result);
     }
 
+    #[test]
+    fn test_generic_inversion_tree() {
+        let tree: InversionTree<Vec<u8>> = InversionTree::new(5);
+        assert_eq!(5, tree.total_shard_count());
+        assert_eq!(None, tree.get(&[]));
+        assert_eq!(None, tree.get(&[2]));
+
+        tree.insert(&[], Arc::new(vec![0])).unwrap();
+        tree.insert(&[2], Arc::new(vec![2])).unwrap();
+        tree.insert(&[0, 4], Arc::new(vec![0, 4])).unwrap();
+        assert_eq!(Some(Arc::new(vec![0])), tree.get(&[]));
+        assert_eq!(Some(Arc::new(vec![2])), tree.get(&[2]));
+        assert_eq!(Some(Arc::new(vec![0, 4])), tree.get(&[0, 4]));
+        assert_eq!(None, tree.get(&[0]));
+
+        tree.insert(&[2], Arc::new(vec![3])).unwrap();
+        assert_eq!(Some(Arc::new(vec![3])), tree.get(&[2]));
+
+        // not a pattern of missing shards
+        for &indices in &[&[5][..], &[1, 1], &[3, 2]] {
+            assert_eq!(
+                crate::Error::InvalidIndex,
+                tree.insert(indices, Arc::new(vec![])).unwrap_err()
+            );
+            assert_eq!(None, tree.get(indices));
+        }
+
+        tree.clear();
+        assert_eq!(None, tree.get(&[2]));
+        assert_eq!(None, tree.get(&[0, 4]));
+    }
+
     fn make_random_invalid_indices(data_shards: usize, parity_shards: usize) -> Vec<usize> {
         let mut invalid_count = 0;
         let mut res = Vec::new();
@@ -353,7 +509,8 @@ mod tests {
     // inversion tree is functionally the same as a map
     // but more efficient
     fn qc_tree_same_as_hash_map_prop(param: QCTreeTestParam) -> bool {
-        let tree: InversionTree<galois_8::Field> = InversionTree::new(param.data_shards, param.parity_shards);
+        let tree: InversionTree<Matrix<galois_8::Field>> =
+            InversionTree::with_identity(param.data_shards, param.parity_shards);
         let mut map = HashMap::with_capacity(param.matrix_count);
 
         let mut invalid_indices_set = Vec::with_capacity(param.matrix_count);
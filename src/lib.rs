@@ -6,8 +6,10 @@
 //!
 //! You will have to implement error detection separately (e.g. via checksums)
 //! and simply leave out the corrupted shards when attempting to reconstruct
-//! the missing data.
+//! the missing data, or protect the bytes within each shard with the
+//! error correcting code in the `codeword` module.
 #![allow(dead_code)]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 
 #[cfg(test)]
 #[macro_use]
@@ -21,30 +23,115 @@ extern crate smallvec;
 #[cfg(feature = "simd-accel")]
 extern crate libc;
 
+#[cfg(feature = "async")]
+extern crate bytes;
+
+#[cfg(feature = "async")]
+extern crate futures;
+
+#[cfg(feature = "rkyv")]
+extern crate rkyv;
+
+#[cfg(feature = "object_store")]
+extern crate object_store;
+
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::io;
 use std::iter::{self, FromIterator};
-use std::sync::Arc;
+use std::mem::{self, MaybeUninit};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
 
 use smallvec::SmallVec;
 
 #[macro_use]
 mod macros;
 
+mod aligned;
 mod errors;
-mod inversion_tree;
 mod matrix;
+mod pool;
+mod shard_set;
 
 #[cfg(test)]
 mod tests;
 
+pub mod accumulator;
+pub mod calibration;
+pub mod codeword;
+pub mod das;
 pub mod galois_8;
 pub mod galois_16;
-
+pub mod generator;
+pub mod geometry;
+pub mod hdfs;
+pub mod inversion_tree;
+pub mod manifest;
+pub mod placement;
+pub mod product;
+pub mod realtime;
+pub mod registry;
+pub mod repair;
+pub mod sim;
+pub mod stream;
+pub mod throttle;
+pub mod tiny;
+pub mod vectors;
+
+#[cfg(feature = "async")]
+pub mod fetch;
+
+#[cfg(feature = "rkyv")]
+pub mod archive;
+
+#[cfg(feature = "async")]
+pub mod pipeline;
+
+#[cfg(any(feature = "quickcheck", feature = "proptest"))]
+pub mod arbitrary;
+
+#[cfg(feature = "object_store")]
+pub mod object_shards;
+
+#[cfg(feature = "test-support")]
+pub mod faults;
+
+#[cfg(feature = "allocator-api")]
+pub mod allocator;
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod uring;
+
+pub use crate::aligned::{AlignedShard, SHARD_ALIGN};
 pub use crate::errors::Error;
 pub use crate::errors::SBSError;
+pub use crate::shard_set::ShardSet;
 
 use crate::inversion_tree::InversionTree;
 use crate::matrix::Matrix;
 
+/// Per-shard bookkeeping (rows, slices, indices) used while coding.
+///
+/// Up to 32 entries are kept on the stack. With the `wide-codes` feature
+/// up to 128 are, so that wide codes do not spill to the heap on every
+/// call.
+#[cfg(not(feature = "wide-codes"))]
+type ShardVec<T> = SmallVec<[T; 32]>;
+
+/// Per-shard bookkeeping (rows, slices, indices) used while coding.
+///
+/// Up to 128 entries are kept on the stack, as selected by the
+/// `wide-codes` feature.
+#[cfg(feature = "wide-codes")]
+type ShardVec<T> = SmallVec<[T; 128]>;
+
 /// A finite field to perform encoding over.
 pub trait Field: Sized {
     /// The order of the field. This is a limit on the number of shards
@@ -52,7 +139,7 @@ pub trait Field: Sized {
     const ORDER: usize;
 
     /// The representational type of the field.
-    type Elem: Default + Clone + Copy + PartialEq + std::fmt::Debug;
+    type Elem: Default + Clone + Copy + PartialEq + std::fmt::Debug + Send + Sync;
 
     /// Add two elements together.
     fn add(a: Self::Elem, b: Self::Elem) -> Self::Elem;
@@ -96,10 +183,33 @@ pub trait Field: Sized {
     fn mul_slice_add(elem: Self::Elem, input: &[Self::Elem], out: &mut [Self::Elem]) {
         assert_eq!(input.len(), out.len());
 
+        if elem == Self::one() {
+            for (i, o) in input.iter().zip(out) {
+                *o = Self::add(*o, *i)
+            }
+            return;
+        }
+
         for (i, o) in input.iter().zip(out) {
             *o = Self::add(o.clone(), Self::mul(elem.clone(), i.clone())) 
         }
     }
+
+    /// Multiply several slices of elements by an element each, adding all
+    /// results to the corresponding value in `out`.
+    ///
+    /// Fields may override this to add several inputs per pass over `out`.
+    ///
+    /// # Panics
+    /// Panics if `elems` and `inputs` differ in length, or if the output
+    /// slice does not have equal length to an input.
+    fn mul_slices_add(elems: &[Self::Elem], inputs: &[&[Self::Elem]], out: &mut [Self::Elem]) {
+        assert_eq!(elems.len(), inputs.len());
+
+        for (&elem, input) in elems.iter().zip(inputs) {
+            Self::mul_slice_add(elem, input, out);
+        }
+    }
 }
 
 /// Something which might hold a shard.
@@ -113,6 +223,14 @@ pub trait ReconstructShard<F: Field> {
     /// Get a mutable reference to the shard data, returning `None` if uninitialized.
     fn get(&mut self) -> Option<&mut [F::Elem]>;
 
+    /// Get a reference to the shard data, returning `None` if uninitialized.
+    ///
+    /// Reconstruction only reads the shards which are present, through this
+    /// method, so containers of borrowed shards need not copy them.
+    fn get_ref(&mut self) -> Option<&[F::Elem]> {
+        self.get().map(|x| &*x)
+    }
+
     /// Get a mutable reference to the shard data, initializing it to the
     /// given length if it was `None`. Returns an error if initialization fails.
     fn get_or_initialize(&mut self, len: usize) -> Result<&mut [F::Elem], Result<&mut [F::Elem], Error>>;
@@ -141,6 +259,80 @@ impl<F: Field, T: AsRef<[F::Elem]> + AsMut<[F::Elem]> + FromIterator<F::Elem>> R
     }
 }
 
+/// Something which can build the buffer of a missing shard, for use in
+/// `ReedSolomon::reconstruct_alloc`.
+///
+/// Implemented for closures taking the shard length.
+pub trait ShardAllocator<T> {
+    /// Returns a buffer of `len` elements, whose content does not matter.
+    fn allocate(&mut self, len: usize) -> T;
+}
+
+impl<T, A: FnMut(usize) -> T> ShardAllocator<T> for A {
+    fn allocate(&mut self, len: usize) -> T {
+        self(len)
+    }
+}
+
+/// A shard which is borrowed, owned, or missing (`None`).
+///
+/// Reconstructing `CowShard`s leaves the shards which are present as they
+/// are, so borrowed shards stay borrowed, and only the reconstructed shards
+/// are allocated as owned ones.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CowShard<'a, T: Clone>(pub Option<Cow<'a, [T]>>);
+
+impl<'a, T: Clone> CowShard<'a, T> {
+    /// Creates a shard borrowing `shard`.
+    pub fn borrowed(shard: &'a [T]) -> CowShard<'a, T> {
+        CowShard(Some(Cow::Borrowed(shard)))
+    }
+
+    /// Creates a missing shard.
+    pub fn missing() -> CowShard<'a, T> {
+        CowShard(None)
+    }
+
+    /// Consumes the shard, returning the inner `Cow`.
+    pub fn into_inner(self) -> Option<Cow<'a, [T]>> {
+        self.0
+    }
+}
+
+impl<'a, T: Clone> From<Option<Cow<'a, [T]>>> for CowShard<'a, T> {
+    fn from(shard: Option<Cow<'a, [T]>>) -> Self {
+        CowShard(shard)
+    }
+}
+
+impl<'a, F: Field> ReconstructShard<F> for CowShard<'a, F::Elem> {
+    fn len(&self) -> Option<usize> {
+        self.0.as_ref().map(|x| x.len())
+    }
+
+    fn get(&mut self) -> Option<&mut [F::Elem]> {
+        self.0.as_mut().map(|x| &mut x.to_mut()[..])
+    }
+
+    fn get_ref(&mut self) -> Option<&[F::Elem]> {
+        self.0.as_deref()
+    }
+
+    fn get_or_initialize(&mut self, len: usize) -> Result<&mut [F::Elem], Result<&mut [F::Elem], Error>> {
+        let is_some = self.0.is_some();
+        let x = &mut self
+            .0
+            .get_or_insert_with(|| Cow::Owned(vec![F::zero(); len]))
+            .to_mut()[..];
+
+        if is_some {
+            Ok(x)
+        } else {
+            Err(Ok(x))
+        }
+    }
+}
+
 impl<F: Field, T: AsRef<[F::Elem]> + AsMut<[F::Elem]>> ReconstructShard<F> for (T, bool) {
     fn len(&self) -> Option<usize> {
         if !self.1 {
@@ -172,20 +364,214 @@ impl<F: Field, T: AsRef<[F::Elem]> + AsMut<[F::Elem]>> ReconstructShard<F> for (
     }
 }
 
+/// Maximum number of inputs added to an output per pass over it.
+const FUSED_INPUTS: usize = 4;
+
+/// Default number of elements of every shard coded at a time, see
+/// `ParallelParam::cache_chunk_len`.
+const CACHE_CHUNK_LEN: usize = 4096;
+
+/// Number of elements per shard processed at a time by the fused
+/// encode and hash methods, small enough for the chunks of all shards
+/// to stay in cache until they are hashed.
+const HASH_CHUNK_LEN: usize = 4096;
+
+/// The random coefficients of a round of `ReedSolomon::verify_probabilistic`,
+/// one per parity shard, and the same combination of the parity rows.
+type Combination<E> = (ShardVec<E>, ShardVec<E>);
+
+/// Whether the target can spawn threads. `wasm32` without the `atomics`
+/// target feature, e.g. `wasm32-unknown-unknown`, cannot, and the parallel
+/// methods then do all the work on the calling thread.
+const THREADS_SUPPORTED: bool = !cfg!(all(target_arch = "wasm32", not(target_feature = "atomics")));
+
+/// How the parallel methods divide the pieces of work among threads.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum ChunkAssignment {
+    /// Divide the (parity shard, range) pieces evenly among the threads,
+    /// which keeps all threads busy whatever the shape of the code.
+    Interleaved,
+    /// Give every thread one contiguous byte range of all shards.
+    ///
+    /// The ranges are the same on every call, but the threads are not
+    /// pinned, so which thread codes which range may change between calls.
+    ByteRange,
+}
+
+/// Where the parallel methods get their threads from.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum ThreadSource {
+    /// Spawn threads for every call, which exit before the call returns.
+    Scoped,
+    /// Hand the work to a built-in pool of threads, started on first use
+    /// and kept for the life of the process.
+    ///
+    /// This saves spawning threads on every call, which matters for the
+    /// many small calls of e.g. a storage server coding a stripe per
+    /// request.
+    Pool,
+}
+
+impl ThreadSource {
+    /// Calls `f` on every group, each on its own thread, the first group on
+    /// the calling thread, and returns once all calls are done.
+    fn for_each<T, G>(self, groups: &mut [T], f: &G)
+    where
+        T: Send,
+        G: Fn(&mut T) + Sync,
+    {
+        match self {
+            ThreadSource::Scoped => std::thread::scope(|scope| {
+                let mut groups = groups.iter_mut();
+                let first = groups.next();
+                for group in groups {
+                    scope.spawn(move || f(group));
+                }
+                if let Some(group) = first {
+                    f(group);
+                }
+            }),
+            ThreadSource::Pool => pool::for_each(groups, f),
+        }
+    }
+}
+
 /// Parameters for parallelism.
+///
+/// The parallel methods split the work both across parity shards and
+/// across ranges of `bytes_per_encode` elements within each shard, and
+/// share the resulting (shard, range) pieces among `thread_count` threads.
+///
+/// Build one with `new` or `default` and the `with_*` methods. More fields
+/// may be added without a major version bump.
 #[derive(PartialEq, Debug, Clone, Copy)]
+#[non_exhaustive]
 pub struct ParallelParam {
     /// Number of bytes to split the slices into for computations
     /// which can be done in parallel.
     ///
     /// Default is 32768.
     pub bytes_per_encode: usize,
+    /// Maximum number of threads to use.
+    ///
+    /// Default is the available parallelism of the machine. Ignored on
+    /// targets without threads, e.g. `wasm32-unknown-unknown`.
+    pub thread_count: usize,
+    /// How pieces are assigned to threads.
+    ///
+    /// Default is `ChunkAssignment::Interleaved`.
+    pub chunk_assignment: ChunkAssignment,
+    /// Minimum number of output bytes worth handing to a thread.
+    ///
+    /// Fewer threads than `thread_count` are used when there is not enough
+    /// work for all of them, and none are spawned at all for work below
+    /// this size.
+    ///
+    /// Default is 65536.
+    pub min_bytes_per_thread: usize,
+    /// Number of elements of a piece coded from all data shards before
+    /// moving on to the next elements of the piece.
+    ///
+    /// Large pieces would otherwise be read and written once per group of
+    /// data shards, which stalls on memory once the piece does not fit in
+    /// the cache. `0` codes whole pieces at a time.
+    ///
+    /// The methods which do not take a `ParallelParam` use
+    /// `ReedSolomon::cache_chunk_len` instead.
+    ///
+    /// Default is 4096.
+    pub cache_chunk_len: usize,
+    /// Where the threads come from.
+    ///
+    /// Default is `ThreadSource::Scoped`.
+    pub thread_source: ThreadSource,
 }
 
 impl ParallelParam {
     /// Create a new `ParallelParam` with the given split arity.
     pub fn new(bytes_per_encode: usize) -> ParallelParam {
-        ParallelParam { bytes_per_encode }
+        let thread_count = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        ParallelParam {
+            bytes_per_encode,
+            thread_count,
+            chunk_assignment: ChunkAssignment::Interleaved,
+            min_bytes_per_thread: 65536,
+            cache_chunk_len: CACHE_CHUNK_LEN,
+            thread_source: ThreadSource::Scoped,
+        }
+    }
+
+    /// Sets the maximum number of threads to use.
+    pub fn with_thread_count(mut self, thread_count: usize) -> ParallelParam {
+        self.thread_count = thread_count;
+        self
+    }
+
+    /// Sets how pieces are assigned to threads.
+    pub fn with_chunk_assignment(mut self, chunk_assignment: ChunkAssignment) -> ParallelParam {
+        self.chunk_assignment = chunk_assignment;
+        self
+    }
+
+    /// Sets the minimum number of output bytes worth handing to a thread.
+    pub fn with_min_bytes_per_thread(mut self, min_bytes_per_thread: usize) -> ParallelParam {
+        self.min_bytes_per_thread = min_bytes_per_thread;
+        self
+    }
+
+    /// Sets the number of elements of a piece coded from all data shards
+    /// at a time.
+    pub fn with_cache_chunk_len(mut self, cache_chunk_len: usize) -> ParallelParam {
+        self.cache_chunk_len = cache_chunk_len;
+        self
+    }
+
+    /// Sets where the threads come from.
+    pub fn with_thread_source(mut self, thread_source: ThreadSource) -> ParallelParam {
+        self.thread_source = thread_source;
+        self
+    }
+
+    /// Adapts the parameters to coding `output_count` outputs of
+    /// `shard_len` elements.
+    ///
+    /// Limits the threads to the amount of work, then shrinks the pieces
+    /// when they are too few to give every thread at least two.
+    fn adapted(&self, output_count: usize, shard_len: usize) -> ParallelParam {
+        let work = output_count * shard_len;
+
+        let max_threads = if THREADS_SUPPORTED {
+            self.thread_count.max(1)
+        } else {
+            1
+        };
+        let thread_count = (work / self.min_bytes_per_thread.max(1)).clamp(1, max_threads);
+
+        let mut bytes_per_encode = self.bytes_per_encode.max(1);
+        if thread_count > 1 {
+            let chunks_per_output = (thread_count * 2).div_ceil(output_count.max(1));
+            bytes_per_encode = bytes_per_encode
+                .min(shard_len.div_ceil(chunks_per_output))
+                .max(1);
+        }
+
+        ParallelParam {
+            bytes_per_encode,
+            thread_count,
+            ..*self
+        }
+    }
+
+    /// Length of the byte range of each thread with
+    /// `ChunkAssignment::ByteRange`, a multiple of `bytes_per_encode`.
+    fn byte_range_len(&self, shard_len: usize) -> usize {
+        let chunk_len = self.bytes_per_encode.max(1);
+        let chunks = shard_len.div_ceil(chunk_len);
+
+        chunks.div_ceil(self.thread_count.max(1)).max(1) * chunk_len
     }
 }
 
@@ -249,19 +635,108 @@ impl Default for ParallelParam {
 /// ```
 #[derive(PartialEq, Debug)]
 pub struct ShardByShard<'a, F: 'a + Field> {
-    codec: &'a ReedSolomon<F>,
+    codec: SbsCodec<'a, F>,
     cur_input: usize,
+    aborted: bool,
+}
+
+/// A `ShardByShard` holding its codec through an `Arc`, to be stored
+/// without borrowing the codec, e.g. in the state of a connection.
+///
+/// See `ShardByShard::new_owned`.
+pub type OwnedShardByShard<F> = ShardByShard<'static, F>;
+
+/// The codec of a `ShardByShard`.
+#[derive(PartialEq, Debug)]
+enum SbsCodec<'a, F: 'a + Field> {
+    Borrowed(&'a ReedSolomon<F>),
+    Owned(Arc<ReedSolomon<F>>),
+}
+
+impl<'a, F: 'a + Field> std::ops::Deref for SbsCodec<'a, F> {
+    type Target = ReedSolomon<F>;
+
+    fn deref(&self) -> &ReedSolomon<F> {
+        match self {
+            SbsCodec::Borrowed(codec) => codec,
+            SbsCodec::Owned(codec) => codec,
+        }
+    }
+}
+
+/// What a `ShardByShardGuard` does with an incomplete stripe when dropped.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum DropPolicy {
+    /// Panic, unless the thread is already panicking.
+    Panic,
+    /// Complete the parity shards as if the remaining input data shards
+    /// were all zeros, see `ShardByShard::finish`.
+    Finish,
+    /// Reset the bookkeeping and flag the stripe as aborted, see
+    /// `ShardByShard::aborted`.
+    Abort,
 }
 
 impl<'a, F: 'a + Field> ShardByShard<'a, F> {
     /// Creates a new instance of the bookkeeping struct.
     pub fn new(codec: &'a ReedSolomon<F>) -> ShardByShard<'a, F> {
         ShardByShard {
-            codec,
+            codec: SbsCodec::Borrowed(codec),
+            cur_input: 0,
+            aborted: false,
+        }
+    }
+
+    /// Creates a new instance of the bookkeeping struct owning a share of
+    /// `codec`, with the same API as one borrowing its codec.
+    pub fn new_owned(codec: Arc<ReedSolomon<F>>) -> OwnedShardByShard<F> {
+        ShardByShard {
+            codec: SbsCodec::Owned(codec),
             cur_input: 0,
+            aborted: false,
         }
     }
 
+    /// Returns the codec.
+    pub fn codec(&self) -> &ReedSolomon<F> {
+        &self.codec
+    }
+
+    /// Starts encoding a stripe of `shards`, returning a guard which
+    /// handles an incomplete stripe according to `policy` when dropped.
+    ///
+    /// The bookkeeping is reset first, as with `reset`.
+    ///
+    /// Returns `SBSError::LeftoverShards` when there are shards encoded
+    /// but parity shards are not ready to use, and `SBSError::RSError`
+    /// when the shards do not fit the codec.
+    pub fn guard<'s, U>(
+        &'s mut self,
+        shards: &'s mut [U],
+        policy: DropPolicy,
+    ) -> Result<ShardByShardGuard<'s, 'a, F, U>, SBSError>
+    where
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.reset()?;
+        self.sbs_encode_checks(shards)?;
+
+        Ok(ShardByShardGuard {
+            sbs: self,
+            shards,
+            policy,
+        })
+    }
+
+    /// Checks if the last stripe was aborted by a `ShardByShardGuard`
+    /// dropped with `DropPolicy::Abort`.
+    ///
+    /// The flag is cleared by the next call to `encode`, `encode_sep`
+    /// or a reset.
+    pub fn aborted(&self) -> bool {
+        self.aborted
+    }
+
     /// Checks if the parity shards are ready to use.
     pub fn parity_ready(&self) -> bool {
         self.cur_input == self.codec.data_shard_count
@@ -276,10 +751,14 @@ impl<'a, F: 'a + Field> ShardByShard<'a, F> {
     /// but parity shards are not ready to use.
     pub fn reset(&mut self) -> Result<(), SBSError> {
         if self.cur_input > 0 && !self.parity_ready() {
-            return Err(SBSError::LeftoverShards);
+            return Err(SBSError::LeftoverShards {
+                cur_input: self.cur_input,
+                data_shard_count: self.codec.data_shard_count,
+            });
         }
 
         self.cur_input = 0;
+        self.aborted = false;
 
         Ok(())
     }
@@ -287,6 +766,7 @@ impl<'a, F: 'a + Field> ShardByShard<'a, F> {
     /// Resets the bookkeeping data without checking.
     pub fn reset_force(&mut self) {
         self.cur_input = 0;
+        self.aborted = false;
     }
 
     /// Returns the current input shard index.
@@ -294,8 +774,22 @@ impl<'a, F: 'a + Field> ShardByShard<'a, F> {
         self.cur_input
     }
 
+    fn too_many_calls(&self) -> SBSError {
+        SBSError::TooManyCalls {
+            data_shard_count: self.codec.data_shard_count,
+        }
+    }
+
+    fn rs_error(&self, error: Error) -> SBSError {
+        SBSError::RSError {
+            cur_input: self.cur_input,
+            error,
+        }
+    }
+
     fn return_ok_and_incre_cur_input(&mut self) -> Result<(), SBSError> {
         self.cur_input += 1;
+        self.aborted = false;
         Ok(())
     }
 
@@ -311,12 +805,12 @@ impl<'a, F: 'a + Field> ShardByShard<'a, F> {
         };
 
         if self.parity_ready() {
-            return Err(SBSError::TooManyCalls);
+            return Err(self.too_many_calls());
         }
 
-        match internal_checks(self.codec, slices) {
+        match internal_checks(&self.codec, slices) {
             Ok(()) => Ok(()),
-            Err(e) => Err(SBSError::RSError(e)),
+            Err(e) => Err(self.rs_error(e)),
         }
     }
 
@@ -334,12 +828,12 @@ impl<'a, F: 'a + Field> ShardByShard<'a, F> {
         };
 
         if self.parity_ready() {
-            return Err(SBSError::TooManyCalls);
+            return Err(self.too_many_calls());
         }
 
-        match internal_checks(self.codec, data, parity) {
+        match internal_checks(&self.codec, data, parity) {
             Ok(()) => Ok(()),
-            Err(e) => Err(SBSError::RSError(e)),
+            Err(e) => Err(self.rs_error(e)),
         }
     }
 
@@ -377,472 +871,3694 @@ impl<'a, F: 'a + Field> ShardByShard<'a, F> {
 
         self.return_ok_and_incre_cur_input()
     }
+
+    /// Skips the current input data shard, as if it were all zeros,
+    /// leaving it untouched.
+    ///
+    /// This lets a session encode only some of the data shards, e.g. on
+    /// one of the machines sharing the encoding of a stripe, see
+    /// `ReedSolomon::merge_parity`.
+    ///
+    /// Returns `SBSError::TooManyCalls` when all input data shards
+    /// have already been filled in via `encode`
+    pub fn skip<T, U>(&mut self, mut shards: T) -> Result<(), SBSError>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let shards = shards.as_mut();
+        self.sbs_encode_checks(shards)?;
+
+        let parity = &mut shards[self.codec.data_shard_count..];
+        self.skip_parity(parity)
+    }
+
+    /// Skips the current input data shard, as if it were all zeros.
+    ///
+    /// Returns `SBSError::TooManyCalls` when all input data shards
+    /// have already been filled in via `encode_sep`
+    pub fn skip_sep<U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+        &mut self,
+        parity: &mut [U],
+    ) -> Result<(), SBSError> {
+        let internal_checks = |codec: &ReedSolomon<F>, parity: &mut [U]| {
+            check_piece_count!(parity => codec, parity);
+            check_slices!(multi => parity);
+
+            Ok(())
+        };
+
+        if self.parity_ready() {
+            return Err(self.too_many_calls());
+        }
+        internal_checks(&self.codec, parity).map_err(|e| self.rs_error(e))?;
+
+        self.skip_parity(parity)
+    }
+
+    fn skip_parity<U: AsMut<[F::Elem]>>(&mut self, parity: &mut [U]) -> Result<(), SBSError> {
+        // Later data shards are added to the parity shards, which hold
+        // whatever was there before until the first shard is encoded.
+        if self.cur_input == 0 {
+            for shard in parity.iter_mut() {
+                shard.as_mut().iter_mut().for_each(|x| *x = F::zero());
+            }
+        }
+
+        self.return_ok_and_incre_cur_input()
+    }
+
+    /// Completes the parity shards as if the remaining input data shards
+    /// were all zeros, and zero-fills those data shards.
+    ///
+    /// This is useful for the final stripe of a stream, which may
+    /// hold fewer data shards than the codec.
+    ///
+    /// Returns `SBSError::TooManyCalls` when all input data shards
+    /// have already been filled in via `encode`
+    pub fn finish<T, U>(&mut self, mut shards: T) -> Result<(), SBSError>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let shards = shards.as_mut();
+        self.sbs_encode_checks(shards)?;
+
+        let (data, parity) = shards.split_at_mut(self.codec.data_shard_count);
+        for shard in data[self.cur_input..].iter_mut() {
+            shard.as_mut().iter_mut().for_each(|x| *x = F::zero());
+        }
+
+        self.finish_parity(parity)
+    }
+
+    /// Completes the parity shards as if the remaining input data shards
+    /// were all zeros.
+    ///
+    /// Returns `SBSError::TooManyCalls` when all input data shards
+    /// have already been filled in via `encode_sep`
+    pub fn finish_sep<U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+        &mut self,
+        parity: &mut [U],
+    ) -> Result<(), SBSError> {
+        let internal_checks = |codec: &ReedSolomon<F>, parity: &mut [U]| {
+            check_piece_count!(parity => codec, parity);
+            check_slices!(multi => parity);
+
+            Ok(())
+        };
+
+        if self.parity_ready() {
+            return Err(self.too_many_calls());
+        }
+        internal_checks(&self.codec, parity).map_err(|e| self.rs_error(e))?;
+
+        self.finish_parity(parity)
+    }
+
+    fn finish_parity<U: AsMut<[F::Elem]>>(&mut self, parity: &mut [U]) -> Result<(), SBSError> {
+        // Zero data shards add nothing to the parity shards, which only
+        // need clearing if no data shard was encoded into them yet.
+        if self.cur_input == 0 {
+            for shard in parity.iter_mut() {
+                shard.as_mut().iter_mut().for_each(|x| *x = F::zero());
+            }
+        }
+
+        self.cur_input = self.codec.data_shard_count;
+
+        Ok(())
+    }
 }
 
-/// Reed-Solomon erasure code encoder/decoder.
-///
-/// # Common error handling
-///
-/// ## For `encode`, `encode_shards`, `verify`, `verify_shards`, `reconstruct`, `reconstruct_data`, `reconstruct_shards`, `reconstruct_data_shards`
-///
-/// Return `Error::TooFewShards` or `Error::TooManyShards`
-/// when the number of provided shards
-/// does not match the codec's one.
-///
-/// Return `Error::EmptyShard` when the first shard provided is
-/// of zero length.
-///
-/// Return `Error::IncorrectShardSize` when the provided shards
-/// are of different lengths.
-///
-/// ## For `reconstruct`, `reconstruct_data`, `reconstruct_shards`, `reconstruct_data_shards`
-///
-/// Return `Error::TooFewShardsPresent` when there are not
-/// enough shards for reconstruction.
-///
-/// Return `Error::InvalidShardFlags` when the number of flags does not match
-/// the total number of shards.
-///
-/// # Variants of encoding methods
-///
-/// ## `sep`
-///
-/// Methods ending in `_sep` takes an immutable reference to data shards,
-/// and a mutable reference to parity shards.
-///
-/// They are useful as they do not need to borrow the data shards mutably,
-/// and other work that only needs read-only access to data shards can be done
-/// in parallel/concurrently during the encoding.
-///
-/// Following is a table of all the `sep` variants
+/// Magic bytes opening the binary form of a `ShardByShardState`.
+const SBS_STATE_MAGIC: [u8; 4] = *b"RSSB";
+
+const SBS_STATE_VERSION: u8 = 1;
+
+/// The progress of a `ShardByShard` through a stripe, along with the
+/// partial parity shards, so that the stripe can be resumed after a
+/// restart of the process or on another worker.
 ///
-/// | not `sep` | `sep` |
-/// | --- | --- |
-/// | `encode_single` | `encode_single_sep` |
-/// | `encode`        | `encode_sep` |
-///
-/// The `sep` variants do similar checks on the provided data shards and
-/// parity shards.
-///
-/// Return `Error::TooFewDataShards`, `Error::TooManyDataShards`,
-/// `Error::TooFewParityShards`, or `Error::TooManyParityShards` when applicable.
-///
-/// ## `single`
-///
-/// Methods containing `single` facilitate shard by shard encoding, where
-/// the parity shards are partially constructed using one data shard at a time.
-/// See `ShardByShard` struct for more details on how shard by shard encoding
-/// can be useful.
-///
-/// They are prone to **misuse**, and it is recommended to use the `ShardByShard`
-/// bookkeeping struct instead for shard by shard encoding.
-///
-/// The ones that are also `sep` are **ESPECIALLY** prone to **misuse**.
-/// Only use them when you actually need the flexibility.
-///
-/// Following is a table of all the shard by shard variants
-///
-/// | all shards at once | shard by shard |
-/// | --- | --- |
-/// | `encode` | `encode_single` |
-/// | `encode_sep` | `encode_single_sep` |
-///
-/// The `single` variants do similar checks on the provided data shards and parity shards,
-/// and also do index check on `i_data`.
-///
-/// Return `Error::InvalidIndex` if `i_data >= data_shard_count`.
-///
-/// # Encoding behaviour
-/// ## For `encode`
+/// See `ShardByShard::export_state` and `ShardByShard::import_state`.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct ShardByShardState {
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    matrix_kind: MatrixKind,
+    cur_input: usize,
+    parity: Vec<Vec<u8>>,
+}
+
+impl ShardByShardState {
+    /// Returns the index of the next data shard to encode.
+    pub fn cur_input_index(&self) -> usize {
+        self.cur_input
+    }
+
+    /// Returns the partial parity shards.
+    pub fn parity(&self) -> &[Vec<u8>] {
+        &self.parity
+    }
+
+    /// Returns the binary form of the state: the magic bytes `b"RSSB"`, a
+    /// version byte, then the data shard count, the parity shard count, the
+    /// kind of matrix (0 for Vandermonde, 1 for Cauchy), the index of the
+    /// next data shard, the shard length and the parity shards, all
+    /// little-endian, the kind of matrix on 1 byte, the shard length on 8
+    /// bytes and the others on 2 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let shard_len = self.parity.first().map_or(0, Vec::len);
+        let mut bytes = Vec::with_capacity(20 + self.parity.len() * shard_len);
+
+        bytes.extend_from_slice(&SBS_STATE_MAGIC);
+        bytes.push(SBS_STATE_VERSION);
+        bytes.extend_from_slice(&(self.data_shard_count as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.parity_shard_count as u16).to_le_bytes());
+        bytes.push(match self.matrix_kind {
+            MatrixKind::Vandermonde => 0,
+            MatrixKind::Cauchy => 1,
+        });
+        bytes.extend_from_slice(&(self.cur_input as u16).to_le_bytes());
+        bytes.extend_from_slice(&(shard_len as u64).to_le_bytes());
+        for shard in self.parity.iter() {
+            bytes.extend_from_slice(shard);
+        }
+
+        bytes
+    }
+
+    /// Parses the binary form of a state.
+    ///
+    /// Returns an error of kind `InvalidData` if `bytes` is not a state of
+    /// a supported version.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<ShardByShardState> {
+        let mut reader = vectors::ByteReader { bytes };
+
+        if reader.take(4)? != SBS_STATE_MAGIC || reader.take(1)?[0] != SBS_STATE_VERSION {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let data_shard_count = reader.u16()?;
+        let parity_shard_count = reader.u16()?;
+        let matrix_kind = match reader.take(1)?[0] {
+            0 => MatrixKind::Vandermonde,
+            1 => MatrixKind::Cauchy,
+            _ => return Err(io::ErrorKind::InvalidData.into()),
+        };
+        let cur_input = reader.u16()?;
+        let shard_len = reader.u64()?;
+        if data_shard_count == 0
+            || parity_shard_count == 0
+            || cur_input > data_shard_count
+            || shard_len == 0
+            || shard_len.saturating_mul(parity_shard_count as u64) != reader.bytes.len() as u64
+        {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let parity = reader
+            .bytes
+            .chunks(shard_len as usize)
+            .map(<[u8]>::to_vec)
+            .collect();
+
+        Ok(ShardByShardState {
+            data_shard_count,
+            parity_shard_count,
+            matrix_kind,
+            cur_input,
+            parity,
+        })
+    }
+}
+
+impl<'a, F: 'a + Field<Elem = u8>> ShardByShard<'a, F> {
+    /// Exports the bookkeeping along with `parity`, the partial parity
+    /// shards of the stripe being encoded.
+    ///
+    /// Returns `SBSError::RSError` when the parity shards do not fit the
+    /// codec.
+    pub fn export_state<U: AsRef<[u8]>>(
+        &self,
+        parity: &[U],
+    ) -> Result<ShardByShardState, SBSError> {
+        let internal_checks = |codec: &ReedSolomon<F>, parity: &[U]| {
+            check_piece_count!(parity => codec, parity);
+            check_slices!(multi => parity);
+
+            Ok(())
+        };
+        internal_checks(&self.codec, parity).map_err(|e| self.rs_error(e))?;
+
+        Ok(ShardByShardState {
+            data_shard_count: self.codec.data_shard_count,
+            parity_shard_count: self.codec.parity_shard_count,
+            matrix_kind: self.codec.matrix_kind,
+            cur_input: self.cur_input,
+            parity: parity.iter().map(|shard| shard.as_ref().to_vec()).collect(),
+        })
+    }
+
+    /// Resumes the stripe of `state`, copying its partial parity shards
+    /// into `parity`. The next data shard to encode is then that of the
+    /// exported bookkeeping.
+    ///
+    /// The bookkeeping is reset first, as with `reset`.
+    ///
+    /// Returns `SBSError::LeftoverShards` when there are shards encoded
+    /// but parity shards are not ready to use, and `SBSError::RSError` when
+    /// the parity shards do not fit the codec or the shard length of
+    /// `state`, with `Error::InvalidCheckpoint` if `state` was exported
+    /// with another codec.
+    pub fn import_state<U: AsMut<[u8]>>(
+        &mut self,
+        state: &ShardByShardState,
+        parity: &mut [U],
+    ) -> Result<(), SBSError> {
+        self.reset()?;
+
+        let internal_checks = |codec: &ReedSolomon<F>, parity: &mut [U]| {
+            if state.data_shard_count != codec.data_shard_count
+                || state.parity_shard_count != codec.parity_shard_count
+                || state.matrix_kind != codec.matrix_kind
+            {
+                return Err(Error::InvalidCheckpoint);
+            }
+            check_piece_count!(parity => codec, parity);
+            for shard in parity.iter_mut() {
+                if shard.as_mut().len() != state.parity[0].len() {
+                    return Err(Error::IncorrectShardSize);
+                }
+            }
+
+            Ok(())
+        };
+        internal_checks(&self.codec, parity).map_err(|e| self.rs_error(e))?;
+
+        for (shard, saved) in parity.iter_mut().zip(state.parity.iter()) {
+            shard.as_mut().copy_from_slice(saved);
+        }
+        self.cur_input = state.cur_input;
+
+        Ok(())
+    }
+}
+
+/// Guard over the encoding of a single stripe with `ShardByShard`.
 ///
-/// You do not need to clear the parity shards beforehand, as the methods
-/// will overwrite them completely.
+/// The guard holds the shards of the stripe, so data shards are filled in
+/// through `shards_mut` between calls to `encode`. If the guard is dropped
+/// before the parity shards are ready, its `DropPolicy` applies, so an
+/// incomplete stripe never goes unnoticed.
 ///
-/// ## For `encode_single`, `encode_single_sep`
+/// See `ShardByShard::guard`.
+#[derive(Debug)]
+pub struct ShardByShardGuard<'s, 'a: 's, F: 'a + Field, U: AsRef<[F::Elem]> + AsMut<[F::Elem]>> {
+    sbs: &'s mut ShardByShard<'a, F>,
+    shards: &'s mut [U],
+    policy: DropPolicy,
+}
+
+impl<'s, 'a: 's, F: 'a + Field, U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>
+    ShardByShardGuard<'s, 'a, F, U>
+{
+    /// Constructs the parity shards partially using the current input data shard.
+    ///
+    /// Returns `SBSError::TooManyCalls` when all input data shards
+    /// have already been filled in via `encode`
+    pub fn encode(&mut self) -> Result<(), SBSError> {
+        self.sbs.encode(&mut *self.shards)
+    }
+
+    /// Completes the parity shards as if the remaining input data shards
+    /// were all zeros, see `ShardByShard::finish`.
+    ///
+    /// Does nothing if the parity shards are already ready. If the shards
+    /// no longer fit the codec, the stripe is aborted as with
+    /// `DropPolicy::Abort`.
+    pub fn finish(mut self) -> Result<(), SBSError> {
+        let result = self.finish_if_incomplete();
+        if result.is_err() {
+            self.policy = DropPolicy::Abort;
+        }
+        result
+    }
+
+    /// Checks if the parity shards are ready to use.
+    pub fn parity_ready(&self) -> bool {
+        self.sbs.parity_ready()
+    }
+
+    /// Returns the current input shard index.
+    pub fn cur_input_index(&self) -> usize {
+        self.sbs.cur_input_index()
+    }
+
+    /// Returns the shards of the stripe.
+    pub fn shards(&self) -> &[U] {
+        self.shards
+    }
+
+    /// Returns the shards of the stripe, e.g. to fill in the next data shard.
+    pub fn shards_mut(&mut self) -> &mut [U] {
+        self.shards
+    }
+
+    fn finish_if_incomplete(&mut self) -> Result<(), SBSError> {
+        if self.sbs.parity_ready() {
+            return Ok(());
+        }
+        self.sbs.finish(&mut *self.shards)
+    }
+}
+
+impl<'s, 'a: 's, F: 'a + Field, U: AsRef<[F::Elem]> + AsMut<[F::Elem]>> Drop
+    for ShardByShardGuard<'s, 'a, F, U>
+{
+    fn drop(&mut self) {
+        if self.sbs.parity_ready() {
+            return;
+        }
+
+        match self.policy {
+            DropPolicy::Panic => {
+                if !std::thread::panicking() {
+                    panic!("ShardByShardGuard dropped before the parity shards are ready");
+                }
+            }
+            DropPolicy::Finish => {
+                // The shards were checked when the guard was created.
+                let _ = self.finish_if_incomplete();
+            }
+            DropPolicy::Abort => {
+                self.sbs.reset_force();
+                self.sbs.aborted = true;
+            }
+        }
+    }
+}
+
+/// Bookkeeping of the shard by shard encoding of a stripe, shared by the
+/// threads of a pipelined producer.
 ///
-/// Calling them with `i_data` being `0` will overwrite the parity shards
-/// completely. If you are using the methods correctly, then you do not need
-/// to clear the parity shards beforehand.
+/// Unlike `ShardByShard`, the parity shards are held by the bookkeeping,
+/// and data shards are encoded through `&self` in any order, each by its
+/// index, so one thread can encode data shard `i` while another prepares
+/// data shard `i + 1`. As the code is linear, the order of the data shards
+/// does not change the parity shards.
 ///
-/// # Variants of verifying methods
+/// Each parity shard sits behind its own lock, held while one data shard
+/// is added to it, so the work on each parity shard is serialized: at most
+/// one thread adds to a given parity shard at a time. Concurrent calls of
+/// `encode` go through the parity shards in the same order, following one
+/// another, so no more threads than there are parity shards make progress
+/// at once, and the others wait on the locks.
 ///
-/// `verify` allocate sa buffer on the heap of the same size
-/// as the parity shards, and encode the input once using the buffer to store
-/// the computed parity shards, then check if the provided parity shards
-/// match the computed ones.
+/// The count of encoded data shards is atomic: once `parity_ready` returns
+/// `true`, all data shards are in the parity shards, which `copy_parity`
+/// then hands out. Resetting takes `&mut self`, so it cannot race with
+/// encoding.
 ///
-/// `verify_with_buffer`, allows you to provide
-/// the buffer to avoid making heap allocation(s) for the buffer in every call.
+/// # Example
 ///
-/// The `with_buffer` variants also guarantee that the buffer contains the correct
-/// parity shards if the result is `Ok(_)` (i.e. it does not matter whether the
-/// verification passed or not, as long as the result is not an error, the buffer
-/// will contain the correct parity shards after the call).
+/// ```
+/// use reed_solomon_erasure::galois_8::{ReedSolomon, SyncShardByShard};
+/// let r = ReedSolomon::new(3, 2).unwrap();
 ///
-/// Following is a table of all the `with_buffer` variants
+/// let sbs = SyncShardByShard::new(&r, 4);
+/// let data = [[0u8, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]];
 ///
-/// | not `with_buffer` | `with_buffer` |
-/// | --- | --- |
-/// | `verify` | `verify_with_buffer` |
+/// std::thread::scope(|s| {
+///     for (i, shard) in data.iter().enumerate() {
+///         let sbs = &sbs;
+///         s.spawn(move || sbs.encode(i, shard).unwrap());
+///     }
+/// });
 ///
-/// The `with_buffer` variants also check the dimensions of the buffer and return
-/// `Error::TooFewBufferShards`, `Error::TooManyBufferShards`, `Error::EmptyShard`,
-/// or `Error::IncorrectShardSize` when applicable.
+/// assert!(sbs.parity_ready());
+/// let mut parity = vec![vec![0u8; 4]; 2];
+/// sbs.copy_parity(&mut parity).unwrap();
 ///
+/// let mut shards: Vec<Vec<u8>> = data.iter().map(|shard| shard.to_vec()).collect();
+/// shards.extend(parity);
+/// assert!(r.verify(&shards).unwrap());
+/// ```
 #[derive(Debug)]
-pub struct ReedSolomon<F: Field> {
-    data_shard_count: usize,
-    parity_shard_count: usize,
-    total_shard_count: usize,
-    matrix: Matrix<F>,
-    tree: InversionTree<F>,
+pub struct SyncShardByShard<'a, F: 'a + Field> {
+    codec: SbsCodec<'a, F>,
+    shard_len: usize,
+    parity: Vec<Mutex<Vec<F::Elem>>>,
+    // set once the data shard of the same index is claimed by a call
+    claimed: Vec<AtomicBool>,
+    encoded: AtomicUsize,
 }
 
-impl<F: Field> Clone for ReedSolomon<F> {
-    fn clone(&self) -> ReedSolomon<F> {
-        ReedSolomon::new(
-            self.data_shard_count,
-            self.parity_shard_count,
-        )
-        .expect("basic checks already passed as precondition of existence of self")
+impl<'a, F: 'a + Field> SyncShardByShard<'a, F> {
+    /// Creates a new instance of the bookkeeping struct, for shards of
+    /// `shard_len` elements.
+    pub fn new(codec: &'a ReedSolomon<F>, shard_len: usize) -> SyncShardByShard<'a, F> {
+        Self::with_codec(SbsCodec::Borrowed(codec), shard_len)
     }
-}
 
-impl<F: Field> PartialEq for ReedSolomon<F> {
-    fn eq(&self, rhs: &ReedSolomon<F>) -> bool {
-        self.data_shard_count == rhs.data_shard_count
-            && self.parity_shard_count == rhs.parity_shard_count
+    /// Creates a new instance of the bookkeeping struct owning a share of
+    /// `codec`, for shards of `shard_len` elements.
+    pub fn new_owned(codec: Arc<ReedSolomon<F>>, shard_len: usize) -> SyncShardByShard<'static, F> {
+        SyncShardByShard::with_codec(SbsCodec::Owned(codec), shard_len)
     }
-}
-
-impl<F: Field> ReedSolomon<F> {
-    // AUDIT
-    //
-    // Error detection responsibilities
-    //
-    // Terminologies and symbols:
-    //   X =A, B, C=> Y: X delegates error checking responsibilities A, B, C to Y
-    //   X:= A, B, C: X needs to handle responsibilities A, B, C
-    //
-    // Encode methods
-    //
-    // `encode_single`:=
-    //   - check index `i_data` within range [0, data shard count)
-    //   - check length of `slices` matches total shard count exactly
-    //   - check consistency of length of individual slices
-    // `encode_single_sep`:=
-    //   - check index `i_data` within range [0, data shard count)
-    //   - check length of `parity` matches parity shard count exactly
-    //   - check consistency of length of individual parity slices
-    //   - check length of `single_data` matches length of first parity slice
-    // `encode`:=
-    //   - check length of `slices` matches total shard count exactly
-    //   - check consistency of length of individual slices
-    // `encode_sep`:=
-    //   - check length of `data` matches data shard count exactly
-    //   - check length of `parity` matches parity shard count exactly
-    //   - check consistency of length of individual data slices
-    //   - check consistency of length of individual parity slices
-    //   - check length of first parity slice matches length of first data slice
-    //
-    // Verify methods
-    //
-    // `verify`:=
-    //   - check length of `slices` matches total shard count exactly
-    //   - check consistency of length of individual slices
-    //
-    //   Generates buffer then passes control to verify_with_buffer
-    //
-    // `verify_with_buffer`:=
-    //   - check length of `slices` matches total shard count exactly
-    //   - check length of `buffer` matches parity shard count exactly
-    //   - check consistency of length of individual slices
-    //   - check consistency of length of individual slices in buffer
-    //   - check length of first slice in buffer matches length of first slice
-    //
-    // Reconstruct methods
-    //
-    // `reconstruct` =ALL=> `reconstruct_internal`
-    // `reconstruct_data`=ALL=> `reconstruct_internal`
-    // `reconstruct_internal`:=
-    //   - check length of `slices` matches total shard count exactly
-    //   - check consistency of length of individual slices
-    //   - check length of `slice_present` matches length of `slices`
 
-    fn get_parity_rows(&self) -> SmallVec<[&[F::Elem]; 32]> {
-        let mut parity_rows = SmallVec::with_capacity(self.parity_shard_count);
-        let matrix = &self.matrix;
-        for i in self.data_shard_count..self.total_shard_count {
-            parity_rows.push(matrix.get_row(i));
+    fn with_codec(codec: SbsCodec<'a, F>, shard_len: usize) -> SyncShardByShard<'a, F> {
+        SyncShardByShard {
+            parity: (0..codec.parity_shard_count)
+                .map(|_| Mutex::new(vec![F::zero(); shard_len]))
+                .collect(),
+            claimed: (0..codec.data_shard_count)
+                .map(|_| AtomicBool::new(false))
+                .collect(),
+            encoded: AtomicUsize::new(0),
+            codec,
+            shard_len,
         }
+    }
 
-        parity_rows
+    /// Returns the codec.
+    pub fn codec(&self) -> &ReedSolomon<F> {
+        &self.codec
     }
 
-    fn build_matrix(data_shards: usize, total_shards: usize) -> Matrix<F> {
-        let vandermonde = Matrix::vandermonde(total_shards, data_shards);
+    /// Returns the length of the shards.
+    pub fn shard_len(&self) -> usize {
+        self.shard_len
+    }
 
-        let top = vandermonde.sub_matrix(0, 0, data_shards, data_shards);
+    /// Returns the number of data shards encoded or skipped so far.
+    ///
+    /// Data shards being encoded by other threads are not counted until
+    /// they are done.
+    pub fn encoded_count(&self) -> usize {
+        self.encoded.load(Ordering::Acquire)
+    }
 
-        vandermonde.multiply(&top.invert().unwrap())
+    /// Checks if the parity shards are ready to use.
+    pub fn parity_ready(&self) -> bool {
+        self.encoded_count() == self.codec.data_shard_count
     }
 
-    /// Creates a new instance of Reed-Solomon erasure code encoder/decoder.
+    fn rs_error(&self, error: Error) -> SBSError {
+        SBSError::RSError {
+            cur_input: self.encoded_count(),
+            error,
+        }
+    }
+
+    fn claim(&self, i_data: usize) -> Result<(), SBSError> {
+        match self.claimed.get(i_data) {
+            Some(claimed) if !claimed.swap(true, Ordering::AcqRel) => Ok(()),
+            _ => Err(self.rs_error(Error::InvalidIndex)),
+        }
+    }
+
+    /// Adds data shard `i_data` to the parity shards, one at a time, each
+    /// while holding its lock.
     ///
-    /// Returns `Error::TooFewDataShards` if `data_shards == 0`.
+    /// Returns `SBSError::RSError` with `Error::InvalidIndex` if `i_data`
+    /// is not the index of a data shard, or if that data shard was already
+    /// encoded or skipped, and with `Error::IncorrectShardSize` if `data`
+    /// is not `shard_len` elements long.
+    pub fn encode(&self, i_data: usize, data: &[F::Elem]) -> Result<(), SBSError> {
+        if data.len() != self.shard_len {
+            return Err(self.rs_error(Error::IncorrectShardSize));
+        }
+        self.claim(i_data)?;
+
+        let parity_rows = self.codec.get_parity_rows();
+        for (row, parity) in parity_rows.iter().zip(self.parity.iter()) {
+            // a panic while holding the lock leaves the shard unusable
+            // anyway, as `encoded` never reaches the data shard count
+            let mut parity = parity.lock().unwrap_or_else(|e| e.into_inner());
+            ReedSolomon::<F>::code_slice(row[i_data], data, &mut parity, false);
+        }
+        ReedSolomon::<F>::count_bytes(&self.codec.counters.bytes_encoded, 1, data.len());
+
+        self.encoded.fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+
+    /// Skips data shard `i_data`, as if it were all zeros.
     ///
-    /// Returns `Error::TooFewParityShards` if `parity_shards == 0`.
+    /// Returns `SBSError::RSError` with `Error::InvalidIndex` if `i_data`
+    /// is not the index of a data shard, or if that data shard was already
+    /// encoded or skipped.
+    pub fn skip(&self, i_data: usize) -> Result<(), SBSError> {
+        self.claim(i_data)?;
+
+        self.encoded.fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+
+    /// Copies the parity shards into `parity`.
+    ///
+    /// Returns `SBSError::LeftoverShards` when the parity shards are not
+    /// ready to use, and `SBSError::RSError` when `parity` does not fit
+    /// the codec.
+    pub fn copy_parity<U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+        &self,
+        parity: &mut [U],
+    ) -> Result<(), SBSError> {
+        let internal_checks = |codec: &ReedSolomon<F>, shard_len: usize, parity: &mut [U]| {
+            check_piece_count!(parity => codec, parity);
+            check_slices!(multi => parity);
+            if parity[0].as_ref().len() != shard_len {
+                return Err(Error::IncorrectShardSize);
+            }
+
+            Ok(())
+        };
+
+        let encoded = self.encoded_count();
+        if encoded < self.codec.data_shard_count {
+            return Err(SBSError::LeftoverShards {
+                cur_input: encoded,
+                data_shard_count: self.codec.data_shard_count,
+            });
+        }
+        internal_checks(&self.codec, self.shard_len, parity).map_err(|e| self.rs_error(e))?;
+
+        for (shard, output) in self.parity.iter().zip(parity.iter_mut()) {
+            let shard = shard.lock().unwrap_or_else(|e| e.into_inner());
+            output.as_mut().copy_from_slice(&shard);
+        }
+
+        Ok(())
+    }
+
+    /// Resets the bookkeeping data and zeros the parity shards.
+    ///
+    /// Returns `SBSError::LeftoverShards` when there are shards encoded
+    /// but parity shards are not ready to use.
+    pub fn reset(&mut self) -> Result<(), SBSError> {
+        let encoded = self.encoded_count();
+        if encoded > 0 && !self.parity_ready() {
+            return Err(SBSError::LeftoverShards {
+                cur_input: encoded,
+                data_shard_count: self.codec.data_shard_count,
+            });
+        }
+
+        self.reset_force();
+
+        Ok(())
+    }
+
+    /// Resets the bookkeeping data and zeros the parity shards without
+    /// checking.
+    pub fn reset_force(&mut self) {
+        for shard in self.parity.iter_mut() {
+            let shard = shard.get_mut().unwrap_or_else(|e| e.into_inner());
+            shard.iter_mut().for_each(|x| *x = F::zero());
+        }
+        for claimed in self.claimed.iter_mut() {
+            *claimed.get_mut() = false;
+        }
+        *self.encoded.get_mut() = 0;
+    }
+}
+
+/// Bookkeeping of the shard by shard encoding of a stripe, adding the data
+/// shards to the parity shards in the background.
+///
+/// Each data shard handed to `submit` is encoded by a job on the built-in
+/// pool of threads of `ThreadSource::Pool`, so the loop receiving the data
+/// shards, e.g. from the network, never waits for the encoding. The data
+/// shards are encoded as with `SyncShardByShard`, in any order.
+///
+/// A `ParityReady` handle, from `parity_ready_handle`, tells when all data
+/// shards of the stripe are encoded. It can be waited for with `wait`, or
+/// awaited, as it is also a `Future`.
+///
+/// # Example
+///
+/// ```
+/// use reed_solomon_erasure::galois_8::{BackgroundShardByShard, ReedSolomon};
+/// use std::sync::Arc;
+///
+/// let r = Arc::new(ReedSolomon::new(3, 2).unwrap());
+/// let data = vec![vec![0u8, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9, 10, 11]];
+///
+/// let sbs = BackgroundShardByShard::new(Arc::clone(&r), 4);
+/// let ready = sbs.parity_ready_handle();
+/// for (i, shard) in data.iter().enumerate() {
+///     sbs.submit(i, shard.clone()).unwrap();
+/// }
+///
+/// ready.wait().unwrap();
+/// let mut parity = vec![vec![0u8; 4]; 2];
+/// sbs.copy_parity(&mut parity).unwrap();
+///
+/// let mut shards = data;
+/// shards.extend(parity);
+/// assert!(r.verify(&shards).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct BackgroundShardByShard<F: 'static + Field> {
+    sbs: Arc<SyncShardByShard<'static, F>>,
+    progress: Arc<BackgroundProgress>,
+}
+
+/// The progress of the jobs of a `BackgroundShardByShard`, shared with its
+/// `ParityReady` handles.
+#[derive(Debug)]
+struct BackgroundProgress {
+    data_shard_count: usize,
+    state: Mutex<BackgroundState>,
+    // notified when the stripe is done
+    done: Condvar,
+}
+
+#[derive(Debug)]
+struct BackgroundState {
+    // counts the stripes started by resets, so that the handles of an
+    // earlier stripe stay ready
+    stripe: u64,
+    pending: usize,
+    encoded: usize,
+    error: Option<SBSError>,
+    wakers: Vec<Waker>,
+}
+
+impl BackgroundState {
+    fn stripe_done(&self, data_shard_count: usize) -> bool {
+        self.pending == 0 && (self.encoded == data_shard_count || self.error.is_some())
+    }
+}
+
+impl BackgroundProgress {
+    fn lock(&self) -> MutexGuard<'_, BackgroundState> {
+        // the state is consistent after every update, so a poisoned lock
+        // still holds a consistent state
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, result: Result<(), SBSError>) {
+        let mut state = self.lock();
+        state.pending -= 1;
+        match result {
+            Ok(()) => state.encoded += 1,
+            Err(e) => {
+                state.error.get_or_insert(e);
+            }
+        }
+        if state.stripe_done(self.data_shard_count) {
+            self.done.notify_all();
+            state.wakers.drain(..).for_each(Waker::wake);
+        }
+    }
+}
+
+impl<F: 'static + Field> BackgroundShardByShard<F> {
+    /// Creates a new instance of the bookkeeping struct, for shards of
+    /// `shard_len` elements.
+    pub fn new(codec: Arc<ReedSolomon<F>>, shard_len: usize) -> BackgroundShardByShard<F> {
+        let data_shard_count = codec.data_shard_count;
+        BackgroundShardByShard {
+            sbs: Arc::new(SyncShardByShard::new_owned(codec, shard_len)),
+            progress: Arc::new(BackgroundProgress {
+                data_shard_count,
+                state: Mutex::new(BackgroundState {
+                    stripe: 0,
+                    pending: 0,
+                    encoded: 0,
+                    error: None,
+                    wakers: Vec::new(),
+                }),
+                done: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Returns the codec.
+    pub fn codec(&self) -> &ReedSolomon<F> {
+        self.sbs.codec()
+    }
+
+    /// Returns the length of the shards.
+    pub fn shard_len(&self) -> usize {
+        self.sbs.shard_len()
+    }
+
+    /// Returns a handle telling when the parity shards of the current
+    /// stripe are ready to use.
+    pub fn parity_ready_handle(&self) -> ParityReady {
+        ParityReady {
+            progress: Arc::clone(&self.progress),
+            stripe: self.progress.lock().stripe,
+        }
+    }
+
+    /// Checks if the parity shards are ready to use.
+    pub fn parity_ready(&self) -> bool {
+        self.sbs.parity_ready()
+    }
+
+    /// Hands data shard `i_data` to a job adding it to the parity shards,
+    /// and returns without waiting for the job.
+    ///
+    /// Returns `SBSError::RSError` with `Error::IncorrectShardSize` if
+    /// `data` is not `shard_len` elements long. The errors of the job,
+    /// e.g. as `i_data` was already submitted, are those of
+    /// `SyncShardByShard::encode`, and are returned by the `ParityReady`
+    /// handles. A job which panics, e.g. in `data.as_ref`, fails with
+    /// `SBSError::JobPanicked`.
+    pub fn submit<T>(&self, i_data: usize, data: T) -> Result<(), SBSError>
+    where
+        T: AsRef<[F::Elem]> + Send + 'static,
+    {
+        if data.as_ref().len() != self.shard_len() {
+            return Err(SBSError::RSError {
+                cur_input: self.sbs.encoded_count(),
+                error: Error::IncorrectShardSize,
+            });
+        }
+
+        self.progress.lock().pending += 1;
+        let sbs = Arc::clone(&self.sbs);
+        let progress = Arc::clone(&self.progress);
+        pool::spawn(move || {
+            // a panic of `data` must neither lose the worker nor leave the
+            // job pending forever
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let result = sbs.encode(i_data, data.as_ref());
+                drop(data);
+                result
+            }))
+            .unwrap_or_else(|_| {
+                Err(SBSError::JobPanicked {
+                    cur_input: sbs.encoded_count(),
+                })
+            });
+            // `reset` takes the bookkeeping back once no job is pending
+            drop(sbs);
+            progress.record(result);
+        });
+
+        Ok(())
+    }
+
+    /// Skips data shard `i_data`, as if it were all zeros.
+    ///
+    /// Returns `SBSError::RSError` with `Error::InvalidIndex` if `i_data`
+    /// is not the index of a data shard, or if that data shard was already
+    /// skipped or encoded.
+    pub fn skip(&self, i_data: usize) -> Result<(), SBSError> {
+        let mut state = self.progress.lock();
+        self.sbs.skip(i_data)?;
+        state.encoded += 1;
+        if state.stripe_done(self.progress.data_shard_count) {
+            self.progress.done.notify_all();
+            state.wakers.drain(..).for_each(Waker::wake);
+        }
+
+        Ok(())
+    }
+
+    /// Copies the parity shards into `parity`.
+    ///
+    /// Returns `SBSError::LeftoverShards` when the parity shards are not
+    /// ready to use, and `SBSError::RSError` when `parity` does not fit
+    /// the codec.
+    pub fn copy_parity<U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+        &self,
+        parity: &mut [U],
+    ) -> Result<(), SBSError> {
+        self.sbs.copy_parity(parity)
+    }
+
+    /// Waits for the submitted jobs, then resets the bookkeeping data and
+    /// zeros the parity shards for the next stripe.
+    ///
+    /// Returns `SBSError::LeftoverShards` when there are shards encoded
+    /// but parity shards are not ready to use.
+    pub fn reset(&mut self) -> Result<(), SBSError> {
+        self.wait_for_jobs();
+        self.sbs_mut().reset()?;
+        self.next_stripe();
+
+        Ok(())
+    }
+
+    /// Waits for the submitted jobs, then resets the bookkeeping data and
+    /// zeros the parity shards without checking.
+    pub fn reset_force(&mut self) {
+        self.wait_for_jobs();
+        self.sbs_mut().reset_force();
+        self.next_stripe();
+    }
+
+    fn wait_for_jobs(&self) {
+        let mut state = self.progress.lock();
+        while state.pending > 0 {
+            state = self
+                .progress
+                .done
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn sbs_mut(&mut self) -> &mut SyncShardByShard<'static, F> {
+        Arc::get_mut(&mut self.sbs)
+            .expect("the jobs drop the bookkeeping before they are done; qed")
+    }
+
+    fn next_stripe(&self) {
+        let mut state = self.progress.lock();
+        state.stripe += 1;
+        state.encoded = 0;
+        state.error = None;
+        // the stripe of the waiting handles is over
+        self.progress.done.notify_all();
+        state.wakers.drain(..).for_each(Waker::wake);
+    }
+}
+
+/// Handle telling when the parity shards of a stripe of a
+/// `BackgroundShardByShard` are ready to use.
+///
+/// The handle is ready once all data shards of the stripe are encoded or
+/// skipped, or once a job failed and no job is left running. The handles
+/// of a stripe ended by a reset are ready as well, the stripe being over.
+///
+/// As a `Future`, the handle resolves to the result of `wait`.
+#[derive(Debug, Clone)]
+pub struct ParityReady {
+    progress: Arc<BackgroundProgress>,
+    stripe: u64,
+}
+
+impl ParityReady {
+    fn result(&self, state: &BackgroundState) -> Option<Result<(), SBSError>> {
+        if state.stripe != self.stripe {
+            return Some(Ok(()));
+        }
+        if !state.stripe_done(self.progress.data_shard_count) {
+            return None;
+        }
+        match state.error {
+            Some(e) => Some(Err(e)),
+            None => Some(Ok(())),
+        }
+    }
+
+    /// Checks if the handle is ready.
+    pub fn is_ready(&self) -> bool {
+        self.result(&self.progress.lock()).is_some()
+    }
+
+    /// Blocks until the handle is ready.
+    ///
+    /// Returns the error of the first job which failed, if any.
+    pub fn wait(&self) -> Result<(), SBSError> {
+        let mut state = self.progress.lock();
+        loop {
+            if let Some(result) = self.result(&state) {
+                return result;
+            }
+            state = self
+                .progress
+                .done
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl Future for ParityReady {
+    type Output = Result<(), SBSError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.progress.lock();
+        match self.result(&state) {
+            Some(result) => Poll::Ready(result),
+            None => {
+                let waker = cx.waker();
+                if !state.wakers.iter().any(|w| w.will_wake(waker)) {
+                    state.wakers.push(waker.clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Source of the ids of codecs, starting at 1 so that 0 means no codec.
+static NEXT_CODEC_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Caller-owned workspace of `ReedSolomon::reconstruct_with_scratch`.
+///
+/// Holds the index arrays of the reconstruction along with the data decode
+/// matrix of the last erasure pattern seen. Once the arrays have grown to
+/// the size of the code and the decode matrix of an erasure pattern is
+/// cached, reconstructing with that pattern does not allocate.
+///
+/// A workspace may be used with any codec, but only caches the decode
+/// matrix of the last codec it was used with.
+#[derive(Debug)]
+pub struct ReconstructScratch<F: Field> {
+    valid_indices: Vec<usize>,
+    invalid_indices: Vec<usize>,
+    // identifies the codec the decode matrix belongs to
+    codec_id: usize,
+    decode_key: Vec<usize>,
+    decode_matrix: Option<Arc<Matrix<F>>>,
+}
+
+impl<F: Field> ReconstructScratch<F> {
+    /// Creates an empty workspace, which grows on first use.
+    pub fn new() -> ReconstructScratch<F> {
+        ReconstructScratch {
+            valid_indices: Vec::new(),
+            invalid_indices: Vec::new(),
+            codec_id: 0,
+            decode_key: Vec::new(),
+            decode_matrix: None,
+        }
+    }
+}
+
+impl<F: Field> Default for ReconstructScratch<F> {
+    fn default() -> Self {
+        ReconstructScratch::new()
+    }
+}
+
+/// How `ReedSolomon::verify_with_policy` compares the parity shards.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum VerifyPolicy {
+    /// Stop at the first mismatching parity shard, as `verify` does.
+    ShortCircuit,
+    /// Compare every element and count the mismatches, rather than stopping
+    /// at the first one.
+    ///
+    /// Only the comparison goes through the whole shards whatever their
+    /// content. Computing the expected parity looks up tables and skips
+    /// coefficients 0 and 1, so the time taken still depends on the data.
+    ConstantTime,
+    /// Check random linear combinations of the parity shards, as
+    /// `verify_probabilistic` does, `rounds` of them.
+    Probabilistic { rounds: u32 },
+}
+
+/// The result of `ReedSolomon::verify_with_policy`.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct VerifyReport {
+    /// Whether the parity shards are correct.
+    pub valid: bool,
+    /// Number of mismatching elements of the parity shards, i.e. bytes over
+    /// GF(2^8), with `VerifyPolicy::ConstantTime`.
+    pub mismatched: Option<usize>,
+}
+
+/// Counters of the work done by a codec, see `ReedSolomon::stats`.
+///
+/// Sizes are in bytes whatever the field, and the counters wrap around on
+/// overflow.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub struct CodecStats {
+    /// Bytes of data shards encoded into parity shards.
+    pub bytes_encoded: u64,
+    /// Bytes of missing shards reconstructed.
+    pub bytes_reconstructed: u64,
+    /// Number of stripes checked by `verify`, `verify_with_buffer` and
+    /// `verify_partial`.
+    pub stripes_verified: u64,
+    /// Number of data decode matrices found in the cache.
+    pub cache_hits: u64,
+    /// Number of data decode matrices which had to be computed.
+    pub cache_misses: u64,
+}
+
+/// The live counters behind `CodecStats`.
+#[derive(Debug, Default)]
+struct Counters {
+    bytes_encoded: AtomicU64,
+    bytes_reconstructed: AtomicU64,
+    stripes_verified: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+impl Counters {
+    fn add(counter: &AtomicU64, n: usize) {
+        counter.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> CodecStats {
+        CodecStats {
+            bytes_encoded: self.bytes_encoded.load(Ordering::Relaxed),
+            bytes_reconstructed: self.bytes_reconstructed.load(Ordering::Relaxed),
+            stripes_verified: self.stripes_verified.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The kind of encoding matrix of a codec.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub enum MatrixKind {
+    /// A Vandermonde matrix made systematic, see `ReedSolomon::new`.
+    Vandermonde,
+    /// A Cauchy matrix below the identity matrix, see `ReedSolomon::new_cauchy`.
+    Cauchy,
+}
+
+/// Reed-Solomon erasure code encoder/decoder.
+///
+/// # Common error handling
+///
+/// ## For `encode`, `encode_shards`, `verify`, `verify_shards`, `reconstruct`, `reconstruct_data`, `reconstruct_shards`, `reconstruct_data_shards`
+///
+/// Return `Error::TooFewShards` or `Error::TooManyShards`
+/// when the number of provided shards
+/// does not match the codec's one.
+///
+/// Return `Error::EmptyShard` when the first shard provided is
+/// of zero length.
+///
+/// Return `Error::IncorrectShardSize` when the provided shards
+/// are of different lengths.
+///
+/// ## For `reconstruct`, `reconstruct_data`, `reconstruct_shards`, `reconstruct_data_shards`
+///
+/// Return `Error::TooFewShardsPresent` when there are not
+/// enough shards for reconstruction.
+///
+/// Return `Error::InvalidShardFlags` when the number of flags does not match
+/// the total number of shards.
+///
+/// # Variants of encoding methods
+///
+/// ## `sep`
+///
+/// Methods ending in `_sep` takes an immutable reference to data shards,
+/// and a mutable reference to parity shards.
+///
+/// They are useful as they do not need to borrow the data shards mutably,
+/// and other work that only needs read-only access to data shards can be done
+/// in parallel/concurrently during the encoding.
+///
+/// Following is a table of all the `sep` variants
+///
+/// | not `sep` | `sep` |
+/// | --- | --- |
+/// | `encode_single` | `encode_single_sep` |
+/// | `encode`        | `encode_sep` |
+///
+/// The `sep` variants do similar checks on the provided data shards and
+/// parity shards.
+///
+/// Return `Error::TooFewDataShards`, `Error::TooManyDataShards`,
+/// `Error::TooFewParityShards`, or `Error::TooManyParityShards` when applicable.
+///
+/// ## `single`
+///
+/// Methods containing `single` facilitate shard by shard encoding, where
+/// the parity shards are partially constructed using one data shard at a time.
+/// See `ShardByShard` struct for more details on how shard by shard encoding
+/// can be useful.
+///
+/// They are prone to **misuse**, and it is recommended to use the `ShardByShard`
+/// bookkeeping struct instead for shard by shard encoding.
+///
+/// The ones that are also `sep` are **ESPECIALLY** prone to **misuse**.
+/// Only use them when you actually need the flexibility.
+///
+/// Following is a table of all the shard by shard variants
+///
+/// | all shards at once | shard by shard |
+/// | --- | --- |
+/// | `encode` | `encode_single` |
+/// | `encode_sep` | `encode_single_sep` |
+///
+/// `encode_partial` and `encode_partial_sep` encode a burst of data shards
+/// in one call, as many calls to `encode_single` would.
+///
+/// The `single` variants do similar checks on the provided data shards and parity shards,
+/// and also do index check on `i_data`.
+///
+/// Return `Error::InvalidIndex` if `i_data >= data_shard_count`.
+///
+/// # Encoding behaviour
+/// ## For `encode`
+///
+/// You do not need to clear the parity shards beforehand, as the methods
+/// will overwrite them completely.
+///
+/// ## For `encode_single`, `encode_single_sep`
+///
+/// Calling them with `i_data` being `0` will overwrite the parity shards
+/// completely. If you are using the methods correctly, then you do not need
+/// to clear the parity shards beforehand.
+///
+/// # Variants of verifying methods
+///
+/// `verify` allocate sa buffer on the heap of the same size
+/// as the parity shards, and encode the input once using the buffer to store
+/// the computed parity shards, then check if the provided parity shards
+/// match the computed ones.
+///
+/// `verify_with_buffer`, allows you to provide
+/// the buffer to avoid making heap allocation(s) for the buffer in every call.
+///
+/// The `with_buffer` variants also guarantee that the buffer contains the correct
+/// parity shards if the result is `Ok(_)` (i.e. it does not matter whether the
+/// verification passed or not, as long as the result is not an error, the buffer
+/// will contain the correct parity shards after the call).
+///
+/// Following is a table of all the `with_buffer` variants
+///
+/// | not `with_buffer` | `with_buffer` |
+/// | --- | --- |
+/// | `verify` | `verify_with_buffer` |
+///
+/// The `with_buffer` variants also check the dimensions of the buffer and return
+/// `Error::TooFewBufferShards`, `Error::TooManyBufferShards`, `Error::EmptyShard`,
+/// or `Error::IncorrectShardSize` when applicable.
+///
+#[derive(Debug)]
+pub struct ReedSolomon<F: Field> {
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    total_shard_count: usize,
+    matrix_kind: MatrixKind,
+    matrix: Matrix<F>,
+    // the parity rows of `matrix`, so that coding needs no setup
+    parity_rows: Vec<Vec<F::Elem>>,
+    tree: InversionTree<Matrix<F>>,
+    // temporary shards of `verify` and `verify_partial`, kept between calls
+    scratch: Mutex<Vec<Vec<F::Elem>>>,
+    // unique among codecs, identifies the codec in a `ReconstructScratch`
+    id: usize,
+    counters: Counters,
+    // see `ReedSolomon::cache_chunk_len`
+    cache_chunk_len: AtomicUsize,
+    // see `ReedSolomon::pparam`, the default if `None`
+    pparam: Option<ParallelParam>,
+}
+
+impl<F: Field> Clone for ReedSolomon<F> {
+    fn clone(&self) -> ReedSolomon<F> {
+        let mut codec = ReedSolomon::with_matrix(
+            self.data_shard_count,
+            self.parity_shard_count,
+            self.matrix_kind,
+            self.matrix.clone(),
+        );
+        codec.set_cache_chunk_len(self.cache_chunk_len());
+        codec.pparam = self.pparam;
+        codec
+    }
+}
+
+/// Codecs are equal when they produce the same parity shards, i.e. when
+/// their shard counts and encoding matrices are equal. The field, and with
+/// it the polynomial, is fixed by `F`.
+impl<F: Field> PartialEq for ReedSolomon<F> {
+    fn eq(&self, rhs: &ReedSolomon<F>) -> bool {
+        self.data_shard_count == rhs.data_shard_count
+            && self.parity_shard_count == rhs.parity_shard_count
+            && self.matrix_kind == rhs.matrix_kind
+            // the rest of the matrix is the identity
+            && self.parity_rows == rhs.parity_rows
+    }
+}
+
+impl<F: Field> Eq for ReedSolomon<F> where F::Elem: Eq {}
+
+/// Hashes what `PartialEq` compares, so codecs can key maps and caches.
+///
+/// The caches and counters inside a codec take no part in its hash, so
+/// Clippy's `mutable_key_type` lint is a false positive for codec keys.
+impl<F: Field> Hash for ReedSolomon<F>
+where
+    F::Elem: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data_shard_count.hash(state);
+        self.parity_shard_count.hash(state);
+        self.matrix_kind.hash(state);
+        self.parity_rows.hash(state);
+    }
+}
+
+impl<F: Field> ReedSolomon<F> {
+    // AUDIT
+    //
+    // Error detection responsibilities
+    //
+    // Terminologies and symbols:
+    //   X =A, B, C=> Y: X delegates error checking responsibilities A, B, C to Y
+    //   X:= A, B, C: X needs to handle responsibilities A, B, C
+    //
+    // Encode methods
+    //
+    // `encode_single`:=
+    //   - check index `i_data` within range [0, data shard count)
+    //   - check length of `slices` matches total shard count exactly
+    //   - check consistency of length of individual slices
+    // `encode_single_sep`:=
+    //   - check index `i_data` within range [0, data shard count)
+    //   - check length of `parity` matches parity shard count exactly
+    //   - check consistency of length of individual parity slices
+    //   - check length of `single_data` matches length of first parity slice
+    // `encode`:=
+    //   - check length of `slices` matches total shard count exactly
+    //   - check consistency of length of individual slices
+    // `encode_sep`:=
+    //   - check length of `data` matches data shard count exactly
+    //   - check length of `parity` matches parity shard count exactly
+    //   - check consistency of length of individual data slices
+    //   - check consistency of length of individual parity slices
+    //   - check length of first parity slice matches length of first data slice
+    //
+    // Verify methods
+    //
+    // `verify`:=
+    //   - check length of `slices` matches total shard count exactly
+    //   - check consistency of length of individual slices
+    //
+    //   Generates buffer then passes control to verify_with_buffer
+    //
+    // `verify_with_buffer`:=
+    //   - check length of `slices` matches total shard count exactly
+    //   - check length of `buffer` matches parity shard count exactly
+    //   - check consistency of length of individual slices
+    //   - check consistency of length of individual slices in buffer
+    //   - check length of first slice in buffer matches length of first slice
+    //
+    // Reconstruct methods
+    //
+    // `reconstruct` =ALL=> `reconstruct_internal`
+    // `reconstruct_data`=ALL=> `reconstruct_internal`
+    // `reconstruct_internal`:=
+    //   - check length of `slices` matches total shard count exactly
+    //   - check consistency of length of individual slices
+    //   - check length of `slice_present` matches length of `slices`
+
+    fn get_parity_rows(&self) -> &[Vec<F::Elem>] {
+        &self.parity_rows
+    }
+
+    fn build_parity_rows(data_shards: usize, matrix: &Matrix<F>) -> Vec<Vec<F::Elem>> {
+        (data_shards..matrix.row_count())
+            .map(|i| matrix.get_row(i).to_vec())
+            .collect()
+    }
+
+    /// Builds the Vandermonde matrix of `total_shards` rows, multiplied by
+    /// the inverse of its top square so that it starts with the identity.
+    ///
+    /// Row `r` of the Vandermonde matrix evaluates a polynomial at `x_r`,
+    /// `F::nth(r)`, from its coefficients, and the inverse of the top square
+    /// interpolates these coefficients from the values at `x_0..x_k`. Row
+    /// `r` of the product thus holds the Lagrange basis polynomials of
+    /// `x_0..x_k` evaluated at `x_r`,
+    /// `L_j(x_r) = prod(x_r - x_i) / ((x_r - x_j) * prod(x_j - x_i))`,
+    /// `i != j` in the second product. These are computed directly, in
+    /// `O(k * k + m * k)` operations rather than the `O(n * k * k)` of
+    /// inverting and multiplying.
+    fn build_matrix(data_shards: usize, total_shards: usize) -> Matrix<F> {
+        let x = F::nth;
+        // subtraction is addition in fields of characteristic 2
+        let mut weights = Matrix::<F>::new(data_shards, 1);
+        weights.fill_rows(0, data_shards, |j, weight| {
+            weight[0] = (0..data_shards)
+                .filter(|&i| i != j)
+                .fold(F::one(), |acc, i| F::mul(acc, F::add(x(j), x(i))));
+        });
+        let weights: Vec<F::Elem> = (0..data_shards).map(|j| weights.get(j, 0)).collect();
+
+        let mut matrix = Matrix::new(total_shards, data_shards);
+        for r in 0..data_shards {
+            matrix.set(r, r, F::one());
+        }
+        matrix.fill_rows(data_shards, 4 * data_shards, |r, row| {
+            let product = (0..data_shards).fold(F::one(), |acc, i| F::mul(acc, F::add(x(r), x(i))));
+            for (j, (elem, &weight)) in row.iter_mut().zip(weights.iter()).enumerate() {
+                let denominator = F::mul(F::add(x(r), x(j)), weight);
+                *elem = F::div(product, denominator);
+            }
+        });
+
+        matrix
+    }
+
+    fn build_cauchy_matrix(data_shards: usize, total_shards: usize) -> Matrix<F> {
+        let mut matrix = Matrix::new(total_shards, data_shards);
+
+        for r in 0..data_shards {
+            matrix.set(r, r, F::one());
+        }
+        matrix.fill_rows(data_shards, 2 * data_shards, |r, row| {
+            for (c, elem) in row.iter_mut().enumerate() {
+                *elem = Self::cauchy_coefficient(r, c);
+            }
+        });
+
+        matrix
+    }
+
+    fn cauchy_coefficient(r: usize, c: usize) -> F::Elem {
+        F::div(F::one(), F::add(F::nth(r), F::nth(c)))
+    }
+
+    fn check_shard_counts(data_shards: usize, parity_shards: usize) -> Result<(), Error> {
+        if data_shards == 0 {
+            return Err(Error::TooFewDataShards);
+        }
+        if parity_shards == 0 {
+            return Err(Error::TooFewParityShards);
+        }
+        if data_shards + parity_shards > F::ORDER {
+            return Err(Error::TooManyShards);
+        }
+
+        Ok(())
+    }
+
+    fn with_matrix(
+        data_shards: usize,
+        parity_shards: usize,
+        matrix_kind: MatrixKind,
+        matrix: Matrix<F>,
+    ) -> ReedSolomon<F> {
+        ReedSolomon {
+            data_shard_count: data_shards,
+            parity_shard_count: parity_shards,
+            total_shard_count: data_shards + parity_shards,
+            matrix_kind,
+            parity_rows: Self::build_parity_rows(data_shards, &matrix),
+            matrix,
+            tree: InversionTree::with_identity(data_shards, parity_shards),
+            scratch: Mutex::new(Vec::new()),
+            id: NEXT_CODEC_ID.fetch_add(1, Ordering::Relaxed),
+            counters: Counters::default(),
+            cache_chunk_len: AtomicUsize::new(CACHE_CHUNK_LEN),
+            pparam: None,
+        }
+    }
+
+    /// Adds `shard_count` shards of `shard_len` elements to `counter`.
+    fn count_bytes(counter: &AtomicU64, shard_count: usize, shard_len: usize) {
+        Counters::add(counter, shard_count * shard_len * mem::size_of::<F::Elem>());
+    }
+
+    /// Takes `count` zeroed buffers of `len` elements from the scratch pool,
+    /// allocating whatever the pool cannot provide.
+    ///
+    /// The pool is skipped when another thread holds it, so concurrent
+    /// calls never wait on each other.
+    fn take_scratch(&self, count: usize, len: usize) -> ShardVec<Vec<F::Elem>> {
+        let mut bufs = SmallVec::with_capacity(count);
+
+        if let Ok(mut pool) = self.scratch.try_lock() {
+            while bufs.len() < count {
+                match pool.pop() {
+                    Some(mut buf) => {
+                        buf.clear();
+                        buf.resize(len, F::zero());
+                        bufs.push(buf);
+                    }
+                    None => break,
+                }
+            }
+        }
+        while bufs.len() < count {
+            bufs.push(vec![F::zero(); len]);
+        }
+
+        bufs
+    }
+
+    /// Returns buffers taken with `take_scratch` to the pool, which keeps
+    /// at most `parity_shard_count` of them.
+    fn return_scratch<I: IntoIterator<Item = Vec<F::Elem>>>(&self, bufs: I) {
+        if let Ok(mut pool) = self.scratch.try_lock() {
+            let room = self.parity_shard_count.saturating_sub(pool.len());
+            pool.extend(bufs.into_iter().take(room));
+        }
+    }
+
+    /// Creates a new instance of Reed-Solomon erasure code encoder/decoder.
+    ///
+    /// Returns `Error::TooFewDataShards` if `data_shards == 0`.
+    ///
+    /// Returns `Error::TooFewParityShards` if `parity_shards == 0`.
     ///
     /// Returns `Error::TooManyShards` if `data_shards + parity_shards > F::ORDER`.
     pub fn new(data_shards: usize, parity_shards: usize) -> Result<ReedSolomon<F>, Error> {
-        if data_shards == 0 {
+        Self::check_shard_counts(data_shards, parity_shards)?;
+
+        let matrix = Self::build_matrix(data_shards, data_shards + parity_shards);
+
+        Ok(Self::with_matrix(
+            data_shards,
+            parity_shards,
+            MatrixKind::Vandermonde,
+            matrix,
+        ))
+    }
+
+    /// Creates a new instance of Reed-Solomon erasure code encoder/decoder
+    /// using a Cauchy encoding matrix.
+    ///
+    /// Parity row `r` has the coefficients `1 / (F::nth(r) + F::nth(c))`
+    /// for each data column `c`. Over GF(2^8) this is the matrix of
+    /// ISA-L's `gf_gen_cauchy1_matrix`, as used by the RS codec of HDFS.
+    ///
+    /// Returns the same errors as `new`.
+    pub fn new_cauchy(data_shards: usize, parity_shards: usize) -> Result<ReedSolomon<F>, Error> {
+        Self::check_shard_counts(data_shards, parity_shards)?;
+
+        let matrix = Self::build_cauchy_matrix(data_shards, data_shards + parity_shards);
+
+        Ok(Self::with_matrix(
+            data_shards,
+            parity_shards,
+            MatrixKind::Cauchy,
+            matrix,
+        ))
+    }
+
+    /// Returns the kind of encoding matrix used.
+    pub fn matrix_kind(&self) -> MatrixKind {
+        self.matrix_kind
+    }
+
+    pub fn data_shard_count(&self) -> usize {
+        self.data_shard_count
+    }
+
+    pub fn parity_shard_count(&self) -> usize {
+        self.parity_shard_count
+    }
+
+    pub fn total_shard_count(&self) -> usize {
+        self.total_shard_count
+    }
+
+    /// Returns the number of elements of every shard coded from all data
+    /// shards before moving on to the next elements, as
+    /// `ParallelParam::cache_chunk_len` for the methods not taking a
+    /// `ParallelParam`.
+    ///
+    /// Default is 4096, `0` codes whole shards at a time.
+    pub fn cache_chunk_len(&self) -> usize {
+        self.cache_chunk_len.load(Ordering::Relaxed)
+    }
+
+    /// Sets the number of elements returned by `cache_chunk_len`.
+    ///
+    /// The setting takes effect for all threads using the codec, and is
+    /// kept by its clones.
+    pub fn set_cache_chunk_len(&self, len: usize) {
+        self.cache_chunk_len.store(len, Ordering::Relaxed);
+    }
+
+    /// Returns the parameters of the parallel methods called without
+    /// their own, i.e. with `None` as `param`.
+    ///
+    /// Default is `ParallelParam::default()`.
+    pub fn pparam(&self) -> ParallelParam {
+        self.pparam.unwrap_or_default()
+    }
+
+    /// Sets the parameters returned by `pparam`, e.g. to go from many
+    /// threads on small pieces for packets to fewer, larger pieces for bulk
+    /// files, keeping the caches of the codec.
+    ///
+    /// The parameters are kept by the clones of the codec.
+    pub fn set_pparam(&mut self, param: ParallelParam) {
+        self.pparam = Some(param);
+    }
+
+    /// Returns the counters of the work done by the codec since its
+    /// creation or the last `reset_stats`.
+    ///
+    /// The counters are shared by all threads using the codec, and a clone
+    /// of the codec starts with zeroed counters.
+    pub fn stats(&self) -> CodecStats {
+        self.counters.snapshot()
+    }
+
+    /// Zeroes the counters returned by `stats`.
+    pub fn reset_stats(&self) {
+        let counters = &self.counters;
+        for counter in [
+            &counters.bytes_encoded,
+            &counters.bytes_reconstructed,
+            &counters.stripes_verified,
+            &counters.cache_hits,
+            &counters.cache_misses,
+        ]
+        .iter()
+        {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn code_some_slices<R: AsRef<[F::Elem]>, T: AsRef<[F::Elem]>, U: AsMut<[F::Elem]>>(
+        &self,
+        matrix_rows: &[R],
+        inputs: &[T],
+        outputs: &mut [U],
+    ) {
+        let shard_len = inputs.first().map_or(0, |input| input.as_ref().len());
+        let chunk_len = match self.cache_chunk_len() {
+            0 => shard_len.max(1),
+            len => len,
+        };
+
+        // The chunks of all inputs stay in cache while coding all outputs.
+        for start in (0..shard_len).step_by(chunk_len) {
+            let end = (start + chunk_len).min(shard_len);
+            for (matrix_row, output) in matrix_rows.iter().zip(outputs.iter_mut()) {
+                let output = &mut output.as_mut()[start..end];
+                self.code_row(matrix_row.as_ref(), inputs, start..end, output);
+            }
+        }
+    }
+
+    /// Computes `output` from the elements `range` of `inputs`, with the
+    /// coefficients of `matrix_row`.
+    ///
+    /// The first input overwrites `output`, then the others with non-zero
+    /// coefficients are added `FUSED_INPUTS` at a time, so that `output` is
+    /// read and written once per group of inputs rather than per input.
+    fn code_row<T: AsRef<[F::Elem]>>(
+        &self,
+        matrix_row: &[F::Elem],
+        inputs: &[T],
+        range: std::ops::Range<usize>,
+        output: &mut [F::Elem],
+    ) {
+        Self::code_slice(
+            matrix_row[0],
+            &inputs[0].as_ref()[range.clone()],
+            output,
+            true,
+        );
+
+        let mut elems = [F::zero(); FUSED_INPUTS];
+        let mut group: [&[F::Elem]; FUSED_INPUTS] = [&[]; FUSED_INPUTS];
+        let mut count = 0;
+        for i_input in 1..self.data_shard_count {
+            if matrix_row[i_input] == F::zero() {
+                continue;
+            }
+
+            elems[count] = matrix_row[i_input];
+            group[count] = &inputs[i_input].as_ref()[range.clone()];
+            count += 1;
+            if count == FUSED_INPUTS {
+                F::mul_slices_add(&elems, &group, output);
+                count = 0;
+            }
+        }
+        if count > 0 {
+            F::mul_slices_add(&elems[..count], &group[..count], output);
+        }
+    }
+
+    fn count_encoded<T: AsRef<[F::Elem]>>(&self, data: &[T]) {
+        let shard_len = data.first().map_or(0, |shard| shard.as_ref().len());
+        Self::count_bytes(&self.counters.bytes_encoded, data.len(), shard_len);
+    }
+
+    fn code_single_slice<R: AsRef<[F::Elem]>, U: AsMut<[F::Elem]>>(
+        &self,
+        matrix_rows: &[R],
+        i_input: usize,
+        input: &[F::Elem],
+        outputs: &mut [U],
+    ) {
+        outputs.iter_mut().enumerate().for_each(|(i_row, output)| {
+            let matrix_row_to_use = matrix_rows[i_row].as_ref()[i_input];
+
+            Self::code_slice(matrix_row_to_use, input, output.as_mut(), i_input == 0);
+        })
+    }
+
+    /// Multiplies `input` by `c` into `output`, overwriting `output` if
+    /// `first` is set and adding to it otherwise.
+    ///
+    /// Decode matrices often have rows close to the identity, so the
+    /// coefficients 0 and 1 skip the multiplication.
+    fn code_slice(c: F::Elem, input: &[F::Elem], output: &mut [F::Elem], first: bool) {
+        if c == F::zero() {
+            if first {
+                output.iter_mut().for_each(|x| *x = F::zero());
+            }
+        } else if c == F::one() && first {
+            output.copy_from_slice(input);
+        } else if first {
+            F::mul_slice(c, input, output);
+        } else {
+            F::mul_slice_add(c, input, output);
+        }
+    }
+
+    fn check_some_slices_with_buffer<R, T, V, U>(
+        &self,
+        matrix_rows: &[R],
+        inputs: &[T],
+        to_check: &[V],
+        buffer: &mut [U],
+    ) -> bool
+    where
+        R: AsRef<[F::Elem]>,
+        T: AsRef<[F::Elem]>,
+        V: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.code_some_slices(matrix_rows, inputs, buffer);
+
+        // Slice equality stops at the first mismatching shard, and compares
+        // byte slices with `memcmp`, which is already word/SIMD-wide.
+        buffer
+            .iter()
+            .zip(to_check.iter())
+            .all(|(expected_parity_shard, parity_shard)| {
+                expected_parity_shard.as_ref() == parity_shard.as_ref()
+            })
+    }
+
+    /// Counts the elements of `to_check` which differ from `expected`,
+    /// comparing all of them.
+    fn count_mismatches<U, V>(expected: &[U], to_check: &[V]) -> usize
+    where
+        U: AsRef<[F::Elem]>,
+        V: AsRef<[F::Elem]>,
+    {
+        expected
+            .iter()
+            .zip(to_check.iter())
+            .flat_map(|(expected, actual)| expected.as_ref().iter().zip(actual.as_ref().iter()))
+            .map(|(a, b)| usize::from(a != b))
+            .sum()
+    }
+
+    /// Draws the combinations of `rounds` rounds of `verify_probabilistic`.
+    fn random_combinations(&self, rounds: u32) -> Vec<Combination<F::Elem>> {
+        let parity_rows = self.get_parity_rows();
+        let mut state = RandomState::new().build_hasher().finish() | 1;
+
+        (0..rounds)
+            .map(|_| {
+                let coefficients: ShardVec<F::Elem> = (0..self.parity_shard_count)
+                    .map(|_| F::nth((sim::next_random(&mut state) % F::ORDER as u64) as usize))
+                    .collect();
+
+                let mut row: ShardVec<F::Elem> =
+                    SmallVec::from_elem(F::zero(), self.data_shard_count);
+                for (parity_row, &c) in parity_rows.iter().zip(coefficients.iter()) {
+                    for (x, &y) in row.iter_mut().zip(parity_row.iter()) {
+                        *x = F::add(*x, F::mul(c, y));
+                    }
+                }
+
+                (coefficients, row)
+            })
+            .collect()
+    }
+
+    /// Checks the parity shards against every combination of
+    /// `random_combinations`, with two buffers of the shard length.
+    fn check_combinations<T, V>(
+        &self,
+        combinations: &[Combination<F::Elem>],
+        data: &[T],
+        parity: &[V],
+        buffers: &mut [Vec<F::Elem>],
+    ) -> bool
+    where
+        T: AsRef<[F::Elem]>,
+        V: AsRef<[F::Elem]>,
+    {
+        let (expected, actual) = buffers.split_at_mut(1);
+        let actual = &mut actual[0][..];
+
+        combinations.iter().all(|(coefficients, row)| {
+            self.code_some_slices(&[&row[..]], data, expected);
+            for (i, (parity, &c)) in parity.iter().zip(coefficients.iter()).enumerate() {
+                Self::code_slice(c, parity.as_ref(), actual, i == 0);
+            }
+
+            expected[0] == actual
+        })
+    }
+
+    /// Constructs the parity shards partially using only the data shard
+    /// indexed by `i_data`.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    ///
+    /// # Warning
+    ///
+    /// You must apply this method on the data shards in strict sequential order (0..data shard count),
+    /// otherwise the parity shards will be incorrect.
+    ///
+    /// It is recommended to use the `ShardByShard` bookkeeping struct instead of this method directly.
+    pub fn encode_single<T, U>(&self, i_data: usize, mut shards: T) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let slices = shards.as_mut();
+
+        check_slice_index!(data => self, i_data);
+        check_piece_count!(all=> self, slices);
+        check_slices!(multi => slices);
+
+        // Get the slice of output buffers.
+        let (mut_input, output) = slices.split_at_mut(self.data_shard_count);
+
+        let input = mut_input[i_data].as_ref();
+
+        self.encode_single_sep(i_data, input, output)
+    }
+
+    /// Constructs the parity shards partially using only the data shard provided.
+    ///
+    /// The data shard must match the index `i_data`.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    ///
+    /// # Warning
+    ///
+    /// You must apply this method on the data shards in strict sequential order (0..data shard count),
+    /// otherwise the parity shards will be incorrect.
+    ///
+    /// It is recommended to use the `ShardByShard` bookkeeping struct instead of this method directly.
+    pub fn encode_single_sep<U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+        &self,
+        i_data: usize,
+        single_data: &[F::Elem],
+        parity: &mut [U],
+    ) -> Result<(), Error> {
+        check_slice_index!(data => self, i_data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => parity, single => single_data);
+
+        let parity_rows = self.get_parity_rows();
+
+        // Do the coding.
+        self.code_single_slice(parity_rows, i_data, single_data, parity);
+        Self::count_bytes(&self.counters.bytes_encoded, 1, single_data.len());
+
+        Ok(())
+    }
+
+    /// Constructs the parity shards partially using the data shards indexed
+    /// by `indices`, in a single pass over the parity shards.
+    ///
+    /// This is the same as calling `encode_single` on each index in turn,
+    /// so the slots where the parity shards sit at will be overwritten if
+    /// `indices` starts with `0`, and added to otherwise.
+    ///
+    /// # Warning
+    ///
+    /// The calls must cover the data shards in strict sequential order
+    /// (0..data shard count), otherwise the parity shards will be
+    /// incorrect.
+    ///
+    /// Return `Error::InvalidIndex` if an index is `>= data_shard_count`,
+    /// or if `indices` is not in strictly increasing order.
+    pub fn encode_partial<T, U>(&self, indices: &[usize], mut shards: T) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let slices = shards.as_mut();
+
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let (input, output) = slices.split_at_mut(self.data_shard_count);
+        let data: ShardVec<&[F::Elem]> = indices
+            .iter()
+            .map(|&i| input.get(i).map_or(&[][..], |shard| shard.as_ref()))
+            .collect();
+
+        self.encode_partial_sep(indices, &data, output)
+    }
+
+    /// Constructs the parity shards partially using the data shards
+    /// provided, `data[j]` being the data shard indexed by `indices[j]`.
+    ///
+    /// See `encode_partial`.
+    ///
+    /// Return `Error::TooFewDataShards` or `Error::TooManyDataShards` if
+    /// there are not as many data shards as indices.
+    pub fn encode_partial_sep<T, U>(
+        &self,
+        indices: &[usize],
+        data: &[T],
+        parity: &mut [U],
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        for (j, &i) in indices.iter().enumerate() {
+            check_slice_index!(data => self, i);
+            if j > 0 && indices[j - 1] >= i {
+                return Err(Error::InvalidIndex);
+            }
+        }
+        if data.len() < indices.len() {
             return Err(Error::TooFewDataShards);
         }
-        if parity_shards == 0 {
-            return Err(Error::TooFewParityShards);
+        if data.len() > indices.len() {
+            return Err(Error::TooManyDataShards);
+        }
+        check_piece_count!(parity => self, parity);
+        if data.is_empty() {
+            check_slices!(multi => parity);
+            return Ok(());
+        }
+        check_slices!(multi => data, multi => parity);
+
+        let parity_rows = self.get_parity_rows();
+
+        let first = indices[0] == 0;
+        for (matrix_row, output) in parity_rows.iter().zip(parity.iter_mut()) {
+            let output = output.as_mut();
+            let mut inputs = indices.iter().zip(data.iter());
+            if first {
+                let (&i, input) = inputs.next().expect("indices not empty; qed");
+                Self::code_slice(matrix_row[i], input.as_ref(), output, true);
+            }
+
+            let mut elems = [F::zero(); FUSED_INPUTS];
+            let mut group: [&[F::Elem]; FUSED_INPUTS] = [&[]; FUSED_INPUTS];
+            let mut count = 0;
+            for (&i, input) in inputs {
+                if matrix_row[i] == F::zero() {
+                    continue;
+                }
+
+                elems[count] = matrix_row[i];
+                group[count] = input.as_ref();
+                count += 1;
+                if count == FUSED_INPUTS {
+                    F::mul_slices_add(&elems, &group, output);
+                    count = 0;
+                }
+            }
+            if count > 0 {
+                F::mul_slices_add(&elems[..count], &group[..count], output);
+            }
+        }
+        self.count_encoded(data);
+
+        Ok(())
+    }
+
+    /// Constructs the parity shards.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode<T, U>(&self, mut shards: T) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let slices: &mut [U] = shards.as_mut();
+
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        // Get the slice of output buffers.
+        let (input, output) = slices.split_at_mut(self.data_shard_count);
+
+        self.encode_sep(&*input, output)
+    }
+
+    /// Constructs the parity shards using a read-only view into the
+    /// data shards.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode_sep<T: AsRef<[F::Elem]>, U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+        &self,
+        data: &[T],
+        parity: &mut [U],
+    ) -> Result<(), Error> {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+
+        let parity_rows = self.get_parity_rows();
+
+        // Do the coding.
+        self.code_some_slices(parity_rows, data, parity);
+        self.count_encoded(data);
+
+        Ok(())
+    }
+
+    /// Same as `encode_sep`, but takes the data shards from any container
+    /// iterating over them, e.g. a `VecDeque`, without collecting them into
+    /// a `Vec` first.
+    ///
+    /// The shards are gathered on the stack for most codes, see `ShardVec`.
+    /// Use `encode_sep` for shards already in a slice, which skips this.
+    pub fn encode_sep_iter<I, U>(&self, data: I, parity: &mut [U]) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[F::Elem]>,
+        I::IntoIter: ExactSizeIterator,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let data: ShardVec<I::Item> = data.into_iter().collect();
+        self.encode_sep(&data, parity)
+    }
+
+    /// Constructs the parity shards, skipping the data shards which are
+    /// all zeros.
+    ///
+    /// Zero data shards add nothing to the parity shards, so sparse or
+    /// zero-padded stripes encode faster. Finding them takes a pass over the
+    /// data shards, use `encode_sep_skipping` when they are known upfront.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode_sparse<T, U>(&self, mut shards: T) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let slices: &mut [U] = shards.as_mut();
+
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let (input, output) = slices.split_at_mut(self.data_shard_count);
+
+        let mut zero = ShardSet::new(self.data_shard_count);
+        for (i, shard) in input.iter().enumerate() {
+            if shard.as_ref().iter().all(|&x| x == F::zero()) {
+                zero.insert(i);
+            }
+        }
+
+        self.encode_sep_skipping(&*input, output, &zero)
+    }
+
+    /// Constructs the parity shards using a read-only view into the data
+    /// shards, treating the data shards in `zero` as all zeros without
+    /// reading them.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    ///
+    /// Return `Error::InvalidShardFlags` if `zero` is not over
+    /// `data_shard_count` shards.
+    pub fn encode_sep_skipping<T, U>(
+        &self,
+        data: &[T],
+        parity: &mut [U],
+        zero: &ShardSet,
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+        if zero.shard_count() != self.data_shard_count {
+            return Err(Error::InvalidShardFlags);
+        }
+
+        let parity_rows = self.get_parity_rows();
+
+        let mut first = true;
+        for (i_input, input) in data.iter().enumerate() {
+            if zero.contains(i_input) {
+                continue;
+            }
+            for (matrix_row, output) in parity_rows.iter().zip(parity.iter_mut()) {
+                Self::code_slice(matrix_row[i_input], input.as_ref(), output.as_mut(), first);
+            }
+            first = false;
+        }
+        if first {
+            for output in parity.iter_mut() {
+                output.as_mut().iter_mut().for_each(|x| *x = F::zero());
+            }
+        }
+        self.count_encoded(data);
+
+        Ok(())
+    }
+
+    /// Adds the partial parity shards `partial` into `parity`.
+    ///
+    /// As the code is linear, the parity shards of a stripe are the sum of
+    /// the parity shards of any split of its data shards into disjoint
+    /// sets, the other data shards counting as zeros. The sets can be
+    /// encoded on different machines, e.g. with `ShardByShard` sessions
+    /// skipping or finishing early on the data shards of the other sets,
+    /// and their parity shards merged in any order.
+    ///
+    /// Merging the same partial parity shards twice cancels them out.
+    pub fn merge_parity<T, U>(&self, partial: &[T], parity: &mut [U]) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        check_piece_count!(parity => self, partial);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => partial, multi => parity);
+
+        for (partial, output) in partial.iter().zip(parity.iter_mut()) {
+            F::mul_slice_add(F::one(), partial.as_ref(), output.as_mut());
+        }
+
+        Ok(())
+    }
+
+    /// Constructs the parity shards using multiple threads, as configured
+    /// by `param`, or by `pparam` if `param` is `None`.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode_par<T, U, P>(&self, mut shards: T, param: P) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        P: Into<Option<ParallelParam>>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]> + Send + Sync,
+        F::Elem: Send + Sync,
+    {
+        let slices: &mut [U] = shards.as_mut();
+
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let (input, output) = slices.split_at_mut(self.data_shard_count);
+
+        self.encode_sep_par(&*input, output, param)
+    }
+
+    /// Constructs the parity shards using a read-only view into the
+    /// data shards and multiple threads, as configured by `param`, or by
+    /// `pparam` if `param` is `None`.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode_sep_par<T, U, P>(
+        &self,
+        data: &[T],
+        parity: &mut [U],
+        param: P,
+    ) -> Result<(), Error>
+    where
+        P: Into<Option<ParallelParam>>,
+        T: AsRef<[F::Elem]> + Sync,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]> + Send,
+        F::Elem: Send + Sync,
+    {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+
+        let parity_rows = self.get_parity_rows();
+        let param = param.into().unwrap_or_else(|| self.pparam());
+
+        self.code_some_slices_par(parity_rows, data, parity, param);
+        self.count_encoded(data);
+
+        Ok(())
+    }
+
+    /// Parallel version of `code_some_slices`.
+    ///
+    /// Each output is cut into ranges of `param.bytes_per_encode` elements,
+    /// and the (output, range) pieces are divided among the threads as
+    /// selected by `param.chunk_assignment`.
+    fn code_some_slices_par<R, T, U>(
+        &self,
+        matrix_rows: &[R],
+        inputs: &[T],
+        outputs: &mut [U],
+        param: ParallelParam,
+    ) where
+        R: AsRef<[F::Elem]> + Sync,
+        T: AsRef<[F::Elem]> + Sync,
+        U: AsMut<[F::Elem]> + Send,
+        F::Elem: Send + Sync,
+    {
+        let shard_len = inputs.first().map_or(0, |input| input.as_ref().len());
+        let param = param.adapted(outputs.len(), shard_len);
+        let chunk_len = param.bytes_per_encode;
+
+        let mut groups: Vec<Vec<_>> = Vec::new();
+        match param.chunk_assignment {
+            ChunkAssignment::Interleaved => {
+                let mut pieces = Vec::new();
+                for (i_row, output) in outputs.iter_mut().enumerate() {
+                    for (i_chunk, chunk) in output.as_mut().chunks_mut(chunk_len).enumerate() {
+                        pieces.push((i_row, i_chunk * chunk_len, chunk));
+                    }
+                }
+
+                let thread_count = param.thread_count.clamp(1, pieces.len().max(1));
+                let pieces_per_thread = pieces.len().div_ceil(thread_count).max(1);
+
+                while !pieces.is_empty() {
+                    let rest = pieces.split_off(pieces_per_thread.min(pieces.len()));
+                    groups.push(pieces);
+                    pieces = rest;
+                }
+            }
+            ChunkAssignment::ByteRange => {
+                for (i_row, output) in outputs.iter_mut().enumerate() {
+                    let output = output.as_mut();
+                    let range_len = param.byte_range_len(output.len());
+
+                    for (i_range, range) in output.chunks_mut(range_len).enumerate() {
+                        if groups.len() <= i_range {
+                            groups.push(Vec::new());
+                        }
+                        for (i_chunk, chunk) in range.chunks_mut(chunk_len).enumerate() {
+                            let offset = i_range * range_len + i_chunk * chunk_len;
+                            groups[i_range].push((i_row, offset, chunk));
+                        }
+                    }
+                }
+            }
+        }
+
+        let cache_chunk_len = match param.cache_chunk_len {
+            0 => chunk_len,
+            len => len,
+        };
+        let code_pieces = |pieces: &mut [(usize, usize, &mut [F::Elem])]| {
+            for (i_row, offset, output) in pieces.iter_mut() {
+                for (i_chunk, output) in output.chunks_mut(cache_chunk_len).enumerate() {
+                    let start = *offset + i_chunk * cache_chunk_len;
+                    let range = start..start + output.len();
+                    self.code_row(matrix_rows[*i_row].as_ref(), inputs, range, output);
+                }
+            }
+        };
+
+        param
+            .thread_source
+            .for_each(&mut groups, &|group: &mut Vec<_>| code_pieces(group));
+    }
+
+    /// Allocates `total_shard_count` zeroed shards of `shard_len` elements
+    /// for use with the parallel methods.
+    ///
+    /// With `ChunkAssignment::ByteRange`, the byte ranges the parallel
+    /// methods use are zeroed on several threads. No thread is pinned to a
+    /// core or NUMA node, so this says nothing about where the pages end
+    /// up. With `ChunkAssignment::Interleaved`, this is the same as
+    /// allocating zeroed shards.
+    ///
+    /// `param` should be that of the parallel methods the shards are used
+    /// with, `None` for `pparam`.
+    pub fn alloc_shards_par<P>(&self, shard_len: usize, param: P) -> Vec<Vec<F::Elem>>
+    where
+        P: Into<Option<ParallelParam>>,
+        F::Elem: Send,
+    {
+        let param = param.into().unwrap_or_else(|| self.pparam());
+        let mut shards: Vec<Vec<F::Elem>> = (0..self.total_shard_count)
+            .map(|_| Vec::with_capacity(shard_len))
+            .collect();
+
+        if param.chunk_assignment == ChunkAssignment::Interleaved
+            || shard_len == 0
+            || !THREADS_SUPPORTED
+        {
+            for shard in shards.iter_mut() {
+                shard.resize(shard_len, F::zero());
+            }
+            return shards;
+        }
+
+        // the same ranges as `code_some_slices_par` uses for the parity shards
+        let range_len = param
+            .adapted(self.parity_shard_count, shard_len)
+            .byte_range_len(shard_len);
+        let mut groups: Vec<Vec<&mut [MaybeUninit<F::Elem>]>> = Vec::new();
+        for shard in shards.iter_mut() {
+            let spare = &mut shard.spare_capacity_mut()[..shard_len];
+            for (i_range, range) in spare.chunks_mut(range_len).enumerate() {
+                if groups.len() <= i_range {
+                    groups.push(Vec::new());
+                }
+                groups[i_range].push(range);
+            }
+        }
+
+        let zero_ranges = |group: &mut Vec<&mut [MaybeUninit<F::Elem>]>| {
+            for range in group.iter_mut() {
+                for x in range.iter_mut() {
+                    *x = MaybeUninit::new(F::zero());
+                }
+            }
+        };
+        param.thread_source.for_each(&mut groups, &zero_ranges);
+
+        for shard in shards.iter_mut() {
+            // every element was initialized by one of the threads above
+            unsafe { shard.set_len(shard_len) };
+        }
+
+        shards
+    }
+
+    /// Constructs the parity shards, passing the content of every shard to
+    /// `update` in the same pass, e.g. to compute per-shard digests.
+    ///
+    /// The shards are processed in chunks. For each chunk, `update` is
+    /// called with the index of every shard, data shards first, along with
+    /// the chunk of that shard. The chunks of a shard are passed in order,
+    /// so feeding them to a hasher per shard yields the hash of each shard.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode_hashed<T, U, D>(&self, mut shards: T, update: D) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+        D: FnMut(usize, &[F::Elem]),
+    {
+        let slices: &mut [U] = shards.as_mut();
+
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        // Get the slice of output buffers.
+        let (input, output) = slices.split_at_mut(self.data_shard_count);
+
+        self.encode_sep_hashed(&*input, output, update)
+    }
+
+    /// Constructs the parity shards using a read-only view into the
+    /// data shards, passing the content of every shard to `update` as in
+    /// `encode_hashed`.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode_sep_hashed<T, U, D>(
+        &self,
+        data: &[T],
+        parity: &mut [U],
+        mut update: D,
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+        D: FnMut(usize, &[F::Elem]),
+    {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+
+        let parity_rows = self.get_parity_rows();
+        let shard_len = data[0].as_ref().len();
+
+        for start in (0..shard_len).step_by(HASH_CHUNK_LEN) {
+            let end = (start + HASH_CHUNK_LEN).min(shard_len);
+
+            let inputs: ShardVec<&[F::Elem]> = data
+                .iter()
+                .map(|shard| &shard.as_ref()[start..end])
+                .collect();
+            {
+                let mut outputs: ShardVec<&mut [F::Elem]> = parity
+                    .iter_mut()
+                    .map(|shard| &mut shard.as_mut()[start..end])
+                    .collect();
+
+                // Do the coding.
+                self.code_some_slices(parity_rows, &inputs, &mut outputs);
+            }
+
+            // The chunks are still in cache.
+            for (i, input) in inputs.iter().enumerate() {
+                update(i, input);
+            }
+            for (i, output) in parity.iter().enumerate() {
+                update(self.data_shard_count + i, &output.as_ref()[start..end]);
+            }
+        }
+        self.count_encoded(data);
+
+        Ok(())
+    }
+
+    /// Constructs the parity shards into uninitialized buffers, so freshly
+    /// allocated buffers do not need to be zero-filled first.
+    ///
+    /// Returns the parity shards, which are the buffers of `parity`
+    /// fully initialized.
+    ///
+    /// Return `Error::IncorrectShardSize` if a parity buffer is not of the
+    /// length of the data shards.
+    pub fn encode_sep_uninit<'a, T, U>(
+        &self,
+        data: &[T],
+        parity: &'a mut [U],
+    ) -> Result<Vec<&'a mut [F::Elem]>, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsMut<[MaybeUninit<F::Elem>]>,
+    {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data);
+        let shard_len = data[0].as_ref().len();
+        if parity
+            .iter_mut()
+            .any(|shard| shard.as_mut().len() != shard_len)
+        {
+            return Err(Error::IncorrectShardSize);
+        }
+
+        let parity_rows = self.get_parity_rows();
+
+        let mut outputs = Vec::with_capacity(self.parity_shard_count);
+        for (matrix_row, shard) in parity_rows.iter().zip(parity.iter_mut()) {
+            let shard = shard.as_mut();
+
+            // The first data shard initializes every element.
+            for (out, &input) in shard.iter_mut().zip(data[0].as_ref().iter()) {
+                *out = MaybeUninit::new(F::mul(matrix_row[0], input));
+            }
+            // Safe as every element has just been initialized.
+            let shard = unsafe { &mut *(shard as *mut [MaybeUninit<F::Elem>] as *mut [F::Elem]) };
+
+            for (i_input, input) in data.iter().enumerate().skip(1) {
+                Self::code_slice(matrix_row[i_input], input.as_ref(), shard, false);
+            }
+
+            outputs.push(shard);
+        }
+        self.count_encoded(data);
+
+        Ok(outputs)
+    }
+
+    /// Constructs the parity shards into newly allocated buffers.
+    ///
+    /// Returns the parity shards.
+    pub fn encode_owned<T: AsRef<[F::Elem]>>(
+        &self,
+        data: &[T],
+    ) -> Result<Vec<Vec<F::Elem>>, Error> {
+        check_piece_count!(data => self, data);
+        check_slices!(multi => data);
+
+        let shard_len = data[0].as_ref().len();
+        let mut parity = vec![vec![F::zero(); shard_len]; self.parity_shard_count];
+
+        self.encode_sep(data, &mut parity)?;
+
+        Ok(parity)
+    }
+
+    /// Constructs the parity shards and appends them to `shards`, which
+    /// holds the data shards.
+    ///
+    /// `shards` is not touched on error.
+    pub fn encode_append(&self, shards: &mut Vec<Vec<F::Elem>>) -> Result<(), Error> {
+        let parity = self.encode_owned(&shards[..])?;
+
+        shards.extend(parity);
+
+        Ok(())
+    }
+
+    /// Computes the coefficients of the parity shard `i_parity`, which may lie
+    /// beyond the parity shard count of this codec.
+    ///
+    /// Row `r` of the encoding matrix evaluates, at the point `F::nth(r)`, the
+    /// polynomial of degree < `data_shard_count` taking the values of the
+    /// data shards at the points of the data rows. The coefficients are thus
+    /// the Lagrange basis polynomials evaluated at `F::nth(r)`, and do not
+    /// depend on the parity shard count.
+    ///
+    /// Rows of the Cauchy matrix are likewise defined for any index.
+    fn extended_parity_row(&self, i_parity: usize) -> ShardVec<F::Elem> {
+        let r = self.data_shard_count + i_parity;
+
+        if self.matrix_kind == MatrixKind::Cauchy {
+            return (0..self.data_shard_count)
+                .map(|c| Self::cauchy_coefficient(r, c))
+                .collect();
+        }
+
+        let x = F::nth(r);
+
+        (0..self.data_shard_count)
+            .map(|c| {
+                let x_c = F::nth(c);
+                let mut numerator = F::one();
+                let mut denominator = F::one();
+                for d in (0..self.data_shard_count).filter(|&d| d != c) {
+                    let x_d = F::nth(d);
+                    // subtraction is addition in fields of characteristic 2
+                    numerator = F::mul(numerator, F::add(x, x_d));
+                    denominator = F::mul(denominator, F::add(x_c, x_d));
+                }
+                F::div(numerator, denominator)
+            })
+            .collect()
+    }
+
+    /// Constructs the parity shard `i_parity`, which may lie beyond the parity
+    /// shard count of this codec, for rateless transmission.
+    ///
+    /// A sender can keep generating new parity shards with increasing
+    /// `i_parity` until the receiver manages to decode. For `i_parity` below
+    /// the parity shard count, this is the usual parity shard.
+    ///
+    /// The parity shard `i_parity` of any codec with the same data shard count
+    /// and a parity shard count greater than `i_parity` is identical, so the
+    /// receiver can decode with a codec sized to the highest `i_parity`
+    /// received, marking the parity shards it did not receive as missing.
+    ///
+    /// The slot where the parity shard sits at will be overwritten.
+    ///
+    /// Return `Error::InvalidIndex` if `data_shard_count + i_parity >= F::ORDER`.
+    pub fn encode_extended_parity<T: AsRef<[F::Elem]>>(
+        &self,
+        i_parity: usize,
+        data: &[T],
+        parity: &mut [F::Elem],
+    ) -> Result<(), Error> {
+        if self.data_shard_count + i_parity >= F::ORDER {
+            return Err(Error::InvalidIndex);
+        }
+        check_piece_count!(data => self, data);
+        check_slices!(multi => data, single => parity);
+
+        let row = if i_parity < self.parity_shard_count {
+            SmallVec::from_slice(&self.get_parity_rows()[i_parity])
+        } else {
+            self.extended_parity_row(i_parity)
+        };
+
+        // Do the coding.
+        self.code_some_slices(&[&row[..]], data, &mut [parity]);
+        self.count_encoded(data);
+
+        Ok(())
+    }
+
+    /// Constructs only the parity shards selected by `rows`.
+    ///
+    /// `rows` holds parity shard indices in range [0, parity shard count),
+    /// and `parity[i]` receives the parity shard `rows[i]`. The slots where
+    /// the selected parity shards sit at will be overwritten.
+    ///
+    /// Return `Error::InvalidIndex` if any row is `>= parity_shard_count`.
+    ///
+    /// Return `Error::TooFewParityShards` or `Error::TooManyParityShards` when
+    /// the number of provided parity shards does not match the number of rows.
+    pub fn encode_parity_rows<T, U>(
+        &self,
+        rows: &[usize],
+        data: &[T],
+        parity: &mut [U],
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        check_piece_count!(data => self, data);
+        if parity.len() < rows.len() {
+            return Err(Error::TooFewParityShards);
+        }
+        if parity.len() > rows.len() {
+            return Err(Error::TooManyParityShards);
+        }
+        for &row in rows.iter() {
+            check_slice_index!(parity => self, row);
+        }
+
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        check_slices!(multi => data, multi => parity);
+
+        let parity_rows = self.get_parity_rows();
+        let matrix_rows: ShardVec<&[F::Elem]> =
+            rows.iter().map(|&row| &parity_rows[row][..]).collect();
+
+        // Do the coding.
+        self.code_some_slices(&matrix_rows, data, parity);
+        self.count_encoded(data);
+
+        Ok(())
+    }
+
+    /// Checks if the parity shards are correct.
+    ///
+    /// This is a wrapper of `verify_with_buffer`. The buffer is reused
+    /// across calls on the same codec.
+    pub fn verify<T: AsRef<[F::Elem]>>(&self, slices: &[T]) -> Result<bool, Error> {
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let slice_len = slices[0].as_ref().len();
+
+        let mut buffer = self.take_scratch(self.parity_shard_count, slice_len);
+
+        let result = self.verify_with_buffer(slices, &mut buffer);
+
+        self.return_scratch(buffer);
+
+        result
+    }
+
+    /// Checks if the parity shards are correct.
+    pub fn verify_with_buffer<T, U>(&self, slices: &[T], buffer: &mut [U]) -> Result<bool, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        check_piece_count!(all => self, slices);
+        check_piece_count!(parity_buf => self, buffer);
+        check_slices!(multi => slices, multi => buffer);
+
+        let data = &slices[0..self.data_shard_count];
+        let to_check = &slices[self.data_shard_count..];
+
+        let parity_rows = self.get_parity_rows();
+        Counters::add(&self.counters.stripes_verified, 1);
+
+        Ok(self.check_some_slices_with_buffer(parity_rows, data, to_check, buffer))
+    }
+
+    /// Checks if the parity shards are correct, with the data shards and
+    /// the parity shards given separately as in `encode_sep`.
+    ///
+    /// The buffer is reused across calls as in `verify`.
+    pub fn verify_sep<T, U>(&self, data: &[T], parity: &[U]) -> Result<bool, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]>,
+    {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+
+        let slice_len = data[0].as_ref().len();
+
+        let mut buffer = self.take_scratch(self.parity_shard_count, slice_len);
+
+        let result = self.verify_sep_with_buffer(data, parity, &mut buffer);
+
+        self.return_scratch(buffer);
+
+        result
+    }
+
+    /// Checks if the parity shards are correct, with the data shards and
+    /// the parity shards given separately as in `encode_sep`.
+    pub fn verify_sep_with_buffer<T, V, U>(
+        &self,
+        data: &[T],
+        parity: &[V],
+        buffer: &mut [U],
+    ) -> Result<bool, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        V: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_piece_count!(parity_buf => self, buffer);
+        check_slices!(multi => data, multi => parity);
+        check_slices!(multi => data, multi => buffer);
+
+        let parity_rows = self.get_parity_rows();
+        Counters::add(&self.counters.stripes_verified, 1);
+
+        Ok(self.check_some_slices_with_buffer(parity_rows, data, parity, buffer))
+    }
+
+    /// Checks if the parity shards are probably correct, computing a single
+    /// shard per round instead of every parity shard.
+    ///
+    /// Every round draws random coefficients `c`, one per parity shard, and
+    /// checks that the sum of `c[j]` times parity shard `j` equals the sum
+    /// of the data shards with the same combination of the parity rows.
+    /// This takes one pass over every shard, rather than a pass over every
+    /// data shard per parity shard as `verify` does, so it is about
+    /// `parity_shard_count` times cheaper per round.
+    ///
+    /// Correct parity shards are always accepted. Incorrect ones are
+    /// accepted with probability at most `1 / F::ORDER` per round, i.e.
+    /// `1 / 256` over GF(2^8), see `false_accept_probability`. The
+    /// coefficients are drawn afresh on every call, so corruption cannot be
+    /// made to pass without knowing them.
+    ///
+    /// The buffers are reused across calls as in `verify`.
+    pub fn verify_probabilistic<T: AsRef<[F::Elem]>>(
+        &self,
+        slices: &[T],
+        rounds: u32,
+    ) -> Result<bool, Error> {
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let (data, parity) = slices.split_at(self.data_shard_count);
+        let slice_len = data[0].as_ref().len();
+        Counters::add(&self.counters.stripes_verified, 1);
+
+        let combinations = self.random_combinations(rounds);
+        let mut buffers = self.take_scratch(2, slice_len);
+        let valid = self.check_combinations(&combinations, data, parity, &mut buffers);
+        self.return_scratch(buffers);
+
+        Ok(valid)
+    }
+
+    /// Returns the probability that `verify_probabilistic` accepts
+    /// incorrect parity shards, at most, with `rounds` rounds.
+    ///
+    /// For example, 4 rounds over GF(2^8) accept fewer than one stripe of
+    /// incorrect parity shards in 4 billion.
+    pub fn false_accept_probability(&self, rounds: u32) -> f64 {
+        (1.0 / F::ORDER as f64).powi(rounds.min(i32::MAX as u32) as i32)
+    }
+
+    /// Checks if the parity shards are correct, comparing them as selected
+    /// by `policy`.
+    ///
+    /// The buffer is reused across calls as in `verify`.
+    pub fn verify_with_policy<T: AsRef<[F::Elem]>>(
+        &self,
+        slices: &[T],
+        policy: VerifyPolicy,
+    ) -> Result<VerifyReport, Error> {
+        match policy {
+            VerifyPolicy::ShortCircuit => {
+                return Ok(VerifyReport {
+                    valid: self.verify(slices)?,
+                    mismatched: None,
+                })
+            }
+            VerifyPolicy::Probabilistic { rounds } => {
+                return Ok(VerifyReport {
+                    valid: self.verify_probabilistic(slices, rounds)?,
+                    mismatched: None,
+                })
+            }
+            VerifyPolicy::ConstantTime => {}
+        }
+
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let slice_len = slices[0].as_ref().len();
+        let mut buffer = self.take_scratch(self.parity_shard_count, slice_len);
+
+        let (data, to_check) = slices.split_at(self.data_shard_count);
+        self.code_some_slices(self.get_parity_rows(), data, &mut buffer);
+        Counters::add(&self.counters.stripes_verified, 1);
+
+        let mismatched = Self::count_mismatches(&buffer, to_check);
+
+        self.return_scratch(buffer);
+
+        Ok(VerifyReport {
+            valid: mismatched == 0,
+            mismatched: Some(mismatched),
+        })
+    }
+
+    /// Checks the parity shards of many stripes, comparing them as selected
+    /// by `policy`, and returns a report per stripe, in order.
+    ///
+    /// This is meant for scrubbing, where calling `verify` per stripe
+    /// spends much of its time setting up each call. Here the buffers are
+    /// taken once per thread, and with `VerifyPolicy::Probabilistic`, the
+    /// random coefficients are drawn once for the whole batch. The stripes
+    /// are spread over threads as configured by `param`, or by `pparam` if
+    /// `param` is `None`, and may differ in shard length.
+    ///
+    /// Returns the error of the first stripe whose shards do not fit the
+    /// codec, as `verify` would, and then no stripe is checked.
+    pub fn verify_batch<S, T, P>(
+        &self,
+        stripes: &[S],
+        policy: VerifyPolicy,
+        param: P,
+    ) -> Result<Vec<VerifyReport>, Error>
+    where
+        S: AsRef<[T]> + Sync,
+        T: AsRef<[F::Elem]> + Sync,
+        P: Into<Option<ParallelParam>>,
+        F::Elem: Send + Sync,
+    {
+        let param = param.into().unwrap_or_else(|| self.pparam());
+        let mut work = 0;
+        for stripe in stripes {
+            let slices = stripe.as_ref();
+            check_piece_count!(all => self, slices);
+            check_slices!(multi => slices);
+            work += slices.len() * slices[0].as_ref().len();
+        }
+
+        let (combinations, buffer_count) = match policy {
+            VerifyPolicy::Probabilistic { rounds } => (self.random_combinations(rounds), 2),
+            _ => (Vec::new(), self.parity_shard_count),
+        };
+        let parity_rows = self.get_parity_rows();
+        Counters::add(&self.counters.stripes_verified, stripes.len());
+
+        let max_threads = if THREADS_SUPPORTED {
+            param.thread_count.max(1)
+        } else {
+            1
+        };
+        let thread_count = (work / param.min_bytes_per_thread.max(1))
+            .clamp(1, max_threads)
+            .min(stripes.len().max(1));
+        let stripes_per_thread = stripes.len().div_ceil(thread_count).max(1);
+
+        let mut reports = vec![
+            VerifyReport {
+                valid: true,
+                mismatched: None,
+            };
+            stripes.len()
+        ];
+        let mut groups: Vec<_> = stripes
+            .chunks(stripes_per_thread)
+            .zip(reports.chunks_mut(stripes_per_thread))
+            .collect();
+
+        let verify_group = |(stripes, reports): &mut (&[S], &mut [VerifyReport])| {
+            let mut buffers = self.take_scratch(buffer_count, 0);
+            for (stripe, report) in stripes.iter().zip(reports.iter_mut()) {
+                let (data, parity) = stripe.as_ref().split_at(self.data_shard_count);
+                let slice_len = data[0].as_ref().len();
+                for buffer in buffers.iter_mut() {
+                    buffer.resize(slice_len, F::zero());
+                }
+
+                *report = match policy {
+                    VerifyPolicy::ShortCircuit => VerifyReport {
+                        valid: self.check_some_slices_with_buffer(
+                            parity_rows,
+                            data,
+                            parity,
+                            &mut buffers,
+                        ),
+                        mismatched: None,
+                    },
+                    VerifyPolicy::ConstantTime => {
+                        self.code_some_slices(parity_rows, data, &mut buffers);
+                        let mismatched = Self::count_mismatches(&buffers, parity);
+                        VerifyReport {
+                            valid: mismatched == 0,
+                            mismatched: Some(mismatched),
+                        }
+                    }
+                    VerifyPolicy::Probabilistic { .. } => VerifyReport {
+                        valid: self.check_combinations(&combinations, data, parity, &mut buffers),
+                        mismatched: None,
+                    },
+                };
+            }
+            self.return_scratch(buffers);
+        };
+        param.thread_source.for_each(&mut groups, &verify_group);
+
+        Ok(reports)
+    }
+
+    /// Checks the consistency of an incomplete set of shards, using
+    /// whichever parity shards are present.
+    ///
+    /// Shards marked `None` are missing. The first `data_shard_count` present
+    /// shards determine the data, and every further present shard (always a
+    /// parity shard) is checked against it.
+    ///
+    /// Returns whether all checks passed along with the number of shards
+    /// checked. No check is possible when exactly `data_shard_count` shards
+    /// are present, in which case `Ok((true, 0))` is returned.
+    ///
+    /// Return `Error::TooFewShardsPresent` when fewer than
+    /// `data_shard_count` shards are present.
+    pub fn verify_partial<T: AsRef<[F::Elem]>>(
+        &self,
+        slices: &[Option<T>],
+    ) -> Result<(bool, usize), Error> {
+        check_piece_count!(all => self, slices);
+
+        let data_shard_count = self.data_shard_count;
+
+        let mut number_present = 0;
+        let mut shard_len = None;
+
+        for slice in slices.iter().flatten() {
+            let len = slice.as_ref().len();
+            if len == 0 {
+                return Err(Error::EmptyShard);
+            }
+            if let Some(old_len) = shard_len {
+                if len != old_len {
+                    return Err(Error::IncorrectShardSize);
+                }
+            }
+            shard_len = Some(len);
+            number_present += 1;
+        }
+
+        if number_present < data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+        if number_present == data_shard_count {
+            return Ok((true, 0));
+        }
+
+        let shard_len = shard_len.expect("at least one shard present; qed");
+
+        // Split into the shards used to determine the data, the shards
+        // to check, and the missing shards, as in `reconstruct_internal`.
+        let mut sub_shards: ShardVec<&[F::Elem]> = SmallVec::with_capacity(data_shard_count);
+        let mut valid_indices: ShardVec<usize> = SmallVec::with_capacity(data_shard_count);
+        let mut invalid_indices: ShardVec<usize> = SmallVec::with_capacity(data_shard_count);
+        let mut to_check: ShardVec<usize> = SmallVec::new();
+
+        for (i, slice) in slices.iter().enumerate() {
+            match *slice {
+                Some(ref slice) => {
+                    if sub_shards.len() < data_shard_count {
+                        sub_shards.push(slice.as_ref());
+                        valid_indices.push(i);
+                    } else {
+                        to_check.push(i);
+                    }
+                }
+                None => invalid_indices.push(i),
+            }
+        }
+
+        // Recover any missing data shards into temporary buffers.
+        let data_decode_matrix = self.get_data_decode_matrix(&valid_indices, &invalid_indices);
+
+        let mut matrix_rows: ShardVec<&[F::Elem]> = SmallVec::new();
+        for &i in invalid_indices
+            .iter()
+            .take_while(|&&i| i < data_shard_count)
+        {
+            matrix_rows.push(data_decode_matrix.get_row(i));
+        }
+        let mut missing_data = self.take_scratch(matrix_rows.len() + 1, shard_len);
+        let mut buffer = [missing_data.pop().expect("at least one buffer taken; qed")];
+
+        self.code_some_slices(&matrix_rows, &sub_shards, &mut missing_data);
+
+        // The present data shards are the leading entries of `sub_shards`.
+        let mut all_data: ShardVec<&[F::Elem]> = SmallVec::with_capacity(data_shard_count);
+        let mut present_data = sub_shards.iter();
+        let mut recovered_data = missing_data.iter();
+        for slice in slices.iter().take(data_shard_count) {
+            match *slice {
+                Some(_) => all_data.push(present_data.next().unwrap()),
+                None => all_data.push(recovered_data.next().unwrap()),
+            }
+        }
+
+        // Check the remaining parity shards against the data.
+        let parity_rows = self.get_parity_rows();
+        let mut all_match = true;
+        for &i in to_check.iter() {
+            self.code_some_slices(&parity_rows[i - data_shard_count..], &all_data, &mut buffer);
+            if slices[i].as_ref().map(|x| x.as_ref()) != Some(&buffer[0][..]) {
+                all_match = false;
+            }
+        }
+
+        drop(all_data);
+        Counters::add(&self.counters.stripes_verified, 1);
+        self.return_scratch(
+            missing_data
+                .into_iter()
+                .chain(buffer.iter_mut().map(std::mem::take)),
+        );
+
+        Ok((all_match, to_check.len()))
+    }
+
+    /// Reconstructs all shards.
+    ///
+    /// The shards marked not present are only overwritten when no error
+    /// is detected. All provided shards must have the same length.
+    ///
+    /// This means if the method returns an `Error`, then nothing is touched.
+    ///
+    /// `reconstruct`, `reconstruct_data`, `reconstruct_shards`,
+    /// `reconstruct_data_shards` share the same core code base.
+    pub fn reconstruct<T: ReconstructShard<F>>(&self, slices: &mut [T]) -> Result<(), Error> {
+        self.reconstruct_internal(slices, false)
+    }
+
+    /// Reconstructs only the data shards.
+    ///
+    /// The shards marked not present are only overwritten when no error
+    /// is detected. All provided shards must have the same length.
+    ///
+    /// This means if the method returns an `Error`, then nothing is touched.
+    ///
+    /// `reconstruct`, `reconstruct_data`, `reconstruct_shards`,
+    /// `reconstruct_data_shards` share the same core code base.
+    pub fn reconstruct_data<T: ReconstructShard<F>>(&self, slices: &mut [T]) -> Result<(), Error> {
+        self.reconstruct_internal(slices, true)
+    }
+
+    /// Reconstructs all shards, like `reconstruct`, then checks the whole
+    /// stripe against its parity.
+    ///
+    /// Reconstruction only reads `data_shard_count` of the shards present,
+    /// so a corrupt shard among them yields wrong shards which look right.
+    /// When more shards are present, the others are checked against the
+    /// reconstructed ones, and `Error::InconsistentShards` is returned if
+    /// they do not match. The missing shards are then filled in, but with
+    /// wrong contents, and must be discarded.
+    ///
+    /// With exactly `data_shard_count` shards present, there is nothing to
+    /// check against, and the result is that of `reconstruct`.
+    pub fn reconstruct_verified<T: ReconstructShard<F>>(
+        &self,
+        slices: &mut [T],
+    ) -> Result<(), Error> {
+        let present = slices.iter().filter(|shard| shard.len().is_some()).count();
+
+        self.reconstruct_internal(slices, false)?;
+        if present == self.data_shard_count {
+            return Ok(());
+        }
+
+        let slices: ShardVec<&[F::Elem]> = slices
+            .iter_mut()
+            .map(|shard| {
+                shard
+                    .get_ref()
+                    .expect("all shards are present after reconstruction; qed")
+            })
+            .collect();
+        if self.verify(&slices)? {
+            Ok(())
+        } else {
+            Err(Error::InconsistentShards)
+        }
+    }
+
+    /// Reconstructs all shards, like `reconstruct`, using the caller-owned
+    /// workspace `scratch` for the index arrays and the decode matrix.
+    ///
+    /// Repeated calls with the same workspace and erasure pattern do not
+    /// allocate, except for the spill of internal buffers of codes wider
+    /// than their inline capacity (see the `wide-codes` feature).
+    pub fn reconstruct_with_scratch<T: ReconstructShard<F>>(
+        &self,
+        slices: &mut [T],
+        scratch: &mut ReconstructScratch<F>,
+    ) -> Result<(), Error> {
+        self.reconstruct_scratch_internal(slices, false, scratch)
+    }
+
+    /// Reconstructs only the data shards, like `reconstruct_data`, using the
+    /// caller-owned workspace `scratch`.
+    ///
+    /// See `reconstruct_with_scratch`.
+    pub fn reconstruct_data_with_scratch<T: ReconstructShard<F>>(
+        &self,
+        slices: &mut [T],
+        scratch: &mut ReconstructScratch<F>,
+    ) -> Result<(), Error> {
+        self.reconstruct_scratch_internal(slices, true, scratch)
+    }
+
+    /// Reconstructs all shards, with the shards present given by `present`
+    /// rather than marked in the shards themselves.
+    ///
+    /// The buffers of the shards missing from `present` must have the same
+    /// length as the present shards, and are overwritten.
+    ///
+    /// Return `Error::InvalidShardFlags` if `present` is not over
+    /// `total_shard_count` shards.
+    ///
+    /// Otherwise the errors are those of `reconstruct`.
+    pub fn reconstruct_present<U>(&self, shards: &mut [U], present: &ShardSet) -> Result<(), Error>
+    where
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.reconstruct_present_internal(shards, present, false)
+    }
+
+    /// Reconstructs only the data shards, with the shards present given by
+    /// `present`.
+    ///
+    /// The errors are those of `reconstruct_present`.
+    pub fn reconstruct_data_present<U>(
+        &self,
+        shards: &mut [U],
+        present: &ShardSet,
+    ) -> Result<(), Error>
+    where
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.reconstruct_present_internal(shards, present, true)
+    }
+
+    /// Reconstructs all shards, with the shards present given by the flags
+    /// in `present` rather than marked in the shards themselves.
+    ///
+    /// The buffers of the shards not flagged present must have the same
+    /// length as the present shards, and are overwritten.
+    ///
+    /// Return `Error::InvalidShardFlags` if the number of flags does not
+    /// match `total_shard_count`.
+    ///
+    /// Otherwise the errors are those of `reconstruct`.
+    pub fn reconstruct_flags<U>(&self, shards: &mut [U], present: &[bool]) -> Result<(), Error>
+    where
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        check_piece_count!(all => self, shards);
+        if present.len() != self.total_shard_count {
+            return Err(Error::InvalidShardFlags);
+        }
+        self.reconstruct_flagged(shards, |i| present[i], false)
+    }
+
+    /// Reconstructs only the data shards, with the shards present given by
+    /// the flags in `present`.
+    ///
+    /// The errors are those of `reconstruct_flags`.
+    pub fn reconstruct_data_flags<U>(&self, shards: &mut [U], present: &[bool]) -> Result<(), Error>
+    where
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        check_piece_count!(all => self, shards);
+        if present.len() != self.total_shard_count {
+            return Err(Error::InvalidShardFlags);
+        }
+        self.reconstruct_flagged(shards, |i| present[i], true)
+    }
+
+    /// Reconstructs all shards, building each missing shard with `alloc`
+    /// rather than by collecting zeros, e.g. to take the buffers from a pool
+    /// or an aligned allocator.
+    ///
+    /// The buffers returned by `alloc` are overwritten, so they need not be
+    /// zeroed.
+    ///
+    /// Return `Error::IncorrectShardSize` if `alloc` returns a buffer of a
+    /// different length than requested. Otherwise the errors are those of
+    /// `reconstruct`. `shards` is not touched on error.
+    pub fn reconstruct_alloc<T, A>(&self, shards: &mut [Option<T>], alloc: A) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+        A: ShardAllocator<T>,
+    {
+        self.reconstruct_alloc_internal(shards, alloc, false)
+    }
+
+    /// Reconstructs only the data shards, building each missing data shard
+    /// with `alloc` as in `reconstruct_alloc`.
+    ///
+    /// The errors are those of `reconstruct_alloc`.
+    pub fn reconstruct_data_alloc<T, A>(
+        &self,
+        shards: &mut [Option<T>],
+        alloc: A,
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+        A: ShardAllocator<T>,
+    {
+        self.reconstruct_alloc_internal(shards, alloc, true)
+    }
+
+    /// Reconstructs the missing shards, reading the shards present from
+    /// `shards` without modifying them, and writing the missing shards to
+    /// `outputs`.
+    ///
+    /// `shards` holds `None` for each missing shard. `outputs` holds one
+    /// buffer per missing shard, data or parity, in increasing shard index
+    /// order, of the same length as the shards present. The buffers are
+    /// overwritten. Every missing shard is decoded straight from the
+    /// shards present, so the recovered shards are never read back.
+    ///
+    /// Return `Error::TooFewBufferShards` or `Error::TooManyBufferShards`
+    /// when the number of buffers does not match the number of missing
+    /// shards, and `Error::IncorrectShardSize` when a buffer is not of the
+    /// length of the shards. Otherwise the errors are those of
+    /// `reconstruct`. `outputs` is not touched on error.
+    pub fn reconstruct_into<T, U>(
+        &self,
+        shards: &[Option<T>],
+        outputs: &mut [U],
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.reconstruct_sep_internal(shards, outputs, false)
+    }
+
+    /// Reconstructs only the data shards, reading the shards present from
+    /// `shards` without modifying them, and writing the missing data shards
+    /// to `outputs`.
+    ///
+    /// `shards` holds `None` for each missing shard. `outputs` holds one
+    /// buffer per missing data shard, in increasing shard index order, of
+    /// the same length as the shards present. The buffers are overwritten.
+    ///
+    /// Return `Error::TooFewBufferShards` or `Error::TooManyBufferShards`
+    /// when the number of buffers does not match the number of missing data
+    /// shards, and `Error::IncorrectShardSize` when a buffer is not of the
+    /// length of the shards. Otherwise the errors are those of
+    /// `reconstruct_data`. `outputs` is not touched on error.
+    pub fn reconstruct_data_sep<T, U>(
+        &self,
+        shards: &[Option<T>],
+        outputs: &mut [U],
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.reconstruct_sep_internal(shards, outputs, true)
+    }
+
+    /// Reconstructs the data shards as `reconstruct_data_sep` does, writing
+    /// them back-to-back into `output` in shard order, the data shards
+    /// present being copied.
+    ///
+    /// `output` receives the first `output.len()` elements of the data
+    /// shards, so that it can be exactly the length of the original object,
+    /// leaving out the padding. Only the missing data shards overlapping
+    /// `output` are decoded.
+    ///
+    /// Return `Error::IncorrectShardSize` if `output` is longer than the
+    /// data shards together. Otherwise the errors are those of
+    /// `reconstruct_data`. `output` is not touched on error.
+    pub fn reconstruct_data_gather<T>(
+        &self,
+        shards: &[Option<T>],
+        output: &mut [F::Elem],
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+    {
+        let (shard_len, valid_indices, invalid_indices, sub_shards) = self.sep_sources(shards)?;
+        if output.len() > shard_len * self.data_shard_count {
+            return Err(Error::IncorrectShardSize);
+        }
+
+        let mut missing: ShardVec<usize> = SmallVec::new();
+        for (i, chunk) in output.chunks_mut(shard_len).enumerate() {
+            match shards[i] {
+                Some(ref shard) => chunk.copy_from_slice(&shard.as_ref()[..chunk.len()]),
+                None => missing.push(i),
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let data_decode_matrix = self.get_data_decode_matrix(&valid_indices, &invalid_indices);
+
+        // Only the last chunk may be shorter than the shards, the elements
+        // of a shard only depending on the same elements of the others.
+        let mut chunks: ShardVec<&mut [F::Elem]> = output
+            .chunks_mut(shard_len)
+            .enumerate()
+            .filter(|(i, _)| missing.contains(i))
+            .map(|(_, chunk)| chunk)
+            .collect();
+        let partial = match chunks.last() {
+            Some(chunk) if chunk.len() < shard_len => chunks.pop(),
+            _ => None,
+        };
+
+        let matrix_rows: ShardVec<&[F::Elem]> = missing
+            .iter()
+            .map(|&i| data_decode_matrix.get_row(i))
+            .collect();
+        let (full_rows, partial_rows) = matrix_rows.split_at(chunks.len());
+        self.code_some_slices(full_rows, &sub_shards, &mut chunks);
+        if let Some(partial) = partial {
+            let len = partial.len();
+            let sub_shards: ShardVec<&[F::Elem]> =
+                sub_shards.iter().map(|shard| &shard[..len]).collect();
+            self.code_some_slices(partial_rows, &sub_shards, &mut [partial]);
+        }
+
+        let bytes_reconstructed = &self.counters.bytes_reconstructed;
+        Self::count_bytes(bytes_reconstructed, missing.len(), shard_len);
+
+        Ok(())
+    }
+
+    /// Checks the shards of `reconstruct_data_sep` and its variants,
+    /// returning the shard length, the indices of the shards used as
+    /// decoding input, the indices of the missing shards, and the shards
+    /// used.
+    #[allow(clippy::type_complexity)]
+    fn sep_sources<'a, T: AsRef<[F::Elem]>>(
+        &self,
+        shards: &'a [Option<T>],
+    ) -> Result<
+        (
+            usize,
+            ShardVec<usize>,
+            ShardVec<usize>,
+            ShardVec<&'a [F::Elem]>,
+        ),
+        Error,
+    > {
+        check_piece_count!(all => self, shards);
+
+        let mut shard_len = None;
+        let mut valid_indices: ShardVec<usize> = SmallVec::with_capacity(self.data_shard_count);
+        let mut invalid_indices: ShardVec<usize> = SmallVec::with_capacity(self.data_shard_count);
+        let mut sub_shards: ShardVec<&[F::Elem]> = SmallVec::with_capacity(self.data_shard_count);
+        for (i, shard) in shards.iter().enumerate() {
+            let shard = match shard {
+                Some(shard) => shard.as_ref(),
+                None => {
+                    invalid_indices.push(i);
+                    continue;
+                }
+            };
+            if shard.is_empty() {
+                return Err(Error::EmptyShard);
+            }
+            if shard_len.is_some_and(|len| len != shard.len()) {
+                return Err(Error::IncorrectShardSize);
+            }
+            shard_len = Some(shard.len());
+
+            if sub_shards.len() < self.data_shard_count {
+                sub_shards.push(shard);
+                valid_indices.push(i);
+            }
+        }
+
+        if sub_shards.len() < self.data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+        let shard_len = shard_len.expect("at least one shard present; qed");
+
+        Ok((shard_len, valid_indices, invalid_indices, sub_shards))
+    }
+
+    fn reconstruct_sep_internal<T, U>(
+        &self,
+        shards: &[Option<T>],
+        outputs: &mut [U],
+        data_only: bool,
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let (shard_len, valid_indices, invalid_indices, sub_shards) = self.sep_sources(shards)?;
+
+        let missing_data_count = invalid_indices
+            .iter()
+            .take_while(|&&i| i < self.data_shard_count)
+            .count();
+        let missing_count = if data_only {
+            missing_data_count
+        } else {
+            invalid_indices.len()
+        };
+        if outputs.len() < missing_count {
+            return Err(Error::TooFewBufferShards);
+        }
+        if outputs.len() > missing_count {
+            return Err(Error::TooManyBufferShards);
+        }
+        if outputs
+            .iter()
+            .any(|output| output.as_ref().len() != shard_len)
+        {
+            return Err(Error::IncorrectShardSize);
         }
-        if data_shards + parity_shards > F::ORDER {
-            return Err(Error::TooManyShards);
+        if missing_count == 0 {
+            return Ok(());
         }
 
-        let total_shards = data_shards + parity_shards;
+        let data_decode_matrix = self.get_data_decode_matrix(&valid_indices, &invalid_indices);
 
-        let matrix = Self::build_matrix(data_shards, total_shards);
+        // A missing parity shard is its encoding row applied to the data
+        // shards, which are in turn decoded from the shards present.
+        let parity_rows: ShardVec<Vec<F::Elem>> = invalid_indices
+            [missing_data_count..missing_count]
+            .iter()
+            .map(|&i| {
+                let encoding_row = self.matrix.get_row(i);
+                (0..self.data_shard_count)
+                    .map(|j| {
+                        encoding_row
+                            .iter()
+                            .enumerate()
+                            .fold(F::zero(), |acc, (c, &coef)| {
+                                F::add(acc, F::mul(coef, data_decode_matrix.get(c, j)))
+                            })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let matrix_rows: ShardVec<&[F::Elem]> = invalid_indices[..missing_data_count]
+            .iter()
+            .map(|&i| data_decode_matrix.get_row(i))
+            .chain(parity_rows.iter().map(Vec::as_slice))
+            .collect();
 
-        Ok(ReedSolomon {
-            data_shard_count: data_shards,
-            parity_shard_count: parity_shards,
-            total_shard_count: total_shards,
-            matrix,
-            tree: InversionTree::new(data_shards, parity_shards),
-        })
-    }
+        self.code_some_slices(&matrix_rows, &sub_shards, outputs);
+        let bytes_reconstructed = &self.counters.bytes_reconstructed;
+        Self::count_bytes(bytes_reconstructed, missing_count, shard_len);
 
-    pub fn data_shard_count(&self) -> usize {
-        self.data_shard_count
+        Ok(())
     }
 
-    pub fn parity_shard_count(&self) -> usize {
-        self.parity_shard_count
-    }
+    fn reconstruct_alloc_internal<T, A>(
+        &self,
+        shards: &mut [Option<T>],
+        mut alloc: A,
+        data_only: bool,
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+        A: ShardAllocator<T>,
+    {
+        check_piece_count!(all => self, shards);
 
-    pub fn total_shard_count(&self) -> usize {
-        self.total_shard_count
-    }
+        // Check the shards present upfront, so that nothing is allocated
+        // for a reconstruction bound to fail.
+        let mut shard_len = None;
+        let mut number_present = 0;
+        for shard in shards.iter().flatten() {
+            let len = shard.as_ref().len();
+            if len == 0 {
+                return Err(Error::EmptyShard);
+            }
+            if shard_len.is_some_and(|old_len| old_len != len) {
+                return Err(Error::IncorrectShardSize);
+            }
+            shard_len = Some(len);
+            number_present += 1;
+        }
+        if number_present == self.total_shard_count {
+            return Ok(());
+        }
+        if number_present < self.data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+        let shard_len = shard_len.expect("at least one shard present; qed");
 
-    fn code_some_slices<T: AsRef<[F::Elem]>, U: AsMut<[F::Elem]>>(
-        &self,
-        matrix_rows: &[&[F::Elem]],
-        inputs: &[T],
-        outputs: &mut [U],
-    ) {
-        for i_input in 0..self.data_shard_count {
-            self.code_single_slice(matrix_rows, i_input, inputs[i_input].as_ref(), outputs);
+        let wanted = if data_only {
+            self.data_shard_count
+        } else {
+            self.total_shard_count
+        };
+        let mut allocated: ShardVec<(usize, T)> = SmallVec::new();
+        for i in (0..wanted).filter(|&i| shards[i].is_none()) {
+            let mut shard = alloc.allocate(shard_len);
+            if shard.as_mut().len() != shard_len {
+                return Err(Error::IncorrectShardSize);
+            }
+            allocated.push((i, shard));
         }
-    }
 
-    fn code_single_slice<U: AsMut<[F::Elem]>>(
-        &self,
-        matrix_rows: &[&[F::Elem]],
-        i_input: usize,
-        input: &[F::Elem],
-        outputs: &mut [U],
-    ) {
-        outputs.iter_mut().enumerate().for_each(|(i_row, output)| {
-            let matrix_row_to_use = matrix_rows[i_row][i_input];
-            let output = output.as_mut();
+        let present: ShardVec<bool> = shards.iter().map(Option::is_some).collect();
+        for (i, shard) in allocated {
+            shards[i] = Some(shard);
+        }
 
-            if i_input == 0 {
-                F::mul_slice(matrix_row_to_use, input, output);
-            } else {
-                F::mul_slice_add(matrix_row_to_use, input, output);
-            }
-        })
+        let mut flagged: ShardVec<(&mut [F::Elem], bool)> = shards
+            .iter_mut()
+            .zip(present.iter())
+            .map(|(shard, &present)| match *shard {
+                Some(ref mut shard) => (shard.as_mut(), present),
+                // Only parity shards left out when reconstructing data.
+                None => (&mut [][..], false),
+            })
+            .collect();
+
+        self.reconstruct_internal(&mut flagged, data_only)
     }
 
-    fn check_some_slices_with_buffer<T, U>(
+    fn reconstruct_present_internal<U>(
         &self,
-        matrix_rows: &[&[F::Elem]],
-        inputs: &[T],
-        to_check: &[T],
-        buffer: &mut [U],
-    ) -> bool
+        shards: &mut [U],
+        present: &ShardSet,
+        data_only: bool,
+    ) -> Result<(), Error>
     where
-        T: AsRef<[F::Elem]>,
         U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
     {
-        self.code_some_slices(matrix_rows, inputs, buffer);
-
-        let at_least_one_mismatch_present = buffer
-            .iter_mut()
-            .enumerate()
-            .map(|(i, expected_parity_shard)|
-                expected_parity_shard.as_ref() == to_check[i].as_ref()
-            )
-            .any(|x| !x); // find the first false (some slice is different from the expected one)
-        !at_least_one_mismatch_present
+        check_piece_count!(all => self, shards);
+        if present.shard_count() != self.total_shard_count {
+            return Err(Error::InvalidShardFlags);
+        }
+        self.reconstruct_flagged(shards, |i| present.contains(i), data_only)
     }
 
-    /// Constructs the parity shards partially using only the data shard
-    /// indexed by `i_data`.
-    ///
-    /// The slots where the parity shards sit at will be overwritten.
-    ///
-    /// # Warning
-    ///
-    /// You must apply this method on the data shards in strict sequential order (0..data shard count),
-    /// otherwise the parity shards will be incorrect.
-    ///
-    /// It is recommended to use the `ShardByShard` bookkeeping struct instead of this method directly.
-    pub fn encode_single<T, U>(&self, i_data: usize, mut shards: T) -> Result<(), Error>
+    fn reconstruct_flagged<U, P>(
+        &self,
+        shards: &mut [U],
+        is_present: P,
+        data_only: bool,
+    ) -> Result<(), Error>
     where
-        T: AsRef<[U]> + AsMut<[U]>,
         U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+        P: Fn(usize) -> bool,
     {
-        let slices = shards.as_mut();
-
-        check_slice_index!(data => self, i_data);
-        check_piece_count!(all=> self, slices);
-        check_slices!(multi => slices);
-
-        // Get the slice of output buffers.
-        let (mut_input, output) = slices.split_at_mut(self.data_shard_count);
-
-        let input = mut_input[i_data].as_ref();
+        let mut flagged: ShardVec<_> = shards
+            .iter_mut()
+            .enumerate()
+            .map(|(i, shard)| (shard.as_mut(), is_present(i)))
+            .collect();
 
-        self.encode_single_sep(i_data, input, output)
+        self.reconstruct_internal(&mut flagged, data_only)
     }
 
-    /// Constructs the parity shards partially using only the data shard provided.
+    /// Reconstructs the shards missing from `shards`, a map from shard
+    /// index to shard data, and inserts them into the map.
     ///
-    /// The data shard must match the index `i_data`.
-    ///
-    /// The slots where the parity shards sit at will be overwritten.
-    ///
-    /// # Warning
+    /// Return `Error::InvalidIndex` if any key is `>= total_shard_count`.
     ///
-    /// You must apply this method on the data shards in strict sequential order (0..data shard count),
-    /// otherwise the parity shards will be incorrect.
+    /// Otherwise the errors are those of `reconstruct`, and the map is not
+    /// touched on error.
+    pub fn reconstruct_map<S: BuildHasher>(
+        &self,
+        shards: &mut HashMap<usize, Vec<F::Elem>, S>,
+    ) -> Result<(), Error> {
+        self.reconstruct_map_internal(shards, false)
+    }
+
+    /// Reconstructs only the data shards missing from `shards`, a map from
+    /// shard index to shard data, and inserts them into the map.
     ///
-    /// It is recommended to use the `ShardByShard` bookkeeping struct instead of this method directly.
-    pub fn encode_single_sep<U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+    /// The errors are those of `reconstruct_map`.
+    pub fn reconstruct_data_map<S: BuildHasher>(
         &self,
-        i_data: usize,
-        single_data: &[F::Elem],
-        parity: &mut [U],
+        shards: &mut HashMap<usize, Vec<F::Elem>, S>,
     ) -> Result<(), Error> {
-        check_slice_index!(data => self, i_data);
-        check_piece_count!(parity => self, parity);
-        check_slices!(multi => parity, single => single_data);
+        self.reconstruct_map_internal(shards, true)
+    }
 
-        let parity_rows = self.get_parity_rows();
+    /// Reconstructs all shards from `(index, shard)` pairs, in any order.
+    ///
+    /// Returns all shards, data shards first. If an index appears more than
+    /// once, the last shard given for it is used.
+    ///
+    /// The errors are those of `reconstruct_map`.
+    pub fn reconstruct_indexed<I>(&self, shards: I) -> Result<Vec<Vec<F::Elem>>, Error>
+    where
+        I: IntoIterator<Item = (usize, Vec<F::Elem>)>,
+    {
+        let mut slots: Vec<Option<Vec<F::Elem>>> = vec![None; self.total_shard_count];
+        for (index, shard) in shards {
+            check_slice_index!(all => self, index);
+            slots[index] = Some(shard);
+        }
 
-        // Do the coding.
-        self.code_single_slice(&parity_rows, i_data, single_data, parity);
+        self.reconstruct_internal(&mut slots, false)?;
 
-        Ok(())
+        Ok(slots
+            .into_iter()
+            .map(|shard| shard.expect("all shards are reconstructed; qed"))
+            .collect())
     }
 
-    /// Constructs the parity shards.
+    /// Reconstructs the data shards from shards obtained on demand.
     ///
-    /// The slots where the parity shards sit at will be overwritten.
-    pub fn encode<T, U>(&self, mut shards: T) -> Result<(), Error>
+    /// `provider` is called with shard indices in ascending order, data
+    /// shards first, until `data_shard_count` shards are obtained, so the
+    /// remaining shards are never requested. It returns `None` for a shard
+    /// which is unavailable. A shard which is empty, or of a different
+    /// length than the first shard obtained, is treated as unavailable.
+    ///
+    /// Returns the data shards.
+    ///
+    /// Return `Error::TooFewShardsPresent` when fewer than
+    /// `data_shard_count` shards could be obtained.
+    pub fn reconstruct_data_lazy<T, P>(&self, provider: P) -> Result<Vec<Vec<F::Elem>>, Error>
     where
-        T: AsRef<[U]> + AsMut<[U]>,
-        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+        T: AsRef<[F::Elem]>,
+        P: FnMut(usize) -> Option<T>,
     {
-        let slices: &mut [U] = shards.as_mut();
-
-        check_piece_count!(all => self, slices);
-        check_slices!(multi => slices);
+        let mut shards = self.provide_shards(provider)?;
 
-        // Get the slice of output buffers.
-        let (input, output) = slices.split_at_mut(self.data_shard_count);
+        self.reconstruct_internal(&mut shards, true)?;
 
-        self.encode_sep(&*input, output)
+        Ok(shards
+            .into_iter()
+            .take(self.data_shard_count)
+            .map(|shard| shard.expect("data shards are reconstructed; qed"))
+            .collect())
     }
 
-    /// Constructs the parity shards using a read-only view into the
-    /// data shards.
+    /// Reconstructs all shards from shards obtained on demand.
     ///
-    /// The slots where the parity shards sit at will be overwritten.
-    pub fn encode_sep<T: AsRef<[F::Elem]>, U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
-        &self,
-        data: &[T],
-        parity: &mut [U],
-    ) -> Result<(), Error> {
-        check_piece_count!(data => self, data);
-        check_piece_count!(parity => self, parity);
-        check_slices!(multi => data, multi => parity);
-
-        let parity_rows = self.get_parity_rows();
+    /// Returns all shards, data shards first.
+    ///
+    /// `provider` is called as in `reconstruct_data_lazy`, and the errors
+    /// are the same.
+    pub fn reconstruct_lazy<T, P>(&self, provider: P) -> Result<Vec<Vec<F::Elem>>, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        P: FnMut(usize) -> Option<T>,
+    {
+        let mut shards = self.provide_shards(provider)?;
 
-        // Do the coding.
-        self.code_some_slices(&parity_rows, data, parity);
+        self.reconstruct_internal(&mut shards, false)?;
 
-        Ok(())
+        Ok(shards
+            .into_iter()
+            .map(|shard| shard.expect("all shards are reconstructed; qed"))
+            .collect())
     }
 
-    /// Checks if the parity shards are correct.
-    ///
-    /// This is a wrapper of `verify_with_buffer`.
-    pub fn verify<T: AsRef<[F::Elem]>>(&self, slices: &[T]) -> Result<bool, Error> {
-        check_piece_count!(all => self, slices);
-        check_slices!(multi => slices);
+    fn provide_shards<T, P>(&self, mut provider: P) -> Result<Vec<Option<Vec<F::Elem>>>, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        P: FnMut(usize) -> Option<T>,
+    {
+        let mut shards: Vec<Option<Vec<F::Elem>>> = vec![None; self.total_shard_count];
+        let mut shard_len = None;
+        let mut number_present = 0;
 
-        let slice_len = slices[0].as_ref().len();
+        for (index, slot) in shards.iter_mut().enumerate() {
+            if number_present == self.data_shard_count {
+                break;
+            }
+
+            let shard = match provider(index) {
+                Some(shard) => shard,
+                None => continue,
+            };
+            let shard = shard.as_ref();
+            if shard.is_empty() || shard_len.is_some_and(|len| len != shard.len()) {
+                continue;
+            }
 
-        let mut buffer: SmallVec<[Vec<F::Elem>; 32]> = SmallVec::with_capacity(self.parity_shard_count);
+            shard_len = Some(shard.len());
+            *slot = Some(shard.to_vec());
+            number_present += 1;
+        }
 
-        for _ in 0..self.parity_shard_count {
-            buffer.push(vec![F::zero(); slice_len]);
+        if number_present < self.data_shard_count {
+            return Err(Error::TooFewShardsPresent);
         }
 
-        self.verify_with_buffer(slices, &mut buffer)
+        Ok(shards)
     }
 
-    /// Checks if the parity shards are correct.
-    pub fn verify_with_buffer<T, U>(&self, slices: &[T], buffer: &mut [U]) -> Result<bool, Error>
-    where
-        T: AsRef<[F::Elem]>,
-        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
-    {
-        check_piece_count!(all => self, slices);
-        check_piece_count!(parity_buf => self, buffer);
-        check_slices!(multi => slices, multi => buffer);
+    fn reconstruct_map_internal<S: BuildHasher>(
+        &self,
+        shards: &mut HashMap<usize, Vec<F::Elem>, S>,
+        data_only: bool,
+    ) -> Result<(), Error> {
+        for &index in shards.keys() {
+            check_slice_index!(all => self, index);
+        }
 
-        let data = &slices[0..self.data_shard_count];
-        let to_check = &slices[self.data_shard_count..];
+        let mut slots: Vec<Option<Vec<F::Elem>>> = (0..self.total_shard_count)
+            .map(|i| shards.remove(&i))
+            .collect();
 
-        let parity_rows = self.get_parity_rows();
+        let result = self.reconstruct_internal(&mut slots, data_only);
 
-        Ok(self.check_some_slices_with_buffer(&parity_rows, data, to_check, buffer))
-    }
+        // On error only the shards taken out of the map are present.
+        for (i, shard) in slots.into_iter().enumerate() {
+            if let Some(shard) = shard {
+                shards.insert(i, shard);
+            }
+        }
 
-    /// Reconstructs all shards.
-    ///
-    /// The shards marked not present are only overwritten when no error
-    /// is detected. All provided shards must have the same length.
-    ///
-    /// This means if the method returns an `Error`, then nothing is touched.
-    ///
-    /// `reconstruct`, `reconstruct_data`, `reconstruct_shards`,
-    /// `reconstruct_data_shards` share the same core code base.
-    pub fn reconstruct<T: ReconstructShard<F>>(&self, slices: &mut [T]) -> Result<(), Error> {
-        self.reconstruct_internal(slices, false)
+        result
     }
 
-    /// Reconstructs only the data shards.
-    ///
-    /// The shards marked not present are only overwritten when no error
-    /// is detected. All provided shards must have the same length.
-    ///
-    /// This means if the method returns an `Error`, then nothing is touched.
-    ///
-    /// `reconstruct`, `reconstruct_data`, `reconstruct_shards`,
-    /// `reconstruct_data_shards` share the same core code base.
-    pub fn reconstruct_data<T: ReconstructShard<F>>(&self, slices: &mut [T]) -> Result<(), Error> {
-        self.reconstruct_internal(slices, true)
+    /// Builds the matrix generating the data shards from the shards of
+    /// `valid_indices`, which holds exactly `data_shard_count` indices.
+    fn build_data_decode_matrix(&self, valid_indices: &[usize]) -> Matrix<F> {
+        // Pull out the rows of the matrix that correspond to the
+        // shards that we have and build a square matrix.  This
+        // matrix could be used to generate the shards that we have
+        // from the original data.  It is built transposed, as that
+        // is the matrix factorized below.
+        let mut sub_matrix_t: Matrix<F> = Matrix::new(self.data_shard_count, self.data_shard_count);
+        for (c, &valid_index) in valid_indices.iter().enumerate() {
+            for (r, &x) in self.matrix.get_row(valid_index).iter().enumerate() {
+                sub_matrix_t.set(r, c, x);
+            }
+        }
+        // The inverse of the sub matrix maps the shards that we have
+        // back to the original data, so its rows generate the data
+        // shards, but not the parity shards.
+        //
+        // The rows of the data shards that we have are unit rows, as
+        // the encoding matrix is systematic.  The other rows are
+        // solved one by one from the LU factorization, which is
+        // cheaper than inverting the whole matrix.  The matrices
+        // stay on the stack up to 32 data shards, and the vectors up
+        // to the inline capacity of `ShardVec`.
+        let lu = sub_matrix_t.into_lu().unwrap();
+        let mut data_decode_matrix = Matrix::new(self.data_shard_count, self.data_shard_count);
+        let mut unit: ShardVec<F::Elem> = SmallVec::from_elem(F::zero(), self.data_shard_count);
+        for r in 0..self.data_shard_count {
+            match valid_indices.iter().position(|&i| i == r) {
+                Some(c) => data_decode_matrix.set(r, c, F::one()),
+                None => {
+                    unit[r] = F::one();
+                    lu.solve_into(&unit, data_decode_matrix.get_row_mut(r));
+                    unit[r] = F::zero();
+                }
+            }
+        }
+
+        data_decode_matrix
     }
 
     fn get_data_decode_matrix(
@@ -857,22 +4573,7 @@ impl<F: Field> ReedSolomon<F> {
             // construct it ourselves and insert it into the tree for the
             // future.  In this way the inversion tree is lazily loaded.
             None => {
-                // Pull out the rows of the matrix that correspond to the
-                // shards that we have and build a square matrix.  This
-                // matrix could be used to generate the shards that we have
-                // from the original data.
-                let mut sub_matrix = Matrix::new(self.data_shard_count, self.data_shard_count);
-                for (sub_matrix_row, &valid_index) in valid_indices.into_iter().enumerate() {
-                    for c in 0..self.data_shard_count {
-                        sub_matrix.set(sub_matrix_row, c, self.matrix.get(valid_index, c));
-                    }
-                }
-                // Invert the matrix, so we can go from the encoded shards
-                // back to the original data.  Then pull out the row that
-                // generates the shard that we want to decode.  Note that
-                // since this matrix maps back to the original data, it can
-                // be used to create a data shard, but not a parity shard.
-                let data_decode_matrix = Arc::new(sub_matrix.invert().unwrap());
+                let data_decode_matrix = Arc::new(self.build_data_decode_matrix(valid_indices));
 
                 // Cache the inverted matrix in the tree for future use keyed on the
                 // indices of the invalid rows.
@@ -880,9 +4581,14 @@ impl<F: Field> ReedSolomon<F> {
                     .insert_inverted_matrix(&invalid_indices, &data_decode_matrix)
                     .unwrap();
 
+                Counters::add(&self.counters.cache_misses, 1);
+
                 data_decode_matrix
             }
-            Some(m) => m,
+            Some(m) => {
+                Counters::add(&self.counters.cache_hits, 1);
+                m
+            }
         }
     }
 
@@ -891,6 +4597,82 @@ impl<F: Field> ReedSolomon<F> {
         shards: &mut [T],
         data_only: bool,
     ) -> Result<(), Error> {
+        let mut valid_indices: ShardVec<usize> = SmallVec::with_capacity(self.data_shard_count);
+        let mut invalid_indices: ShardVec<usize> = SmallVec::with_capacity(self.data_shard_count);
+
+        self.reconstruct_with_indices(
+            shards,
+            data_only,
+            &mut valid_indices,
+            &mut invalid_indices,
+            None,
+        )
+    }
+
+    fn reconstruct_scratch_internal<T: ReconstructShard<F>>(
+        &self,
+        shards: &mut [T],
+        data_only: bool,
+        scratch: &mut ReconstructScratch<F>,
+    ) -> Result<(), Error> {
+        let mut valid_indices = std::mem::take(&mut scratch.valid_indices);
+        let mut invalid_indices = std::mem::take(&mut scratch.invalid_indices);
+        valid_indices.clear();
+        invalid_indices.clear();
+
+        let result = self.reconstruct_with_indices(
+            shards,
+            data_only,
+            &mut valid_indices,
+            &mut invalid_indices,
+            Some(scratch),
+        );
+
+        scratch.valid_indices = valid_indices;
+        scratch.invalid_indices = invalid_indices;
+
+        result
+    }
+
+    /// Returns the data decode matrix, from the workspace if it holds the
+    /// one of the same codec and erasure pattern.
+    fn get_data_decode_matrix_scratch(
+        &self,
+        valid_indices: &[usize],
+        invalid_indices: &[usize],
+        scratch: &mut ReconstructScratch<F>,
+    ) -> Arc<Matrix<F>> {
+        let codec_id = self.id;
+
+        if let Some(ref matrix) = scratch.decode_matrix {
+            if scratch.codec_id == codec_id && scratch.decode_key[..] == *invalid_indices {
+                Counters::add(&self.counters.cache_hits, 1);
+                return Arc::clone(matrix);
+            }
+        }
+
+        let matrix = self.get_data_decode_matrix(valid_indices, invalid_indices);
+
+        scratch.codec_id = codec_id;
+        scratch.decode_key.clear();
+        scratch.decode_key.extend_from_slice(invalid_indices);
+        scratch.decode_matrix = Some(Arc::clone(&matrix));
+
+        matrix
+    }
+
+    fn reconstruct_with_indices<T, V>(
+        &self,
+        shards: &mut [T],
+        data_only: bool,
+        valid_indices: &mut V,
+        invalid_indices: &mut V,
+        scratch: Option<&mut ReconstructScratch<F>>,
+    ) -> Result<(), Error>
+    where
+        T: ReconstructShard<F>,
+        V: Extend<usize> + std::ops::Deref<Target = [usize]>,
+    {
         check_piece_count!(all => self, shards);
 
         let data_shard_count = self.data_shard_count;
@@ -945,30 +4727,33 @@ impl<F: Field> ReedSolomon<F> {
         // as the data decode matrix is a N x N matrix, thus only needs
         // N valid indices for determining the N rows to pick from
         // `self.matrix`.
-        let mut sub_shards: SmallVec<[&[F::Elem]; 32]> = SmallVec::with_capacity(data_shard_count);
-        let mut missing_data_slices: SmallVec<[&mut [F::Elem]; 32]> =
+        let mut sub_shards: ShardVec<&[F::Elem]> = SmallVec::with_capacity(data_shard_count);
+        let mut missing_data_slices: ShardVec<&mut [F::Elem]> =
             SmallVec::with_capacity(self.parity_shard_count);
-        let mut missing_parity_slices: SmallVec<[&mut [F::Elem]; 32]> =
+        let mut missing_parity_slices: ShardVec<&mut [F::Elem]> =
             SmallVec::with_capacity(self.parity_shard_count);
-        let mut valid_indices: SmallVec<[usize; 32]> = SmallVec::with_capacity(data_shard_count);
-        let mut invalid_indices: SmallVec<[usize; 32]> = SmallVec::with_capacity(data_shard_count);
 
         // Separate the shards into groups
         for (matrix_row, shard) in shards.into_iter().enumerate() {
             // get or initialize the shard so we can reconstruct in-place,
             // but if we are only reconstructing data shard,
             // do not initialize if the shard is not a data shard
-            let shard_data = if matrix_row >= data_shard_count && data_only {
-                shard.get().ok_or(None)
+            let shard_data = if shard.len().is_some() {
+                Ok(shard.get_ref().expect("present shards hold data; qed"))
+            } else if matrix_row >= data_shard_count && data_only {
+                Err(None)
             } else {
-                shard.get_or_initialize(shard_len).map_err(Some)
+                shard
+                    .get_or_initialize(shard_len)
+                    .map(|x| &*x)
+                    .map_err(Some)
             };
 
             match shard_data {
                 Ok(shard) => {
                     if sub_shards.len() < data_shard_count {
                         sub_shards.push(shard);
-                        valid_indices.push(matrix_row);
+                        valid_indices.extend(iter::once(matrix_row));
                     } else {
                         // Already have enough shards in `sub_shards`
                         // as we only need N shards, where N = `data_shard_count`,
@@ -980,7 +4765,7 @@ impl<F: Field> ReedSolomon<F> {
                 Err(None) => {
                     // the shard data is not meant to be initialized here,
                     // but we should still note it missing.
-                    invalid_indices.push(matrix_row);
+                    invalid_indices.extend(iter::once(matrix_row));
                 }
                 Err(Some(x)) => {
                     // initialized missing shard data.
@@ -991,19 +4776,24 @@ impl<F: Field> ReedSolomon<F> {
                         missing_parity_slices.push(shard);
                     }
 
-                    invalid_indices.push(matrix_row);
+                    invalid_indices.extend(iter::once(matrix_row));
                 }
             }
         }
 
-        let data_decode_matrix = self.get_data_decode_matrix(&valid_indices, &invalid_indices);
+        let data_decode_matrix = match scratch {
+            Some(scratch) => {
+                self.get_data_decode_matrix_scratch(valid_indices, invalid_indices, scratch)
+            }
+            None => self.get_data_decode_matrix(valid_indices, invalid_indices),
+        };
 
         // Re-create any data shards that were missing.
         //
         // The input to the coding is all of the shards we actually
         // have, and the output is the missing data shards. The computation
         // is done using the special decode matrix we just built.
-        let mut matrix_rows: SmallVec<[&[F::Elem]; 32]> =
+        let mut matrix_rows: ShardVec<&[F::Elem]> =
             SmallVec::with_capacity(self.parity_shard_count);
 
         for i_slice in invalid_indices
@@ -1015,6 +4805,8 @@ impl<F: Field> ReedSolomon<F> {
         }
 
         self.code_some_slices(&matrix_rows, &sub_shards, &mut missing_data_slices);
+        let bytes_reconstructed = &self.counters.bytes_reconstructed;
+        Self::count_bytes(bytes_reconstructed, missing_data_slices.len(), shard_len);
 
         if data_only {
             Ok(())
@@ -1025,7 +4817,7 @@ impl<F: Field> ReedSolomon<F> {
             // The input to the coding is ALL of the data shards, including
             // any that we just calculated.  The output is whichever of the
             // parity shards were missing.
-            let mut matrix_rows: SmallVec<[&[F::Elem]; 32]> =
+            let mut matrix_rows: ShardVec<&[F::Elem]> =
                 SmallVec::with_capacity(self.parity_shard_count);
             let parity_rows = self.get_parity_rows();
 
@@ -1034,7 +4826,7 @@ impl<F: Field> ReedSolomon<F> {
                 .cloned()
                 .skip_while(|i| i < &data_shard_count)
             {
-                matrix_rows.push(parity_rows[i_slice - data_shard_count]);
+                matrix_rows.push(&parity_rows[i_slice - data_shard_count]);
             }
             {
                 // Gather up all the data shards.
@@ -1043,7 +4835,7 @@ impl<F: Field> ReedSolomon<F> {
                 let mut i_old_data_slice = 0;
                 let mut i_new_data_slice = 0;
 
-                let mut all_data_slices: SmallVec<[&[F::Elem]; 32]> =
+                let mut all_data_slices: ShardVec<&[F::Elem]> =
                     SmallVec::with_capacity(data_shard_count);
 
                 let mut next_maybe_good = 0;
@@ -1073,6 +4865,7 @@ impl<F: Field> ReedSolomon<F> {
                 // parity shards
                 self.code_some_slices(&matrix_rows, &all_data_slices, &mut missing_parity_slices);
             }
+            Self::count_bytes(bytes_reconstructed, missing_parity_slices.len(), shard_len);
 
             Ok(())
         }
@@ -0,0 +1,314 @@
+//! Manifests of sharded objects.
+//!
+//! A `Manifest` records what the shards of an object should look like: the
+//! geometry of the codec, the length and the digest of every shard, and the
+//! length of the object before padding. Stored next to the shards, it lets
+//! a reader reject corrupted or stale shards with `Manifest::validate`
+//! before reconstructing from them, then strip the padding.
+//!
+//! The digests are computed by a function of the caller, e.g. SHA-256 or
+//! CRC-32C, and stored as opaque bytes.
+//!
+//! # Binary form
+//!
+//! The binary form is canonical: equal manifests have equal binary forms.
+//! All integers are little-endian.
+//!
+//! | Field               | Size                                       |
+//! |---------------------|--------------------------------------------|
+//! | magic `b"RSMF"`     | 4                                          |
+//! | version, `1`        | 1                                          |
+//! | data shards `k`     | 2                                          |
+//! | parity shards `m`   | 2                                          |
+//! | original length     | 8                                          |
+//! | shards              | `k + m` times an 8 byte shard length, a 2 byte digest length and the digest |
+
+use std::io;
+
+use crate::vectors::ByteReader;
+use crate::{Error, Field, ReedSolomon};
+
+const MAGIC: &[u8; 4] = b"RSMF";
+
+const VERSION: u8 = 1;
+
+/// The expected geometry, shard lengths and shard digests of an object.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Manifest {
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    original_len: u64,
+    shard_lens: Vec<usize>,
+    digests: Vec<Vec<u8>>,
+}
+
+/// Collects the shards of a `Manifest` one at a time, e.g. as they are
+/// written out by a streaming encoder.
+#[derive(Debug, Clone)]
+pub struct ManifestBuilder {
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    original_len: u64,
+    shards: Vec<Option<(usize, Vec<u8>)>>,
+}
+
+impl ManifestBuilder {
+    /// Creates a builder for the shards of `codec`, encoding an object of
+    /// `original_len` elements.
+    pub fn new<F: Field>(codec: &ReedSolomon<F>, original_len: u64) -> ManifestBuilder {
+        ManifestBuilder {
+            data_shard_count: codec.data_shard_count,
+            parity_shard_count: codec.parity_shard_count,
+            original_len,
+            shards: vec![None; codec.total_shard_count],
+        }
+    }
+
+    /// Records the length, in elements, and the digest of the shard at
+    /// `index`, replacing any earlier record of that shard.
+    ///
+    /// Returns `Error::InvalidIndex` if `index` is not a valid shard index.
+    pub fn add_shard(&mut self, index: usize, len: usize, digest: &[u8]) -> Result<(), Error> {
+        let shard = self.shards.get_mut(index).ok_or(Error::InvalidIndex)?;
+        *shard = Some((len, digest.to_vec()));
+        Ok(())
+    }
+
+    /// Builds the manifest.
+    ///
+    /// Returns `Error::InvalidManifest` if some shards were not added, if
+    /// the shards are not all of the same non-zero length, if they cannot
+    /// hold `original_len` elements, or if a digest is longer than 65535
+    /// bytes.
+    pub fn build(self) -> Result<Manifest, Error> {
+        let mut shard_lens = Vec::with_capacity(self.shards.len());
+        let mut digests = Vec::with_capacity(self.shards.len());
+        for shard in self.shards {
+            let (len, digest) = shard.ok_or(Error::InvalidManifest)?;
+            shard_lens.push(len);
+            digests.push(digest);
+        }
+
+        Manifest::checked(
+            self.data_shard_count,
+            self.parity_shard_count,
+            self.original_len,
+            shard_lens,
+            digests,
+        )
+        .ok_or(Error::InvalidManifest)
+    }
+}
+
+impl Manifest {
+    fn checked(
+        data_shard_count: usize,
+        parity_shard_count: usize,
+        original_len: u64,
+        shard_lens: Vec<usize>,
+        digests: Vec<Vec<u8>>,
+    ) -> Option<Manifest> {
+        let shard_len = shard_lens[0];
+        if shard_len == 0
+            || shard_lens.iter().any(|&len| len != shard_len)
+            || original_len > shard_len as u64 * data_shard_count as u64
+            || digests
+                .iter()
+                .any(|digest| digest.len() > u16::MAX as usize)
+        {
+            return None;
+        }
+
+        Some(Manifest {
+            data_shard_count,
+            parity_shard_count,
+            original_len,
+            shard_lens,
+            digests,
+        })
+    }
+
+    pub fn data_shard_count(&self) -> usize {
+        self.data_shard_count
+    }
+
+    pub fn parity_shard_count(&self) -> usize {
+        self.parity_shard_count
+    }
+
+    pub fn total_shard_count(&self) -> usize {
+        self.data_shard_count + self.parity_shard_count
+    }
+
+    /// Returns the length of the object in elements, the data shards
+    /// holding it followed by padding.
+    pub fn original_len(&self) -> u64 {
+        self.original_len
+    }
+
+    /// Returns the length of the shard at `index` in elements, or `None` if
+    /// `index` is not a valid shard index.
+    pub fn shard_len(&self, index: usize) -> Option<usize> {
+        self.shard_lens.get(index).cloned()
+    }
+
+    /// Returns the digest of the shard at `index`, or `None` if `index` is
+    /// not a valid shard index.
+    pub fn digest(&self, index: usize) -> Option<&[u8]> {
+        self.digests.get(index).map(Vec::as_slice)
+    }
+
+    /// Checks `shards` against the manifest before a reconstruction,
+    /// removing the shards of the wrong length or not matching their
+    /// digest.
+    ///
+    /// `digest` must be the function the manifest was built with. Returns
+    /// the indices of the removed shards in ascending order, missing shards
+    /// not included. If the result is empty and all shards are present,
+    /// nothing needs repairing.
+    ///
+    /// `shards` is not touched on error.
+    ///
+    /// Returns `Error::InvalidManifest` if `codec` does not have the
+    /// geometry of the manifest.
+    ///
+    /// Returns `Error::TooFewShards` or `Error::TooManyShards` if the
+    /// number of shards is not `total_shard_count`.
+    ///
+    /// Returns `Error::TooFewShardsPresent` if fewer than
+    /// `data_shard_count` shards would be left.
+    pub fn validate<F, T, H, D>(
+        &self,
+        codec: &ReedSolomon<F>,
+        shards: &mut [Option<T>],
+        mut digest: H,
+    ) -> Result<Vec<usize>, Error>
+    where
+        F: Field,
+        T: AsRef<[F::Elem]>,
+        H: FnMut(&[F::Elem]) -> D,
+        D: AsRef<[u8]>,
+    {
+        if codec.data_shard_count != self.data_shard_count
+            || codec.parity_shard_count != self.parity_shard_count
+        {
+            return Err(Error::InvalidManifest);
+        }
+        check_piece_count!(all => codec, shards);
+
+        let mut rejected = Vec::new();
+        let mut present = 0;
+        for (i, shard) in shards.iter().enumerate() {
+            if let Some(shard) = shard {
+                let shard = shard.as_ref();
+                if shard.len() == self.shard_lens[i]
+                    && digest(shard).as_ref() == &self.digests[i][..]
+                {
+                    present += 1;
+                } else {
+                    rejected.push(i);
+                }
+            }
+        }
+
+        if present < self.data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+        for &i in rejected.iter() {
+            shards[i] = None;
+        }
+
+        Ok(rejected)
+    }
+
+    /// Returns the binary form of the manifest.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(self.data_shard_count as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.parity_shard_count as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.original_len.to_le_bytes());
+        for (&len, digest) in self.shard_lens.iter().zip(self.digests.iter()) {
+            bytes.extend_from_slice(&(len as u64).to_le_bytes());
+            bytes.extend_from_slice(&(digest.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(digest);
+        }
+
+        bytes
+    }
+
+    /// Parses the binary form of a manifest.
+    ///
+    /// Returns an error of kind `InvalidData` if `bytes` is not a manifest
+    /// of a supported version, or not a manifest `ManifestBuilder::build`
+    /// would build.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Manifest> {
+        let mut reader = ByteReader { bytes };
+
+        if reader.take(4)? != MAGIC || reader.take(1)?[0] != VERSION {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let data_shard_count = reader.u16()?;
+        let parity_shard_count = reader.u16()?;
+        let original_len = reader.u64()?;
+        if data_shard_count == 0 {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let total_shard_count = data_shard_count + parity_shard_count;
+
+        let mut shard_lens = Vec::with_capacity(total_shard_count);
+        let mut digests = Vec::with_capacity(total_shard_count);
+        for _ in 0..total_shard_count {
+            shard_lens.push(reader.u64()? as usize);
+            let digest_len = reader.u16()?;
+            digests.push(reader.take(digest_len)?.to_vec());
+        }
+        if !reader.bytes.is_empty() {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        Manifest::checked(
+            data_shard_count,
+            parity_shard_count,
+            original_len,
+            shard_lens,
+            digests,
+        )
+        .ok_or_else(|| io::ErrorKind::InvalidData.into())
+    }
+}
+
+impl<F: Field> ReedSolomon<F> {
+    /// Builds the manifest of the encoded `shards` of an object of
+    /// `original_len` elements, computing the shard digests with `digest`.
+    ///
+    /// Returns `Error::TooFewShards` or `Error::TooManyShards` if the
+    /// number of shards is not `total_shard_count`.
+    ///
+    /// Returns `Error::InvalidManifest` if the shards are not all of the
+    /// same non-zero length, if they cannot hold `original_len` elements,
+    /// or if a digest is longer than 65535 bytes.
+    pub fn manifest<T, H, D>(
+        &self,
+        shards: &[T],
+        original_len: u64,
+        mut digest: H,
+    ) -> Result<Manifest, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        H: FnMut(&[F::Elem]) -> D,
+        D: AsRef<[u8]>,
+    {
+        check_piece_count!(all => self, shards);
+
+        let mut builder = ManifestBuilder::new(self, original_len);
+        for (i, shard) in shards.iter().enumerate() {
+            let shard = shard.as_ref();
+            builder.add_shard(i, shard.len(), digest(shard).as_ref())?;
+        }
+
+        builder.build()
+    }
+}
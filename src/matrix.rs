@@ -1,7 +1,20 @@
 #![allow(dead_code)]
-use crate::Field;
+use crate::{pool, Field, ShardVec};
 use smallvec::SmallVec;
 
+/// Size from which `into_lu` spreads the row operations over threads.
+/// Below it, handing work to other threads costs more than it saves, and
+/// only wide codes over GF(2^16) reach it.
+const PAR_ELIM_MIN_ELEMS: usize = 1 << 17;
+
+/// Fewest elements below the pivot that `into_lu` hands to a thread of the
+/// pool. The last columns leave too few rows to be worth splitting.
+const PAR_ELIM_MIN_BLOCK_ELEMS: usize = 1 << 14;
+
+/// Number of field operations from which `fill_rows` spreads the rows
+/// over threads, reached by encoding matrices of wide codes over GF(2^16).
+const PAR_FILL_MIN_OPS: usize = 1 << 18;
+
 #[derive(Debug)]
 pub enum Error {
     SingularMatrix,
@@ -25,7 +38,7 @@ pub fn flatten<T>(m: Vec<Vec<T>>) -> Vec<T> {
     result
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug)]
 pub struct Matrix<F: Field> {
     row_count: usize,
     col_count: usize,
@@ -33,6 +46,16 @@ pub struct Matrix<F: Field> {
                                 // the smallvec can hold a matrix of size up to 32x32 in stack
 }
 
+impl<F: Field> Clone for Matrix<F> {
+    fn clone(&self) -> Matrix<F> {
+        Matrix {
+            row_count: self.row_count,
+            col_count: self.col_count,
+            data: self.data.clone(),
+        }
+    }
+}
+
 fn calc_matrix_row_start_end(col_count: usize, row: usize) -> (usize, usize) {
     let start = row * col_count;
     let end = start + col_count;
@@ -40,13 +63,56 @@ fn calc_matrix_row_start_end(col_count: usize, row: usize) -> (usize, usize) {
     (start, end)
 }
 
+/// The PLU factorization of a square matrix `A`, i.e. `P * A = L * U`
+/// with `L` unit lower triangular and `U` upper triangular, both stored in
+/// `lu`. Solving `A * x = b` then takes `O(n^2)` instead of the `O(n^3)` of
+/// an inversion.
+#[derive(Debug)]
+pub struct Lu<F: Field> {
+    lu: Matrix<F>,
+    // row `i` of `P * A` is row `perm[i]` of `A`
+    perm: ShardVec<usize>,
+}
+
+impl<F: Field> Lu<F> {
+    /// Solves `A * x = b`.
+    pub fn solve(&self, b: &[F::Elem]) -> Vec<F::Elem> {
+        let mut x = vec![F::zero(); self.lu.row_count];
+        self.solve_into(b, &mut x);
+        x
+    }
+
+    /// Solves `A * x = b`, writing `x` to `x` rather than allocating it.
+    pub fn solve_into(&self, b: &[F::Elem], x: &mut [F::Elem]) {
+        let lu = &self.lu;
+        let n = lu.row_count;
+
+        // L * y = P * b
+        for (x, &i) in x.iter_mut().zip(self.perm.iter()) {
+            *x = b[i];
+        }
+        for i in 0..n {
+            for j in 0..i {
+                x[i] = F::add(x[i], F::mul(acc!(lu, i, j), x[j]));
+            }
+        }
+        // U * x = y
+        for i in (0..n).rev() {
+            for j in i + 1..n {
+                x[i] = F::add(x[i], F::mul(acc!(lu, i, j), x[j]));
+            }
+            x[i] = F::div(x[i], acc!(lu, i, i));
+        }
+    }
+}
+
 impl<F: Field> Matrix<F> {
     fn calc_row_start_end(&self, row: usize) -> (usize, usize) {
         calc_matrix_row_start_end(self.col_count, row)
     }
 
     pub fn new(rows: usize, cols: usize) -> Matrix<F> {
-        let data = SmallVec::from_vec(vec![F::zero(); rows * cols]);
+        let data = SmallVec::from_elem(F::zero(), rows * cols);
 
         Matrix {
             row_count: rows,
@@ -160,7 +226,7 @@ impl<F: Field> Matrix<F> {
         let mut result = Self::new(rmax - rmin, cmax - cmin);
         for r in rmin..rmax {
             for c in cmin..cmax {
-                acc!(result, r - rmin, c - cmin) = acc!(self, r, c).clone();
+                acc!(result, r - rmin, c - cmin) = acc!(self, r, c);
             }
         }
         result
@@ -172,6 +238,12 @@ impl<F: Field> Matrix<F> {
         &self.data[start..end]
     }
 
+    pub fn get_row_mut(&mut self, row: usize) -> &mut [F::Elem] {
+        let (start, end) = self.calc_row_start_end(row);
+
+        &mut self.data[start..end]
+    }
+
     pub fn swap_rows(&mut self, r1: usize, r2: usize) {
         let (r1_s, _) = self.calc_row_start_end(r1);
         let (r2_s, _) = self.calc_row_start_end(r2);
@@ -190,6 +262,8 @@ impl<F: Field> Matrix<F> {
     }
 
     pub fn gaussian_elim(&mut self) -> Result<(), Error> {
+        let col_count = self.col_count;
+
         for r in 0..self.row_count {
             if acc!(self, r, r) == F::zero() {
                 for r_below in r + 1..self.row_count {
@@ -206,41 +280,80 @@ impl<F: Field> Matrix<F> {
             // Scale to 1.
             if acc!(self, r, r) != F::one() {
                 let scale = F::div(F::one(), acc!(self, r, r).clone());
-                for c in 0..self.col_count {
+                for c in r..col_count {
                     acc!(self, r, c) = F::mul(scale, acc!(self, r, c).clone());
                 }
             }
-            // Make everything below the 1 be a 0 by subtracting
-            // a multiple of it.  (Subtraction and addition are
-            // both exclusive or in the Galois field.)
-            for r_below in r + 1..self.row_count {
-                if acc!(self, r_below, r) != F::zero() {
-                    let scale = acc!(self, r_below, r).clone();
-                    for c in 0..self.col_count {
-                        acc!(self, r_below, c) = F::add(
-                            acc!(self, r_below, c).clone(),
-                            F::mul(scale, acc!(self, r, c).clone())
-                        );
-                    }
+
+            // Make everything above and below the 1 be a 0 by subtracting
+            // a multiple of it.  (Subtraction and addition are both
+            // exclusive or in the Galois field.)  The columns left of `r`
+            // are zero in the pivot row, so are left as they are.
+            let (above, rest) = self.data.split_at_mut(r * col_count);
+            let (pivot_row, below) = rest.split_at_mut(col_count);
+            let pivot_row = &pivot_row[r..];
+
+            for row in above
+                .chunks_mut(col_count)
+                .chain(below.chunks_mut(col_count))
+            {
+                let scale = row[r];
+                if scale != F::zero() {
+                    F::mul_slice_add(scale, pivot_row, &mut row[r..]);
                 }
             }
         }
 
-        // Now clear the part above the main diagonal.
-        for d in 0..self.row_count {
-            for r_above in 0..d {
-                if acc!(self, r_above, d) != F::zero() {
-                    let scale = acc!(self, r_above, d).clone();
-                    for c in 0..self.col_count {
-                        acc!(self, r_above, c) = F::add(
-                            acc!(self, r_above, c).clone(),
-                            F::mul(scale, acc!(self, d, c).clone())
-                        );
-                    }
-                }
+        Ok(())
+    }
+
+    /// Sets the rows from `start_row` on with `fill`, which is given the
+    /// index of a row and its elements to overwrite, and does about
+    /// `ops_per_row` field operations per row.
+    ///
+    /// The rows are shared among threads when there is enough work.
+    pub fn fill_rows<G>(&mut self, start_row: usize, ops_per_row: usize, fill: G)
+    where
+        G: Fn(usize, &mut [F::Elem]) + Sync,
+    {
+        let ops = (self.row_count - start_row).saturating_mul(ops_per_row);
+        let thread_count = if crate::THREADS_SUPPORTED && ops >= PAR_FILL_MIN_OPS {
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            1
+        };
+
+        self.fill_rows_threads(start_row, thread_count, fill)
+    }
+
+    fn fill_rows_threads<G>(&mut self, start_row: usize, thread_count: usize, fill: G)
+    where
+        G: Fn(usize, &mut [F::Elem]) + Sync,
+    {
+        let col_count = self.col_count;
+        if col_count == 0 {
+            return;
+        }
+        let rows = &mut self.data[start_row * col_count..];
+
+        let rows_per_thread = (rows.len() / col_count).div_ceil(thread_count).max(1);
+        let fill_block = |first_row: usize, block: &mut [F::Elem]| {
+            for (i, row) in block.chunks_mut(col_count).enumerate() {
+                fill(first_row + i, row);
             }
+        };
+
+        if thread_count == 1 {
+            fill_block(start_row, rows);
+        } else {
+            std::thread::scope(|scope| {
+                let fill_block = &fill_block;
+                for (i, block) in rows.chunks_mut(rows_per_thread * col_count).enumerate() {
+                    let first_row = start_row + i * rows_per_thread;
+                    scope.spawn(move || fill_block(first_row, block));
+                }
+            });
         }
-        Ok(())
     }
 
     pub fn invert(&self) -> Result<Matrix<F>, Error> {
@@ -257,6 +370,88 @@ impl<F: Field> Matrix<F> {
         Ok(work.sub_matrix(0, row_count, col_count, col_count * 2))
     }
 
+    pub fn transpose(&self) -> Matrix<F> {
+        let mut result = Self::new(self.col_count, self.row_count);
+        for r in 0..self.row_count {
+            for c in 0..self.col_count {
+                acc!(result, c, r) = acc!(self, r, c);
+            }
+        }
+        result
+    }
+
+    /// Computes the PLU factorization of a square matrix.
+    pub fn lu(&self) -> Result<Lu<F>, Error> {
+        self.clone().into_lu()
+    }
+
+    /// Same as `lu`, but factorizes the matrix in place rather than a copy
+    /// of it.
+    pub fn into_lu(self) -> Result<Lu<F>, Error> {
+        let elems = self.row_count * self.col_count;
+        let thread_count = if crate::THREADS_SUPPORTED && elems >= PAR_ELIM_MIN_ELEMS {
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            1
+        };
+
+        let min_block_rows = PAR_ELIM_MIN_BLOCK_ELEMS.div_ceil(self.col_count.max(1));
+        self.into_lu_threads(thread_count, min_block_rows)
+    }
+
+    /// Factorizes the matrix, splitting the rows below each pivot into up to
+    /// `thread_count` blocks of at least `min_block_rows` rows, which run on
+    /// the threads of the built-in pool.
+    fn into_lu_threads(self, thread_count: usize, min_block_rows: usize) -> Result<Lu<F>, Error> {
+        if !self.is_square() {
+            panic!("Trying to factorize a non-square matrix")
+        }
+
+        let n = self.row_count;
+        let mut lu = self;
+        let mut perm: ShardVec<usize> = (0..n).collect();
+
+        for col in 0..n {
+            let pivot = (col..n)
+                .find(|&r| acc!(lu, r, col) != F::zero())
+                .ok_or(Error::SingularMatrix)?;
+            lu.swap_rows(col, pivot);
+            perm.swap(col, pivot);
+
+            // Each row below the pivot keeps its factor in the pivot
+            // column, which is part of L, and has that multiple of the
+            // pivot row added to the rest of it, which is part of U.  The
+            // rows are independent of each other.
+            let inv = F::div(F::one(), acc!(lu, col, col));
+            let (upper, below) = lu.data.split_at_mut((col + 1) * n);
+            let pivot_row = &upper[col * n + col + 1..];
+
+            let eliminate = |rows: &mut [F::Elem]| {
+                for row in rows.chunks_mut(n) {
+                    if row[col] == F::zero() {
+                        continue;
+                    }
+                    let factor = F::mul(row[col], inv);
+                    row[col] = factor;
+                    F::mul_slice_add(factor, pivot_row, &mut row[col + 1..]);
+                }
+            };
+
+            let rows_below = n - col - 1;
+            let block_count = thread_count.min(rows_below / min_block_rows.max(1)).max(1);
+            if block_count == 1 {
+                eliminate(below);
+            } else {
+                let rows_per_block = rows_below.div_ceil(block_count);
+                let mut blocks: ShardVec<&mut [F::Elem]> =
+                    below.chunks_mut(rows_per_block * n).collect();
+                pool::for_each(&mut blocks, &|block: &mut &mut [F::Elem]| eliminate(block));
+            }
+        }
+
+        Ok(Lu { lu, perm })
+    }
+
     pub fn vandermonde(rows: usize, cols: usize) -> Matrix<F> {
         let mut result = Self::new(rows, cols);
 
@@ -415,4 +610,87 @@ mod tests {
     fn test_matrix_inverse_singular() {
         matrix!([4, 2], [12, 6]).invert().unwrap();
     }
+
+    #[test]
+    fn test_into_lu_threads() {
+        let m = Matrix::<galois_8::Field>::vandermonde(60, 60);
+        let inverse = m.invert().unwrap();
+
+        let serial = m.clone().into_lu_threads(1, 1).unwrap();
+        for &(threads, min_rows) in [(2, 1), (3, 1), (8, 1), (100, 1), (8, 20)].iter() {
+            let parallel = m.clone().into_lu_threads(threads, min_rows).unwrap();
+            assert_eq!(serial.lu, parallel.lu);
+            assert_eq!(serial.perm, parallel.perm);
+        }
+
+        // the columns of the inverse solve for the unit vectors
+        for i in 0..60 {
+            let mut e = vec![0; 60];
+            e[i] = 1;
+            for (r, &x) in serial.solve(&e).iter().enumerate() {
+                assert_eq!(inverse.get(r, i), x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_rows_threads() {
+        let fill = |r: usize, row: &mut [u8]| {
+            for (c, x) in row.iter_mut().enumerate() {
+                *x = (r * 7 + c) as u8;
+            }
+        };
+
+        let mut serial = Matrix::<galois_8::Field>::new(50, 9);
+        serial.fill_rows_threads(3, 1, fill);
+        assert_eq!(&[0u8; 9], serial.get_row(2));
+        assert_eq!(&[21u8, 22, 23, 24, 25, 26, 27, 28, 29], serial.get_row(3));
+
+        for &thread_count in [2, 3, 8, 100].iter() {
+            let mut parallel = Matrix::new(50, 9);
+            parallel.fill_rows_threads(3, thread_count, fill);
+            assert_eq!(serial, parallel);
+        }
+    }
+
+    #[test]
+    fn test_lu_solve() {
+        let m = matrix!([0, 3, 1], [2, 0, 7], [5, 4, 0]);
+        let lu = m.lu().unwrap();
+        let inverse = m.invert().unwrap();
+
+        // the columns of the inverse solve for the unit vectors
+        for i in 0..3 {
+            let mut e = vec![0; 3];
+            e[i] = 1;
+            for (r, &x) in lu.solve(&e).iter().enumerate() {
+                assert_eq!(inverse.get(r, i), x);
+            }
+        }
+
+        let m = Matrix::<galois_8::Field>::make_random(40);
+        if let Ok(inverse) = m.invert() {
+            let lu = m.transpose().lu().unwrap();
+            for i in 0..40 {
+                let mut e = vec![0; 40];
+                e[i] = 1;
+                assert_eq!(inverse.get_row(i), &lu.solve(&e)[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lu_in_place() {
+        let m = matrix!([56, 23, 98], [3, 100, 200], [45, 201, 123]);
+
+        let lu = m.clone().into_lu().unwrap();
+        let mut x = [0; 3];
+        lu.solve_into(&[1, 2, 3], &mut x);
+        assert_eq!(m.lu().unwrap().solve(&[1, 2, 3]), x);
+    }
+
+    #[test]
+    fn test_lu_singular() {
+        assert!(matrix!([4, 2], [12, 6]).lu().is_err());
+    }
 }
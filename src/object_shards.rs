@@ -0,0 +1,253 @@
+//! Shards stored as objects with the `object_store` crate.
+//!
+//! `ObjectShards` puts the shards of the stripes of a codec to an
+//! `ObjectStore`, one object per shard, and reconstructs a stripe from
+//! whichever of its objects can be fetched, with the drivers of the `fetch`
+//! module. The store can be any of those of `object_store`, e.g. S3, Google
+//! Cloud Storage or Azure Blob Storage, with the matching feature of
+//! `object_store` enabled.
+//!
+//! The object of shard `i` of stripe `s` is at `<prefix>/<s>/<i>`. Its
+//! metadata hold the envelope of the shard: the data and parity shard
+//! counts and the matrix kind of the codec, and the stripe and shard
+//! indices. An object whose envelope does not match the shard it is fetched
+//! as, e.g. one left by a codec of another configuration, counts as
+//! missing.
+//!
+//! This module is only available with the `object_store` feature.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::future;
+use object_store::path::Path;
+use object_store::{Attribute, AttributeValue, Attributes, ObjectStore, PutOptions};
+
+use crate::fetch::ShardSource;
+use crate::{Error, Field, MatrixKind, ReedSolomon};
+
+const DATA_SHARDS_KEY: &str = "rse-data-shards";
+const PARITY_SHARDS_KEY: &str = "rse-parity-shards";
+const MATRIX_KIND_KEY: &str = "rse-matrix-kind";
+const STRIPE_KEY: &str = "rse-stripe";
+const INDEX_KEY: &str = "rse-index";
+
+/// Error returned by `ObjectShards` when putting shards.
+#[derive(Debug)]
+pub enum PutError {
+    /// The shards provided do not fit the codec.
+    RSError(Error),
+    /// Putting some of the shards failed.
+    ///
+    /// Holds the index of each shard which failed along with its error.
+    /// The other shards were put normally.
+    Store(Vec<(usize, object_store::Error)>),
+}
+
+impl std::fmt::Display for PutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            PutError::RSError(ref e) => write!(f, "{}", e),
+            PutError::Store(ref errors) => {
+                write!(f, "Failed to put shard(s)")?;
+                for &(i, ref e) in errors.iter() {
+                    write!(f, " {}: {};", i, e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for PutError {}
+
+/// The shards of the stripes of a codec, stored as objects under a prefix
+/// of an `ObjectStore`.
+#[derive(Debug)]
+pub struct ObjectShards<'a, F: 'a + Field> {
+    codec: &'a ReedSolomon<F>,
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl<'a, F: 'a + Field<Elem = u8>> ObjectShards<'a, F> {
+    /// Creates a new instance storing the shards of `codec` in `store`,
+    /// under `prefix`.
+    pub fn new(
+        codec: &'a ReedSolomon<F>,
+        store: Arc<dyn ObjectStore>,
+        prefix: Path,
+    ) -> ObjectShards<'a, F> {
+        ObjectShards {
+            codec,
+            store,
+            prefix,
+        }
+    }
+
+    /// Returns the codec.
+    pub fn codec(&self) -> &ReedSolomon<F> {
+        self.codec
+    }
+
+    /// Returns the path of the object of shard `index` of stripe `stripe`.
+    pub fn shard_path(&self, stripe: u64, index: usize) -> Path {
+        self.prefix
+            .child(stripe.to_string())
+            .child(index.to_string())
+    }
+
+    /// Puts every shard of stripe `stripe` to its object, all at once.
+    ///
+    /// Returns `PutError::RSError` without putting anything when the
+    /// number of shards does not match the codec, or the shards are not
+    /// of the same length. A stripe of which some shards failed is still
+    /// recoverable as long as at most `parity_shard_count` of them did.
+    pub async fn put_stripe<T: AsRef<[u8]>>(
+        &self,
+        stripe: u64,
+        shards: &[T],
+    ) -> Result<(), PutError> {
+        self.check_shards(shards).map_err(PutError::RSError)?;
+
+        let puts = shards.iter().enumerate().map(|(i, shard)| async move {
+            let put = self
+                .put(stripe, i, Bytes::copy_from_slice(shard.as_ref()))
+                .await;
+            (i, put)
+        });
+        let errors: Vec<_> = future::join_all(puts)
+            .await
+            .into_iter()
+            .filter_map(|(i, put)| put.err().map(|e| (i, e)))
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(PutError::Store(errors))
+        }
+    }
+
+    /// Puts shard `index` of stripe `stripe` to its object, e.g. after
+    /// rebuilding a lost shard.
+    ///
+    /// Returns `PutError::RSError` with `Error::InvalidIndex` if `index`
+    /// is not the index of a shard, and with `Error::EmptyShard` if
+    /// `shard` is empty.
+    pub async fn put_shard(&self, stripe: u64, index: usize, shard: Bytes) -> Result<(), PutError> {
+        if index >= self.codec.total_shard_count {
+            return Err(PutError::RSError(Error::InvalidIndex));
+        }
+        if shard.is_empty() {
+            return Err(PutError::RSError(Error::EmptyShard));
+        }
+
+        self.put(stripe, index, shard)
+            .await
+            .map_err(|e| PutError::Store(vec![(index, e)]))
+    }
+
+    /// Returns the shards of stripe `stripe` as a `ShardSource`, for the
+    /// drivers of the `fetch` module.
+    pub fn stripe(&self, stripe: u64) -> StripeObjects<'_, 'a, F> {
+        StripeObjects {
+            shards: self,
+            stripe,
+        }
+    }
+
+    /// Fetches just enough objects to recover the data shards of stripe
+    /// `stripe`, then reconstructs the data shards.
+    ///
+    /// Behaves as `ReedSolomon::reconstruct_data_from`.
+    pub async fn get_data(
+        &self,
+        stripe: u64,
+        max_concurrent: usize,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        self.codec
+            .reconstruct_data_from(&self.stripe(stripe), max_concurrent)
+            .await
+    }
+
+    /// Fetches just enough objects to recover stripe `stripe`, then
+    /// reconstructs all its shards, data shards first.
+    ///
+    /// Behaves as `ReedSolomon::reconstruct_from`.
+    pub async fn get_stripe(
+        &self,
+        stripe: u64,
+        max_concurrent: usize,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        self.codec
+            .reconstruct_from(&self.stripe(stripe), max_concurrent)
+            .await
+    }
+
+    async fn put(&self, stripe: u64, index: usize, shard: Bytes) -> object_store::Result<()> {
+        let options = PutOptions::from(self.envelope(stripe, index));
+        self.store
+            .put_opts(&self.shard_path(stripe, index), shard.into(), options)
+            .await
+            .map(|_| ())
+    }
+
+    /// Returns the metadata of the object of shard `index` of stripe
+    /// `stripe`.
+    fn envelope(&self, stripe: u64, index: usize) -> Attributes {
+        let matrix_kind = match self.codec.matrix_kind() {
+            MatrixKind::Vandermonde => "vandermonde",
+            MatrixKind::Cauchy => "cauchy",
+        };
+        let metadata = [
+            (DATA_SHARDS_KEY, self.codec.data_shard_count.to_string()),
+            (PARITY_SHARDS_KEY, self.codec.parity_shard_count.to_string()),
+            (MATRIX_KIND_KEY, matrix_kind.to_string()),
+            (STRIPE_KEY, stripe.to_string()),
+            (INDEX_KEY, index.to_string()),
+        ];
+        metadata
+            .iter()
+            .map(|(key, value)| {
+                (
+                    Attribute::Metadata((*key).into()),
+                    AttributeValue::from(value.clone()),
+                )
+            })
+            .collect()
+    }
+
+    fn check_shards<T: AsRef<[u8]>>(&self, shards: &[T]) -> Result<(), Error> {
+        check_piece_count!(all => self.codec, shards);
+        check_slices!(multi => shards);
+
+        Ok(())
+    }
+}
+
+/// The objects of the shards of a stripe, fetched on demand.
+#[derive(Debug)]
+pub struct StripeObjects<'s, 'a: 's, F: 'a + Field> {
+    shards: &'s ObjectShards<'a, F>,
+    stripe: u64,
+}
+
+impl<'s, 'a: 's, F: 'a + Field<Elem = u8>> ShardSource for StripeObjects<'s, 'a, F> {
+    /// Fetches the object of shard `index`, returning `None` if it cannot
+    /// be fetched or its envelope does not match the shard.
+    async fn fetch(&self, index: usize) -> Option<Bytes> {
+        let path = self.shards.shard_path(self.stripe, index);
+        let result = self.shards.store.get(&path).await.ok()?;
+
+        let envelope = self.shards.envelope(self.stripe, index);
+        if envelope
+            .iter()
+            .any(|(key, value)| result.attributes.get(key) != Some(value))
+        {
+            return None;
+        }
+
+        result.bytes().await.ok()
+    }
+}
@@ -0,0 +1,274 @@
+//! `Stream` and `Sink` adapters for encoding within async pipelines.
+//!
+//! `EncodeStream` turns a stream of chunks into a stream of encoded
+//! stripes, one stripe per chunk. `EncodeSink` accepts data shards one at
+//! a time, and forwards each full stripe encoded to an inner sink, e.g. a
+//! network fan-out.
+//!
+//! Both adapters are driven by their consumer, so a slow consumer slows
+//! down the producer instead of stripes piling up in between.
+//!
+//! This module is only available with the `async` feature.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::sink::Sink;
+use futures::stream::Stream;
+
+use crate::stream::Stripe;
+use crate::{Error, Field, ReedSolomon};
+
+/// Stream of the encoded stripes of a stream of chunks.
+///
+/// See `ReedSolomon::encode_stream`.
+#[derive(Debug)]
+pub struct EncodeStream<'a, F: 'a + Field, S> {
+    codec: &'a ReedSolomon<F>,
+    chunks: S,
+    next_index: usize,
+}
+
+impl<'a, F: 'a + Field<Elem = u8>, S> EncodeStream<'a, F, S> {
+    /// Consumes the stream, returning the underlying stream of chunks.
+    pub fn into_inner(self) -> S {
+        self.chunks
+    }
+}
+
+impl<'a, F, S, B> Stream for EncodeStream<'a, F, S>
+where
+    F: 'a + Field<Elem = u8>,
+    S: Stream<Item = B> + Unpin,
+    B: AsRef<[u8]>,
+{
+    type Item = Result<Stripe, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let chunk = match Pin::new(&mut this.chunks).poll_next(cx) {
+            Poll::Ready(Some(chunk)) => chunk,
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+        let chunk = chunk.as_ref();
+
+        let mut shards = match this.codec.split(chunk) {
+            Ok(shards) => shards,
+            Err(e) => return Poll::Ready(Some(Err(e))),
+        };
+        let shard_len = shards[0].len();
+        shards.resize(this.codec.total_shard_count, vec![0; shard_len]);
+
+        this.codec
+            .encode(&mut shards)
+            .expect("shard count and sizes are valid by construction; qed");
+
+        let stripe = Stripe::new(
+            this.next_index,
+            this.codec.data_shard_count,
+            chunk.len(),
+            shards,
+        );
+        this.next_index += 1;
+
+        Poll::Ready(Some(Ok(stripe)))
+    }
+}
+
+/// Error returned by `EncodeSink`.
+#[derive(Debug)]
+pub enum SinkError<E> {
+    /// A shard does not fit the stripe being collected.
+    RSError(Error),
+    /// The inner sink failed.
+    Sink(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for SinkError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            SinkError::RSError(ref e) => write!(f, "{}", e),
+            SinkError::Sink(ref e) => write!(f, "Failed to send stripe: {}", e),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for SinkError<E> {}
+
+/// Sink collecting data shards into stripes, and sending each stripe
+/// encoded to an inner sink.
+///
+/// See `ReedSolomon::encode_sink`.
+#[derive(Debug)]
+pub struct EncodeSink<'a, F: 'a + Field, Si> {
+    codec: &'a ReedSolomon<F>,
+    inner: Si,
+    shards: Vec<Vec<u8>>,
+    data_len: usize,
+    pending: Option<Stripe>,
+    next_index: usize,
+}
+
+impl<'a, F: 'a + Field<Elem = u8>, Si> EncodeSink<'a, F, Si> {
+    /// Consumes the sink, returning the inner sink.
+    ///
+    /// Shards of an incomplete stripe, and a stripe not sent yet, are
+    /// dropped. Close the sink first to send them.
+    pub fn into_inner(self) -> Si {
+        self.inner
+    }
+
+    // Pads the data shards collected so far with zero shards, and encodes
+    // them into the pending stripe.
+    fn seal_stripe(&mut self) {
+        let shard_len = self.shards[0].len();
+        let mut shards = std::mem::take(&mut self.shards);
+        shards.resize(self.codec.total_shard_count, vec![0; shard_len]);
+
+        self.codec
+            .encode(&mut shards)
+            .expect("shard count and sizes are valid by construction; qed");
+
+        self.pending = Some(Stripe::new(
+            self.next_index,
+            self.codec.data_shard_count,
+            self.data_len,
+            shards,
+        ));
+        self.data_len = 0;
+        self.next_index += 1;
+    }
+}
+
+impl<'a, F, Si> EncodeSink<'a, F, Si>
+where
+    F: 'a + Field<Elem = u8>,
+    Si: Sink<Stripe> + Unpin,
+{
+    // Hands the pending stripe, if any, to the inner sink.
+    fn poll_send_pending(&mut self, cx: &mut Context) -> Poll<Result<(), SinkError<Si::Error>>> {
+        if self.pending.is_none() {
+            return Poll::Ready(Ok(()));
+        }
+
+        match Pin::new(&mut self.inner).poll_ready(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(SinkError::Sink(e))),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        let stripe = self.pending.take().expect("checked above; qed");
+        Poll::Ready(
+            Pin::new(&mut self.inner)
+                .start_send(stripe)
+                .map_err(SinkError::Sink),
+        )
+    }
+}
+
+impl<'a, F, Si> Sink<Vec<u8>> for EncodeSink<'a, F, Si>
+where
+    F: 'a + Field<Elem = u8>,
+    Si: Sink<Stripe> + Unpin,
+{
+    type Error = SinkError<Si::Error>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, shard: Vec<u8>) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+
+        if shard.is_empty() {
+            return Err(SinkError::RSError(Error::EmptyShard));
+        }
+        if let Some(first) = this.shards.first() {
+            if first.len() != shard.len() {
+                return Err(SinkError::RSError(Error::IncorrectShardSize));
+            }
+        }
+
+        this.data_len += shard.len();
+        this.shards.push(shard);
+
+        if this.shards.len() == this.codec.data_shard_count {
+            this.seal_stripe();
+        }
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        match this.poll_send_pending(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+
+        Pin::new(&mut this.inner)
+            .poll_flush(cx)
+            .map_err(SinkError::Sink)
+    }
+
+    /// Sends the shards of an incomplete stripe, padded with zero shards,
+    /// before closing the inner sink. See `Stripe::data_len`.
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        if this.pending.is_none() && !this.shards.is_empty() {
+            this.seal_stripe();
+        }
+
+        match this.poll_send_pending(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+
+        Pin::new(&mut this.inner)
+            .poll_close(cx)
+            .map_err(SinkError::Sink)
+    }
+}
+
+impl<F: Field<Elem = u8>> ReedSolomon<F> {
+    /// Returns a stream which splits each chunk of `chunks` into data
+    /// shards as `split` does, and yields them encoded as a stripe.
+    ///
+    /// Chunks are pulled from `chunks` only as stripes are polled for.
+    /// `Stripe::data_len` is the length of the chunk.
+    ///
+    /// An empty chunk yields `Error::EmptyShard`, and the stream goes on
+    /// with the next chunk.
+    pub fn encode_stream<S>(&self, chunks: S) -> EncodeStream<'_, F, S> {
+        EncodeStream {
+            codec: self,
+            chunks,
+            next_index: 0,
+        }
+    }
+
+    /// Returns a sink which collects `data_shard_count` data shards at a
+    /// time, and sends them encoded as a stripe to `inner`.
+    ///
+    /// The sink is ready for another shard only once the previous full
+    /// stripe was accepted by `inner`. Closing the sink sends an incomplete
+    /// final stripe padded with zero shards.
+    ///
+    /// Sending an empty shard returns `Error::EmptyShard`, and sending a
+    /// shard of a different length than the others of its stripe returns
+    /// `Error::IncorrectShardSize`. The shard is dropped in both cases.
+    pub fn encode_sink<Si>(&self, inner: Si) -> EncodeSink<'_, F, Si> {
+        EncodeSink {
+            codec: self,
+            inner,
+            shards: Vec::with_capacity(self.data_shard_count),
+            data_len: 0,
+            pending: None,
+            next_index: 0,
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Placement of the shards of stripes on nodes.
+//!
+//! A `Placer` assigns each of the `total_shard_count` shards of a stripe to
+//! a distinct node, the nodes being grouped into failure domains, e.g.
+//! racks. However the shards are placed, no domain holds more than
+//! `max_per_domain` shards of a stripe, so that losing `f` domains loses at
+//! most `f * max_per_domain` shards. With `max_per_domain` at most
+//! `parity_shard_count / f`, every stripe survives the loss of any `f`
+//! domains.
+//!
+//! The placement of a stripe only depends on the nodes, the policy and the
+//! index of the stripe, so it can be recomputed rather than stored. A
+//! `Placement` maps the shards to their nodes, and gives the shards left by
+//! failed nodes as the `ShardSet` taken by `ReedSolomon::repair_plan`.
+
+use std::collections::HashMap;
+
+use crate::{Error, Field, ReedSolomon, ShardSet};
+
+/// A node shards are stored on.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Node {
+    /// The failure domain of the node, e.g. its rack.
+    pub domain: usize,
+    /// The share of shards the node gets under `PlacementPolicy::Weighted`,
+    /// relative to the other nodes. Nodes of weight 0 get none.
+    pub weight: u32,
+}
+
+/// How the nodes of the shards of a stripe are chosen.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum PlacementPolicy {
+    /// The nodes are taken in order, starting from a node shifted by one
+    /// for each stripe, skipping the nodes of full domains.
+    RoundRobin,
+    /// The domains are taken in turn, starting from a domain shifted by one
+    /// for each stripe, so that the shards are spread as evenly as
+    /// possible over the domains. Within a domain, the nodes are taken in
+    /// order, also shifted by one for each stripe.
+    RackAware,
+    /// The nodes are drawn by rendezvous hashing, each in proportion to its
+    /// weight, skipping the nodes of full domains.
+    Weighted,
+}
+
+/// Assigns the shards of stripes to nodes.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Placer {
+    nodes: Vec<Node>,
+    policy: PlacementPolicy,
+    total_shard_count: usize,
+    max_per_domain: usize,
+    // the indices of the nodes of each domain, domains in order of first
+    // appearance
+    domains: Vec<Vec<usize>>,
+}
+
+/// The nodes of the shards of a stripe.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Placement {
+    stripe: u64,
+    // `nodes[i]` is the index of the node of shard `i`
+    nodes: Vec<usize>,
+}
+
+impl Placement {
+    /// Returns the index of the stripe.
+    pub fn stripe(&self) -> u64 {
+        self.stripe
+    }
+
+    /// Returns the index of the node of every shard, by shard index.
+    pub fn nodes(&self) -> &[usize] {
+        &self.nodes
+    }
+
+    /// Returns the index of the node of the shard at `index`, or `None` if
+    /// `index` is not a valid shard index.
+    pub fn node(&self, index: usize) -> Option<usize> {
+        self.nodes.get(index).cloned()
+    }
+
+    /// Returns the index of the shard stored on `node`, if any.
+    pub fn shard_on(&self, node: usize) -> Option<usize> {
+        self.nodes.iter().position(|&n| n == node)
+    }
+
+    /// Returns the shards left when the nodes `failed` are lost, for
+    /// `ReedSolomon::repair_plan`.
+    pub fn present_without(&self, failed: &[usize]) -> ShardSet {
+        let mut present = ShardSet::full(self.nodes.len());
+        for (i, node) in self.nodes.iter().enumerate() {
+            if failed.contains(node) {
+                present.remove(i);
+            }
+        }
+        present
+    }
+}
+
+impl Placer {
+    /// Creates a placer of the shards of `codec` on `nodes`, under
+    /// `policy`, with at most `max_per_domain` shards of a stripe in any
+    /// domain.
+    ///
+    /// Returns `Error::InvalidPlacement` if the nodes cannot hold the
+    /// shards of a stripe, a shard per node, within `max_per_domain`
+    /// shards per domain. Under `PlacementPolicy::Weighted`, only the nodes
+    /// of non-zero weight count.
+    pub fn new<F: Field>(
+        codec: &ReedSolomon<F>,
+        nodes: Vec<Node>,
+        policy: PlacementPolicy,
+        max_per_domain: usize,
+    ) -> Result<Placer, Error> {
+        let mut domains: Vec<Vec<usize>> = Vec::new();
+        let mut domain_indices = HashMap::new();
+        for (i, node) in nodes.iter().enumerate() {
+            if policy == PlacementPolicy::Weighted && node.weight == 0 {
+                continue;
+            }
+            let d = *domain_indices.entry(node.domain).or_insert_with(|| {
+                domains.push(Vec::new());
+                domains.len() - 1
+            });
+            domains[d].push(i);
+        }
+
+        let capacity: usize = domains.iter().map(|d| d.len().min(max_per_domain)).sum();
+        if capacity < codec.total_shard_count {
+            return Err(Error::InvalidPlacement);
+        }
+
+        Ok(Placer {
+            nodes,
+            policy,
+            total_shard_count: codec.total_shard_count,
+            max_per_domain,
+            domains,
+        })
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn policy(&self) -> PlacementPolicy {
+        self.policy
+    }
+
+    pub fn max_per_domain(&self) -> usize {
+        self.max_per_domain
+    }
+
+    /// Returns the placement of the shards of stripe `stripe`.
+    pub fn place(&self, stripe: u64) -> Placement {
+        let nodes = match self.policy {
+            PlacementPolicy::RoundRobin => {
+                let start = (stripe % self.nodes.len() as u64) as usize;
+                let order = (0..self.nodes.len()).map(|i| (start + i) % self.nodes.len());
+                self.take_in_order(order)
+            }
+            PlacementPolicy::RackAware => self.place_rack_aware(stripe),
+            PlacementPolicy::Weighted => {
+                let mut scored: Vec<(f64, usize)> = self
+                    .domains
+                    .iter()
+                    .flatten()
+                    .map(|&i| {
+                        // uniform in (0, 1]
+                        let u = ((mix(stripe, i as u64) >> 11) + 1) as f64 / (1u64 << 53) as f64;
+                        (-u.ln() / self.nodes[i].weight as f64, i)
+                    })
+                    .collect();
+                scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap().then(a.1.cmp(&b.1)));
+                self.take_in_order(scored.into_iter().map(|(_, i)| i))
+            }
+        };
+
+        Placement { stripe, nodes }
+    }
+
+    /// Takes the nodes of `order` in turn, skipping the nodes of full
+    /// domains, until all shards are placed.
+    fn take_in_order<I: Iterator<Item = usize>>(&self, order: I) -> Vec<usize> {
+        let mut per_domain: HashMap<usize, usize> = HashMap::new();
+        let mut nodes = Vec::with_capacity(self.total_shard_count);
+        for i in order {
+            let count = per_domain.entry(self.nodes[i].domain).or_insert(0);
+            if *count < self.max_per_domain {
+                *count += 1;
+                nodes.push(i);
+                if nodes.len() == self.total_shard_count {
+                    break;
+                }
+            }
+        }
+        nodes
+    }
+
+    fn place_rack_aware(&self, stripe: u64) -> Vec<usize> {
+        let domain_count = self.domains.len();
+        let start = (stripe % domain_count as u64) as usize;
+
+        let mut nodes = Vec::with_capacity(self.total_shard_count);
+        // the number of shards placed in each domain so far
+        let mut taken = vec![0; domain_count];
+        while nodes.len() < self.total_shard_count {
+            for d in (0..domain_count).map(|d| (start + d) % domain_count) {
+                let domain = &self.domains[d];
+                if taken[d] == domain.len().min(self.max_per_domain) {
+                    continue;
+                }
+                let shift = (stripe % domain.len() as u64) as usize;
+                nodes.push(domain[(shift + taken[d]) % domain.len()]);
+                taken[d] += 1;
+                if nodes.len() == self.total_shard_count {
+                    break;
+                }
+            }
+        }
+        nodes
+    }
+}
+
+/// Hashes a stripe and a node into 64 uniform bits, with the finalizer of
+/// SplitMix64.
+fn mix(stripe: u64, node: u64) -> u64 {
+    let mut z = stripe
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        .wrapping_add(node.wrapping_mul(0xC2B2_AE3D_27D4_EB4F));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,184 @@
+//! The built-in pool of threads of `ThreadSource::Pool`.
+//!
+//! The worker threads are started on first use, and kept for the life of
+//! the process, waiting on a shared queue of jobs. The pool grows to the
+//! largest number of jobs handed to it at once.
+//!
+//! The jobs borrow from the stack of the calling thread, as with
+//! `std::thread::scope`: `for_each` only returns once all of its jobs are
+//! done, even when one of them panics. While it waits, the calling thread
+//! runs queued jobs itself, so that parallel methods called from a job
+//! cannot deadlock on a pool whose workers all wait.
+//!
+//! `spawn` hands a job to the pool without waiting for it, for the
+//! background encoding of `BackgroundShardByShard`.
+
+use std::collections::VecDeque;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+type Panic = Box<dyn std::any::Any + Send + 'static>;
+
+struct Pool {
+    state: Mutex<State>,
+    // notified on new jobs and on finished jobs
+    changed: Condvar,
+}
+
+struct State {
+    jobs: VecDeque<Job>,
+    worker_count: usize,
+}
+
+// the jobs of a call to `for_each`
+struct Batch {
+    remaining: AtomicUsize,
+    panic: Mutex<Option<Panic>>,
+}
+
+fn pool() -> &'static Pool {
+    static POOL: OnceLock<Pool> = OnceLock::new();
+    POOL.get_or_init(|| Pool {
+        state: Mutex::new(State {
+            jobs: VecDeque::new(),
+            worker_count: 0,
+        }),
+        changed: Condvar::new(),
+    })
+}
+
+impl Pool {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // jobs run outside of the lock and never panic, so a poisoned lock
+        // still holds a consistent state
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed.wait(state).unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts workers until there are `worker_count`, or a worker cannot
+    /// be started.
+    fn grow(&'static self, state: &mut State, worker_count: usize) {
+        while state.worker_count < worker_count {
+            let spawned = thread::Builder::new()
+                .name("reed-solomon-pool".to_string())
+                .spawn(move || self.work());
+            if spawned.is_err() {
+                break;
+            }
+            state.worker_count += 1;
+        }
+    }
+
+    fn work(&'static self) {
+        let mut state = self.lock();
+        loop {
+            match state.jobs.pop_front() {
+                Some(job) => {
+                    drop(state);
+                    job();
+                    state = self.lock();
+                }
+                None => state = self.wait(state),
+            }
+        }
+    }
+}
+
+/// Calls `f` on every item of `items`, the first item on the calling
+/// thread and the others on the pool, and returns once all calls are done.
+///
+/// A panic of `f` is resumed on the calling thread, once all calls are done.
+pub(crate) fn for_each<T, F>(items: &mut [T], f: &F)
+where
+    T: Send,
+    F: Fn(&mut T) + Sync,
+{
+    let (first, rest) = match items.split_first_mut() {
+        Some(split) => split,
+        None => return,
+    };
+
+    let pool = pool();
+    let batch = Arc::new(Batch {
+        remaining: AtomicUsize::new(rest.len()),
+        panic: Mutex::new(None),
+    });
+
+    {
+        let mut state = pool.lock();
+        for item in rest.iter_mut() {
+            let batch = Arc::clone(&batch);
+            let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+                if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| f(item))) {
+                    let mut panic = batch.panic.lock().unwrap_or_else(|e| e.into_inner());
+                    panic.get_or_insert(payload);
+                }
+                // under the lock, so that the waiting thread cannot miss it
+                let _state = pool.lock();
+                batch.remaining.fetch_sub(1, Ordering::Release);
+                pool.changed.notify_all();
+            });
+            // the job only borrows `items` and `f`, which outlive it, as
+            // this function waits for all jobs to be done before returning
+            let job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + '_>, Job>(job) };
+            state.jobs.push_back(job);
+        }
+
+        // the calling thread runs the jobs left while it waits if the
+        // workers cannot be started
+        pool.grow(&mut state, rest.len());
+        pool.changed.notify_all();
+    }
+
+    let first_result = panic::catch_unwind(AssertUnwindSafe(|| f(first)));
+
+    let mut state = pool.lock();
+    while batch.remaining.load(Ordering::Acquire) > 0 {
+        match state.jobs.pop_front() {
+            Some(job) => {
+                drop(state);
+                job();
+                state = pool.lock();
+            }
+            None => state = pool.wait(state),
+        }
+    }
+    drop(state);
+
+    if let Err(payload) = first_result {
+        panic::resume_unwind(payload);
+    }
+    let panic = batch.panic.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(payload) = panic {
+        panic::resume_unwind(payload);
+    }
+}
+
+/// Runs `job` on the pool, without waiting for it.
+///
+/// Nothing runs these jobs but the workers, so the pool grows to the
+/// number of jobs queued, up to the available parallelism. If no worker
+/// can be started, `job` runs on the calling thread.
+///
+/// `job` must not panic, as the worker running it would be lost.
+pub(crate) fn spawn<F: FnOnce() + Send + 'static>(job: F) {
+    let pool = pool();
+    let mut state = pool.lock();
+    let parallelism = thread::available_parallelism().map_or(1, |n| n.get());
+    let worker_count = (state.jobs.len() + 1).min(parallelism);
+    pool.grow(&mut state, worker_count);
+    if state.worker_count == 0 {
+        drop(state);
+        job();
+        return;
+    }
+    state.jobs.push_back(Box::new(job));
+    pool.changed.notify_all();
+}
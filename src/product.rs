@@ -0,0 +1,216 @@
+//! Product codes, protecting a matrix of cells across both its rows and its
+//! columns.
+//!
+//! The data fills the top left `data_rows * data_columns` cells of a grid
+//! of `rows * columns` cells, in row-major order. Every data row is extended
+//! with the parity cells of the row codec, then every column, parity
+//! columns included, with the parity cells of the column codec. As both
+//! codes are linear, every row of the grid, the parity rows included, is
+//! then a stripe of the row codec, and every column a stripe of the column
+//! codec.
+//!
+//! Missing cells are rebuilt by repairing the rows and columns missing few
+//! enough cells in turn, each repair possibly bringing other rows or
+//! columns within reach. With `m1` and `m2` parity cells per row and
+//! column, any `(m1 + 1) * (m2 + 1) - 1` missing cells are recoverable, and
+//! most patterns of many more scattered ones are, where a single stripe of
+//! the same size would fail as soon as more cells than its parity cells
+//! are missing.
+
+use std::iter::FromIterator;
+
+use crate::{Error, Field, ReedSolomon};
+
+/// Encodes and repairs grids of cells with a row codec and a column codec.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ProductCode<F: Field> {
+    row_codec: ReedSolomon<F>,
+    column_codec: ReedSolomon<F>,
+}
+
+impl<F: Field> ProductCode<F> {
+    /// Creates a product code with rows encoded by `row_codec` and columns
+    /// encoded by `column_codec`.
+    pub fn new(row_codec: ReedSolomon<F>, column_codec: ReedSolomon<F>) -> ProductCode<F> {
+        ProductCode {
+            row_codec,
+            column_codec,
+        }
+    }
+
+    pub fn row_codec(&self) -> &ReedSolomon<F> {
+        &self.row_codec
+    }
+
+    pub fn column_codec(&self) -> &ReedSolomon<F> {
+        &self.column_codec
+    }
+
+    /// Returns the number of rows of the grid, data and parity.
+    pub fn rows(&self) -> usize {
+        self.column_codec.total_shard_count()
+    }
+
+    /// Returns the number of columns of the grid, data and parity.
+    pub fn columns(&self) -> usize {
+        self.row_codec.total_shard_count()
+    }
+
+    pub fn data_rows(&self) -> usize {
+        self.column_codec.data_shard_count()
+    }
+
+    pub fn data_columns(&self) -> usize {
+        self.row_codec.data_shard_count()
+    }
+
+    /// Returns the number of cells of the grid.
+    pub fn cell_count(&self) -> usize {
+        self.rows() * self.columns()
+    }
+
+    /// Constructs the parity cells of the grid `cells`, in row-major order,
+    /// from its data cells.
+    ///
+    /// Returns `Error::TooFewShards` or `Error::TooManyShards` if there are
+    /// not `cell_count` cells, and the errors of `ReedSolomon::encode` for
+    /// cells of different lengths.
+    pub fn encode<T>(&self, cells: &mut [T]) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.check_cell_count(cells.len())?;
+
+        let columns = self.columns();
+        for row in cells.chunks_mut(columns).take(self.data_rows()) {
+            self.row_codec.encode(row)?;
+        }
+        for c in 0..columns {
+            let mut column: Vec<&mut [F::Elem]> = cells
+                .iter_mut()
+                .skip(c)
+                .step_by(columns)
+                .map(|cell| cell.as_mut())
+                .collect();
+            self.column_codec.encode(&mut column)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the missing cells of a grid can be rebuilt by `reconstruct`,
+    /// `present` flagging the cells present in row-major order.
+    ///
+    /// Returns `Error::TooFewShards` or `Error::TooManyShards` if there are
+    /// not `cell_count` flags.
+    pub fn is_recoverable(&self, present: &[bool]) -> Result<bool, Error> {
+        self.check_cell_count(present.len())?;
+
+        let mut present = present.to_vec();
+        self.repair(&mut present, |_| Ok(()))?;
+        Ok(present.iter().all(|&x| x))
+    }
+
+    /// Rebuilds the missing cells, `None`, of the grid `cells` in row-major
+    /// order, repairing rows and columns in turn.
+    ///
+    /// Returns `Error::TooFewShardsPresent` if the cells cannot all be
+    /// rebuilt, see `is_recoverable`, `Error::EmptyShard` for empty cells,
+    /// and `Error::IncorrectShardSize` for cells of different lengths. On
+    /// error nothing is touched.
+    pub fn reconstruct<T>(&self, cells: &mut [Option<T>]) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]> + FromIterator<F::Elem>,
+    {
+        self.check_cell_count(cells.len())?;
+
+        let mut lens = cells.iter().flatten().map(|cell| cell.as_ref().len());
+        if let Some(cell_len) = lens.next() {
+            if cell_len == 0 {
+                return Err(Error::EmptyShard);
+            }
+            if lens.any(|len| len != cell_len) {
+                return Err(Error::IncorrectShardSize);
+            }
+        }
+
+        let mut present: Vec<bool> = cells.iter().map(Option::is_some).collect();
+        if !self.is_recoverable(&present)? {
+            return Err(Error::TooFewShardsPresent);
+        }
+
+        let rows = self.rows();
+        let columns = self.columns();
+        self.repair(&mut present, |line| match line {
+            Line::Row(r) => self
+                .row_codec
+                .reconstruct(&mut cells[r * columns..(r + 1) * columns]),
+            Line::Column(c) => {
+                // moves the cells of the column next to each other, as the
+                // codec takes them
+                let mut column: Vec<Option<T>> =
+                    (0..rows).map(|r| cells[r * columns + c].take()).collect();
+                let result = self.column_codec.reconstruct(&mut column);
+                for (r, cell) in column.into_iter().enumerate() {
+                    cells[r * columns + c] = cell;
+                }
+                result
+            }
+        })
+    }
+
+    fn check_cell_count(&self, count: usize) -> Result<(), Error> {
+        if count < self.cell_count() {
+            Err(Error::TooFewShards)
+        } else if count > self.cell_count() {
+            Err(Error::TooManyShards)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Repairs the rows and columns with missing cells, but few enough of
+    /// them, with `repair_line`, until no more can be repaired. `present`
+    /// is updated as lines are repaired.
+    fn repair<R>(&self, present: &mut [bool], mut repair_line: R) -> Result<(), Error>
+    where
+        R: FnMut(Line) -> Result<(), Error>,
+    {
+        let rows = self.rows();
+        let columns = self.columns();
+
+        loop {
+            let mut progress = false;
+
+            for r in 0..rows {
+                let line = &mut present[r * columns..(r + 1) * columns];
+                let missing = line.iter().filter(|&&x| !x).count();
+                if missing > 0 && missing <= self.row_codec.parity_shard_count() {
+                    repair_line(Line::Row(r))?;
+                    line.iter_mut().for_each(|x| *x = true);
+                    progress = true;
+                }
+            }
+
+            for c in 0..columns {
+                let missing = (0..rows).filter(|&r| !present[r * columns + c]).count();
+                if missing > 0 && missing <= self.column_codec.parity_shard_count() {
+                    repair_line(Line::Column(c))?;
+                    (0..rows).for_each(|r| present[r * columns + c] = true);
+                    progress = true;
+                }
+            }
+
+            if !progress {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// A row or column of the grid.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Line {
+    Row(usize),
+    Column(usize),
+}
@@ -0,0 +1,305 @@
+//! Coding with bounded worst-case execution time, for real-time loops.
+//!
+//! `ReedSolomon` builds the decode matrix of an erasure pattern on first
+//! use and caches it in a tree guarded by a lock, and `verify` draws its
+//! buffers from a pool guarded by another lock. Both make the time taken
+//! by a call depend on the calls made before it, and on other threads.
+//!
+//! `RealTimeCodec` instead builds the decode matrices of all the erasure
+//! patterns it supports upfront. Its methods then take no locks, never
+//! allocate, and look the decode matrix up in a time only depending on the
+//! number of data shards.
+//!
+//! # Worst-case execution
+//!
+//! With `k` data shards, `m` parity shards, shards of `len` elements, and
+//! `r` missing shards:
+//!
+//! - `encode` and `encode_sep` do `k * m` passes over `len` elements.
+//! - `verify_with_buffer` does `k * m` passes over `len` elements, plus at
+//!   most `m` comparisons of `len` elements.
+//! - `reconstruct` does `k` steps to find the decode matrix, then at most
+//!   `k * r` passes over `len` elements. `reconstruct_data` only does the
+//!   passes rebuilding the missing data shards.
+//!
+//! A pass multiplies a shard by a constant of the field and adds it to
+//! another. The passes by `0` or `1` are cheaper, so the figures above are
+//! upper bounds.
+//!
+//! The methods only touch memory on the stack, the shards, and the codec,
+//! as long as there are at most 32 shards in total, 128 with the
+//! `wide-codes` feature. The work counters of `ReedSolomon::stats` are
+//! still updated, by atomic additions.
+//!
+//! # Memory
+//!
+//! Supporting up to `e` missing shards takes `C(k + e, e)` decode matrices
+//! of `k * k` elements each, e.g. about 100 KB for 10 data shards and 4
+//! parity shards over GF(2^8). See `RealTimeCodec::pattern_count`.
+
+use smallvec::SmallVec;
+
+use crate::{Error, Field, ReedSolomon, ShardVec};
+
+/// Codec taking no locks and not allocating, with all decode matrices
+/// built upfront.
+///
+/// See the module documentation for the worst-case execution of its
+/// methods.
+#[derive(Debug)]
+pub struct RealTimeCodec<F: Field> {
+    codec: ReedSolomon<F>,
+    max_erasures: usize,
+    // `binomials[c * (k + 1) + i]` is `C(c, i)`, for `c < k + max_erasures`
+    binomials: Vec<usize>,
+    // the data decode matrices, `k * k` elements each, in the colexicographic
+    // order of the indices of the shards they decode from
+    matrices: Vec<F::Elem>,
+}
+
+impl<F: Field> RealTimeCodec<F> {
+    /// Builds the decode matrices of `codec` for every erasure pattern of up
+    /// to `max_erasures` missing shards.
+    ///
+    /// `max_erasures` is capped at the number of parity shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of decode matrices overflows `usize`.
+    pub fn new(codec: ReedSolomon<F>, max_erasures: usize) -> RealTimeCodec<F> {
+        let k = codec.data_shard_count;
+        let max_erasures = max_erasures.min(codec.parity_shard_count);
+        let n = k + max_erasures;
+
+        let mut binomials = vec![0usize; n * (k + 1)];
+        for c in 0..n {
+            binomials[c * (k + 1)] = 1;
+            for i in 1..=k {
+                // `C(c, i) = C(c - 1, i - 1) + C(c - 1, i)`, unused entries
+                // may saturate
+                binomials[c * (k + 1) + i] = match c {
+                    0 => 0,
+                    _ => binomials[(c - 1) * (k + 1) + i - 1]
+                        .saturating_add(binomials[(c - 1) * (k + 1) + i]),
+                };
+            }
+        }
+
+        let pattern_count = Self::binomial(n, max_erasures);
+        let len = pattern_count
+            .checked_mul(k * k)
+            .expect("number of decode matrices overflows usize");
+        let mut matrices = vec![F::zero(); len];
+
+        let mut rt = RealTimeCodec {
+            codec,
+            max_erasures,
+            binomials,
+            matrices: Vec::new(),
+        };
+
+        // Go through the `k`-subsets of the first `n` shards, the indices of
+        // the shards decoded from.
+        let mut valid_indices: Vec<usize> = (0..k).collect();
+        loop {
+            let start = rt.rank(&valid_indices) * k * k;
+            let matrix = rt.codec.build_data_decode_matrix(&valid_indices);
+            for r in 0..k {
+                matrices[start + r * k..start + (r + 1) * k].copy_from_slice(matrix.get_row(r));
+            }
+
+            match (0..k).rev().find(|&i| valid_indices[i] < n - k + i) {
+                None => break,
+                Some(i) => {
+                    valid_indices[i] += 1;
+                    for j in i + 1..k {
+                        valid_indices[j] = valid_indices[j - 1] + 1;
+                    }
+                }
+            }
+        }
+
+        rt.matrices = matrices;
+        rt
+    }
+
+    /// Returns the codec.
+    ///
+    /// The methods of the codec itself may take locks and allocate.
+    pub fn codec(&self) -> &ReedSolomon<F> {
+        &self.codec
+    }
+
+    /// Returns the maximum number of missing shards supported.
+    pub fn max_erasures(&self) -> usize {
+        self.max_erasures
+    }
+
+    /// Returns the number of decode matrices built.
+    pub fn pattern_count(&self) -> usize {
+        let k = self.codec.data_shard_count;
+        self.matrices.len() / (k * k)
+    }
+
+    /// Constructs the parity shards, as `ReedSolomon::encode`.
+    pub fn encode<T, U>(&self, shards: T) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.codec.encode(shards)
+    }
+
+    /// Constructs the parity shards from separate data shards, as
+    /// `ReedSolomon::encode_sep`.
+    pub fn encode_sep<T, U>(&self, data: &[T], parity: &mut [U]) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.codec.encode_sep(data, parity)
+    }
+
+    /// Checks if the parity shards are correct, as
+    /// `ReedSolomon::verify_with_buffer`.
+    pub fn verify_with_buffer<T, U>(&self, slices: &[T], buffer: &mut [U]) -> Result<bool, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.codec.verify_with_buffer(slices, buffer)
+    }
+
+    /// Reconstructs all shards in place, the missing shards being those
+    /// flagged `false`.
+    ///
+    /// The shards flagged missing must still hold buffers of the right
+    /// length, and only they are overwritten. The flags are left untouched.
+    ///
+    /// Returns `Error::ErasurePatternNotPrecomputed` when no decode matrix
+    /// was built for the missing shards, which cannot happen with at most
+    /// `max_erasures` of them. The other errors are those of
+    /// `ReedSolomon::reconstruct`, and on error nothing is touched.
+    pub fn reconstruct<T>(&self, shards: &mut [(T, bool)]) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.reconstruct_internal(shards, false)
+    }
+
+    /// Reconstructs only the data shards in place, as `reconstruct`.
+    pub fn reconstruct_data<T>(&self, shards: &mut [(T, bool)]) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        self.reconstruct_internal(shards, true)
+    }
+
+    fn binomial(n: usize, k: usize) -> usize {
+        (0..k).fold(1usize, |acc, i| {
+            acc.checked_mul(n - i)
+                .expect("number of decode matrices overflows usize")
+                / (i + 1)
+        })
+    }
+
+    /// Returns the position of the decode matrix of the shards of
+    /// `valid_indices`, which are sorted and below `k + max_erasures`.
+    fn rank(&self, valid_indices: &[usize]) -> usize {
+        let k = self.codec.data_shard_count;
+
+        valid_indices
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| self.binomials[c * (k + 1) + i + 1])
+            .sum()
+    }
+
+    fn reconstruct_internal<T>(
+        &self,
+        shards: &mut [(T, bool)],
+        data_only: bool,
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let codec = &self.codec;
+        let k = codec.data_shard_count;
+
+        check_piece_count!(all => codec, shards);
+
+        let shard_len = shards[0].0.as_ref().len();
+        if shard_len == 0 {
+            return Err(Error::EmptyShard);
+        }
+        if shards
+            .iter()
+            .any(|(shard, _)| shard.as_ref().len() != shard_len)
+        {
+            return Err(Error::IncorrectShardSize);
+        }
+
+        // The data shards are decoded from the first `k` shards present.
+        let mut valid_indices: ShardVec<usize> = SmallVec::with_capacity(k);
+        for (i, &(_, present)) in shards.iter().enumerate() {
+            if present && valid_indices.len() < k {
+                valid_indices.push(i);
+            }
+        }
+        if valid_indices.len() < k {
+            return Err(Error::TooFewShardsPresent);
+        }
+        if valid_indices[k - 1] >= k + self.max_erasures {
+            return Err(Error::ErasurePatternNotPrecomputed);
+        }
+
+        let start = self.rank(&valid_indices) * k * k;
+        let data_decode_matrix = &self.matrices[start..start + k * k];
+
+        {
+            let mut inputs: ShardVec<&[F::Elem]> = SmallVec::with_capacity(k);
+            let mut outputs: ShardVec<&mut [F::Elem]> = SmallVec::with_capacity(k);
+            let mut matrix_rows: ShardVec<&[F::Elem]> = SmallVec::with_capacity(k);
+
+            for (i, (shard, present)) in shards.iter_mut().enumerate() {
+                if *present {
+                    if inputs.len() < k {
+                        inputs.push(T::as_ref(shard));
+                    }
+                } else if i < k {
+                    matrix_rows.push(&data_decode_matrix[i * k..(i + 1) * k]);
+                    outputs.push(T::as_mut(shard));
+                }
+            }
+
+            codec.code_some_slices(&matrix_rows, &inputs, &mut outputs);
+            let bytes_reconstructed = &codec.counters.bytes_reconstructed;
+            ReedSolomon::<F>::count_bytes(bytes_reconstructed, outputs.len(), shard_len);
+        }
+
+        if !data_only {
+            // Now that all of the data shards are intact, compute the
+            // missing parity shards from them.
+            let (data, parity) = shards.split_at_mut(k);
+            let parity_rows = codec.get_parity_rows();
+
+            let inputs: ShardVec<&[F::Elem]> =
+                data.iter().map(|(shard, _)| shard.as_ref()).collect();
+            let mut outputs: ShardVec<&mut [F::Elem]> = SmallVec::with_capacity(parity.len());
+            let mut matrix_rows: ShardVec<&[F::Elem]> = SmallVec::with_capacity(parity.len());
+
+            for (i, (shard, present)) in parity.iter_mut().enumerate() {
+                if !*present {
+                    matrix_rows.push(&parity_rows[i]);
+                    outputs.push(T::as_mut(shard));
+                }
+            }
+
+            codec.code_some_slices(&matrix_rows, &inputs, &mut outputs);
+            let bytes_reconstructed = &codec.counters.bytes_reconstructed;
+            ReedSolomon::<F>::count_bytes(bytes_reconstructed, outputs.len(), shard_len);
+        }
+
+        Ok(())
+    }
+}
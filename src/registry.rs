@@ -0,0 +1,168 @@
+//! A shared cache of codecs, keyed by their shape.
+//!
+//! Building a codec inverts matrices, and every codec keeps its own cache
+//! of decode matrices. Services handling stripes of many shapes can fetch
+//! their codecs from a `CodecRegistry` instead, which builds each codec
+//! once and shares it among all users, so the decode matrices cached by one
+//! request serve all later ones.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::{Error, Field, MatrixKind, ReedSolomon};
+
+/// The shape of a codec, keying it in a `CodecRegistry`.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct CodecKey {
+    /// The number of data shards.
+    pub data_shards: usize,
+    /// The number of parity shards.
+    pub parity_shards: usize,
+    /// The kind of encoding matrix.
+    pub matrix_kind: MatrixKind,
+}
+
+impl CodecKey {
+    /// Creates the key of a codec with a Vandermonde matrix, as built by
+    /// `ReedSolomon::new`.
+    pub fn new(data_shards: usize, parity_shards: usize) -> CodecKey {
+        CodecKey {
+            data_shards,
+            parity_shards,
+            matrix_kind: MatrixKind::Vandermonde,
+        }
+    }
+
+    /// Creates the key of a codec with a Cauchy matrix, as built by
+    /// `ReedSolomon::new_cauchy`.
+    pub fn new_cauchy(data_shards: usize, parity_shards: usize) -> CodecKey {
+        CodecKey {
+            data_shards,
+            parity_shards,
+            matrix_kind: MatrixKind::Cauchy,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry<F: Field> {
+    codec: Arc<ReedSolomon<F>>,
+    last_used: u64,
+}
+
+#[derive(Debug)]
+struct Codecs<F: Field> {
+    entries: HashMap<CodecKey, Entry<F>>,
+    // incremented on every lookup, orders the entries by last use
+    clock: u64,
+}
+
+/// Thread-safe cache of shared codecs, keyed by `CodecKey`.
+///
+/// Codecs are built on first use. Once `capacity` codecs are cached, the
+/// least recently used one is evicted to make room for a new one. An
+/// evicted codec stays valid for as long as it is used elsewhere.
+#[derive(Debug)]
+pub struct CodecRegistry<F: Field> {
+    capacity: usize,
+    codecs: Mutex<Codecs<F>>,
+}
+
+impl<F: Field> CodecRegistry<F> {
+    /// Creates an empty registry holding at most `capacity` codecs.
+    ///
+    /// A `capacity` of `0` is treated as `1`.
+    pub fn new(capacity: usize) -> CodecRegistry<F> {
+        CodecRegistry {
+            capacity: capacity.max(1),
+            codecs: Mutex::new(Codecs {
+                entries: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    /// Returns the codec of shape `key`, building it if it is not cached.
+    ///
+    /// The codec is built without holding the lock of the registry, so
+    /// lookups of other shapes are not held up meanwhile.
+    ///
+    /// Returns the errors of `ReedSolomon::new` for invalid shard counts.
+    pub fn get(&self, key: CodecKey) -> Result<Arc<ReedSolomon<F>>, Error> {
+        if let Some(codec) = self.lookup(&key) {
+            return Ok(codec);
+        }
+
+        let codec = Arc::new(match key.matrix_kind {
+            MatrixKind::Vandermonde => ReedSolomon::new(key.data_shards, key.parity_shards)?,
+            MatrixKind::Cauchy => ReedSolomon::new_cauchy(key.data_shards, key.parity_shards)?,
+        });
+
+        let mut codecs = self.codecs.lock().unwrap();
+        codecs.clock += 1;
+        let clock = codecs.clock;
+
+        // Another thread may have built the same codec meanwhile, keep the
+        // one cached first so that all users share it.
+        if let Some(entry) = codecs.entries.get_mut(&key) {
+            entry.last_used = clock;
+            return Ok(Arc::clone(&entry.codec));
+        }
+
+        if codecs.entries.len() >= self.capacity {
+            let lru = codecs
+                .entries
+                .iter()
+                .min_by_key(|&(_, entry)| entry.last_used)
+                .map(|(&key, _)| key)
+                .expect("capacity is at least 1; qed");
+            codecs.entries.remove(&lru);
+        }
+
+        codecs.entries.insert(
+            key,
+            Entry {
+                codec: Arc::clone(&codec),
+                last_used: clock,
+            },
+        );
+
+        Ok(codec)
+    }
+
+    /// Removes the codec of shape `key`, returning whether it was cached.
+    pub fn evict(&self, key: &CodecKey) -> bool {
+        self.codecs.lock().unwrap().entries.remove(key).is_some()
+    }
+
+    /// Removes all codecs.
+    pub fn clear(&self) {
+        self.codecs.lock().unwrap().entries.clear();
+    }
+
+    /// Returns the number of codecs cached.
+    pub fn len(&self) -> usize {
+        self.codecs.lock().unwrap().entries.len()
+    }
+
+    /// Checks if no codec is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum number of codecs cached.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn lookup(&self, key: &CodecKey) -> Option<Arc<ReedSolomon<F>>> {
+        let mut codecs = self.codecs.lock().unwrap();
+        codecs.clock += 1;
+        let clock = codecs.clock;
+
+        codecs.entries.get_mut(key).map(|entry| {
+            entry.last_used = clock;
+            Arc::clone(&entry.codec)
+        })
+    }
+}
@@ -0,0 +1,264 @@
+//! Primitives for distributed repair.
+//!
+//! A lost shard is a linear combination of any `data_shard_count` surviving
+//! shards. Instead of shipping the surviving shards to a single node which
+//! reconstructs the lost shard, each surviving node multiplies its own shard
+//! by its coefficient with `RepairPlan::partial_repair`, and the partial
+//! results are added together with `RepairPlan::combine`.
+//!
+//! As the addition is associative, partial results can be combined in any
+//! order and grouping, e.g. by nodes along the path to the coordinator.
+//!
+//! `ReedSolomon::scrub_and_repair` covers the local read-repair flow,
+//! finding the shards which fail their checksums and rebuilding them.
+
+use std::iter::FromIterator;
+
+use crate::{Error, Field, ReedSolomon, ShardSet};
+
+/// The coefficients repairing one lost shard from a fixed set of surviving
+/// shards.
+#[derive(PartialEq, Debug, Clone)]
+pub struct RepairPlan<F: Field> {
+    lost: usize,
+    sources: Vec<usize>,
+    coefficients: Vec<F::Elem>,
+}
+
+impl<F: Field> RepairPlan<F> {
+    /// Returns the index of the shard repaired by this plan.
+    pub fn lost(&self) -> usize {
+        self.lost
+    }
+
+    /// Returns the indices of the shards contributing to the repair,
+    /// in ascending order.
+    pub fn sources(&self) -> &[usize] {
+        &self.sources
+    }
+
+    /// Returns the coefficient of the shard at `index`, or `None` if that
+    /// shard does not contribute to the repair.
+    pub fn coefficient(&self, index: usize) -> Option<F::Elem> {
+        self.sources
+            .iter()
+            .position(|&source| source == index)
+            .map(|i| self.coefficients[i])
+    }
+
+    /// Computes the contribution of the shard at `index` to the repair.
+    ///
+    /// Return `Error::InvalidIndex` if the shard at `index` is not one of
+    /// the sources of this plan.
+    ///
+    /// Return `Error::EmptyShard` if `shard` is empty.
+    pub fn partial_repair(&self, index: usize, shard: &[F::Elem]) -> Result<Vec<F::Elem>, Error> {
+        let coefficient = self.coefficient(index).ok_or(Error::InvalidIndex)?;
+        if shard.is_empty() {
+            return Err(Error::EmptyShard);
+        }
+
+        let mut partial = vec![F::zero(); shard.len()];
+        F::mul_slice(coefficient, shard, &mut partial);
+
+        Ok(partial)
+    }
+
+    /// Adds up partial results.
+    ///
+    /// Combining the partial results of all sources yields the lost shard.
+    /// Combining only some of them yields another partial result, which can
+    /// be combined further.
+    ///
+    /// Return `Error::TooFewShardsPresent` if `partials` is empty.
+    ///
+    /// Return `Error::EmptyShard` or `Error::IncorrectShardSize` if the
+    /// partial results are empty or not of the same length.
+    pub fn combine<T: AsRef<[F::Elem]>>(&self, partials: &[T]) -> Result<Vec<F::Elem>, Error> {
+        if partials.is_empty() {
+            return Err(Error::TooFewShardsPresent);
+        }
+        check_slices!(multi => partials);
+
+        let mut combined = partials[0].as_ref().to_vec();
+        for partial in partials[1..].iter() {
+            for (c, &p) in combined.iter_mut().zip(partial.as_ref().iter()) {
+                *c = F::add(*c, p);
+            }
+        }
+
+        Ok(combined)
+    }
+}
+
+impl<F: Field> ReedSolomon<F> {
+    /// Computes the plan repairing the shard at `lost` from the shards
+    /// marked present in `present`.
+    ///
+    /// The first `data_shard_count` present shards, other than `lost`
+    /// itself, are picked as the sources of the repair.
+    ///
+    /// Return `Error::InvalidShardFlags` if `present` is not over
+    /// `total_shard_count` shards.
+    ///
+    /// Return `Error::InvalidIndex` if `lost` is not a valid shard index.
+    ///
+    /// Return `Error::TooFewShardsPresent` if fewer than `data_shard_count`
+    /// shards other than `lost` are present.
+    pub fn repair_plan(&self, present: &ShardSet, lost: usize) -> Result<RepairPlan<F>, Error> {
+        if present.shard_count() != self.total_shard_count {
+            return Err(Error::InvalidShardFlags);
+        }
+        check_slice_index!(all => self, lost);
+
+        let mut sources = Vec::with_capacity(self.data_shard_count);
+        let mut invalid_indices = Vec::with_capacity(self.parity_shard_count);
+        for i in 0..self.total_shard_count {
+            if !present.contains(i) || i == lost {
+                invalid_indices.push(i);
+            } else if sources.len() < self.data_shard_count {
+                sources.push(i);
+            }
+        }
+
+        if sources.len() < self.data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+
+        let data_decode_matrix = self.get_data_decode_matrix(&sources, &invalid_indices);
+
+        let coefficients = if lost < self.data_shard_count {
+            data_decode_matrix.get_row(lost).to_vec()
+        } else {
+            // The lost parity shard is its encoding row applied to the
+            // data shards, which are in turn decoded from the sources.
+            let encoding_row = self.matrix.get_row(lost);
+            (0..self.data_shard_count)
+                .map(|j| {
+                    encoding_row
+                        .iter()
+                        .enumerate()
+                        .fold(F::zero(), |acc, (c, &coef)| {
+                            F::add(acc, F::mul(coef, data_decode_matrix.get(c, j)))
+                        })
+                })
+                .collect()
+        };
+
+        Ok(RepairPlan {
+            lost,
+            sources,
+            coefficients,
+        })
+    }
+
+    /// Finds the missing shards and the shards not matching their checksum,
+    /// and reconstructs them.
+    ///
+    /// `checksums[i]` is the expected checksum of the shard at `i`, and
+    /// `digest` computes the checksum of a shard. Returns the indices of
+    /// the reconstructed shards in ascending order, i.e. the shards to
+    /// write back to storage.
+    ///
+    /// `shards` is not touched on error.
+    ///
+    /// Return `Error::TooFewShards` or `Error::TooManyShards` if the number
+    /// of shards or checksums is not `total_shard_count`.
+    ///
+    /// Return `Error::TooFewShardsPresent` if fewer than `data_shard_count`
+    /// shards match their checksum, along with the errors of
+    /// `reconstruct_checked`.
+    pub fn scrub_and_repair<T, D, H>(
+        &self,
+        shards: &mut [Option<T>],
+        checksums: &[D],
+        mut digest: H,
+    ) -> Result<Vec<usize>, Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]> + FromIterator<F::Elem>,
+        D: PartialEq,
+        H: FnMut(&[F::Elem]) -> D,
+    {
+        check_piece_count!(all => self, shards);
+        check_piece_count!(all => self, checksums);
+
+        let to_repair: Vec<usize> = shards
+            .iter()
+            .zip(checksums.iter())
+            .enumerate()
+            .filter(|(_, (shard, checksum))| match shard {
+                Some(shard) => digest(shard.as_ref()) != **checksum,
+                None => true,
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        if to_repair.is_empty() {
+            return Ok(to_repair);
+        }
+        if self.total_shard_count - to_repair.len() < self.data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+
+        // Keep the corrupted shards to put them back on error.
+        let corrupted: Vec<(usize, Option<T>)> =
+            to_repair.iter().map(|&i| (i, shards[i].take())).collect();
+
+        if let Err(e) = self.reconstruct_checked(shards, checksums, digest) {
+            for (i, shard) in corrupted {
+                shards[i] = shard;
+            }
+            return Err(e);
+        }
+
+        Ok(to_repair)
+    }
+
+    /// Reconstructs all shards as `reconstruct` does, then checks the
+    /// reconstructed shards against their expected checksums.
+    ///
+    /// `checksums[i]` is the expected checksum of the shard at `i`, and
+    /// `digest` computes the checksum of a shard. Only the checksums of the
+    /// missing shards are used, so corruption of a present shard shows up
+    /// as a mismatch of the shards reconstructed from it.
+    ///
+    /// `shards` is not touched on error, the missing shards are left
+    /// missing.
+    ///
+    /// Return `Error::TooFewShards` or `Error::TooManyShards` if the number
+    /// of shards or checksums is not `total_shard_count`.
+    ///
+    /// Return `Error::ChecksumMismatch` if a reconstructed shard does not
+    /// match its checksum, along with the errors of `reconstruct`.
+    pub fn reconstruct_checked<T, D, H>(
+        &self,
+        shards: &mut [Option<T>],
+        checksums: &[D],
+        mut digest: H,
+    ) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]> + AsMut<[F::Elem]> + FromIterator<F::Elem>,
+        D: PartialEq,
+        H: FnMut(&[F::Elem]) -> D,
+    {
+        check_piece_count!(all => self, shards);
+        check_piece_count!(all => self, checksums);
+
+        let missing: Vec<usize> = (0..shards.len()).filter(|&i| shards[i].is_none()).collect();
+
+        self.reconstruct(shards)?;
+
+        let all_match = missing.iter().all(|&i| {
+            let shard = shards[i].as_ref().expect("reconstructed; qed");
+            digest(shard.as_ref()) == checksums[i]
+        });
+        if !all_match {
+            for &i in missing.iter() {
+                shards[i] = None;
+            }
+            return Err(Error::ChecksumMismatch);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use std::iter::FromIterator;
+
+use smallvec::SmallVec;
+
+const WORD_BITS: usize = 64;
+
+/// A set of shard indices, e.g. of the shards present in a stripe.
+///
+/// The indices are kept as bits, so sets of up to 256 shards
+/// are stored without allocation.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct ShardSet {
+    shard_count: usize,
+    // bits beyond `shard_count` are always clear
+    words: SmallVec<[u64; 4]>,
+}
+
+impl ShardSet {
+    /// Creates an empty set over `shard_count` shards.
+    pub fn new(shard_count: usize) -> ShardSet {
+        ShardSet {
+            shard_count,
+            words: SmallVec::from_elem(0, shard_count.div_ceil(WORD_BITS)),
+        }
+    }
+
+    /// Creates a set holding all of `shard_count` shards.
+    pub fn full(shard_count: usize) -> ShardSet {
+        let mut set = ShardSet::new(shard_count);
+        for word in set.words.iter_mut() {
+            *word = !0;
+        }
+        set.clear_excess_bits();
+        set
+    }
+
+    fn clear_excess_bits(&mut self) {
+        let excess = self.shard_count % WORD_BITS;
+        if excess != 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << excess) - 1;
+            }
+        }
+    }
+
+    /// Returns the number of shards the set is over.
+    pub fn shard_count(&self) -> usize {
+        self.shard_count
+    }
+
+    /// Returns the number of indices in the set.
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Returns whether `index` is in the set.
+    pub fn contains(&self, index: usize) -> bool {
+        index < self.shard_count && self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    /// Adds `index` to the set, returning whether it was absent.
+    ///
+    /// # Panics
+    /// Panics if `index >= shard_count`.
+    pub fn insert(&mut self, index: usize) -> bool {
+        assert!(index < self.shard_count, "shard index out of range");
+
+        let was_absent = !self.contains(index);
+        self.words[index / WORD_BITS] |= 1 << (index % WORD_BITS);
+        was_absent
+    }
+
+    /// Removes `index` from the set, returning whether it was present.
+    pub fn remove(&mut self, index: usize) -> bool {
+        let was_present = self.contains(index);
+        if was_present {
+            self.words[index / WORD_BITS] &= !(1 << (index % WORD_BITS));
+        }
+        was_present
+    }
+
+    /// Returns the set of the shards not in this set.
+    pub fn complement(&self) -> ShardSet {
+        let mut set = self.clone();
+        for word in set.words.iter_mut() {
+            *word = !*word;
+        }
+        set.clear_excess_bits();
+        set
+    }
+
+    /// Returns the indices in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.shard_count).filter(move |&i| self.contains(i))
+    }
+
+    /// Returns the set as one flag per shard.
+    pub fn to_flags(&self) -> Vec<bool> {
+        (0..self.shard_count).map(|i| self.contains(i)).collect()
+    }
+}
+
+impl<'a> From<&'a [bool]> for ShardSet {
+    fn from(flags: &'a [bool]) -> ShardSet {
+        let mut set = ShardSet::new(flags.len());
+        for (i, _) in flags.iter().enumerate().filter(|&(_, &flag)| flag) {
+            set.insert(i);
+        }
+        set
+    }
+}
+
+impl FromIterator<bool> for ShardSet {
+    fn from_iter<I: IntoIterator<Item = bool>>(flags: I) -> ShardSet {
+        let mut set = ShardSet::new(0);
+        for flag in flags {
+            let index = set.shard_count;
+            if index.is_multiple_of(WORD_BITS) {
+                set.words.push(0);
+            }
+            set.shard_count += 1;
+            if flag {
+                set.insert(index);
+            }
+        }
+        set
+    }
+}
@@ -0,0 +1,281 @@
+//! Simulation of shard loss, to help choosing the numbers of data and
+//! parity shards for a channel or a storage system.
+//!
+//! A `Simulation` draws the shards lost in each stripe from a `LossModel`,
+//! and `Simulation::run` reports how often the stripes could be
+//! reconstructed over many trials. As the codes of this crate are MDS,
+//! a stripe can be reconstructed if and only if no more than
+//! `parity_shards` of its shards are lost.
+//!
+//! The simulation uses its own seeded pseudo-random generator, so runs
+//! with the same seed are reproducible.
+
+use crate::{Error, ShardSet};
+
+/// Steps the xorshift64* generator with non-zero state `state`.
+pub(crate) fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state >> 12;
+    *state ^= *state << 25;
+    *state ^= *state >> 27;
+    state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+}
+
+/// A model of how shards are lost.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum LossModel {
+    /// Every shard is lost independently with probability `loss_prob`.
+    Uniform { loss_prob: f64 },
+    /// Losses come in bursts, as modelled by a Gilbert-Elliott channel.
+    ///
+    /// The channel moves between a good and a bad state before each shard,
+    /// and loses the shard with probability `loss_good` or `loss_bad`
+    /// depending on its state. The shards of a stripe are sent in order,
+    /// and the state carries over from one stripe to the next.
+    GilbertElliott {
+        good_to_bad: f64,
+        bad_to_good: f64,
+        loss_good: f64,
+        loss_bad: f64,
+    },
+    /// The shards are stored on nodes, `shards_per_node` consecutive shards
+    /// on each, and every node fails independently with probability
+    /// `failure_prob`, losing all of its shards.
+    NodeFailure {
+        shards_per_node: usize,
+        failure_prob: f64,
+    },
+}
+
+impl LossModel {
+    fn check(&self) -> Result<(), Error> {
+        let is_prob = |p: f64| (0.0..=1.0).contains(&p);
+
+        let valid = match *self {
+            LossModel::Uniform { loss_prob } => is_prob(loss_prob),
+            LossModel::GilbertElliott {
+                good_to_bad,
+                bad_to_good,
+                loss_good,
+                loss_bad,
+            } => {
+                is_prob(good_to_bad)
+                    && is_prob(bad_to_good)
+                    && is_prob(loss_good)
+                    && is_prob(loss_bad)
+            }
+            LossModel::NodeFailure {
+                shards_per_node,
+                failure_prob,
+            } => shards_per_node > 0 && is_prob(failure_prob),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidLossModel)
+        }
+    }
+}
+
+/// Statistics of a simulation run.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub struct SimReport {
+    /// Number of stripes simulated.
+    pub trials: usize,
+    /// Number of stripes which could be reconstructed.
+    pub recovered: usize,
+    /// Total number of shards lost over all stripes.
+    pub shards_lost: usize,
+    /// Largest number of shards lost in a single stripe.
+    pub max_lost: usize,
+}
+
+impl SimReport {
+    /// Returns the fraction of the stripes which could be reconstructed.
+    pub fn recovery_rate(&self) -> f64 {
+        if self.trials == 0 {
+            return 1.0;
+        }
+        self.recovered as f64 / self.trials as f64
+    }
+
+    /// Returns the mean number of shards lost per stripe.
+    pub fn mean_lost(&self) -> f64 {
+        if self.trials == 0 {
+            return 0.0;
+        }
+        self.shards_lost as f64 / self.trials as f64
+    }
+}
+
+/// Simulation of shard loss in stripes of a given shape.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Simulation {
+    data_shards: usize,
+    parity_shards: usize,
+    model: LossModel,
+    // xorshift64* state, never zero
+    state: u64,
+    // Gilbert-Elliott channel state
+    bad: bool,
+}
+
+impl Simulation {
+    /// Creates a simulation of stripes of `data_shards` data shards and
+    /// `parity_shards` parity shards, losing shards as given by `model`.
+    ///
+    /// Returns `Error::TooFewDataShards` if `data_shards == 0`,
+    /// `Error::TooFewParityShards` if `parity_shards == 0`, and
+    /// `Error::InvalidLossModel` if a probability of the model is not
+    /// within `[0, 1]` or `shards_per_node == 0`.
+    pub fn new(
+        data_shards: usize,
+        parity_shards: usize,
+        model: LossModel,
+    ) -> Result<Simulation, Error> {
+        if data_shards == 0 {
+            return Err(Error::TooFewDataShards);
+        }
+        if parity_shards == 0 {
+            return Err(Error::TooFewParityShards);
+        }
+        model.check()?;
+
+        Ok(Simulation {
+            data_shards,
+            parity_shards,
+            model,
+            state: 0x853C_49E6_748F_EA9B,
+            bad: false,
+        })
+    }
+
+    /// Reseeds the pseudo-random generator, and resets the channel to the
+    /// good state.
+    pub fn with_seed(mut self, seed: u64) -> Simulation {
+        // any non-zero state will do
+        self.state = seed ^ 0x853C_49E6_748F_EA9B;
+        if self.state == 0 {
+            self.state = 1;
+        }
+        self.bad = false;
+        self
+    }
+
+    pub fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    pub fn parity_shards(&self) -> usize {
+        self.parity_shards
+    }
+
+    pub fn model(&self) -> LossModel {
+        self.model
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        next_random(&mut self.state)
+    }
+
+    /// Returns true with probability `p`.
+    fn chance(&mut self, p: f64) -> bool {
+        // 53 random bits give a uniform float in [0, 1)
+        let x = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        x < p
+    }
+
+    /// Draws the shards present in the next stripe.
+    pub fn next_stripe(&mut self) -> ShardSet {
+        let total_shards = self.data_shards + self.parity_shards;
+        let mut present = ShardSet::full(total_shards);
+
+        match self.model {
+            LossModel::Uniform { loss_prob } => {
+                for i in 0..total_shards {
+                    if self.chance(loss_prob) {
+                        present.remove(i);
+                    }
+                }
+            }
+            LossModel::GilbertElliott {
+                good_to_bad,
+                bad_to_good,
+                loss_good,
+                loss_bad,
+            } => {
+                for i in 0..total_shards {
+                    let flip = if self.bad { bad_to_good } else { good_to_bad };
+                    if self.chance(flip) {
+                        self.bad = !self.bad;
+                    }
+
+                    let loss_prob = if self.bad { loss_bad } else { loss_good };
+                    if self.chance(loss_prob) {
+                        present.remove(i);
+                    }
+                }
+            }
+            LossModel::NodeFailure {
+                shards_per_node,
+                failure_prob,
+            } => {
+                for node_start in (0..total_shards).step_by(shards_per_node) {
+                    if self.chance(failure_prob) {
+                        let node_end = (node_start + shards_per_node).min(total_shards);
+                        for i in node_start..node_end {
+                            present.remove(i);
+                        }
+                    }
+                }
+            }
+        }
+
+        present
+    }
+
+    /// Marks the shards lost in the next stripe as missing in `shards`,
+    /// returning whether the stripe can still be reconstructed.
+    ///
+    /// Returns `Error::TooFewShards` or `Error::TooManyShards` if the number
+    /// of shards does not match the shape of the simulation.
+    pub fn apply<T>(&mut self, shards: &mut [Option<T>]) -> Result<bool, Error> {
+        let total_shards = self.data_shards + self.parity_shards;
+        if shards.len() < total_shards {
+            return Err(Error::TooFewShards);
+        }
+        if shards.len() > total_shards {
+            return Err(Error::TooManyShards);
+        }
+
+        let present = self.next_stripe();
+        for (i, shard) in shards.iter_mut().enumerate() {
+            if !present.contains(i) {
+                *shard = None;
+            }
+        }
+
+        Ok(total_shards - present.count() <= self.parity_shards)
+    }
+
+    /// Simulates `trials` stripes.
+    pub fn run(&mut self, trials: usize) -> SimReport {
+        let total_shards = self.data_shards + self.parity_shards;
+        let mut report = SimReport {
+            trials,
+            ..SimReport::default()
+        };
+
+        for _ in 0..trials {
+            let lost = total_shards - self.next_stripe().count();
+
+            if lost <= self.parity_shards {
+                report.recovered += 1;
+            }
+            report.shards_lost += lost;
+            report.max_lost = report.max_lost.max(lost);
+        }
+
+        report
+    }
+}
@@ -0,0 +1,1395 @@
+//! Adapters between the codec and `std::io` sources and sinks.
+//!
+//! The input is cut into stripes of `data_shard_count` data shards of
+//! `shard_len` bytes each, and every stripe is encoded as it is read,
+//! so the whole input never needs to be held in memory at once.
+//!
+//! `ShardWriters` distributes the shards of each stripe over one
+//! destination per shard, and `ReedSolomon::reconstruct_windowed` repairs
+//! shards read from such destinations one window at a time.
+//! `ReedSolomon::encode_to_writers` likewise encodes one window at a time,
+//! writing parity straight to its destinations.
+//! `ReedSolomon::reconstruct_resumable` also reports a `RepairCheckpoint`
+//! after every window, from which an interrupted repair can resume.
+//!
+//! A `ShardTransform` can be set on `ShardWriters` to e.g. compress or
+//! encrypt every shard after encoding. The transformed shards are written
+//! in frames which record their lengths, and `ShardReaders` reverses the
+//! transform as it reads them back, ready for reconstruction.
+//!
+//! `ReedSolomon::encode_stream_to` writes a self-describing stream of
+//! stripes, whose headers let `ReedSolomon::decode_stream_from` restore
+//! the input at its exact length from any `data_shard_count` destinations.
+//! `ReedSolomon::encode_stream_to_par` writes the same stream while reading
+//! and encoding the next stripes on other threads.
+//!
+//! For data which fits in memory, `ReedSolomon::split` cuts a buffer into
+//! data shards and `ReedSolomon::join` writes the original bytes back out.
+//!
+//! These adapters work with fields whose elements are bytes,
+//! i.e. `galois_8::Field`.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::vectors::ByteReader;
+use crate::{AlignedShard, Error, Field, ParallelParam, ReedSolomon, ShardVec};
+
+/// An encoded stripe of shards.
+///
+/// The first `data_shard_count` shards are the data shards, the rest are
+/// the parity shards. All shards are of the same length.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Stripe {
+    index: usize,
+    data_shard_count: usize,
+    data_len: usize,
+    shards: Vec<Vec<u8>>,
+}
+
+impl Stripe {
+    pub(crate) fn new(
+        index: usize,
+        data_shard_count: usize,
+        data_len: usize,
+        shards: Vec<Vec<u8>>,
+    ) -> Stripe {
+        Stripe {
+            index,
+            data_shard_count,
+            data_len,
+            shards,
+        }
+    }
+
+    /// Returns the position of this stripe in the stream, starting from 0.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the number of input bytes carried by the data shards.
+    ///
+    /// This is smaller than the total size of the data shards only for the
+    /// final stripe of a stream, where the data shards are padded with zeros.
+    pub fn data_len(&self) -> usize {
+        self.data_len
+    }
+
+    /// Returns the length of every shard in the stripe.
+    pub fn shard_len(&self) -> usize {
+        self.shards[0].len()
+    }
+
+    /// Returns all shards, data shards first.
+    pub fn shards(&self) -> &[Vec<u8>] {
+        &self.shards
+    }
+
+    /// Returns the data shards.
+    pub fn data_shards(&self) -> &[Vec<u8>] {
+        &self.shards[..self.data_shard_count]
+    }
+
+    /// Returns the parity shards.
+    pub fn parity_shards(&self) -> &[Vec<u8>] {
+        &self.shards[self.data_shard_count..]
+    }
+
+    /// Consumes the stripe, returning all shards, data shards first.
+    pub fn into_shards(self) -> Vec<Vec<u8>> {
+        self.shards
+    }
+}
+
+/// Iterator over the encoded stripes of a reader.
+///
+/// See `ReedSolomon::stripes`.
+#[derive(Debug)]
+pub struct Stripes<'a, F: 'a + Field, R> {
+    codec: &'a ReedSolomon<F>,
+    reader: R,
+    shard_len: usize,
+    next_index: usize,
+    done: bool,
+}
+
+impl<'a, F: 'a + Field<Elem = u8>, R: Read> Stripes<'a, F, R> {
+    /// Returns the length of every shard produced.
+    pub fn shard_len(&self) -> usize {
+        self.shard_len
+    }
+
+    /// Consumes the iterator, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_stripe(&mut self) -> io::Result<Option<Stripe>> {
+        let data_shard_count = self.codec.data_shard_count;
+        let mut shards = vec![vec![0u8; self.shard_len]; self.codec.total_shard_count];
+
+        let mut data_len = 0;
+        for shard in shards[..data_shard_count].iter_mut() {
+            let read = fill_buf(&mut self.reader, shard)?;
+            data_len += read;
+
+            if read < self.shard_len {
+                // The reader is exhausted, the remaining data shards stay zeroed.
+                self.done = true;
+                break;
+            }
+        }
+
+        if data_len == 0 {
+            return Ok(None);
+        }
+
+        self.codec
+            .encode(&mut shards)
+            .expect("shard count and sizes are valid by construction; qed");
+
+        let stripe = Stripe {
+            index: self.next_index,
+            data_shard_count,
+            data_len,
+            shards,
+        };
+        self.next_index += 1;
+
+        Ok(Some(stripe))
+    }
+}
+
+impl<'a, F: 'a + Field<Elem = u8>, R: Read> Iterator for Stripes<'a, F, R> {
+    type Item = io::Result<Stripe>;
+
+    fn next(&mut self) -> Option<io::Result<Stripe>> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_stripe() {
+            Ok(Some(stripe)) => Some(Ok(stripe)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Reads into `buf` until it is full or the reader is exhausted,
+/// returning the number of bytes read.
+fn fill_buf<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Reads the data shards of the next stripe into `shards`, zeroing what
+/// the reader cannot fill. Returns the number of bytes read, and whether
+/// the reader is exhausted.
+fn read_data_shards<R: Read>(reader: &mut R, shards: &mut [Vec<u8>]) -> io::Result<(usize, bool)> {
+    let mut data_len = 0;
+    let mut exhausted = false;
+    for shard in shards.iter_mut() {
+        if exhausted {
+            shard.iter_mut().for_each(|x| *x = 0);
+            continue;
+        }
+
+        let read = fill_buf(reader, shard)?;
+        data_len += read;
+        if read < shard.len() {
+            shard[read..].iter_mut().for_each(|x| *x = 0);
+            exhausted = true;
+        }
+    }
+    Ok((data_len, exhausted))
+}
+
+impl<F: Field<Elem = u8>> ReedSolomon<F> {
+    /// Returns an iterator which reads `reader` in stripes of
+    /// `data_shard_count * shard_len` bytes and yields each stripe encoded.
+    ///
+    /// Reading happens lazily, one stripe per call to `next`. The final
+    /// stripe is padded with zeros if the reader runs out of data partway
+    /// through it, see `Stripe::data_len`.
+    ///
+    /// Iteration stops after the first IO error.
+    ///
+    /// Returns `Error::EmptyShard` if `shard_len == 0`.
+    pub fn stripes<R: Read>(
+        &self,
+        reader: R,
+        shard_len: usize,
+    ) -> Result<Stripes<'_, F, R>, Error> {
+        if shard_len == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        Ok(Stripes {
+            codec: self,
+            reader,
+            shard_len,
+            next_index: 0,
+            done: false,
+        })
+    }
+}
+
+/// Error returned by `ShardWriters`.
+#[derive(Debug)]
+pub enum WriteError {
+    /// The shards provided do not fit the codec.
+    RSError(Error),
+    /// Writing to some of the destinations failed.
+    ///
+    /// Holds the index of each failed destination along with its error.
+    /// The other destinations were written to normally.
+    Io(Vec<(usize, io::Error)>),
+}
+
+impl std::fmt::Display for WriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            WriteError::RSError(ref e) => write!(f, "{}", e),
+            WriteError::Io(ref errors) => {
+                write!(f, "Failed to write to destination(s)")?;
+                for &(i, ref e) in errors.iter() {
+                    write!(f, " {}: {};", i, e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
+impl From<Error> for WriteError {
+    fn from(e: Error) -> WriteError {
+        WriteError::RSError(e)
+    }
+}
+
+/// Converts to the `io::Error` of the codec error for
+/// `WriteError::RSError`. For `WriteError::Io`, converts to the error of
+/// the destination if only one failed, and otherwise to an `io::Error` of
+/// the kind of the first failure, holding all of them.
+impl From<WriteError> for io::Error {
+    fn from(e: WriteError) -> io::Error {
+        match e {
+            WriteError::RSError(e) => e.into(),
+            WriteError::Io(mut errors) => {
+                if errors.len() == 1 {
+                    return errors.pop().unwrap().1;
+                }
+                let kind = errors[0].1.kind();
+                io::Error::new(kind, WriteError::Io(errors))
+            }
+        }
+    }
+}
+
+/// The result of writing to `ShardWriters`.
+pub type WriteResult<T = ()> = Result<T, WriteError>;
+
+/// Transform applied to each shard before it is stored, and reversed
+/// after it is loaded, e.g. compression or encryption.
+pub trait ShardTransform {
+    /// Transforms shard `index` of a stripe before it is stored.
+    fn store(&mut self, index: usize, shard: &[u8]) -> io::Result<Vec<u8>>;
+
+    /// Reverses `store` on shard `index` of a stripe after it is loaded.
+    fn load(&mut self, index: usize, stored: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// Length of the frame header preceding each transformed shard: the
+/// length of the transformed shard, then the length of the shard itself,
+/// both as little-endian `u32`.
+const FRAME_HEADER_LEN: usize = 8;
+
+/// Fan-out sink which writes every shard of a stripe to its own destination.
+///
+/// Shard `i` of each stripe goes to destination `i`, e.g. one file
+/// or socket per storage node.
+///
+/// If a transform is set, each shard is transformed and written in a frame
+/// recording its lengths, to be read back with `ShardReaders`. A shard
+/// which fails to transform fails its destination.
+///
+/// A destination which fails is reported once, in the error of the call
+/// during which it failed, and is skipped by all subsequent writes,
+/// as the shards written to it can no longer be trusted to line up.
+/// The stripe is still recoverable from the other destinations as long as
+/// at most `parity_shard_count` of them have failed.
+pub struct ShardWriters<W> {
+    writers: Vec<W>,
+    failed: Vec<bool>,
+    transform: Option<Box<dyn ShardTransform>>,
+}
+
+impl<W: std::fmt::Debug> std::fmt::Debug for ShardWriters<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        f.debug_struct("ShardWriters")
+            .field("writers", &self.writers)
+            .field("failed", &self.failed)
+            .field("transformed", &self.transform.is_some())
+            .finish()
+    }
+}
+
+impl<W: Write> ShardWriters<W> {
+    /// Creates a new fan-out sink over `writers`, one per shard of `codec`.
+    ///
+    /// Returns `Error::TooFewShards` or `Error::TooManyShards` when the
+    /// number of writers does not match the total shard count of the codec.
+    pub fn new<F: Field>(
+        codec: &ReedSolomon<F>,
+        writers: Vec<W>,
+    ) -> Result<ShardWriters<W>, Error> {
+        check_piece_count!(all => codec, &writers[..]);
+
+        let failed = vec![false; writers.len()];
+
+        Ok(ShardWriters {
+            writers,
+            failed,
+            transform: None,
+        })
+    }
+
+    /// Sets the transform applied to every shard written from now on.
+    pub fn set_transform(&mut self, transform: Box<dyn ShardTransform>) {
+        self.transform = Some(transform);
+    }
+
+    /// Writes every shard of `stripe` to its destination.
+    pub fn write_stripe(&mut self, stripe: &Stripe) -> WriteResult {
+        self.write_shards(stripe.shards())
+    }
+
+    /// Writes every shard to its destination.
+    ///
+    /// Returns `WriteError::RSError` without writing anything when the
+    /// number of shards does not match the number of destinations, or the
+    /// shards are not of the same length.
+    pub fn write_shards<T: AsRef<[u8]>>(&mut self, shards: &[T]) -> WriteResult {
+        self.check_shards(shards)?;
+
+        let mut errors = Vec::new();
+        for (i, shard) in shards.iter().enumerate() {
+            if self.failed[i] {
+                continue;
+            }
+            let written = match self.transform {
+                Some(ref mut transform) => transform.store(i, shard.as_ref()).and_then(|stored| {
+                    write_frame(&mut self.writers[i], &stored, shard.as_ref().len())
+                }),
+                None => self.writers[i].write_all(shard.as_ref()),
+            };
+            if let Err(e) = written {
+                self.failed[i] = true;
+                errors.push((i, e));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(WriteError::Io(errors))
+        }
+    }
+
+    /// Flushes all destinations which have not failed.
+    pub fn flush(&mut self) -> WriteResult {
+        let mut errors = Vec::new();
+        for (i, writer) in self.writers.iter_mut().enumerate() {
+            if self.failed[i] {
+                continue;
+            }
+            if let Err(e) = writer.flush() {
+                self.failed[i] = true;
+                errors.push((i, e));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(WriteError::Io(errors))
+        }
+    }
+
+    /// Returns the indices of the destinations which have failed so far.
+    pub fn failed_indices(&self) -> Vec<usize> {
+        self.failed
+            .iter()
+            .enumerate()
+            .filter(|&(_, &failed)| failed)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Consumes the sink, returning the destinations.
+    pub fn into_inner(self) -> Vec<W> {
+        self.writers
+    }
+
+    fn check_shards<T: AsRef<[u8]>>(&self, shards: &[T]) -> Result<(), Error> {
+        if shards.len() < self.writers.len() {
+            return Err(Error::TooFewShards);
+        }
+        if shards.len() > self.writers.len() {
+            return Err(Error::TooManyShards);
+        }
+        check_slices!(multi => shards);
+
+        Ok(())
+    }
+}
+
+fn frame_len(len: usize) -> io::Result<[u8; 4]> {
+    if len > u32::MAX as usize {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+    Ok((len as u32).to_le_bytes())
+}
+
+fn write_frame<W: Write>(writer: &mut W, stored: &[u8], shard_len: usize) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + stored.len());
+    frame.extend_from_slice(&frame_len(stored.len())?);
+    frame.extend_from_slice(&frame_len(shard_len)?);
+    frame.extend_from_slice(stored);
+
+    writer.write_all(&frame)
+}
+
+/// Reads the next frame, returning `None` at the end of the reader.
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<(Vec<u8>, usize)>> {
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    match fill_buf(reader, &mut header)? {
+        0 => return Ok(None),
+        FRAME_HEADER_LEN => (),
+        _ => return Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+
+    let mut stored_len = [0u8; 4];
+    let mut shard_len = [0u8; 4];
+    stored_len.copy_from_slice(&header[..4]);
+    shard_len.copy_from_slice(&header[4..]);
+
+    let mut stored = vec![0u8; u32::from_le_bytes(stored_len) as usize];
+    reader.read_exact(&mut stored)?;
+
+    Ok(Some((stored, u32::from_le_bytes(shard_len) as usize)))
+}
+
+/// Reads the next frame and reverses the transform on it, returning `None`
+/// at the end of the reader.
+fn load_frame<R: Read>(
+    reader: &mut R,
+    transform: &mut dyn ShardTransform,
+    index: usize,
+) -> io::Result<Option<Vec<u8>>> {
+    let (stored, shard_len) = match read_frame(reader)? {
+        Some(frame) => frame,
+        None => return Ok(None),
+    };
+
+    let shard = transform.load(index, &stored)?;
+    if shard.len() != shard_len {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+
+    Ok(Some(shard))
+}
+
+/// Reader of the transformed shards written by `ShardWriters`, one source
+/// per shard.
+///
+/// A source which fails, whether to read or to reverse the transform,
+/// is skipped by all subsequent reads, and its shards are reported missing.
+pub struct ShardReaders<R> {
+    readers: Vec<Option<R>>,
+    transform: Box<dyn ShardTransform>,
+}
+
+impl<R: std::fmt::Debug> std::fmt::Debug for ShardReaders<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        f.debug_struct("ShardReaders")
+            .field("readers", &self.readers)
+            .finish()
+    }
+}
+
+impl<R: Read> ShardReaders<R> {
+    /// Creates a reader over `readers`, one per shard of `codec`, `None`
+    /// marking the missing shards. `transform` must match the transform
+    /// the shards were written with.
+    ///
+    /// Returns `Error::TooFewShards` or `Error::TooManyShards` when the
+    /// number of readers does not match the total shard count of the codec.
+    pub fn new<F: Field>(
+        codec: &ReedSolomon<F>,
+        readers: Vec<Option<R>>,
+        transform: Box<dyn ShardTransform>,
+    ) -> Result<ShardReaders<R>, Error> {
+        check_piece_count!(all => codec, &readers[..]);
+
+        Ok(ShardReaders { readers, transform })
+    }
+
+    /// Reads the next stripe, with the transform reversed.
+    ///
+    /// Returns `None` once all sources are exhausted. Otherwise returns the
+    /// shards of the stripe, `None` marking the shards which are missing,
+    /// ready to be passed to `ReedSolomon::reconstruct`.
+    pub fn read_stripe(&mut self) -> Option<Vec<Option<Vec<u8>>>> {
+        let mut shards = Vec::with_capacity(self.readers.len());
+
+        for (i, slot) in self.readers.iter_mut().enumerate() {
+            let transform = &mut *self.transform;
+            let shard = match slot.as_mut().map(|reader| load_frame(reader, transform, i)) {
+                Some(Ok(shard)) => shard,
+                Some(Err(_)) => {
+                    *slot = None;
+                    None
+                }
+                None => None,
+            };
+            shards.push(shard);
+        }
+
+        if shards.iter().any(Option::is_some) {
+            Some(shards)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the indices of the sources which are missing or have failed.
+    pub fn failed_indices(&self) -> Vec<usize> {
+        self.readers
+            .iter()
+            .enumerate()
+            .filter(|&(_, r)| r.is_none())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Consumes the reader, returning the sources.
+    pub fn into_inner(self) -> Vec<Option<R>> {
+        self.readers
+    }
+}
+
+/// Magic bytes opening the binary form of a `RepairCheckpoint`.
+const CHECKPOINT_MAGIC: [u8; 4] = *b"RSCP";
+
+const CHECKPOINT_VERSION: u8 = 1;
+
+/// Progress of `ReedSolomon::reconstruct_resumable`: the offset up to
+/// which the missing shards are written, and which shards they are.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct RepairCheckpoint {
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    offset: u64,
+    wanted: Vec<usize>,
+}
+
+impl RepairCheckpoint {
+    /// Returns the offset up to which the missing shards are written.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the indices of the missing shards being written, in
+    /// ascending order.
+    pub fn wanted(&self) -> &[usize] {
+        &self.wanted
+    }
+
+    /// Returns the binary form of the checkpoint: the magic bytes
+    /// `b"RSCP"`, a version byte, then the data shard count, the parity
+    /// shard count, the offset, the number of missing shards written and
+    /// their indices, all little-endian, the offset on 8 bytes and the
+    /// others on 2 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(17 + 2 * self.wanted.len());
+
+        bytes.extend_from_slice(&CHECKPOINT_MAGIC);
+        bytes.push(CHECKPOINT_VERSION);
+        bytes.extend_from_slice(&(self.data_shard_count as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.parity_shard_count as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.offset.to_le_bytes());
+        bytes.extend_from_slice(&(self.wanted.len() as u16).to_le_bytes());
+        for &i in self.wanted.iter() {
+            bytes.extend_from_slice(&(i as u16).to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Parses the binary form of a checkpoint.
+    ///
+    /// Returns an error of kind `InvalidData` if `bytes` is not a
+    /// checkpoint of a supported version.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<RepairCheckpoint> {
+        let mut reader = ByteReader { bytes };
+
+        if reader.take(4)? != CHECKPOINT_MAGIC || reader.take(1)?[0] != CHECKPOINT_VERSION {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let data_shard_count = reader.u16()?;
+        let parity_shard_count = reader.u16()?;
+        let offset = reader.u64()?;
+        let wanted_count = reader.u16()?;
+        let mut wanted = Vec::with_capacity(wanted_count);
+        for _ in 0..wanted_count {
+            wanted.push(reader.u16()?);
+        }
+        if !reader.bytes.is_empty()
+            || wanted.windows(2).any(|w| w[0] >= w[1])
+            || wanted
+                .last()
+                .is_some_and(|&i| i >= data_shard_count + parity_shard_count)
+        {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        Ok(RepairCheckpoint {
+            data_shard_count,
+            parity_shard_count,
+            offset,
+            wanted,
+        })
+    }
+}
+
+impl<F: Field<Elem = u8>> ReedSolomon<F> {
+    /// Constructs the parity shards in fixed-size windows, writing each
+    /// window of parity to `writers` as soon as it is computed.
+    ///
+    /// `writers[i]` receives parity shard `i`, i.e. shard
+    /// `data_shard_count + i` of the stripe. Only `window_len` bytes of
+    /// buffer per parity shard are held at once, instead of the full
+    /// parity shards.
+    ///
+    /// Codec errors are returned as IO errors of kind `InvalidInput`,
+    /// wrapping the `Error` as in `encode_sep`. In addition,
+    /// `Error::EmptyShard` is returned if `window_len == 0`. Nothing is
+    /// written in either case.
+    pub fn encode_to_writers<T: AsRef<[u8]>, W: Write>(
+        &self,
+        data: &[T],
+        writers: &mut [W],
+        window_len: usize,
+    ) -> io::Result<()> {
+        let checks = || {
+            check_piece_count!(data => self, data);
+            check_piece_count!(parity => self, writers);
+            check_slices!(multi => data);
+            if window_len == 0 {
+                return Err(Error::EmptyShard);
+            }
+            Ok(())
+        };
+        checks()?;
+
+        let parity_rows = self.get_parity_rows();
+        let shard_len = data[0].as_ref().len();
+
+        let mut buffers = self.take_scratch(self.parity_shard_count, window_len.min(shard_len));
+
+        for start in (0..shard_len).step_by(window_len) {
+            let end = (start + window_len).min(shard_len);
+
+            let inputs: ShardVec<&[u8]> = data
+                .iter()
+                .map(|shard| &shard.as_ref()[start..end])
+                .collect();
+            let mut outputs: ShardVec<&mut [u8]> = buffers
+                .iter_mut()
+                .map(|buf| &mut buf[..end - start])
+                .collect();
+
+            // Do the coding.
+            self.code_some_slices(parity_rows, &inputs, &mut outputs);
+
+            for (writer, output) in writers.iter_mut().zip(outputs.iter()) {
+                writer.write_all(output)?;
+            }
+        }
+        self.count_encoded(data);
+        self.return_scratch(buffers);
+
+        Ok(())
+    }
+
+    /// Reconstructs missing shards in fixed-size windows, so shards far
+    /// larger than memory can be repaired.
+    ///
+    /// `readers` holds a reader for each present shard and `None` for each
+    /// missing one. `writers` holds a writer for each missing shard that
+    /// should be written out, and `None` otherwise. Both must have exactly
+    /// `total_shard_count` entries.
+    ///
+    /// Window `w` of the first `data_shard_count` present shards is read,
+    /// the same window of the wanted missing shards is decoded and written,
+    /// and the process repeats until the readers are exhausted. Only
+    /// `window_len * total_shard_count` bytes of buffers are held at once.
+    /// The remaining present shards are not read.
+    ///
+    /// Returns the length of the reconstructed shards.
+    ///
+    /// Codec errors are returned as IO errors of kind `InvalidInput`,
+    /// wrapping the `Error` as in `reconstruct`. In addition,
+    /// `Error::EmptyShard` is returned if `window_len == 0`,
+    /// `Error::InvalidShardFlags` if a writer is given for a present shard,
+    /// and `Error::IncorrectShardSize` if the readers yield different
+    /// amounts of data.
+    pub fn reconstruct_windowed<R: Read, W: Write>(
+        &self,
+        readers: &mut [Option<R>],
+        writers: &mut [Option<W>],
+        window_len: usize,
+    ) -> io::Result<u64> {
+        let checks = || {
+            check_piece_count!(all => self, readers);
+            check_piece_count!(all => self, writers);
+            if window_len == 0 {
+                return Err(Error::EmptyShard);
+            }
+            if readers
+                .iter()
+                .zip(writers.iter())
+                .any(|(r, w)| r.is_some() && w.is_some())
+            {
+                return Err(Error::InvalidShardFlags);
+            }
+            if readers.iter().filter(|r| r.is_some()).count() < self.data_shard_count {
+                return Err(Error::TooFewShardsPresent);
+            }
+            Ok(())
+        };
+        checks()?;
+
+        self.reconstruct_windows(readers, writers, window_len, 0, false, |_| Ok(()))
+    }
+
+    /// Reconstructs missing shards in fixed-size windows like
+    /// `reconstruct_windowed`, reporting a checkpoint after every window so
+    /// that an interrupted repair can resume where it stopped.
+    ///
+    /// The writers are flushed before every call to `checkpoint`, which
+    /// should store the checkpoint durably, e.g. with
+    /// `RepairCheckpoint::to_bytes`, or skip some of them to save on
+    /// storage writes. Returning an error from `checkpoint` stops the
+    /// repair.
+    ///
+    /// With `resume`, the readers of the present shards used as decoding
+    /// input and the writers are first seeked to the offset of the
+    /// checkpoint, and the repair carries on from there. The writers must
+    /// be for the same shards as when the checkpoint was taken, the readers
+    /// may differ.
+    ///
+    /// Returns the length of the reconstructed shards, including the part
+    /// repaired before `resume`.
+    ///
+    /// Returns `Error::InvalidCheckpoint` if `resume` was taken with a
+    /// codec of another shape or for other missing shards, along with the
+    /// errors of `reconstruct_windowed`, as IO errors of kind
+    /// `InvalidInput`.
+    pub fn reconstruct_resumable<R, W, C>(
+        &self,
+        readers: &mut [Option<R>],
+        writers: &mut [Option<W>],
+        window_len: usize,
+        resume: Option<&RepairCheckpoint>,
+        mut checkpoint: C,
+    ) -> io::Result<u64>
+    where
+        R: Read + Seek,
+        W: Write + Seek,
+        C: FnMut(&RepairCheckpoint) -> io::Result<()>,
+    {
+        let wanted: Vec<usize> = writers
+            .iter()
+            .enumerate()
+            .filter(|&(_, w)| w.is_some())
+            .map(|(i, _)| i)
+            .collect();
+
+        let checks = || {
+            check_piece_count!(all => self, readers);
+            check_piece_count!(all => self, writers);
+            if window_len == 0 {
+                return Err(Error::EmptyShard);
+            }
+            if readers
+                .iter()
+                .zip(writers.iter())
+                .any(|(r, w)| r.is_some() && w.is_some())
+            {
+                return Err(Error::InvalidShardFlags);
+            }
+            if readers.iter().filter(|r| r.is_some()).count() < self.data_shard_count {
+                return Err(Error::TooFewShardsPresent);
+            }
+            if let Some(resume) = resume {
+                if resume.data_shard_count != self.data_shard_count
+                    || resume.parity_shard_count != self.parity_shard_count
+                    || resume.wanted != wanted
+                {
+                    return Err(Error::InvalidCheckpoint);
+                }
+            }
+            Ok(())
+        };
+        checks()?;
+
+        let offset = resume.map_or(0, |resume| resume.offset);
+        if offset > 0 {
+            let used = readers
+                .iter_mut()
+                .filter_map(Option::as_mut)
+                .take(self.data_shard_count);
+            for reader in used {
+                reader.seek(SeekFrom::Start(offset))?;
+            }
+            for writer in writers.iter_mut().filter_map(Option::as_mut) {
+                writer.seek(SeekFrom::Start(offset))?;
+            }
+        }
+
+        let mut state = RepairCheckpoint {
+            data_shard_count: self.data_shard_count,
+            parity_shard_count: self.parity_shard_count,
+            offset,
+            wanted,
+        };
+        self.reconstruct_windows(readers, writers, window_len, offset, true, |offset| {
+            state.offset = offset;
+            checkpoint(&state)
+        })
+    }
+
+    /// Runs the windows of `reconstruct_windowed` from `offset`, the
+    /// arguments being checked, calling `on_window` with the offset
+    /// reached after every window, the writers flushed first if `flush`.
+    fn reconstruct_windows<R, W, C>(
+        &self,
+        readers: &mut [Option<R>],
+        writers: &mut [Option<W>],
+        window_len: usize,
+        offset: u64,
+        flush: bool,
+        mut on_window: C,
+    ) -> io::Result<u64>
+    where
+        R: Read,
+        W: Write,
+        C: FnMut(u64) -> io::Result<()>,
+    {
+        // The shards used as decoding input.
+        let used: Vec<usize> = readers
+            .iter()
+            .enumerate()
+            .filter(|&(_, r)| r.is_some())
+            .map(|(i, _)| i)
+            .take(self.data_shard_count)
+            .collect();
+
+        let data_only = writers[self.data_shard_count..].iter().all(Option::is_none);
+
+        let mut buffers = vec![vec![0u8; window_len]; self.total_shard_count];
+        let mut shard_len = offset;
+
+        loop {
+            // Read the next window of each shard used.
+            let mut read_len = None;
+            for &i in used.iter() {
+                let reader = readers[i].as_mut().expect("used shards have readers; qed");
+                let read = fill_buf(reader, &mut buffers[i])?;
+
+                if read_len.is_some_and(|len| len != read) {
+                    return Err(Error::IncorrectShardSize.into());
+                }
+                read_len = Some(read);
+            }
+            let read_len = read_len.expect("at least one shard used; qed");
+
+            if read_len == 0 {
+                break;
+            }
+
+            {
+                let mut window: Vec<(&mut [u8], bool)> = buffers
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, buf)| (&mut buf[..read_len], used.contains(&i)))
+                    .collect();
+
+                self.reconstruct_internal(&mut window, data_only)?;
+            }
+
+            for (writer, buf) in writers.iter_mut().zip(buffers.iter()) {
+                if let Some(writer) = writer {
+                    writer.write_all(&buf[..read_len])?;
+                }
+            }
+
+            shard_len += read_len as u64;
+
+            if flush {
+                for writer in writers.iter_mut().filter_map(Option::as_mut) {
+                    writer.flush()?;
+                }
+            }
+            on_window(shard_len)?;
+
+            if read_len < window_len {
+                break;
+            }
+        }
+
+        Ok(shard_len)
+    }
+}
+
+/// Magic bytes opening every destination written by
+/// `ReedSolomon::encode_stream_to`.
+const STREAM_MAGIC: [u8; 4] = *b"RSES";
+
+/// Length of the stream header: the magic bytes, then the data shard
+/// count, the parity shard count, the index of the shard held by the
+/// destination and the shard length, all as little-endian `u32`.
+const STREAM_HEADER_LEN: usize = 20;
+
+/// Length of the stripe header preceding each shard: the number of input
+/// bytes carried by the stripe, as little-endian `u64`.
+const STRIPE_HEADER_LEN: usize = 8;
+
+fn stream_header(
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    index: usize,
+    shard_len: usize,
+) -> io::Result<[u8; STREAM_HEADER_LEN]> {
+    let mut header = [0u8; STREAM_HEADER_LEN];
+    header[..4].copy_from_slice(&STREAM_MAGIC);
+    header[4..8].copy_from_slice(&frame_len(data_shard_count)?);
+    header[8..12].copy_from_slice(&frame_len(parity_shard_count)?);
+    header[12..16].copy_from_slice(&frame_len(index)?);
+    header[16..].copy_from_slice(&frame_len(shard_len)?);
+    Ok(header)
+}
+
+/// Returns the value most entries of `values` hold, the first one on a tie.
+fn most_common<T: Copy + PartialEq>(values: &[Option<T>]) -> Option<T> {
+    let count = |value: T| values.iter().filter(|v| **v == Some(value)).count();
+
+    let mut best: Option<(T, usize)> = None;
+    for value in values.iter().flatten() {
+        let n = count(*value);
+        if best.is_none_or(|(_, best_n)| n > best_n) {
+            best = Some((*value, n));
+        }
+    }
+    best.map(|(value, _)| value)
+}
+
+/// Drops the readers whose entry of `values` is not `agreed`.
+fn drop_disagreeing<R, T: PartialEq>(readers: &mut [Option<R>], values: &[Option<T>], agreed: T) {
+    for (slot, value) in readers.iter_mut().zip(values.iter()) {
+        if value.as_ref() != Some(&agreed) {
+            *slot = None;
+        }
+    }
+}
+
+/// Reads the next shard along with the data length of its stripe,
+/// returning `None` at the end of the reader.
+fn read_stripe_shard<R: Read>(
+    reader: &mut R,
+    shard_len: usize,
+) -> io::Result<Option<(u64, Vec<u8>)>> {
+    let mut header = [0u8; STRIPE_HEADER_LEN];
+    match fill_buf(reader, &mut header)? {
+        0 => return Ok(None),
+        STRIPE_HEADER_LEN => (),
+        _ => return Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+
+    let mut shard = vec![0u8; shard_len];
+    reader.read_exact(&mut shard)?;
+
+    Ok(Some((u64::from_le_bytes(header), shard)))
+}
+
+impl<F: Field<Elem = u8>> ReedSolomon<F> {
+    /// Encodes `reader` in stripes as `stripes` does, writing shard `i` of
+    /// every stripe to `writers[i]`.
+    ///
+    /// Each destination starts with a stream header recording the shape of
+    /// the code and the index of the shard it holds, and every shard is
+    /// preceded by the number of input bytes carried by its stripe. Any
+    /// `data_shard_count` destinations are thus enough for
+    /// `decode_stream_from` to restore the input at its exact length, the
+    /// padding of the final stripe removed.
+    ///
+    /// Returns the length of the input.
+    ///
+    /// Codec errors are returned as IO errors of kind `InvalidInput`:
+    /// `Error::TooFewShards` or `Error::TooManyShards` when the number of
+    /// writers does not match the total shard count, and
+    /// `Error::EmptyShard` if `shard_len == 0`.
+    pub fn encode_stream_to<R: Read, W: Write>(
+        &self,
+        reader: R,
+        writers: &mut [W],
+        shard_len: usize,
+    ) -> io::Result<u64> {
+        let checks = || {
+            check_piece_count!(all => self, writers);
+            self.stripes(reader, shard_len)
+        };
+        let stripes = checks()?;
+
+        for (i, writer) in writers.iter_mut().enumerate() {
+            let header =
+                stream_header(self.data_shard_count, self.parity_shard_count, i, shard_len)?;
+            writer.write_all(&header)?;
+        }
+
+        let mut data_len = 0u64;
+        for stripe in stripes {
+            let stripe = stripe?;
+            let stripe_header = (stripe.data_len() as u64).to_le_bytes();
+
+            for (writer, shard) in writers.iter_mut().zip(stripe.shards().iter()) {
+                writer.write_all(&stripe_header)?;
+                writer.write_all(shard)?;
+            }
+
+            data_len += stripe.data_len() as u64;
+        }
+
+        Ok(data_len)
+    }
+
+    /// Encodes `reader` to `writers` as `encode_stream_to` does, with
+    /// reading, encoding and writing overlapped.
+    ///
+    /// A thread reads the next stripes while another encodes the stripe
+    /// before them, with multiple threads as configured by `param`, or by
+    /// `pparam` if `param` is `None`, and the calling thread writes the
+    /// encoded stripes out. At most two stripes wait between each of these
+    /// stages, and the buffers of the stripes written are reused for the
+    /// stripes read next.
+    ///
+    /// An IO error of any stage stops all of them, and is returned once
+    /// the threads are done. On targets without threads, this is
+    /// `encode_stream_to`.
+    ///
+    /// Returns the same errors as `encode_stream_to`.
+    pub fn encode_stream_to_par<R, W, P>(
+        &self,
+        reader: R,
+        writers: &mut [W],
+        shard_len: usize,
+        param: P,
+    ) -> io::Result<u64>
+    where
+        R: Read + Send,
+        W: Write,
+        P: Into<Option<ParallelParam>>,
+    {
+        if !crate::THREADS_SUPPORTED {
+            return self.encode_stream_to(reader, writers, shard_len);
+        }
+        let param = param.into().unwrap_or_else(|| self.pparam());
+
+        let checks = || {
+            check_piece_count!(all => self, writers);
+            if shard_len == 0 {
+                return Err(Error::EmptyShard);
+            }
+            Ok(())
+        };
+        checks()?;
+
+        for (i, writer) in writers.iter_mut().enumerate() {
+            let header =
+                stream_header(self.data_shard_count, self.parity_shard_count, i, shard_len)?;
+            writer.write_all(&header)?;
+        }
+
+        // every stripe is sent along with the number of input bytes it holds
+        let (read_tx, read_rx) = mpsc::sync_channel::<io::Result<(Vec<Vec<u8>>, usize)>>(1);
+        let (encoded_tx, encoded_rx) = mpsc::sync_channel::<io::Result<(Vec<Vec<u8>>, usize)>>(1);
+        let (free_tx, free_rx) = mpsc::channel::<Vec<Vec<u8>>>();
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                let mut reader = reader;
+                loop {
+                    let mut shards = free_rx
+                        .try_recv()
+                        .unwrap_or_else(|_| vec![vec![0u8; shard_len]; self.total_shard_count]);
+
+                    match read_data_shards(&mut reader, &mut shards[..self.data_shard_count]) {
+                        Ok((0, _)) => break,
+                        Ok((data_len, last)) => {
+                            if read_tx.send(Ok((shards, data_len))).is_err() || last {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            let _ = read_tx.send(Err(e));
+                            break;
+                        }
+                    }
+                }
+            });
+
+            scope.spawn(move || {
+                for stripe in read_rx {
+                    let stripe = stripe.map(|(mut shards, data_len)| {
+                        self.encode_par(&mut shards, param)
+                            .expect("shard count and sizes are valid by construction; qed");
+                        (shards, data_len)
+                    });
+                    if encoded_tx.send(stripe).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            // dropping `encoded_rx` on error stops the other stages
+            let mut data_len = 0u64;
+            for stripe in encoded_rx {
+                let (shards, stripe_len) = stripe?;
+                let stripe_header = (stripe_len as u64).to_le_bytes();
+
+                for (writer, shard) in writers.iter_mut().zip(shards.iter()) {
+                    writer.write_all(&stripe_header)?;
+                    writer.write_all(shard)?;
+                }
+
+                data_len += stripe_len as u64;
+                let _ = free_tx.send(shards);
+            }
+
+            Ok(data_len)
+        })
+    }
+
+    /// Restores the input written by `encode_stream_to` from the
+    /// destinations it wrote to, writing it to `writer`.
+    ///
+    /// `readers` holds a reader for each available destination and `None`
+    /// for each missing one, in shard order. A reader which fails, whose
+    /// header does not match this codec or its position, or whose shard
+    /// length or stripe lengths differ from those of most readers, is
+    /// skipped from then on and its slot is set to `None`.
+    ///
+    /// Returns the length of the input.
+    ///
+    /// Codec errors are returned as IO errors of kind `InvalidInput`:
+    /// `Error::TooFewShards` or `Error::TooManyShards` when the number of
+    /// readers does not match the total shard count, and
+    /// `Error::TooFewShardsPresent` when fewer than `data_shard_count`
+    /// readers are left for a stripe.
+    pub fn decode_stream_from<R: Read, W: Write>(
+        &self,
+        readers: &mut [Option<R>],
+        writer: &mut W,
+    ) -> io::Result<u64> {
+        let checks = || {
+            check_piece_count!(all => self, readers);
+            Ok(())
+        };
+        checks()?;
+
+        // the shard length of each reader with a valid header
+        let mut shard_lens = vec![None; readers.len()];
+        for (i, slot) in readers.iter_mut().enumerate() {
+            let reader = match slot.as_mut() {
+                Some(reader) => reader,
+                None => continue,
+            };
+
+            let mut header = [0u8; STREAM_HEADER_LEN];
+            if reader.read_exact(&mut header).is_err() {
+                *slot = None;
+                continue;
+            }
+
+            let mut len = [0u8; 4];
+            len.copy_from_slice(&header[16..]);
+            let len = u32::from_le_bytes(len) as usize;
+
+            let expected = stream_header(self.data_shard_count, self.parity_shard_count, i, len)?;
+            if header != expected || len == 0 {
+                *slot = None;
+                continue;
+            }
+            shard_lens[i] = Some(len);
+        }
+        let shard_len = match most_common(&shard_lens) {
+            Some(len) => len,
+            None => return Err(Error::TooFewShardsPresent.into()),
+        };
+        drop_disagreeing(readers, &shard_lens, shard_len);
+
+        let mut total_len = 0u64;
+        // the data of a stripe, gathered from its data shards
+        let mut output = Vec::with_capacity(shard_len * self.data_shard_count);
+        loop {
+            let mut shards: Vec<Option<Vec<u8>>> = vec![None; self.total_shard_count];
+            let mut data_lens = vec![None; self.total_shard_count];
+
+            for (i, slot) in readers.iter_mut().enumerate() {
+                let reader = match slot.as_mut() {
+                    Some(reader) => reader,
+                    None => continue,
+                };
+                match read_stripe_shard(reader, shard_len) {
+                    Ok(Some((len, shard))) => {
+                        data_lens[i] = Some(len);
+                        shards[i] = Some(shard);
+                    }
+                    Ok(None) => (),
+                    Err(_) => *slot = None,
+                }
+            }
+
+            let data_len = match most_common(&data_lens) {
+                Some(len) => len,
+                None => break,
+            };
+            // Readers which end before the others are truncated, and
+            // readers disagreeing on the stripe length are wrong.
+            drop_disagreeing(readers, &data_lens, data_len);
+            for (shard, len) in shards.iter_mut().zip(data_lens.iter()) {
+                if *len != Some(data_len) {
+                    *shard = None;
+                }
+            }
+
+            if data_len > (shard_len * self.data_shard_count) as u64 {
+                return Err(Error::IncorrectShardSize.into());
+            }
+            output.resize(data_len as usize, 0);
+            self.reconstruct_data_gather(&shards, &mut output)?;
+            writer.write_all(&output)?;
+
+            total_len += data_len;
+        }
+
+        Ok(total_len)
+    }
+}
+
+impl<F: Field<Elem = u8>> ReedSolomon<F> {
+    /// Splits `data` into `data_shard_count` data shards of equal length,
+    /// padding the last shards with zeros.
+    ///
+    /// The shard length is the length of `data` divided by the number of
+    /// data shards, rounded up. Keep the length of `data` to `join` the
+    /// shards back.
+    ///
+    /// Returns `Error::EmptyShard` if `data` is empty.
+    pub fn split(&self, data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        if data.is_empty() {
+            return Err(Error::EmptyShard);
+        }
+
+        let shard_len = data.len().div_ceil(self.data_shard_count);
+
+        let mut shards = vec![vec![0u8; shard_len]; self.data_shard_count];
+        for (shard, chunk) in shards.iter_mut().zip(data.chunks(shard_len)) {
+            shard[..chunk.len()].copy_from_slice(chunk);
+        }
+
+        Ok(shards)
+    }
+
+    /// Splits `data` into data shards as `split` does, into aligned
+    /// shards.
+    ///
+    /// Returns `Error::EmptyShard` if `data` is empty.
+    pub fn split_aligned(&self, data: &[u8]) -> Result<Vec<AlignedShard>, Error> {
+        if data.is_empty() {
+            return Err(Error::EmptyShard);
+        }
+
+        let shard_len = data.len().div_ceil(self.data_shard_count);
+
+        let mut shards = vec![AlignedShard::zeroed(shard_len); self.data_shard_count];
+        for (shard, chunk) in shards.iter_mut().zip(data.chunks(shard_len)) {
+            shard[..chunk.len()].copy_from_slice(chunk);
+        }
+
+        Ok(shards)
+    }
+
+    /// Writes the first `data_len` bytes held by the data shards to
+    /// `writer`, undoing `split`.
+    ///
+    /// `shards` may hold only the data shards, or all shards, in which
+    /// case the parity shards are ignored.
+    ///
+    /// Codec errors are returned as IO errors of kind `InvalidInput`:
+    /// `Error::TooFewDataShards` if fewer than `data_shard_count` shards
+    /// are given, `Error::IncorrectShardSize` if the data shards are not of
+    /// the same length or hold fewer than `data_len` bytes.
+    pub fn join<W: Write, T: AsRef<[u8]>>(
+        &self,
+        writer: &mut W,
+        shards: &[T],
+        data_len: usize,
+    ) -> io::Result<()> {
+        let checks = || {
+            if shards.len() < self.data_shard_count {
+                return Err(Error::TooFewDataShards);
+            }
+            let data_shards = &shards[..self.data_shard_count];
+            check_slices!(multi => data_shards);
+            if data_shards[0].as_ref().len() * self.data_shard_count < data_len {
+                return Err(Error::IncorrectShardSize);
+            }
+            Ok(())
+        };
+        checks()?;
+
+        let mut remaining = data_len;
+        for shard in shards[..self.data_shard_count].iter() {
+            if remaining == 0 {
+                break;
+            }
+            let shard = shard.as_ref();
+            let len = shard.len().min(remaining);
+            writer.write_all(&shard[..len])?;
+            remaining -= len;
+        }
+
+        Ok(())
+    }
+}
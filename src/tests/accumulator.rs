@@ -0,0 +1,92 @@
+use std::thread;
+use std::time::Duration;
+
+use super::{fill_random, ReedSolomon};
+use crate::accumulator::{RecordLocation, StripeAccumulator};
+use crate::Error;
+
+#[test]
+fn test_accumulator_packs_records() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+    let mut acc = StripeAccumulator::new(r.clone(), 10).unwrap();
+    assert_eq!(30, acc.capacity());
+
+    let mut records = Vec::new();
+    for &len in [4, 12, 0, 13, 9, 30, 1].iter() {
+        let mut record = vec![0u8; len];
+        fill_random(&mut record);
+        records.push(record);
+    }
+
+    let mut commits = Vec::new();
+    for record in records.iter() {
+        commits.extend(acc.push(record).unwrap());
+    }
+    assert_eq!(1, acc.pending_len());
+    assert_eq!(7, acc.next_record());
+    commits.extend(acc.flush());
+    assert!(acc.flush().is_none());
+
+    // 4 + 12 + 0 + 13 fit, 9 starts the next stripe, 30 takes a whole one
+    assert_eq!(4, commits.len());
+    assert_eq!(
+        RecordLocation {
+            record: 1,
+            stripe: 0,
+            shard: 0,
+            offset: 4,
+            len: 12
+        },
+        commits[0].records[1]
+    );
+    assert_eq!(29, commits[0].stripe.data_len());
+    assert_eq!(
+        vec![4],
+        commits[1]
+            .records
+            .iter()
+            .map(|l| l.record)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(30, commits[2].stripe.data_len());
+
+    for (i, commit) in commits.iter().enumerate() {
+        assert_eq!(i, commit.stripe.index());
+        assert!(r.verify(commit.stripe.shards()).unwrap());
+        for location in commit.records.iter() {
+            assert_eq!(i, location.stripe);
+            let read = location.read(commit.stripe.data_shards());
+            assert_eq!(records[location.record as usize], read);
+        }
+    }
+    // the padding is zeros
+    assert_eq!(&[0u8][..], &commits[1].stripe.data_shards()[0][9..]);
+    assert_eq!(vec![0u8; 10], commits[1].stripe.data_shards()[1]);
+
+    assert_eq!(Error::RecordTooLarge, acc.push(&[0u8; 31]).unwrap_err());
+    assert_eq!(7, acc.next_record());
+    assert_eq!(Error::EmptyShard, StripeAccumulator::new(r, 0).unwrap_err());
+}
+
+#[test]
+fn test_accumulator_max_delay() {
+    let mut acc = StripeAccumulator::new(ReedSolomon::new(2, 1).unwrap(), 64).unwrap();
+    assert!(acc.push(b"no delay").unwrap().is_empty());
+    assert!(acc.poll().is_none());
+    assert!(acc.time_to_deadline().is_none());
+
+    acc.set_max_delay(Some(Duration::from_millis(20)));
+    assert!(acc.time_to_deadline().unwrap() <= Duration::from_millis(20));
+    thread::sleep(Duration::from_millis(25));
+    assert!(acc.push(b"late").unwrap().is_empty());
+    assert_eq!(Some(Duration::from_millis(0)), acc.time_to_deadline());
+
+    let commit = acc.poll().unwrap();
+    assert_eq!(2, commit.records.len());
+    assert_eq!(
+        b"late".to_vec(),
+        commit.records[1].read(commit.stripe.data_shards())
+    );
+    assert!(acc.poll().is_none());
+    assert!(acc.time_to_deadline().is_none());
+}
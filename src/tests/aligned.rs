@@ -0,0 +1,81 @@
+use super::{fill_random, ReedSolomon};
+use crate::{AlignedShard, Error, SHARD_ALIGN};
+
+#[test]
+fn test_aligned_shard_alignment_and_padding() {
+    for &len in &[0, 1, 63, 64, 65, 1000] {
+        let shard = AlignedShard::zeroed(len);
+
+        assert_eq!(len, shard.len());
+        assert_eq!(0, shard.padded().as_ptr() as usize % SHARD_ALIGN);
+        assert_eq!(0, shard.padded().len() % SHARD_ALIGN);
+        assert!(shard.padded().len() >= len);
+        assert!(shard.padded().len() < len + SHARD_ALIGN);
+    }
+}
+
+#[test]
+fn test_aligned_shard_from_slice() {
+    let data: Vec<u8> = (0..100).collect();
+    let shard = AlignedShard::from_slice(&data);
+
+    assert_eq!(&data[..], &shard[..]);
+    assert!(shard.padded()[100..].iter().all(|&b| b == 0));
+    assert_eq!(shard, data.iter().cloned().collect());
+}
+
+#[test]
+fn test_aligned_shard_encode_reconstruct() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    let mut shards = r.alloc_aligned_shards(1000);
+    for shard in shards.iter_mut().take(4) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+    assert!(r.verify(&shards).unwrap());
+
+    let mut option_shards: Vec<_> = shards.iter().cloned().map(Some).collect();
+    option_shards[0] = None;
+    option_shards[5] = None;
+    r.reconstruct(&mut option_shards).unwrap();
+
+    let result: Vec<_> = option_shards.into_iter().map(Option::unwrap).collect();
+    assert_eq!(shards, result);
+}
+
+#[test]
+fn test_aligned_shard_encode_padded() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut shards = r.alloc_aligned_shards(100);
+    for shard in shards.iter_mut().take(3) {
+        fill_random(shard);
+    }
+    let mut expected = shards.clone();
+    r.encode(&mut expected).unwrap();
+
+    let mut padded: Vec<&mut [u8]> = shards.iter_mut().map(|s| s.padded_mut()).collect();
+    r.encode(&mut padded).unwrap();
+
+    assert_eq!(expected, shards);
+    assert!(shards
+        .iter()
+        .all(|s| s.padded()[100..].iter().all(|&b| b == 0)));
+}
+
+#[test]
+fn test_split_aligned() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let data: Vec<u8> = (0..250).map(|x| x as u8).collect();
+    let split = r.split(&data).unwrap();
+    let aligned = r.split_aligned(&data).unwrap();
+
+    assert_eq!(split.len(), aligned.len());
+    for (a, b) in split.iter().zip(aligned.iter()) {
+        assert_eq!(&a[..], &b[..]);
+    }
+
+    assert_eq!(Error::EmptyShard, r.split_aligned(&[]).unwrap_err());
+}
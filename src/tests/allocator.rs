@@ -0,0 +1,82 @@
+use std::alloc::{AllocError, Allocator, Global, Layout};
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+use super::{fill_random, ReedSolomon};
+use crate::Error;
+
+/// Allocator counting the allocations made through it.
+#[derive(Clone, Copy)]
+struct Counting<'a>(&'a Cell<usize>);
+
+unsafe impl<'a> Allocator for Counting<'a> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.set(self.0.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { Global.deallocate(ptr, layout) }
+    }
+}
+
+#[test]
+fn test_encode_owned_in() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    let mut shards = vec![vec![0u8; 100]; 6];
+    for shard in shards.iter_mut().take(4) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+
+    let count = Cell::new(0);
+    let parity = r.encode_owned_in(&shards[..4], Counting(&count)).unwrap();
+
+    assert_eq!(3, count.get());
+    assert_eq!(2, parity.len());
+    for (expect, shard) in shards[4..].iter().zip(parity.iter()) {
+        assert_eq!(&expect[..], &shard[..]);
+    }
+
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_owned_in(&shards[..3], Counting(&count))
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_in() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    let mut shards = vec![vec![0u8; 100]; 6];
+    for shard in shards.iter_mut().take(4) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+
+    let count = Cell::new(0);
+    let alloc = Counting(&count);
+    let mut damaged: Vec<_> = shards
+        .iter()
+        .map(|shard| {
+            let mut copy = Vec::new_in(alloc);
+            copy.extend_from_slice(shard);
+            Some(copy)
+        })
+        .collect();
+    damaged[1] = None;
+    damaged[5] = None;
+    count.set(0);
+
+    r.reconstruct_data_in(&mut damaged, alloc).unwrap();
+    assert_eq!(1, count.get());
+    assert!(damaged[5].is_none());
+
+    r.reconstruct_in(&mut damaged, alloc).unwrap();
+    assert_eq!(2, count.get());
+    for (expect, shard) in shards.iter().zip(damaged.iter()) {
+        assert_eq!(&expect[..], &shard.as_ref().unwrap()[..]);
+    }
+}
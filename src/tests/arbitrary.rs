@@ -0,0 +1,84 @@
+#[cfg(feature = "quickcheck")]
+use quickcheck::{Arbitrary, StdGen};
+
+use crate::arbitrary::{LossPattern, Shape, Stripe};
+
+#[cfg(feature = "proptest")]
+use proptest::prelude::any;
+
+#[cfg(feature = "quickcheck")]
+quickcheck! {
+    fn qc_arbitrary_stripe_verifies(stripe: Stripe) -> bool {
+        stripe.shards.len() == stripe.shape.total_shards()
+            && stripe.shape.codec().verify(&stripe.shards).unwrap()
+    }
+
+    fn qc_arbitrary_loss_pattern_reconstructs(pattern: LossPattern, len: usize) -> bool {
+        let len = 1 + len % 100;
+        let data = vec![vec![7u8; len]; pattern.shape.data_shards];
+        let stripe = Stripe::encode(pattern.shape, data);
+
+        let lost = pattern.shape.total_shards() - pattern.present.count();
+
+        let mut shards = pattern.apply(stripe.shards.clone());
+        stripe.shape.codec().reconstruct(&mut shards).unwrap();
+
+        lost <= pattern.shape.parity_shards
+            && shards.into_iter().map(Option::unwrap).collect::<Vec<_>>() == stripe.shards
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+#[test]
+fn test_arbitrary_shrink() {
+    let mut g = StdGen::new(rand::thread_rng(), 20);
+
+    for _ in 0..20 {
+        let shape = Shape::arbitrary(&mut g);
+        for smaller in shape.shrink() {
+            assert!(smaller.data_shards >= 1 && smaller.parity_shards >= 1);
+            assert!(smaller.total_shards() < shape.total_shards());
+        }
+
+        let stripe = Stripe::arbitrary(&mut g);
+        for smaller in stripe.shrink().take(10) {
+            assert!(smaller.shape.codec().verify(&smaller.shards).unwrap());
+        }
+
+        let pattern = LossPattern::arbitrary(&mut g);
+        for smaller in pattern.shrink() {
+            assert_eq!(pattern.present.count() + 1, smaller.present.count());
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+proptest::proptest! {
+    #[test]
+    fn proptest_stripe_verifies(stripe in any::<Stripe>()) {
+        proptest::prop_assert_eq!(stripe.shape.total_shards(), stripe.shards.len());
+        proptest::prop_assert!(stripe.shape.codec().verify(&stripe.shards).unwrap());
+    }
+
+    #[test]
+    fn proptest_loss_pattern_reconstructs(pattern in any::<LossPattern>(), len in 1..100usize) {
+        let data = vec![vec![7u8; len]; pattern.shape.data_shards];
+        let stripe = Stripe::encode(pattern.shape, data);
+
+        let lost = pattern.shape.total_shards() - pattern.present.count();
+        proptest::prop_assert!(lost <= pattern.shape.parity_shards);
+
+        let mut shards = pattern.apply(stripe.shards.clone());
+        stripe.shape.codec().reconstruct(&mut shards).unwrap();
+        proptest::prop_assert_eq!(
+            shards.into_iter().map(Option::unwrap).collect::<Vec<_>>(),
+            stripe.shards
+        );
+    }
+
+    #[test]
+    fn proptest_shape_is_valid(shape in any::<Shape>()) {
+        proptest::prop_assert!(shape.data_shards >= 1 && shape.parity_shards >= 1);
+        proptest::prop_assert_eq!(shape.total_shards(), shape.codec().total_shard_count());
+    }
+}
@@ -0,0 +1,155 @@
+use rkyv::{Deserialize, Infallible};
+
+use super::{fill_random, ReedSolomon};
+use crate::archive::{CodecConfig, DecodeMatrixCache, ShardEnvelope};
+use crate::{Error, MatrixKind};
+
+#[test]
+fn test_codec_config_round_trip() {
+    let r = ReedSolomon::new_cauchy(6, 3).unwrap();
+    let config = CodecConfig::of(&r);
+    assert_eq!(
+        CodecConfig {
+            data_shard_count: 6,
+            parity_shard_count: 3,
+            matrix_kind: MatrixKind::Cauchy,
+        },
+        config
+    );
+
+    let bytes = rkyv::to_bytes::<_, 64>(&config).unwrap();
+    let archived = rkyv::check_archived_root::<CodecConfig>(&bytes).unwrap();
+    let config: CodecConfig = archived.deserialize(&mut Infallible).unwrap();
+
+    let built: ReedSolomon = config.build().unwrap();
+    assert_eq!(MatrixKind::Cauchy, built.matrix_kind());
+    assert_eq!(6, built.data_shard_count());
+    assert_eq!(3, built.parity_shard_count());
+
+    let config = CodecConfig {
+        data_shard_count: 0,
+        ..config
+    };
+    assert_eq!(
+        Error::TooFewDataShards,
+        config.build::<crate::galois_8::Field>().unwrap_err()
+    );
+}
+
+#[test]
+fn test_shard_envelope_read_in_place() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let mut shard = vec![0u8; 100];
+    fill_random(&mut shard);
+    let envelope = ShardEnvelope {
+        config: CodecConfig::of(&r),
+        stripe: 7,
+        index: 5,
+        shard: shard.clone(),
+    };
+
+    let bytes = rkyv::to_bytes::<_, 256>(&envelope).unwrap();
+    let archived = rkyv::check_archived_root::<ShardEnvelope>(&bytes).unwrap();
+    assert_eq!(7, archived.stripe);
+    assert_eq!(5, archived.index);
+    assert_eq!(&shard[..], &archived.shard[..]);
+
+    let deserialized: ShardEnvelope = archived.deserialize(&mut Infallible).unwrap();
+    assert_eq!(envelope, deserialized);
+
+    // a truncated archive is rejected
+    assert!(rkyv::check_archived_root::<ShardEnvelope>(&bytes[..bytes.len() - 8]).is_err());
+}
+
+#[test]
+fn test_decode_matrices_export_import() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+    assert!(r.export_decode_matrices().matrices.is_empty());
+
+    let mut expect = vec![vec![0u8; 64]; 8];
+    for shard in expect.iter_mut().take(5) {
+        fill_random(shard);
+    }
+    r.encode(&mut expect).unwrap();
+
+    let patterns: [&[usize]; 3] = [&[0], &[0, 2, 4], &[1, 6]];
+    for pattern in patterns.iter() {
+        let mut shards: Vec<_> = expect.iter().cloned().map(Some).collect();
+        for &i in pattern.iter() {
+            shards[i] = None;
+        }
+        r.reconstruct(&mut shards).unwrap();
+    }
+
+    let cache = r.export_decode_matrices();
+    assert_eq!(CodecConfig::of(&r), cache.config);
+    let exported: Vec<&[usize]> = cache
+        .matrices
+        .iter()
+        .map(|m| &m.invalid_indices[..])
+        .collect();
+    assert_eq!(patterns.to_vec(), exported);
+    assert!(cache.matrices.iter().all(|m| m.elems.len() == 25));
+
+    let bytes = rkyv::to_bytes::<_, 1024>(&cache).unwrap();
+    let archived = rkyv::check_archived_root::<DecodeMatrixCache<u8>>(&bytes).unwrap();
+
+    // the imported matrices are those reconstruction uses
+    let warmed = ReedSolomon::new(5, 3).unwrap();
+    warmed.import_archived_decode_matrices(archived).unwrap();
+    assert_eq!(cache, warmed.export_decode_matrices());
+    for pattern in patterns.iter() {
+        let mut shards: Vec<_> = expect.iter().cloned().map(Some).collect();
+        for &i in pattern.iter() {
+            shards[i] = None;
+        }
+        warmed.reconstruct(&mut shards).unwrap();
+        let shards: Vec<_> = shards.into_iter().map(Option::unwrap).collect();
+        assert_eq!(expect, shards);
+    }
+
+    let warmed = ReedSolomon::new(5, 3).unwrap();
+    warmed.import_decode_matrices(&cache).unwrap();
+    assert_eq!(cache, warmed.export_decode_matrices());
+}
+
+#[test]
+fn test_import_decode_matrices_rejects_mismatch() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+    let mut shards: Vec<Option<Vec<u8>>> = vec![Some(vec![1u8; 16]); 8];
+    shards[3] = None;
+    r.reconstruct(&mut shards).unwrap();
+    let cache = r.export_decode_matrices();
+
+    let other = ReedSolomon::new_cauchy(5, 3).unwrap();
+    assert_eq!(
+        Error::InvalidDecodeMatrix,
+        other.import_decode_matrices(&cache).unwrap_err()
+    );
+    let other = ReedSolomon::new(5, 2).unwrap();
+    assert_eq!(
+        Error::InvalidDecodeMatrix,
+        other.import_decode_matrices(&cache).unwrap_err()
+    );
+
+    let warmed = ReedSolomon::new(5, 3).unwrap();
+    let mut bad = cache.clone();
+    bad.matrices[0].elems.pop();
+    assert_eq!(
+        Error::InvalidDecodeMatrix,
+        warmed.import_decode_matrices(&bad).unwrap_err()
+    );
+    let mut bad = cache.clone();
+    bad.matrices[0].invalid_indices = vec![2, 1];
+    assert_eq!(
+        Error::InvalidDecodeMatrix,
+        warmed.import_decode_matrices(&bad).unwrap_err()
+    );
+    let mut bad = cache;
+    bad.matrices[0].invalid_indices = vec![0, 1, 2, 3];
+    assert_eq!(
+        Error::InvalidDecodeMatrix,
+        warmed.import_decode_matrices(&bad).unwrap_err()
+    );
+    assert!(warmed.export_decode_matrices().matrices.is_empty());
+}
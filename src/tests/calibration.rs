@@ -0,0 +1,120 @@
+use super::{fill_random, ReedSolomon};
+use crate::calibration::Calibration;
+use crate::galois_16;
+use crate::galois_8::{self, Backend};
+use crate::Error;
+
+#[test]
+fn test_calibration_round_trip() {
+    let calibration = Calibration {
+        backend: Backend::Avx2,
+        cache_chunk_len: 16384,
+    };
+    let text = calibration.to_string();
+    assert_eq!("backend=avx2\ncache_chunk_len=16384\n", text);
+    assert_eq!(calibration, text.parse().unwrap());
+
+    let reordered = "\n cache_chunk_len = 0\nbackend=SCALAR\n";
+    assert_eq!(
+        Calibration {
+            backend: Backend::Scalar,
+            cache_chunk_len: 0,
+        },
+        reordered.parse().unwrap()
+    );
+
+    for text in &[
+        "",
+        "backend=avx2",
+        "backend=avx9\ncache_chunk_len=4096",
+        "backend=avx2\ncache_chunk_len=-1",
+        "backend=avx2\ncache_chunk_len=4096\nthreads=4",
+        "backend avx2\ncache_chunk_len=4096",
+    ] {
+        assert_eq!(
+            Error::InvalidCalibration,
+            text.parse::<Calibration>().unwrap_err()
+        );
+    }
+}
+
+#[test]
+fn test_calibrate_among() {
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    // a single backend leaves the backend of the process alone
+    let calibration = r.calibrate_among(&[galois_8::backend()], 20000, 1);
+    assert_eq!(calibration.cache_chunk_len, r.cache_chunk_len());
+
+    let mut shards = vec![vec![0u8; 5000]; 14];
+    for shard in shards.iter_mut().take(10) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+
+    // the chunk size does not change the results
+    for &len in &[0, 1, 7, 4096, 100000] {
+        r.set_cache_chunk_len(len);
+        assert_eq!(len, r.cache_chunk_len());
+        assert_eq!(len, r.clone().cache_chunk_len());
+        assert!(r.verify(&shards).unwrap());
+    }
+}
+
+#[test]
+fn test_apply_calibration() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+    assert_eq!(4096, r.cache_chunk_len());
+
+    let calibration = Calibration {
+        backend: galois_8::backend(),
+        cache_chunk_len: 1024,
+    };
+    r.apply_calibration(&calibration).unwrap();
+    assert_eq!(1024, r.cache_chunk_len());
+
+    let unavailable = Backend::Neon;
+    if !unavailable.is_available() {
+        let calibration = Calibration {
+            backend: unavailable,
+            cache_chunk_len: 0,
+        };
+        assert_eq!(
+            Error::UnsupportedBackend,
+            r.apply_calibration(&calibration).unwrap_err()
+        );
+        assert_eq!(1024, r.cache_chunk_len());
+    }
+}
+
+#[test]
+fn test_calibrate_cached() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let path = std::env::temp_dir().join(format!(
+        "reed-solomon-erasure-calibration-{}",
+        std::process::id()
+    ));
+
+    // a stored calibration is applied without timing anything
+    let stored = Calibration {
+        backend: galois_8::backend(),
+        cache_chunk_len: 65536,
+    };
+    std::fs::write(&path, stored.to_string()).unwrap();
+    let stats = r.stats();
+    assert_eq!(stored, r.calibrate_cached(&path).unwrap());
+    assert_eq!(65536, r.cache_chunk_len());
+    assert_eq!(stats, r.stats());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_calibration_backends() {
+    let available = Backend::ALL.iter().filter(|b| b.is_available()).count();
+    assert_eq!(available, ReedSolomon::calibration_backends().len());
+
+    // GF(2^16) codecs do not use the GF(2^8) kernels, so only the backend
+    // in use is kept
+    assert_eq!(1, galois_16::ReedSolomon::calibration_backends().len());
+}
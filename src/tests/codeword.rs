@@ -0,0 +1,129 @@
+use rand::{self, thread_rng, Rng};
+
+use super::{fill_random, ReedSolomon};
+use crate::codeword::CodewordCodec;
+use crate::Error;
+
+fn corrupt(encoded: &mut [u8], count: usize) {
+    let mut rng = thread_rng();
+    let mut positions = Vec::new();
+    while positions.len() < count {
+        let p = rng.gen_range(0, encoded.len());
+        if !positions.contains(&p) {
+            positions.push(p);
+        }
+    }
+    for p in positions {
+        encoded[p] ^= rng.gen_range(1, 256) as u8;
+    }
+}
+
+#[test]
+fn test_codeword_codec_new() {
+    assert_eq!(
+        Error::TooFewParityShards,
+        CodewordCodec::new(0).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyParityShards,
+        CodewordCodec::new(255).unwrap_err()
+    );
+
+    let c = CodewordCodec::new(32).unwrap();
+    assert_eq!(16, c.correctable_errors());
+    assert_eq!(223, c.block_len());
+}
+
+#[test]
+fn test_codeword_codec_lengths() {
+    let c = CodewordCodec::new(10).unwrap();
+
+    for &len in [1, 244, 245, 246, 1000].iter() {
+        let encoded_len = c.encoded_len(len);
+        assert_eq!(Some(len), c.decoded_len(encoded_len));
+        assert_eq!(encoded_len, c.encode_shard(&vec![0; len]).len());
+    }
+    assert_eq!(None, c.decoded_len(255 + 10));
+    assert_eq!(
+        Error::IncorrectShardSize,
+        c.decode_shard(&[0; 5]).unwrap_err()
+    );
+}
+
+#[test]
+fn test_codeword_codec_corrects_errors() {
+    for &parity_len in [2, 8, 32].iter() {
+        let c = CodewordCodec::new(parity_len).unwrap();
+
+        for &len in [1, 100, 255, 1000].iter() {
+            let mut data = vec![0u8; len];
+            fill_random(&mut data);
+
+            let encoded = c.encode_shard(&data);
+            assert_eq!((data.clone(), 0), c.decode_shard(&encoded).unwrap());
+
+            // corrupt the maximum number of bytes in a single codeword
+            for errors in 1..=c.correctable_errors() {
+                let mut corrupted = encoded.clone();
+                let first = &mut corrupted[..255.min(encoded.len())];
+                if first.len() < errors {
+                    continue;
+                }
+                corrupt(first, errors);
+
+                assert_eq!((data.clone(), errors), c.decode_shard(&corrupted).unwrap());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_codeword_codec_detects_uncorrectable() {
+    let c = CodewordCodec::new(16).unwrap();
+
+    let mut data = vec![0u8; 239];
+    fill_random(&mut data);
+    let encoded = c.encode_shard(&data);
+
+    let mut detected = 0;
+    for _ in 0..100 {
+        let mut corrupted = encoded.clone();
+        corrupt(&mut corrupted, 20);
+
+        match c.decode_shard(&corrupted) {
+            Err(Error::UncorrectableCodeword) => detected += 1,
+            Ok((decoded, _)) => assert_ne!(data, decoded),
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+    // miscorrection beyond the capability of the code is very unlikely
+    assert!(detected > 90);
+}
+
+#[test]
+fn test_codeword_codec_reconstruct() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let c = CodewordCodec::new(16).unwrap();
+
+    let mut shards = vec![vec![0u8; 500]; 6];
+    for shard in shards.iter_mut().take(4) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+
+    let mut stored: Vec<_> = shards.iter().map(|s| Some(c.encode_shard(s))).collect();
+
+    // bit-rot within the capability of the inner code
+    corrupt(&mut stored[0].as_mut().unwrap()[..255], 4);
+    corrupt(&mut stored[3].as_mut().unwrap()[255..510], 3);
+    // heavy damage, shard becomes an erasure
+    corrupt(&mut stored[1].as_mut().unwrap()[..255], 40);
+    // lost shard
+    stored[5] = None;
+
+    let corrected = c.reconstruct(&r, &mut stored).unwrap();
+    assert_eq!(7, corrected);
+
+    let stored: Vec<_> = stored.into_iter().map(Option::unwrap).collect();
+    assert_eq!(shards, stored);
+}
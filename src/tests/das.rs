@@ -0,0 +1,108 @@
+use super::{fill_random, ReedSolomon};
+use crate::das::{CellGrid, CellIndex};
+use crate::Error;
+
+#[test]
+fn test_cell_grid_layout() {
+    let grid = CellGrid::new(ReedSolomon::new(4, 4).unwrap(), 3, 16).unwrap();
+
+    assert_eq!(3, grid.rows());
+    assert_eq!(8, grid.columns());
+    assert_eq!(24, grid.cell_count());
+    assert_eq!(192, grid.blob_len());
+    assert_eq!(CellIndex::new(1, 2), grid.cell_index(10));
+    assert_eq!(10, grid.cell_position(CellIndex::new(1, 2)));
+
+    assert_eq!(
+        Error::EmptyShard,
+        CellGrid::new(ReedSolomon::new(4, 4).unwrap(), 0, 16).unwrap_err()
+    );
+    assert_eq!(
+        Error::EmptyShard,
+        CellGrid::new(ReedSolomon::new(4, 4).unwrap(), 3, 0).unwrap_err()
+    );
+}
+
+#[test]
+fn test_cell_grid_encode() {
+    let grid = CellGrid::new(ReedSolomon::new(3, 2).unwrap(), 2, 10).unwrap();
+
+    let mut blob = vec![0u8; 55];
+    fill_random(&mut blob);
+    let cells = grid.encode(&blob).unwrap();
+    assert_eq!(10, cells.len());
+
+    // the data cells hold the blob, padded with zeros
+    let mut padded = blob.clone();
+    padded.resize(60, 0);
+    let data: Vec<u8> = cells.chunks(5).flat_map(|row| row[..3].concat()).collect();
+    assert_eq!(padded, data);
+
+    for row in cells.chunks(5) {
+        assert!(grid.codec().verify(row).unwrap());
+    }
+
+    assert_eq!(Error::BlobTooLarge, grid.encode(&[0u8; 61]).unwrap_err());
+}
+
+#[test]
+fn test_cell_grid_sufficiency() {
+    let grid = CellGrid::new(ReedSolomon::new(2, 2).unwrap(), 2, 4).unwrap();
+
+    assert!(!grid.is_sufficient(Vec::new()));
+    assert_eq!(4, grid.missing_cells(Vec::new()));
+
+    let samples = vec![
+        CellIndex::new(0, 3),
+        CellIndex::new(0, 3),
+        CellIndex::new(1, 0),
+        CellIndex::new(1, 2),
+        CellIndex::new(2, 0),
+        CellIndex::new(0, 4),
+    ];
+    // repeated and out of range positions do not count
+    assert!(!grid.is_sufficient(samples.iter().cloned()));
+    assert_eq!(1, grid.missing_cells(samples.iter().cloned()));
+
+    let mut samples = samples;
+    samples.push(CellIndex::new(0, 1));
+    samples.push(CellIndex::new(1, 3));
+    assert!(grid.is_sufficient(samples.iter().cloned()));
+    assert_eq!(0, grid.missing_cells(samples));
+}
+
+#[test]
+fn test_cell_grid_reconstruct() {
+    let grid = CellGrid::new(ReedSolomon::new(4, 4).unwrap(), 4, 32).unwrap();
+
+    let mut blob = vec![0u8; grid.blob_len()];
+    fill_random(&mut blob);
+    let cells = grid.encode(&blob).unwrap();
+
+    // half of every row, with a different pattern per row
+    let samples: Vec<_> = (0..grid.cell_count())
+        .map(|i| grid.cell_index(i))
+        .filter(|cell| (cell.column + cell.row) % 2 == 0)
+        .map(|cell| (cell, cells[grid.cell_position(cell)].clone()))
+        .collect();
+    assert!(grid.is_sufficient(samples.iter().map(|&(cell, _)| cell)));
+
+    assert_eq!(cells, grid.reconstruct(&samples).unwrap());
+    assert_eq!(blob, grid.reconstruct_blob(&samples).unwrap());
+
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        grid.reconstruct(&samples[1..]).unwrap_err()
+    );
+
+    let mut invalid = samples.clone();
+    invalid.push((CellIndex::new(4, 0), vec![0u8; 32]));
+    assert_eq!(Error::InvalidIndex, grid.reconstruct(&invalid).unwrap_err());
+
+    let mut invalid = samples.clone();
+    invalid[0].1.pop();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        grid.reconstruct_blob(&invalid).unwrap_err()
+    );
+}
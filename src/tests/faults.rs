@@ -0,0 +1,91 @@
+use super::{encoded_shards, shards_to_option_shards, ReedSolomon};
+use crate::faults::{Fault, FaultInjector};
+
+#[test]
+fn test_fault_injector_is_reproducible() {
+    let r = ReedSolomon::new(6, 3).unwrap();
+    let stripe = shards_to_option_shards(&encoded_shards(&r, 100));
+
+    let damage = |seed| {
+        let mut shards = stripe.clone();
+        let mut injector = FaultInjector::new(seed);
+        let mut faults = injector.flip_bits(&mut shards, 5);
+        faults.extend(injector.swap(&mut shards));
+        faults.extend(injector.truncate(&mut shards));
+        faults.extend(injector.drop_shards(&mut shards, 2));
+        (shards, faults)
+    };
+
+    let (shards, faults) = damage(7);
+    assert_eq!((shards.clone(), faults.clone()), damage(7));
+    assert_ne!(faults, damage(8).1);
+    assert_eq!(9, faults.len());
+
+    // the faults replay
+    let mut replayed = stripe.clone();
+    for fault in faults.iter() {
+        fault.apply(&mut replayed);
+    }
+    assert_eq!(shards, replayed);
+}
+
+#[test]
+fn test_fault_injector_faults() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let stripe = shards_to_option_shards(&encoded_shards(&r, 50));
+
+    for seed in 0..20 {
+        let mut injector = FaultInjector::new(seed);
+
+        // dropped shards within the parity are recovered
+        let mut shards = stripe.clone();
+        let faults = injector.drop_shards(&mut shards, 2);
+        assert_eq!(2, faults.len());
+        assert_eq!(4, shards.iter().filter(|shard| shard.is_some()).count());
+        r.reconstruct(&mut shards).unwrap();
+        assert_eq!(stripe, shards);
+
+        // a flipped bit is caught
+        let mut shards = stripe.clone();
+        match injector.flip_bits(&mut shards, 1)[..] {
+            [Fault::BitFlip { shard, offset, bit }] => {
+                assert!(bit < 8);
+                let original = stripe[shard].as_ref().unwrap()[offset];
+                assert_eq!(
+                    original ^ (1 << bit),
+                    shards[shard].as_ref().unwrap()[offset]
+                );
+            }
+            ref faults => panic!("unexpected faults {:?}", faults),
+        }
+        assert!(!r
+            .verify(&shards.iter().flatten().collect::<Vec<_>>())
+            .unwrap());
+
+        let mut shards = stripe.clone();
+        match injector.swap(&mut shards) {
+            Some(Fault::Swap { a, b }) => {
+                assert_ne!(a, b);
+                assert_eq!(stripe[a], shards[b]);
+            }
+            fault => panic!("unexpected fault {:?}", fault),
+        }
+
+        let mut shards = stripe.clone();
+        match injector.truncate(&mut shards) {
+            Some(Fault::Truncate { shard, len }) => {
+                assert!(len < 50);
+                assert_eq!(Some(len), shards[shard].as_ref().map(Vec::len));
+            }
+            fault => panic!("unexpected fault {:?}", fault),
+        }
+    }
+
+    // nothing to damage
+    let mut injector = FaultInjector::new(0);
+    let mut shards: Vec<Option<Vec<u8>>> = vec![None, Some(vec![])];
+    assert!(injector.flip_bits(&mut shards, 3).is_empty());
+    assert_eq!(None, injector.truncate(&mut shards));
+    assert_eq!(1, injector.drop_shards(&mut shards, 3).len());
+    assert_eq!(None, injector.swap(&mut shards[..1]));
+}
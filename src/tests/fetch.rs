@@ -0,0 +1,85 @@
+use std::cell::RefCell;
+
+use bytes::Bytes;
+use futures::executor::block_on;
+
+use super::{encoded_shards, ReedSolomon};
+use crate::fetch::ShardSource;
+use crate::Error;
+
+struct MockSource {
+    shards: Vec<Option<Vec<u8>>>,
+    fetched: RefCell<Vec<usize>>,
+}
+
+impl MockSource {
+    fn new(shards: Vec<Option<Vec<u8>>>) -> MockSource {
+        MockSource {
+            shards,
+            fetched: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl ShardSource for MockSource {
+    async fn fetch(&self, index: usize) -> Option<Bytes> {
+        self.fetched.borrow_mut().push(index);
+        self.shards[index].clone().map(Bytes::from)
+    }
+}
+
+#[test]
+fn test_reconstruct_data_from_intact_stripe_fetches_data_only() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+    let shards = encoded_shards(&r, 100);
+
+    let source = MockSource::new(shards.iter().cloned().map(Some).collect());
+
+    let data = block_on(r.reconstruct_data_from(&source, 2)).unwrap();
+
+    assert_eq!(&shards[..4], &data[..]);
+
+    let mut fetched = source.fetched.borrow().clone();
+    fetched.sort();
+    assert_eq!(vec![0, 1, 2, 3], fetched);
+}
+
+#[test]
+fn test_reconstruct_from_falls_back_on_failed_fetches() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+    let shards = encoded_shards(&r, 100);
+
+    let mut available: Vec<_> = shards.iter().cloned().map(Some).collect();
+    available[1] = None;
+    available[3] = None;
+    // wrong length counts as a failed fetch
+    available[4] = Some(vec![0; 99]);
+
+    for &concurrency in [0, 1, 3, 7].iter() {
+        let source = MockSource::new(available.clone());
+
+        let recovered = block_on(r.reconstruct_from(&source, concurrency)).unwrap();
+
+        assert_eq!(shards, recovered);
+        assert_eq!(7, source.fetched.borrow().len());
+    }
+}
+
+#[test]
+fn test_reconstruct_from_too_few_shards() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+    let shards = encoded_shards(&r, 100);
+
+    let mut available: Vec<_> = shards.iter().cloned().map(Some).collect();
+    available[0] = None;
+    available[2] = None;
+    available[3] = None;
+    available[6] = None;
+
+    let source = MockSource::new(available);
+
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        block_on(r.reconstruct_data_from(&source, 4)).unwrap_err()
+    );
+}
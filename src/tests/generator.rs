@@ -0,0 +1,179 @@
+use std::io;
+
+use super::{fill_random, ReedSolomon};
+use crate::generator::GeneratorMatrix;
+use crate::{galois_16, galois_8, Error, MatrixKind};
+
+#[test]
+fn test_generator_matrix() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+    let matrix = r.generator_matrix();
+
+    assert_eq!(MatrixKind::Vandermonde, matrix.matrix_kind());
+    assert_eq!(3, matrix.data_shards());
+    assert_eq!(2, matrix.parity_shards());
+    assert_eq!(
+        &[
+            vec![1, 0, 0],
+            vec![0, 1, 0],
+            vec![0, 0, 1],
+            vec![1, 1, 1],
+            vec![15, 8, 6]
+        ][..],
+        matrix.rows()
+    );
+    assert_eq!(&matrix.rows()[3..], matrix.parity_rows());
+
+    // the rows compute the parity shards
+    let mut shards = vec![vec![3], vec![5], vec![7], vec![0], vec![0]];
+    r.encode(&mut shards).unwrap();
+    for (row, parity) in matrix.parity_rows().iter().zip(shards[3..].iter()) {
+        let expected = row
+            .iter()
+            .zip(shards.iter())
+            .fold(0, |acc, (&c, shard)| acc ^ galois_8::mul(c, shard[0]));
+        assert_eq!(expected, parity[0]);
+    }
+
+    for (i, row) in matrix.rows().iter().enumerate() {
+        assert_eq!(Some(&row[..]), r.coefficients(i));
+    }
+    assert_eq!(None, r.coefficients(5));
+
+    assert_eq!(r.generator_matrix(), r.clone().generator_matrix());
+    assert_ne!(
+        r.generator_matrix(),
+        ReedSolomon::new_cauchy(3, 2).unwrap().generator_matrix()
+    );
+}
+
+#[test]
+fn test_decode_matrix_sharing() {
+    let warm = ReedSolomon::new(4, 3).unwrap();
+    let cold = ReedSolomon::new(4, 3).unwrap();
+
+    let mut shards = vec![vec![0u8; 50]; 7];
+    for shard in shards.iter_mut().take(4) {
+        fill_random(shard);
+    }
+    warm.encode(&mut shards).unwrap();
+
+    let rows = warm.decode_matrix_for(&[0, 2, 5]).unwrap();
+    assert_eq!(1, warm.stats().cache_misses);
+    assert_eq!(rows, warm.decode_matrix_for(&[0, 2, 5]).unwrap());
+    assert_eq!(1, warm.stats().cache_hits);
+
+    // the rows decode the data shards from shards 1, 3, 4 and 6
+    for (r, row) in rows.iter().enumerate() {
+        let mut decoded = vec![0u8; 50];
+        for (&i, &c) in [1, 3, 4, 6].iter().zip(row.iter()) {
+            for (d, &x) in decoded.iter_mut().zip(shards[i].iter()) {
+                *d ^= galois_8::mul(c, x);
+            }
+        }
+        assert_eq!(shards[r], decoded);
+    }
+
+    cold.insert_decode_matrix(&[0, 2, 5], &rows).unwrap();
+    let mut lost: Vec<_> = shards.iter().cloned().map(Some).collect();
+    lost[0] = None;
+    lost[2] = None;
+    lost[5] = None;
+    cold.reconstruct(&mut lost).unwrap();
+    assert_eq!(0, cold.stats().cache_misses);
+    assert_eq!(1, cold.stats().cache_hits);
+    let lost: Vec<_> = lost.into_iter().map(Option::unwrap).collect();
+    assert_eq!(shards, lost);
+
+    let identity = warm.decode_matrix_for(&[]).unwrap();
+    assert_eq!(vec![1, 0, 0, 0], identity[0]);
+    cold.insert_decode_matrix(&[], &identity).unwrap();
+
+    // the matrix of another pattern, or of another codec
+    let cauchy = ReedSolomon::new_cauchy(4, 3).unwrap();
+    for rows in [
+        warm.decode_matrix_for(&[0, 2, 6]).unwrap(),
+        cauchy.decode_matrix_for(&[0, 2, 5]).unwrap(),
+    ] {
+        assert_eq!(
+            Error::InvalidDecodeMatrix,
+            cold.insert_decode_matrix(&[0, 2, 5], &rows).unwrap_err()
+        );
+    }
+    assert_eq!(
+        Error::InvalidDecodeMatrix,
+        cold.insert_decode_matrix(&[1], &rows[1..]).unwrap_err()
+    );
+    assert_eq!(
+        Error::InvalidDecodeMatrix,
+        cold.insert_decode_matrix(&[], &rows).unwrap_err()
+    );
+
+    for &pattern in [&[2, 0][..], &[3, 3], &[7]].iter() {
+        assert_eq!(
+            Error::InvalidIndex,
+            warm.decode_matrix_for(pattern).unwrap_err()
+        );
+        assert_eq!(
+            Error::InvalidIndex,
+            cold.insert_decode_matrix(pattern, &rows).unwrap_err()
+        );
+    }
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        warm.decode_matrix_for(&[0, 1, 2, 3]).unwrap_err()
+    );
+}
+
+#[test]
+fn test_generator_matrix_export() {
+    let matrix = ReedSolomon::new(3, 2).unwrap().generator_matrix();
+
+    assert_eq!(
+        concat!(
+            "{\"field\": \"GF(2^8)\", \"matrix_kind\": \"vandermonde\", \"data_shards\": 3, ",
+            "\"parity_shards\": 2, \"rows\": [[1, 0, 0], [0, 1, 0], [0, 0, 1], [1, 1, 1], ",
+            "[15, 8, 6]]}"
+        ),
+        matrix.to_json()
+    );
+    assert_eq!("1,0,0\n0,1,0\n0,0,1\n1,1,1\n15,8,6\n", matrix.to_csv());
+
+    let bytes = matrix.to_bytes();
+    assert_eq!(
+        &[b'R', b'S', b'G', b'M', 1, 1, 0, 3, 0, 2, 0][..],
+        &bytes[..11]
+    );
+    assert_eq!(11 + 15, bytes.len());
+    assert_eq!(matrix, GeneratorMatrix::from_bytes(&bytes).unwrap());
+
+    for len in [0, 5, 11, bytes.len() - 1].iter() {
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            GeneratorMatrix::<galois_8::Field>::from_bytes(&bytes[..*len])
+                .unwrap_err()
+                .kind()
+        );
+    }
+    // not a matrix over GF(2^16)
+    assert!(GeneratorMatrix::<galois_16::Field>::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn test_generator_matrix_export_galois_16() {
+    let r = galois_16::ReedSolomon::new_cauchy(4, 2).unwrap();
+    let matrix = r.generator_matrix();
+
+    let bytes = matrix.to_bytes();
+    assert_eq!(2, bytes[5]);
+    assert_eq!(1, bytes[6]);
+    assert_eq!(11 + 6 * 4 * 2, bytes.len());
+    assert_eq!(matrix, GeneratorMatrix::from_bytes(&bytes).unwrap());
+
+    assert!(matrix
+        .to_json()
+        .starts_with("{\"field\": \"GF(2^16)\", \"matrix_kind\": \"cauchy\""));
+    let csv = matrix.to_csv();
+    assert_eq!(6, csv.lines().count());
+    assert!(csv.starts_with("1,0,0,0\n"));
+}
@@ -0,0 +1,113 @@
+use crate::geometry::StripeGeometry;
+use crate::Error;
+
+#[test]
+fn test_geometry_sizes() {
+    // 2 full blocks of 100 bytes, then 45 bytes
+    let g = StripeGeometry::new(4, 100, 245).unwrap();
+
+    assert_eq!(4, g.data_shard_count());
+    assert_eq!(100, g.block_size());
+    assert_eq!(245, g.object_size());
+    assert_eq!(25, g.shard_size());
+    assert_eq!(3, g.stripe_count());
+
+    assert_eq!(Some(100), g.stripe_len(1));
+    assert_eq!(Some(45), g.stripe_len(2));
+    assert_eq!(None, g.stripe_len(3));
+
+    assert_eq!(Some(25), g.stripe_shard_size(0));
+    assert_eq!(Some(12), g.stripe_shard_size(2));
+    assert_eq!(None, g.stripe_shard_size(3));
+
+    assert_eq!(200, g.stripe_offset(2));
+    assert_eq!(50, g.shard_file_offset(2));
+    assert_eq!(62, g.shard_file_size());
+}
+
+#[test]
+fn test_geometry_uneven_block() {
+    // blocks of 10 bytes over 3 shards of 4 bytes
+    let g = StripeGeometry::new(3, 10, 20).unwrap();
+
+    assert_eq!(4, g.shard_size());
+    assert_eq!(2, g.stripe_count());
+    assert_eq!(8, g.shard_file_size());
+    assert_eq!((0, 2, 1), g.locate(9).unwrap());
+    assert_eq!((1, 0, 0), g.locate(10).unwrap());
+}
+
+#[test]
+fn test_geometry_empty_object() {
+    let g = StripeGeometry::new(4, 100, 0).unwrap();
+
+    assert_eq!(0, g.stripe_count());
+    assert_eq!(0, g.shard_file_size());
+    assert_eq!(None, g.stripe_len(0));
+    assert_eq!(0..0, g.stripes_for_range(0, 0).unwrap());
+    assert_eq!(Error::InvalidIndex, g.locate(0).unwrap_err());
+}
+
+#[test]
+fn test_geometry_locate() {
+    let g = StripeGeometry::new(4, 100, 245).unwrap();
+
+    assert_eq!((0, 0, 0), g.locate(0).unwrap());
+    assert_eq!((0, 0, 24), g.locate(24).unwrap());
+    assert_eq!((0, 1, 0), g.locate(25).unwrap());
+    assert_eq!((1, 3, 24), g.locate(199).unwrap());
+    // the last stripe has shards of 12 bytes
+    assert_eq!((2, 0, 11), g.locate(211).unwrap());
+    assert_eq!((2, 1, 0), g.locate(212).unwrap());
+    assert_eq!((2, 3, 8), g.locate(244).unwrap());
+    assert_eq!(Error::InvalidIndex, g.locate(245).unwrap_err());
+}
+
+#[test]
+fn test_geometry_locate_matches_layout() {
+    let g = StripeGeometry::new(3, 16, 100).unwrap();
+
+    let object: Vec<u8> = (0..100).collect();
+
+    for (stripe, block) in object.chunks(g.block_size()).enumerate() {
+        let shard_size = g.stripe_shard_size(stripe).unwrap();
+        for (shard, chunk) in block.chunks(shard_size).enumerate() {
+            for (offset, &byte) in chunk.iter().enumerate() {
+                assert_eq!((stripe, shard, offset), g.locate(byte as u64).unwrap());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_geometry_stripes_for_range() {
+    let g = StripeGeometry::new(4, 100, 245).unwrap();
+
+    assert_eq!(0..1, g.stripes_for_range(0, 1).unwrap());
+    assert_eq!(0..1, g.stripes_for_range(0, 100).unwrap());
+    assert_eq!(0..2, g.stripes_for_range(99, 2).unwrap());
+    assert_eq!(1..3, g.stripes_for_range(150, 95).unwrap());
+    assert_eq!(0..3, g.stripes_for_range(0, 245).unwrap());
+    assert_eq!(0..0, g.stripes_for_range(100, 0).unwrap());
+
+    assert_eq!(
+        Error::InvalidIndex,
+        g.stripes_for_range(200, 46).unwrap_err()
+    );
+    assert_eq!(
+        Error::InvalidIndex,
+        g.stripes_for_range(u64::MAX, 2).unwrap_err()
+    );
+}
+
+#[test]
+fn test_geometry_error_handling() {
+    assert_eq!(
+        Error::TooFewDataShards,
+        StripeGeometry::new(0, 100, 245).unwrap_err()
+    );
+    assert_eq!(
+        Error::EmptyShard,
+        StripeGeometry::new(4, 0, 245).unwrap_err()
+    );
+}
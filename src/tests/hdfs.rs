@@ -0,0 +1,136 @@
+use super::fill_random;
+use crate::galois_8;
+use crate::hdfs::EcPolicy;
+use crate::{Error, MatrixKind};
+
+#[test]
+fn test_ec_policy_names() {
+    assert_eq!("RS-3-2-64k", EcPolicy::RS_3_2_64K.name());
+    assert_eq!("RS-6-3-1024k", EcPolicy::RS_6_3_1024K.name());
+    assert_eq!("RS-10-4-1024k", EcPolicy::RS_10_4_1024K.name());
+    assert_eq!("RS-2-1-100", EcPolicy::new(2, 1, 100).unwrap().name());
+}
+
+#[test]
+fn test_ec_policy_new_error_handling() {
+    assert_eq!(Error::EmptyShard, EcPolicy::new(6, 3, 0).unwrap_err());
+    assert_eq!(
+        Error::TooFewDataShards,
+        EcPolicy::new(0, 3, 1024).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyShards,
+        EcPolicy::new(200, 100, 1024).unwrap_err()
+    );
+}
+
+#[test]
+fn test_ec_policy_codec_uses_cauchy_matrix() {
+    let policy = EcPolicy::RS_6_3_1024K;
+    let codec = policy.codec();
+
+    assert_eq!(MatrixKind::Cauchy, codec.matrix_kind());
+
+    // encoding a unit vector yields a column of the parity rows,
+    // which must be 1 / (r ^ c) as in `gf_gen_cauchy1_matrix`
+    for c in 0..6 {
+        let mut shards = vec![vec![0u8; 1]; 9];
+        shards[c][0] = 1;
+        codec.encode(&mut shards).unwrap();
+
+        for (r, shard) in shards.iter().enumerate().skip(6) {
+            assert_eq!(1, galois_8::mul(shard[0], (r ^ c) as u8));
+        }
+    }
+}
+
+#[test]
+fn test_ec_policy_layout() {
+    let policy = EcPolicy::new(3, 2, 10).unwrap();
+
+    assert_eq!((0, 0), policy.locate(0));
+    assert_eq!((0, 9), policy.locate(9));
+    assert_eq!((1, 0), policy.locate(10));
+    assert_eq!((2, 5), policy.locate(25));
+    assert_eq!((0, 10), policy.locate(30));
+    assert_eq!((1, 13), policy.locate(43));
+
+    // 2 full stripes, then 13 bytes
+    let group_len = 73;
+    assert_eq!(30, policy.internal_block_len(group_len, 0).unwrap());
+    assert_eq!(23, policy.internal_block_len(group_len, 1).unwrap());
+    assert_eq!(20, policy.internal_block_len(group_len, 2).unwrap());
+    assert_eq!(30, policy.internal_block_len(group_len, 3).unwrap());
+    assert_eq!(30, policy.internal_block_len(group_len, 4).unwrap());
+    assert_eq!(
+        Error::InvalidIndex,
+        policy.internal_block_len(group_len, 5).unwrap_err()
+    );
+}
+
+#[test]
+fn test_ec_policy_encode_reconstruct_join() {
+    let policy = EcPolicy::new(3, 2, 10).unwrap();
+    let codec = policy.codec();
+
+    for &group_len in [1, 10, 29, 30, 73, 300].iter() {
+        let mut group = vec![0u8; group_len];
+        fill_random(&mut group);
+
+        let blocks = policy.encode(&codec, &group).unwrap();
+        for (i, block) in blocks.iter().enumerate() {
+            assert_eq!(
+                policy.internal_block_len(group_len as u64, i).unwrap(),
+                block.len() as u64
+            );
+        }
+
+        assert_eq!(group, policy.join(&blocks[..3], group_len as u64).unwrap());
+
+        for missing in [[0, 1], [1, 4], [2, 3], [3, 4]].iter() {
+            let mut damaged: Vec<_> = blocks.iter().cloned().map(Some).collect();
+            damaged[missing[0]] = None;
+            damaged[missing[1]] = None;
+
+            policy
+                .reconstruct(&codec, &mut damaged, group_len as u64)
+                .unwrap();
+
+            let repaired: Vec<_> = damaged.into_iter().map(Option::unwrap).collect();
+            assert_eq!(blocks, repaired);
+        }
+    }
+}
+
+#[test]
+fn test_ec_policy_error_handling() {
+    let policy = EcPolicy::new(3, 2, 10).unwrap();
+    let codec = policy.codec();
+
+    assert_eq!(
+        Error::TooManyParityShards,
+        policy
+            .encode(&galois_8::ReedSolomon::new_cauchy(3, 3).unwrap(), &[1])
+            .unwrap_err()
+    );
+    assert_eq!(Error::EmptyShard, policy.encode(&codec, &[]).unwrap_err());
+
+    let blocks = policy.encode(&codec, &[1u8; 45]).unwrap();
+
+    let mut wrong_len: Vec<_> = blocks.iter().cloned().map(Some).collect();
+    wrong_len[0] = None;
+    wrong_len[2].as_mut().unwrap().push(0);
+    assert_eq!(
+        Error::IncorrectShardSize,
+        policy.reconstruct(&codec, &mut wrong_len, 45).unwrap_err()
+    );
+
+    assert_eq!(
+        Error::TooFewDataShards,
+        policy.join(&blocks[..2], 45).unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        policy.join(&blocks[..3], 46).unwrap_err()
+    );
+}
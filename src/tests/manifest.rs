@@ -0,0 +1,146 @@
+use std::io;
+
+use super::{encoded_shards, ReedSolomon};
+use crate::manifest::{Manifest, ManifestBuilder};
+use crate::Error;
+
+// FNV-1a, standing in for a real hash
+fn digest(shard: &[u8]) -> [u8; 8] {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in shard {
+        hash = (hash ^ b as u64).wrapping_mul(0x100_0000_01b3);
+    }
+    hash.to_le_bytes()
+}
+
+#[test]
+fn test_manifest_build() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let shards = encoded_shards(&r, 100);
+
+    let manifest = r.manifest(&shards, 390, digest).unwrap();
+    assert_eq!(4, manifest.data_shard_count());
+    assert_eq!(2, manifest.parity_shard_count());
+    assert_eq!(6, manifest.total_shard_count());
+    assert_eq!(390, manifest.original_len());
+    assert_eq!(Some(100), manifest.shard_len(5));
+    assert_eq!(None, manifest.shard_len(6));
+    assert_eq!(Some(&digest(&shards[3])[..]), manifest.digest(3));
+
+    // the order shards are added in does not matter
+    let mut builder = ManifestBuilder::new(&r, 390);
+    for i in (0..6).rev() {
+        builder.add_shard(i, 100, &digest(&shards[i])).unwrap();
+    }
+    assert_eq!(
+        Error::InvalidIndex,
+        builder.add_shard(6, 100, &[]).unwrap_err()
+    );
+    assert_eq!(manifest, builder.build().unwrap());
+
+    let mut builder = ManifestBuilder::new(&r, 390);
+    for (i, shard) in shards.iter().enumerate().take(5) {
+        builder.add_shard(i, 100, &digest(shard)).unwrap();
+    }
+    assert_eq!(Error::InvalidManifest, builder.clone().build().unwrap_err());
+    builder.add_shard(5, 99, &digest(&shards[5])).unwrap();
+    assert_eq!(Error::InvalidManifest, builder.build().unwrap_err());
+
+    assert_eq!(
+        Error::InvalidManifest,
+        r.manifest(&shards, 401, digest).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShards,
+        r.manifest(&shards[1..], 390, digest).unwrap_err()
+    );
+    let empty = vec![vec![0u8; 0]; 6];
+    assert_eq!(
+        Error::InvalidManifest,
+        r.manifest(&empty, 0, digest).unwrap_err()
+    );
+}
+
+#[test]
+fn test_manifest_validate() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let expect = encoded_shards(&r, 64);
+    let manifest = r.manifest(&expect, 256, digest).unwrap();
+
+    let mut shards: Vec<_> = expect.iter().cloned().map(Some).collect();
+    assert!(manifest
+        .validate(&r, &mut shards, digest)
+        .unwrap()
+        .is_empty());
+
+    // a corrupted shard, a truncated one and a missing one
+    shards[0].as_mut().unwrap()[10] ^= 1;
+    shards[4].as_mut().unwrap().pop();
+    shards[2] = None;
+    let rejected = manifest.validate(&r, &mut shards, digest);
+    assert_eq!(Error::TooFewShardsPresent, rejected.unwrap_err());
+    assert!(shards[0].is_some());
+
+    shards[2] = Some(expect[2].clone());
+    assert_eq!(
+        vec![0, 4],
+        manifest.validate(&r, &mut shards, digest).unwrap()
+    );
+    assert!(shards[0].is_none() && shards[4].is_none());
+
+    r.reconstruct(&mut shards).unwrap();
+    let shards: Vec<_> = shards.into_iter().map(Option::unwrap).collect();
+    assert_eq!(expect, shards);
+
+    let mut shards: Vec<_> = expect.iter().cloned().map(Some).collect();
+    let other = ReedSolomon::new(3, 3).unwrap();
+    assert_eq!(
+        Error::InvalidManifest,
+        manifest.validate(&other, &mut shards, digest).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyShards,
+        manifest
+            .validate(&r, &mut vec![Some(vec![0u8; 64]); 7], digest)
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_manifest_bytes() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+    let shards = encoded_shards(&r, 10);
+    let manifest = r.manifest(&shards, 25, digest).unwrap();
+
+    let bytes = manifest.to_bytes();
+    assert_eq!(
+        &b"RSMF\x01\x03\x00\x02\x00\x19\x00\x00\x00\x00\x00\x00\x00"[..],
+        &bytes[..17]
+    );
+    assert_eq!(17 + 5 * (8 + 2 + 8), bytes.len());
+    assert_eq!(manifest, Manifest::from_bytes(&bytes).unwrap());
+
+    let invalid = |bytes: &[u8]| Manifest::from_bytes(bytes).unwrap_err().kind();
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        invalid(&bytes[..bytes.len() - 1])
+    );
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        invalid(&[&bytes[..], &[0]].concat())
+    );
+
+    let mut corrupt = bytes.clone();
+    corrupt[4] = 2;
+    assert_eq!(io::ErrorKind::InvalidData, invalid(&corrupt));
+
+    // an original length the data shards cannot hold
+    let mut corrupt = bytes.clone();
+    corrupt[9] = 31;
+    assert_eq!(io::ErrorKind::InvalidData, invalid(&corrupt));
+
+    // shards of different lengths
+    let mut corrupt = bytes;
+    corrupt[17] = 9;
+    assert_eq!(io::ErrorKind::InvalidData, invalid(&corrupt));
+}
@@ -1,9 +1,57 @@
 #![allow(dead_code)]
 
-use super::{galois_8, Error, SBSError};
+use super::{
+    galois_8, ChunkAssignment, CodecStats, CowShard, DropPolicy, Error, ParallelParam, SBSError,
+    ShardByShardState, ThreadSource, VerifyPolicy,
+};
 use rand::{self, thread_rng, Rng};
 
+mod accumulator;
+mod aligned;
+mod calibration;
+mod codeword;
+mod das;
 mod galois_16;
+mod generator;
+mod geometry;
+mod hdfs;
+mod manifest;
+mod placement;
+mod pool;
+mod product;
+mod realtime;
+mod registry;
+mod repair;
+mod shard_set;
+mod sim;
+mod stream;
+mod throttle;
+mod tiny;
+mod vectors;
+
+#[cfg(feature = "async")]
+mod fetch;
+
+#[cfg(feature = "rkyv")]
+mod archive;
+
+#[cfg(feature = "async")]
+mod pipeline;
+
+#[cfg(any(feature = "quickcheck", feature = "proptest"))]
+mod arbitrary;
+
+#[cfg(feature = "object_store")]
+mod object_shards;
+
+#[cfg(feature = "test-support")]
+mod faults;
+
+#[cfg(feature = "allocator-api")]
+mod allocator;
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
 
 type ReedSolomon = crate::ReedSolomon<galois_8::Field>;
 type ShardByShard<'a> = crate::ShardByShard<'a, galois_8::Field>;
@@ -42,6 +90,17 @@ pub fn fill_random<T>(arr: &mut [T])
     }
 }
 
+/// Returns a stripe of shards of `len` bytes, random data shards followed
+/// by their parity shards.
+fn encoded_shards(r: &ReedSolomon, len: usize) -> Vec<Vec<u8>> {
+    let mut shards = vec![vec![0u8; len]; r.total_shard_count()];
+    for shard in shards.iter_mut().take(r.data_shard_count()) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+    shards
+}
+
 fn shards_to_option_shards<T: Clone>(shards: &[Vec<T>]) -> Vec<Option<Vec<T>>> {
     let mut result = Vec::with_capacity(shards.len());
 
@@ -134,6 +193,74 @@ fn test_reed_solomon_clone() {
     assert_eq!(r1, r2);
 }
 
+#[test]
+fn test_build_matrix_matches_inverted_vandermonde() {
+    use crate::matrix::Matrix;
+
+    fn inverted_vandermonde<F: crate::Field>(data_shards: usize, total_shards: usize) -> Matrix<F> {
+        let vandermonde = Matrix::vandermonde(total_shards, data_shards);
+        let top = vandermonde.sub_matrix(0, 0, data_shards, data_shards);
+        vandermonde.multiply(&top.invert().unwrap())
+    }
+
+    for &(data, parity) in &[(1, 1), (3, 2), (10, 4), (17, 3), (100, 50), (200, 56)] {
+        let r = ReedSolomon::new(data, parity).unwrap();
+        assert_eq!(inverted_vandermonde(data, data + parity), r.matrix);
+    }
+
+    for &(data, parity) in &[(4, 2), (40, 20)] {
+        let r = crate::ReedSolomon::<crate::galois_16::Field>::new(data, parity).unwrap();
+        assert_eq!(inverted_vandermonde(data, data + parity), r.matrix);
+    }
+}
+
+#[test]
+fn test_build_wide_matrices() {
+    use crate::galois_16::Field as G16;
+    use crate::Field;
+
+    type Codec = crate::ReedSolomon<G16>;
+
+    // wide enough for the rows to be filled on several threads
+    let cauchy = Codec::new_cauchy(1000, 500).unwrap();
+    for &(r, c) in &[(1000, 0), (1000, 999), (1234, 567), (1499, 999)] {
+        assert_eq!(Codec::cauchy_coefficient(r, c), cauchy.matrix.get(r, c));
+    }
+
+    // every parity row interpolates the polynomial of values `x` at `x`
+    let vandermonde = Codec::new(1000, 500).unwrap();
+    for r in 1000..1500 {
+        let row = vandermonde.matrix.get_row(r);
+        let value = (0..1000).fold(G16::zero(), |acc, j| {
+            G16::add(acc, G16::mul(row[j], G16::nth(j)))
+        });
+        assert_eq!(G16::nth(r), value);
+    }
+}
+
+#[test]
+// the caches and counters of a codec do not take part in its hash
+#[allow(clippy::mutable_key_type)]
+fn test_reed_solomon_eq_and_hash_compare_matrix() {
+    use std::collections::HashSet;
+
+    let vandermonde = ReedSolomon::new(10, 3).unwrap();
+    let cauchy = ReedSolomon::new_cauchy(10, 3).unwrap();
+
+    assert_ne!(vandermonde, cauchy);
+    assert_ne!(vandermonde, ReedSolomon::new(10, 4).unwrap());
+
+    let mut codecs = HashSet::new();
+    codecs.insert(vandermonde.clone());
+    codecs.insert(cauchy.clone());
+    codecs.insert(ReedSolomon::new(10, 3).unwrap());
+    codecs.insert(ReedSolomon::new_cauchy(10, 3).unwrap());
+
+    assert_eq!(2, codecs.len());
+    assert!(codecs.contains(&vandermonde));
+    assert!(codecs.contains(&cauchy));
+}
+
 #[test]
 fn test_encoding() {
     let per_shard = 50_000;
@@ -984,6 +1111,47 @@ fn test_verify_shards_with_buffer_gives_correct_parity_shards() {
     }
 }
 
+#[test]
+fn test_verify_sep() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let data = make_random_shards!(100, 10);
+    let mut parity = vec![vec![0u8; 100]; 3];
+    r.encode_sep(&data, &mut parity).unwrap();
+
+    let parity_refs: Vec<&[u8]> = parity.iter().map(|p| &p[..]).collect();
+    assert!(r.verify_sep(&data, &parity_refs).unwrap());
+
+    let mut buffer = vec![vec![0u8; 100]; 3];
+    assert!(r
+        .verify_sep_with_buffer(&data, &parity, &mut buffer)
+        .unwrap());
+    assert_eq!(parity, buffer);
+
+    parity[1][42] ^= 1;
+    assert!(!r.verify_sep(&data, &parity).unwrap());
+
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.verify_sep(&data[..9], &parity).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewParityShards,
+        r.verify_sep(&data, &parity[..2]).unwrap_err()
+    );
+    parity[2].pop();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.verify_sep(&data, &parity).unwrap_err()
+    );
+    parity[2].push(0);
+    assert_eq!(
+        Error::TooFewBufferShards,
+        r.verify_sep_with_buffer(&data, &parity, &mut buffer[..2])
+            .unwrap_err()
+    );
+}
+
 #[test]
 fn test_verify_with_buffer_gives_correct_parity_shards() {
     let r = ReedSolomon::new(10, 3).unwrap();
@@ -1218,6 +1386,535 @@ fn shardbyshard_encode_correctly() {
     }
 }
 
+#[test]
+fn shardbyshard_finish_zero_fills_remaining() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    for &encoded in [0, 4, 9].iter() {
+        let mut sbs = ShardByShard::new(&r);
+
+        let mut shards = make_random_shards!(1_000, 13);
+        let mut expect = shards.clone();
+        for shard in expect[encoded..10].iter_mut() {
+            shard.iter_mut().for_each(|x| *x = 0);
+        }
+        r.encode(&mut expect).unwrap();
+
+        for _ in 0..encoded {
+            sbs.encode(&mut shards).unwrap();
+        }
+        sbs.finish(&mut shards).unwrap();
+
+        assert!(sbs.parity_ready());
+        assert_eq!(expect, shards);
+
+        assert_eq!(
+            SBSError::TooManyCalls {
+                data_shard_count: 10
+            },
+            sbs.finish(&mut shards).unwrap_err()
+        );
+    }
+    {
+        let mut sbs = ShardByShard::new(&r);
+
+        let mut shards = make_random_shards!(1_000, 13);
+        let mut expect = shards.clone();
+        for shard in expect[3..10].iter_mut() {
+            shard.iter_mut().for_each(|x| *x = 0);
+        }
+        r.encode(&mut expect).unwrap();
+
+        let (data, parity) = shards.split_at_mut(10);
+        for _ in 0..3 {
+            sbs.encode_sep(&data[..], parity).unwrap();
+        }
+        sbs.finish_sep(parity).unwrap();
+
+        assert!(sbs.parity_ready());
+        assert_eq!(&expect[10..], &shards[10..]);
+
+        sbs.reset().unwrap();
+        assert_eq!(
+            SBSError::RSError {
+                cur_input: 0,
+                error: Error::TooFewParityShards
+            },
+            sbs.finish_sep(&mut shards[11..]).unwrap_err()
+        );
+        assert_eq!(0, sbs.cur_input_index());
+    }
+}
+
+#[test]
+fn shardbyshard_skip_and_merge_parity() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut expect = make_random_shards!(1_000, 13);
+    r.encode(&mut expect).unwrap();
+
+    // one session encodes data shards 0..6, the other 6..10
+    let mut first = expect.clone();
+    let mut sbs = ShardByShard::new(&r);
+    for _ in 0..6 {
+        sbs.encode(&mut first).unwrap();
+    }
+    sbs.finish_sep(&mut first[10..]).unwrap();
+
+    let mut second = expect.clone();
+    fill_random(&mut second[10]);
+    let (data, parity) = second.split_at_mut(10);
+    let mut sbs = ShardByShard::new(&r);
+    for _ in 0..6 {
+        sbs.skip_sep(parity).unwrap();
+    }
+    for _ in 6..10 {
+        sbs.encode_sep(&data[..], parity).unwrap();
+    }
+    assert!(sbs.parity_ready());
+    assert_eq!(
+        SBSError::TooManyCalls {
+            data_shard_count: 10
+        },
+        sbs.skip_sep(parity).unwrap_err()
+    );
+
+    let mut parity = first[10..].to_vec();
+    r.merge_parity(&second[10..], &mut parity).unwrap();
+    assert_eq!(&expect[10..], &parity[..]);
+
+    // merging again takes the second session back out
+    r.merge_parity(&second[10..], &mut parity).unwrap();
+    assert_eq!(&first[10..], &parity[..]);
+
+    // skipping every other shard
+    let mut shards = expect.clone();
+    let mut sbs = ShardByShard::new(&r);
+    for i in 0..10 {
+        if i % 2 == 0 {
+            sbs.skip(&mut shards).unwrap();
+        } else {
+            sbs.encode(&mut shards).unwrap();
+        }
+    }
+    assert_eq!(&expect[..10], &shards[..10]);
+    let mut zero = crate::ShardSet::new(10);
+    for i in (0..10).step_by(2) {
+        zero.insert(i);
+    }
+    let mut parity = vec![vec![0u8; 1_000]; 3];
+    r.encode_sep_skipping(&expect[..10], &mut parity, &zero)
+        .unwrap();
+    assert_eq!(&parity[..], &shards[10..]);
+
+    assert_eq!(
+        Error::TooFewParityShards,
+        r.merge_parity(&second[11..], &mut parity).unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.merge_parity(&vec![vec![0u8; 999]; 3], &mut parity)
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn shardbyshard_errors_carry_input_index() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let mut sbs = ShardByShard::new(&r);
+
+    let mut shards = make_random_shards!(100, 6);
+    sbs.encode(&mut shards).unwrap();
+    sbs.encode(&mut shards).unwrap();
+
+    let e = sbs.encode(&mut shards[..5]).unwrap_err();
+    assert_eq!(
+        SBSError::RSError {
+            cur_input: 2,
+            error: Error::TooFewShards
+        },
+        e
+    );
+    assert_eq!(2, e.cur_input());
+    assert_eq!(
+        "At input shard 2: The number of provided shards is smaller than the one in codec",
+        e.to_string()
+    );
+
+    let e = sbs.reset().unwrap_err();
+    assert_eq!(
+        SBSError::LeftoverShards {
+            cur_input: 2,
+            data_shard_count: 4
+        },
+        e
+    );
+    assert_eq!(2, e.cur_input());
+}
+
+#[test]
+fn shardbyshard_guard_drop_policies() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut shards = make_random_shards!(1_000, 13);
+    let mut expect = shards.clone();
+    for shard in expect[4..10].iter_mut() {
+        shard.iter_mut().for_each(|x| *x = 0);
+    }
+    r.encode(&mut expect).unwrap();
+
+    let mut sbs = ShardByShard::new(&r);
+    {
+        let mut guard = sbs.guard(&mut shards, DropPolicy::Finish).unwrap();
+        for i in 0..4 {
+            assert_eq!(i, guard.cur_input_index());
+            guard.encode().unwrap();
+        }
+    }
+    assert!(sbs.parity_ready());
+    assert_eq!(expect, shards);
+
+    {
+        let mut guard = sbs.guard(&mut shards, DropPolicy::Abort).unwrap();
+        guard.encode().unwrap();
+    }
+    assert!(sbs.aborted());
+    assert_eq!(0, sbs.cur_input_index());
+
+    {
+        let mut guard = sbs.guard(&mut shards, DropPolicy::Panic).unwrap();
+        guard.shards_mut()[0][0] ^= 1;
+        guard.encode().unwrap();
+        guard.finish().unwrap();
+    }
+    assert!(sbs.parity_ready());
+    assert!(!sbs.aborted());
+
+    sbs.reset_force();
+    sbs.encode(&mut shards).unwrap();
+    assert_eq!(
+        SBSError::LeftoverShards {
+            cur_input: 1,
+            data_shard_count: 10
+        },
+        sbs.guard(&mut shards, DropPolicy::Finish).unwrap_err()
+    );
+
+    sbs.reset_force();
+    shards[12] = vec![];
+    assert_eq!(
+        SBSError::RSError {
+            cur_input: 0,
+            error: Error::IncorrectShardSize
+        },
+        sbs.guard(&mut shards, DropPolicy::Finish).unwrap_err()
+    );
+}
+
+#[test]
+fn shardbyshard_export_import_state() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut expect = make_random_shards!(1_000, 13);
+    r.encode(&mut expect).unwrap();
+
+    // the first worker encodes 4 data shards
+    let mut shards = expect.clone();
+    let mut sbs = ShardByShard::new(&r);
+    for _ in 0..4 {
+        sbs.encode(&mut shards).unwrap();
+    }
+    let state = sbs.export_state(&shards[10..]).unwrap();
+    assert_eq!(4, state.cur_input_index());
+    let bytes = state.to_bytes();
+    assert_eq!(state, ShardByShardState::from_bytes(&bytes).unwrap());
+
+    // another worker resumes from the saved state
+    let other = r.clone();
+    let mut resumed = ShardByShard::new(&other);
+    let mut shards = expect.clone();
+    for shard in shards[10..].iter_mut() {
+        fill_random(shard);
+    }
+    let state = ShardByShardState::from_bytes(&bytes).unwrap();
+    resumed.import_state(&state, &mut shards[10..]).unwrap();
+    assert_eq!(4, resumed.cur_input_index());
+    while !resumed.parity_ready() {
+        resumed.encode(&mut shards).unwrap();
+    }
+    assert_eq!(expect, shards);
+
+    // leftover shards are not overwritten
+    resumed.reset().unwrap();
+    resumed.encode(&mut shards).unwrap();
+    assert_eq!(
+        SBSError::LeftoverShards {
+            cur_input: 1,
+            data_shard_count: 10
+        },
+        resumed.import_state(&state, &mut shards[10..]).unwrap_err()
+    );
+    resumed.reset_force();
+
+    assert_eq!(
+        SBSError::RSError {
+            cur_input: 0,
+            error: Error::IncorrectShardSize
+        },
+        resumed
+            .import_state(&state, &mut make_random_shards!(999, 3))
+            .unwrap_err()
+    );
+    let cauchy = ReedSolomon::new_cauchy(10, 3).unwrap();
+    for codec in [ReedSolomon::new(10, 4).unwrap(), cauchy].iter() {
+        assert_eq!(
+            SBSError::RSError {
+                cur_input: 0,
+                error: Error::InvalidCheckpoint
+            },
+            ShardByShard::new(codec)
+                .import_state(&state, &mut shards[10..])
+                .unwrap_err()
+        );
+    }
+    assert_eq!(
+        SBSError::RSError {
+            cur_input: 0,
+            error: Error::TooFewParityShards
+        },
+        ShardByShard::new(&r)
+            .export_state(&shards[11..])
+            .unwrap_err()
+    );
+
+    // truncated or altered binary forms
+    for len in 0..bytes.len() {
+        assert!(ShardByShardState::from_bytes(&bytes[..len]).is_err());
+    }
+    let mut altered = bytes.clone();
+    altered[9] = 2;
+    assert!(ShardByShardState::from_bytes(&altered).is_err());
+    let mut altered = bytes.clone();
+    altered[10] = 11;
+    assert!(ShardByShardState::from_bytes(&altered).is_err());
+}
+
+#[test]
+fn shardbyshard_owned_codec() {
+    // per-connection state, not borrowing the codec
+    struct Connection {
+        sbs: galois_8::OwnedShardByShard,
+    }
+
+    let r = std::sync::Arc::new(ReedSolomon::new(10, 3).unwrap());
+    let mut conn = Connection {
+        sbs: ShardByShard::new_owned(std::sync::Arc::clone(&r)),
+    };
+    assert_eq!(*r, *conn.sbs.codec());
+
+    let mut expect = make_random_shards!(1_000, 13);
+    r.encode(&mut expect).unwrap();
+
+    let mut shards = expect.clone();
+    for shard in shards[10..].iter_mut() {
+        fill_random(shard);
+    }
+    let handle = std::thread::spawn(move || {
+        while !conn.sbs.parity_ready() {
+            conn.sbs.encode(&mut shards).unwrap();
+        }
+        conn.sbs.reset().unwrap();
+        shards
+    });
+    let shards = handle.join().unwrap();
+
+    assert_eq!(expect, shards);
+    assert!(r.verify(&shards).unwrap());
+}
+
+#[test]
+fn shardbyshard_sync_pipelined() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut expect = make_random_shards!(1_000, 13);
+    // data shard 3 is all zeros, and skipped
+    for x in expect[3].iter_mut() {
+        *x = 0;
+    }
+    r.encode(&mut expect).unwrap();
+
+    let mut sbs = galois_8::SyncShardByShard::new(&r, 1_000);
+
+    // one stage encodes the even data shards while another does the odd ones
+    std::thread::scope(|s| {
+        for stage in [0, 1].iter() {
+            let (sbs, expect) = (&sbs, &expect);
+            s.spawn(move || {
+                for i in (*stage..10).step_by(2) {
+                    if i == 3 {
+                        sbs.skip(i).unwrap();
+                    } else {
+                        sbs.encode(i, &expect[i]).unwrap();
+                    }
+                }
+            });
+        }
+    });
+    assert!(sbs.parity_ready());
+    assert_eq!(10, sbs.encoded_count());
+
+    let mut parity = make_random_shards!(1_000, 3);
+    sbs.copy_parity(&mut parity).unwrap();
+    assert_eq!(&expect[10..], &parity[..]);
+
+    // each data shard is encoded once
+    assert_eq!(
+        SBSError::RSError {
+            cur_input: 10,
+            error: Error::InvalidIndex
+        },
+        sbs.encode(4, &expect[4]).unwrap_err()
+    );
+    assert_eq!(
+        SBSError::RSError {
+            cur_input: 10,
+            error: Error::IncorrectShardSize
+        },
+        sbs.copy_parity(&mut make_random_shards!(999, 3))
+            .unwrap_err()
+    );
+
+    sbs.reset().unwrap();
+    assert_eq!(0, sbs.encoded_count());
+    sbs.encode(0, &expect[0]).unwrap();
+    for i in [0, 10].iter() {
+        assert_eq!(
+            SBSError::RSError {
+                cur_input: 1,
+                error: Error::InvalidIndex
+            },
+            sbs.encode(*i, &expect[0]).unwrap_err()
+        );
+    }
+    assert_eq!(
+        SBSError::RSError {
+            cur_input: 1,
+            error: Error::IncorrectShardSize
+        },
+        sbs.encode(1, &expect[1][1..]).unwrap_err()
+    );
+    assert_eq!(
+        SBSError::LeftoverShards {
+            cur_input: 1,
+            data_shard_count: 10
+        },
+        sbs.copy_parity(&mut parity).unwrap_err()
+    );
+    assert!(sbs.reset().is_err());
+    sbs.reset_force();
+    assert_eq!(0, sbs.encoded_count());
+}
+
+#[test]
+fn shardbyshard_background() {
+    let r = std::sync::Arc::new(ReedSolomon::new(10, 3).unwrap());
+
+    let mut expect = make_random_shards!(1_000, 13);
+    r.encode(&mut expect).unwrap();
+
+    let mut sbs = galois_8::BackgroundShardByShard::new(std::sync::Arc::clone(&r), 1_000);
+    let ready = sbs.parity_ready_handle();
+    assert!(!ready.is_ready());
+    // the receive loop hands the data shards over in any order
+    for i in (0..10).rev() {
+        sbs.submit(i, expect[i].clone()).unwrap();
+    }
+    ready.wait().unwrap();
+    assert!(ready.is_ready());
+    assert!(sbs.parity_ready());
+
+    let mut parity = make_random_shards!(1_000, 3);
+    sbs.copy_parity(&mut parity).unwrap();
+    assert_eq!(&expect[10..], &parity[..]);
+
+    assert_eq!(
+        SBSError::RSError {
+            cur_input: 10,
+            error: Error::IncorrectShardSize
+        },
+        sbs.submit(0, vec![0u8; 999]).unwrap_err()
+    );
+
+    // the next stripe, with a data shard submitted twice
+    sbs.reset().unwrap();
+    assert!(ready.is_ready());
+    let ready = sbs.parity_ready_handle();
+    sbs.skip(0).unwrap();
+    for i in [1, 1].iter() {
+        sbs.submit(*i, expect[*i].clone()).unwrap();
+    }
+    match ready.wait() {
+        Err(SBSError::RSError {
+            error: Error::InvalidIndex,
+            ..
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(sbs.copy_parity(&mut parity).is_err());
+    assert!(sbs.reset().is_err());
+    sbs.reset_force();
+    assert!(!sbs.parity_ready_handle().is_ready());
+}
+
+#[test]
+fn shardbyshard_background_job_panics() {
+    // hands out its bytes to the length check of `submit` only
+    struct PanickyShard(Vec<u8>, std::sync::atomic::AtomicBool);
+
+    impl AsRef<[u8]> for PanickyShard {
+        fn as_ref(&self) -> &[u8] {
+            if self.1.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                panic!("shard gone");
+            }
+            &self.0
+        }
+    }
+
+    let r = std::sync::Arc::new(ReedSolomon::new(3, 2).unwrap());
+    let mut sbs = galois_8::BackgroundShardByShard::new(std::sync::Arc::clone(&r), 100);
+    let ready = sbs.parity_ready_handle();
+    sbs.submit(0, vec![1u8; 100]).unwrap();
+    sbs.submit(
+        1,
+        PanickyShard(vec![2u8; 100], std::sync::atomic::AtomicBool::new(false)),
+    )
+    .unwrap();
+    match ready.wait() {
+        Err(SBSError::JobPanicked { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(!sbs.parity_ready());
+
+    // the pool keeps running jobs, and the bookkeeping can be reset
+    sbs.reset_force();
+    let ready = sbs.parity_ready_handle();
+    for i in 0..3 {
+        sbs.submit(i, vec![i as u8; 100]).unwrap();
+    }
+    ready.wait().unwrap();
+}
+
+#[test]
+#[should_panic]
+fn shardbyshard_guard_panics_on_incomplete_stripe() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+    let mut sbs = ShardByShard::new(&r);
+
+    let mut shards = make_random_shards!(100, 13);
+    let mut guard = sbs.guard(&mut shards, DropPolicy::Panic).unwrap();
+    guard.encode().unwrap();
+}
+
 quickcheck! {
     fn qc_shardbyshard_encode_same_as_encode(data: usize,
                                              parity: usize,
@@ -1589,7 +2286,9 @@ fn shardbyshard_encode_error_handling() {
         assert!(sbs.parity_ready());
 
         assert_eq!(
-            SBSError::TooManyCalls,
+            SBSError::TooManyCalls {
+                data_shard_count: 10
+            },
             sbs.encode(&mut slice_refs).unwrap_err()
         );
 
@@ -1601,7 +2300,13 @@ fn shardbyshard_encode_error_handling() {
             sbs.encode(&mut slice_refs).unwrap();
         }
 
-        assert_eq!(SBSError::LeftoverShards, sbs.reset().unwrap_err());
+        assert_eq!(
+            SBSError::LeftoverShards {
+                cur_input: 1,
+                data_shard_count: 10
+            },
+            sbs.reset().unwrap_err()
+        );
 
         sbs.reset_force();
 
@@ -1619,14 +2324,20 @@ fn shardbyshard_encode_error_handling() {
             assert_eq!(0, sbs.cur_input_index());
 
             assert_eq!(
-                SBSError::RSError(Error::EmptyShard),
+                SBSError::RSError {
+                    cur_input: 0,
+                    error: Error::EmptyShard
+                },
                 sbs.encode(&mut slice_refs).unwrap_err()
             );
 
             assert_eq!(0, sbs.cur_input_index());
 
             assert_eq!(
-                SBSError::RSError(Error::EmptyShard),
+                SBSError::RSError {
+                    cur_input: 0,
+                    error: Error::EmptyShard
+                },
                 sbs.encode(&mut slice_refs).unwrap_err()
             );
 
@@ -1653,14 +2364,20 @@ fn shardbyshard_encode_error_handling() {
             assert_eq!(0, sbs.cur_input_index());
 
             assert_eq!(
-                SBSError::RSError(Error::IncorrectShardSize),
+                SBSError::RSError {
+                    cur_input: 0,
+                    error: Error::IncorrectShardSize
+                },
                 sbs.encode(&mut slice_refs).unwrap_err()
             );
 
             assert_eq!(0, sbs.cur_input_index());
 
             assert_eq!(
-                SBSError::RSError(Error::IncorrectShardSize),
+                SBSError::RSError {
+                    cur_input: 0,
+                    error: Error::IncorrectShardSize
+                },
                 sbs.encode(&mut slice_refs).unwrap_err()
             );
 
@@ -1693,7 +2410,12 @@ fn shardbyshard_encode_shard_error_handling() {
 
         assert!(sbs.parity_ready());
 
-        assert_eq!(SBSError::TooManyCalls, sbs.encode(&mut shards).unwrap_err());
+        assert_eq!(
+            SBSError::TooManyCalls {
+                data_shard_count: 10
+            },
+            sbs.encode(&mut shards).unwrap_err()
+        );
 
         sbs.reset().unwrap();
 
@@ -1703,7 +2425,13 @@ fn shardbyshard_encode_shard_error_handling() {
             sbs.encode(&mut shards).unwrap();
         }
 
-        assert_eq!(SBSError::LeftoverShards, sbs.reset().unwrap_err());
+        assert_eq!(
+            SBSError::LeftoverShards {
+                cur_input: 1,
+                data_shard_count: 10
+            },
+            sbs.reset().unwrap_err()
+        );
 
         sbs.reset_force();
 
@@ -1719,14 +2447,20 @@ fn shardbyshard_encode_shard_error_handling() {
             assert_eq!(0, sbs.cur_input_index());
 
             assert_eq!(
-                SBSError::RSError(Error::EmptyShard),
+                SBSError::RSError {
+                    cur_input: 0,
+                    error: Error::EmptyShard
+                },
                 sbs.encode(&mut shards).unwrap_err()
             );
 
             assert_eq!(0, sbs.cur_input_index());
 
             assert_eq!(
-                SBSError::RSError(Error::EmptyShard),
+                SBSError::RSError {
+                    cur_input: 0,
+                    error: Error::EmptyShard
+                },
                 sbs.encode(&mut shards).unwrap_err()
             );
 
@@ -1749,14 +2483,20 @@ fn shardbyshard_encode_shard_error_handling() {
             assert_eq!(0, sbs.cur_input_index());
 
             assert_eq!(
-                SBSError::RSError(Error::IncorrectShardSize),
+                SBSError::RSError {
+                    cur_input: 0,
+                    error: Error::IncorrectShardSize
+                },
                 sbs.encode(&mut shards).unwrap_err()
             );
 
             assert_eq!(0, sbs.cur_input_index());
 
             assert_eq!(
-                SBSError::RSError(Error::IncorrectShardSize),
+                SBSError::RSError {
+                    cur_input: 0,
+                    error: Error::IncorrectShardSize
+                },
                 sbs.encode(&mut shards).unwrap_err()
             );
 
@@ -1790,7 +2530,9 @@ fn shardbyshard_encode_sep_error_handling() {
         assert!(sbs.parity_ready());
 
         assert_eq!(
-            SBSError::TooManyCalls,
+            SBSError::TooManyCalls {
+                data_shard_count: 10
+            },
             sbs.encode_sep(data, parity).unwrap_err()
         );
 
@@ -1802,7 +2544,13 @@ fn shardbyshard_encode_sep_error_handling() {
             sbs.encode_sep(data, parity).unwrap();
         }
 
-        assert_eq!(SBSError::LeftoverShards, sbs.reset().unwrap_err());
+        assert_eq!(
+            SBSError::LeftoverShards {
+                cur_input: 1,
+                data_shard_count: 10
+            },
+            sbs.reset().unwrap_err()
+        );
 
         sbs.reset_force();
 
@@ -1831,7 +2579,9 @@ fn shardbyshard_encode_sep_error_handling() {
             assert!(sbs.parity_ready());
 
             assert_eq!(
-                SBSError::TooManyCalls,
+                SBSError::TooManyCalls {
+                    data_shard_count: 10
+                },
                 sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
             );
 
@@ -1844,7 +2594,13 @@ fn shardbyshard_encode_sep_error_handling() {
             }
         }
 
-        assert_eq!(SBSError::LeftoverShards, sbs.reset().unwrap_err());
+        assert_eq!(
+            SBSError::LeftoverShards {
+                cur_input: 1,
+                data_shard_count: 10
+            },
+            sbs.reset().unwrap_err()
+        );
 
         sbs.reset_force();
 
@@ -1868,14 +2624,20 @@ fn shardbyshard_encode_sep_error_handling() {
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::EmptyShard),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::EmptyShard
+                    },
                     sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
                 );
 
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::EmptyShard),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::EmptyShard
+                    },
                     sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
                 );
 
@@ -1907,14 +2669,20 @@ fn shardbyshard_encode_sep_error_handling() {
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::EmptyShard),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::EmptyShard
+                    },
                     sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
                 );
 
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::EmptyShard),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::EmptyShard
+                    },
                     sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
                 );
 
@@ -1949,14 +2717,20 @@ fn shardbyshard_encode_sep_error_handling() {
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::IncorrectShardSize),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::IncorrectShardSize
+                    },
                     sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
                 );
 
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::IncorrectShardSize),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::IncorrectShardSize
+                    },
                     sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
                 );
 
@@ -1988,14 +2762,20 @@ fn shardbyshard_encode_sep_error_handling() {
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::IncorrectShardSize),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::IncorrectShardSize
+                    },
                     sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
                 );
 
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::IncorrectShardSize),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::IncorrectShardSize
+                    },
                     sbs.encode_sep(&data_refs, &mut parity_refs).unwrap_err()
                 );
 
@@ -2035,7 +2815,9 @@ fn shardbyshard_encode_shard_sep_error_handling() {
         assert!(sbs.parity_ready());
 
         assert_eq!(
-            SBSError::TooManyCalls,
+            SBSError::TooManyCalls {
+                data_shard_count: 10
+            },
             sbs.encode_sep(data, parity).unwrap_err()
         );
 
@@ -2047,7 +2829,13 @@ fn shardbyshard_encode_shard_sep_error_handling() {
             sbs.encode_sep(data, parity).unwrap();
         }
 
-        assert_eq!(SBSError::LeftoverShards, sbs.reset().unwrap_err());
+        assert_eq!(
+            SBSError::LeftoverShards {
+                cur_input: 1,
+                data_shard_count: 10
+            },
+            sbs.reset().unwrap_err()
+        );
 
         sbs.reset_force();
 
@@ -2068,14 +2856,20 @@ fn shardbyshard_encode_shard_sep_error_handling() {
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::EmptyShard),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::EmptyShard
+                    },
                     sbs.encode_sep(data, parity).unwrap_err()
                 );
 
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::EmptyShard),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::EmptyShard
+                    },
                     sbs.encode_sep(data, parity).unwrap_err()
                 );
 
@@ -2101,14 +2895,20 @@ fn shardbyshard_encode_shard_sep_error_handling() {
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::EmptyShard),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::EmptyShard
+                    },
                     sbs.encode_sep(data, parity).unwrap_err()
                 );
 
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::EmptyShard),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::EmptyShard
+                    },
                     sbs.encode_sep(data, parity).unwrap_err()
                 );
 
@@ -2137,14 +2937,20 @@ fn shardbyshard_encode_shard_sep_error_handling() {
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::IncorrectShardSize),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::IncorrectShardSize
+                    },
                     sbs.encode_sep(data, parity).unwrap_err()
                 );
 
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::IncorrectShardSize),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::IncorrectShardSize
+                    },
                     sbs.encode_sep(data, parity).unwrap_err()
                 );
 
@@ -2170,14 +2976,20 @@ fn shardbyshard_encode_shard_sep_error_handling() {
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::IncorrectShardSize),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::IncorrectShardSize
+                    },
                     sbs.encode_sep(data, parity).unwrap_err()
                 );
 
                 assert_eq!(0, sbs.cur_input_index());
 
                 assert_eq!(
-                    SBSError::RSError(Error::IncorrectShardSize),
+                    SBSError::RSError {
+                        cur_input: 0,
+                        error: Error::IncorrectShardSize
+                    },
                     sbs.encode_sep(data, parity).unwrap_err()
                 );
 
@@ -2295,32 +3107,129 @@ fn test_encode_sep() {
 }
 
 #[test]
-fn test_encode_single_sep_error_handling() {
+fn test_encode_sep_iterator() {
+    use std::collections::VecDeque;
+
     let r = ReedSolomon::new(10, 3).unwrap();
 
-    {
-        let mut shards = make_random_shards!(1000, 13);
+    let mut shards = make_random_shards!(1_000, 13);
+    r.encode(&mut shards).unwrap();
 
-        {
-            let (data, parity) = shards.split_at_mut(10);
+    let data: VecDeque<Vec<u8>> = shards[..10].iter().cloned().collect();
+    let mut parity = vec![vec![0u8; 1_000]; 3];
+    r.encode_sep_iter(&data, &mut parity).unwrap();
+    assert_eq_shards(&shards[10..], &parity);
 
-            for i in 0..10 {
-                r.encode_single_sep(i, &data[i], parity).unwrap();
-            }
+    // shards borrowed from elsewhere, without a container of their own
+    let mut parity = vec![vec![0u8; 1_000]; 3];
+    r.encode_sep_iter(data.iter().map(|shard| &shard[..]), &mut parity)
+        .unwrap();
+    assert_eq_shards(&shards[10..], &parity);
 
-            assert_eq!(
-                Error::InvalidIndex,
-                r.encode_single_sep(10, &data[0], parity).unwrap_err()
-            );
-            assert_eq!(
-                Error::InvalidIndex,
-                r.encode_single_sep(11, &data[0], parity).unwrap_err()
-            );
-            assert_eq!(
-                Error::InvalidIndex,
-                r.encode_single_sep(12, &data[0], parity).unwrap_err()
-            );
-            assert_eq!(
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_sep_iter(data.iter().take(9), &mut parity).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyDataShards,
+        r.encode_sep_iter(shards.iter().take(11), &mut parity)
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_encode_partial() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut expect = make_random_shards!(100, 13);
+    r.encode(&mut expect).unwrap();
+
+    // bursts of one, several and more than FUSED_INPUTS data shards,
+    // between the bounds given
+    for bounds in [&[0, 3, 4, 10][..], &[0, 1, 10], &[0, 10]].iter() {
+        let mut shards = expect.clone();
+        for shard in shards[10..].iter_mut() {
+            fill_random(shard);
+        }
+        for burst in bounds.windows(2) {
+            let indices: Vec<usize> = (burst[0]..burst[1]).collect();
+            r.encode_partial(&indices, &mut shards).unwrap();
+        }
+        assert_eq!(expect, shards);
+    }
+
+    // gaps count as zero data shards
+    let mut zeroed = expect.clone();
+    zeroed[2] = vec![0; 100];
+    zeroed[7] = vec![0; 100];
+    r.encode(&mut zeroed).unwrap();
+    let (data, parity) = expect.split_at(10);
+    let mut parity = parity.to_vec();
+    let indices = [0, 1, 3, 4, 5, 6, 8, 9];
+    let burst: Vec<_> = indices.iter().map(|&i| &data[i]).collect();
+    r.encode_partial_sep(&indices[..3], &burst[..3], &mut parity)
+        .unwrap();
+    r.encode_partial_sep(&indices[3..], &burst[3..], &mut parity)
+        .unwrap();
+    assert_eq!(&zeroed[10..], &parity[..]);
+
+    let mut shards = expect.clone();
+    for indices in [&[10][..], &[1, 1], &[3, 2]].iter() {
+        assert_eq!(
+            Error::InvalidIndex,
+            r.encode_partial(indices, &mut shards).unwrap_err()
+        );
+    }
+    r.encode_partial(&[], &mut shards).unwrap();
+    assert_eq!(expect, shards);
+    assert_eq!(
+        Error::TooFewShards,
+        r.encode_partial(&[0], &mut shards[1..]).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_partial_sep(&[0, 1], &burst[..1], &mut parity)
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyDataShards,
+        r.encode_partial_sep(&[0], &burst[..2], &mut parity)
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewParityShards,
+        r.encode_partial_sep(&[0], &burst[..1], &mut parity[1..])
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_encode_single_sep_error_handling() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    {
+        let mut shards = make_random_shards!(1000, 13);
+
+        {
+            let (data, parity) = shards.split_at_mut(10);
+
+            for i in 0..10 {
+                r.encode_single_sep(i, &data[i], parity).unwrap();
+            }
+
+            assert_eq!(
+                Error::InvalidIndex,
+                r.encode_single_sep(10, &data[0], parity).unwrap_err()
+            );
+            assert_eq!(
+                Error::InvalidIndex,
+                r.encode_single_sep(11, &data[0], parity).unwrap_err()
+            );
+            assert_eq!(
+                Error::InvalidIndex,
+                r.encode_single_sep(12, &data[0], parity).unwrap_err()
+            );
+            assert_eq!(
                 Error::InvalidIndex,
                 r.encode_single_sep(13, &data[0], parity).unwrap_err()
             );
@@ -2611,3 +3520,1618 @@ fn test_encode_single_error_handling() {
         );
     }
 }
+
+#[test]
+fn test_verify_partial() {
+    let r = ReedSolomon::new(4, 4).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    {
+        let shards = shards_to_option_shards(&shards);
+        assert_eq!((true, 4), r.verify_partial(&shards).unwrap());
+    }
+    {
+        let mut shards = shards_to_option_shards(&shards);
+        shards[5] = None;
+        shards[7] = None;
+        assert_eq!((true, 2), r.verify_partial(&shards).unwrap());
+    }
+    {
+        // missing data shards are recovered for the check
+        let mut shards = shards_to_option_shards(&shards);
+        shards[0] = None;
+        shards[2] = None;
+        shards[6] = None;
+        assert_eq!((true, 1), r.verify_partial(&shards).unwrap());
+    }
+    {
+        let mut shards = shards_to_option_shards(&shards);
+        shards[1] = None;
+        shards[4] = None;
+        shards[5] = None;
+        shards[6] = None;
+        assert_eq!((true, 0), r.verify_partial(&shards).unwrap());
+    }
+    {
+        let mut shards = shards_to_option_shards(&shards);
+        shards[2] = None;
+        shards[6] = None;
+        shards[7].as_mut().unwrap()[50] ^= 1;
+        assert_eq!((false, 2), r.verify_partial(&shards).unwrap());
+    }
+    {
+        let mut shards = shards_to_option_shards(&shards);
+        shards[0].as_mut().unwrap()[0] ^= 1;
+        shards[4] = None;
+        assert_eq!((false, 3), r.verify_partial(&shards).unwrap());
+    }
+}
+
+#[test]
+fn test_verify_partial_error_handling() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let shards = make_random_shards!(10, 5);
+
+    {
+        let shards = shards_to_option_shards(&shards[..4]);
+        assert_eq!(Error::TooFewShards, r.verify_partial(&shards).unwrap_err());
+    }
+    {
+        let mut shards = shards_to_option_shards(&shards);
+        shards[0] = None;
+        shards[1] = None;
+        shards[2] = None;
+        assert_eq!(
+            Error::TooFewShardsPresent,
+            r.verify_partial(&shards).unwrap_err()
+        );
+    }
+    {
+        let mut shards = shards_to_option_shards(&shards);
+        shards[3] = Some(vec![0; 9]);
+        assert_eq!(
+            Error::IncorrectShardSize,
+            r.verify_partial(&shards).unwrap_err()
+        );
+    }
+    {
+        let mut shards = shards_to_option_shards(&shards);
+        shards[0] = Some(vec![]);
+        assert_eq!(Error::EmptyShard, r.verify_partial(&shards).unwrap_err());
+    }
+}
+
+#[test]
+fn test_encode_parity_rows() {
+    let r = ReedSolomon::new(5, 4).unwrap();
+
+    let mut shards = make_random_shards!(100, 9);
+    r.encode(&mut shards).unwrap();
+
+    let (data, parity) = shards.split_at(5);
+
+    let rows = [3, 0, 2];
+    let mut selected = make_random_shards!(100, 3);
+    r.encode_parity_rows(&rows, data, &mut selected).unwrap();
+
+    for (i, &row) in rows.iter().enumerate() {
+        assert_eq!(parity[row], selected[i]);
+    }
+
+    let mut none: [Vec<u8>; 0] = [];
+    r.encode_parity_rows(&[], data, &mut none).unwrap();
+}
+
+#[test]
+fn test_encode_parity_rows_error_handling() {
+    let r = ReedSolomon::new(5, 4).unwrap();
+
+    let data = make_random_shards!(100, 5);
+    let mut parity = make_random_shards!(100, 2);
+
+    assert_eq!(
+        Error::InvalidIndex,
+        r.encode_parity_rows(&[0, 4], &data, &mut parity)
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewParityShards,
+        r.encode_parity_rows(&[0, 1, 2], &data, &mut parity)
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyParityShards,
+        r.encode_parity_rows(&[0], &data, &mut parity).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_parity_rows(&[0, 1], &data[..4], &mut parity)
+            .unwrap_err()
+    );
+
+    let mut parity = vec![vec![0; 100], vec![0; 99]];
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.encode_parity_rows(&[0, 1], &data, &mut parity)
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_encode_extended_parity() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let wide = ReedSolomon::new(4, 10).unwrap();
+
+    let mut shards = make_random_shards!(100, 14);
+    wide.encode(&mut shards).unwrap();
+
+    let data = &shards[..4];
+
+    for i_parity in 0..10 {
+        let mut parity = vec![0u8; 100];
+        r.encode_extended_parity(i_parity, data, &mut parity)
+            .unwrap();
+        assert_eq!(shards[4 + i_parity], parity);
+    }
+
+    // receiver only got two data shards and two of the extended parity shards
+    let mut received: Vec<_> = shards.iter().cloned().map(Some).collect();
+    for i in [0, 2, 4, 5, 6, 7, 10, 11, 12, 13].iter() {
+        received[*i] = None;
+    }
+    let receiver = ReedSolomon::new(4, 6).unwrap();
+    let mut received = received[..10].to_vec();
+    receiver.reconstruct_data(&mut received).unwrap();
+    for i in 0..4 {
+        assert_eq!(Some(&shards[i]), received[i].as_ref());
+    }
+}
+
+#[test]
+fn test_encode_extended_parity_error_handling() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    let data = make_random_shards!(100, 4);
+    let mut parity = vec![0u8; 100];
+
+    r.encode_extended_parity(251, &data, &mut parity).unwrap();
+    assert_eq!(
+        Error::InvalidIndex,
+        r.encode_extended_parity(252, &data, &mut parity)
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_extended_parity(2, &data[..3], &mut parity)
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.encode_extended_parity(2, &data, &mut parity[..99])
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_cauchy_encode_verify_reconstruct() {
+    let r = ReedSolomon::new_cauchy(8, 5).unwrap();
+
+    let mut shards = make_random_shards!(100, 13);
+    r.encode(&mut shards).unwrap();
+    assert!(r.verify(&shards).unwrap());
+
+    let mut damaged = shards_to_option_shards(&shards);
+    for &i in [0, 3, 7, 9, 12].iter() {
+        damaged[i] = None;
+    }
+    r.reconstruct(&mut damaged).unwrap();
+    assert_eq!(shards, option_shards_into_shards(damaged));
+
+    let c = r.clone();
+    assert_eq!(crate::MatrixKind::Cauchy, c.matrix_kind());
+    assert!(c.verify(&shards).unwrap());
+
+    // extended parity rows follow the Cauchy construction too
+    let wide = ReedSolomon::new_cauchy(8, 7).unwrap();
+    let mut wide_shards = make_random_shards!(10, 15);
+    wide.encode(&mut wide_shards).unwrap();
+    let mut parity = vec![0u8; 10];
+    r.encode_extended_parity(6, &wide_shards[..8], &mut parity)
+        .unwrap();
+    assert_eq!(wide_shards[14], parity);
+}
+
+#[test]
+fn test_encode_owned() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 13);
+    r.encode(&mut shards).unwrap();
+
+    let parity = r.encode_owned(&shards[..10]).unwrap();
+    assert_eq!(&shards[10..], &parity[..]);
+
+    let mut appended = shards[..10].to_vec();
+    r.encode_append(&mut appended).unwrap();
+    assert_eq!(shards, appended);
+}
+
+#[test]
+fn test_encode_owned_error_handling() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 10);
+
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_owned(&shards[..9]).unwrap_err()
+    );
+
+    shards[3].pop();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.encode_owned(&shards).unwrap_err()
+    );
+
+    let before = shards.clone();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.encode_append(&mut shards).unwrap_err()
+    );
+    assert_eq!(before, shards);
+
+    shards.push(vec![0; 100]);
+    assert_eq!(
+        Error::TooManyDataShards,
+        r.encode_append(&mut shards).unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_map() {
+    use std::collections::HashMap;
+
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut map: HashMap<usize, Vec<u8>> = shards.iter().cloned().enumerate().collect();
+    map.remove(&0);
+    map.remove(&4);
+    map.remove(&6);
+
+    r.reconstruct_data_map(&mut map).unwrap();
+    assert_eq!(7, map.len());
+    assert_eq!(shards[0], map[&0]);
+    assert_eq!(shards[4], map[&4]);
+    assert!(!map.contains_key(&6));
+
+    r.reconstruct_map(&mut map).unwrap();
+    assert_eq!(8, map.len());
+    for (i, shard) in shards.iter().enumerate() {
+        assert_eq!(shard, &map[&i]);
+    }
+}
+
+#[test]
+fn test_reconstruct_map_error_handling() {
+    use std::collections::HashMap;
+
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut map: HashMap<usize, Vec<u8>> = shards.iter().cloned().enumerate().take(4).collect();
+    let before = map.clone();
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_map(&mut map).unwrap_err()
+    );
+    assert_eq!(before, map);
+
+    map.insert(5, vec![0; 99]);
+    let before = map.clone();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_map(&mut map).unwrap_err()
+    );
+    assert_eq!(before, map);
+
+    map.insert(8, vec![0; 100]);
+    assert_eq!(
+        Error::InvalidIndex,
+        r.reconstruct_data_map(&mut map).unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_cow_shards() {
+    use std::borrow::Cow;
+
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut cow_shards: Vec<_> = shards.iter().map(|s| CowShard::borrowed(s)).collect();
+    cow_shards[1] = CowShard::missing();
+    cow_shards[6] = CowShard::missing();
+
+    r.reconstruct_data(&mut cow_shards).unwrap();
+    assert_eq!(CowShard::missing(), cow_shards[6]);
+
+    r.reconstruct(&mut cow_shards).unwrap();
+
+    for (i, shard) in cow_shards.into_iter().enumerate() {
+        match shard.into_inner() {
+            Some(Cow::Borrowed(x)) => {
+                assert!(i != 1 && i != 6);
+                assert_eq!(&shards[i][..], x);
+            }
+            Some(Cow::Owned(x)) => {
+                assert!(i == 1 || i == 6);
+                assert_eq!(shards[i], x);
+            }
+            None => panic!("shard {} not reconstructed", i),
+        }
+    }
+}
+
+#[test]
+fn test_reconstruct_alloc() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut damaged: Vec<_> = shards.iter().cloned().map(Some).collect();
+    damaged[0] = None;
+    damaged[3] = None;
+    damaged[7] = None;
+
+    let mut lens = Vec::new();
+    r.reconstruct_data_alloc(&mut damaged, |len| {
+        lens.push(len);
+        vec![0xFFu8; len]
+    })
+    .unwrap();
+    assert_eq!(vec![100, 100], lens);
+    assert_eq!(None, damaged[7]);
+
+    r.reconstruct_alloc(&mut damaged, |len| vec![0xFFu8; len])
+        .unwrap();
+    let damaged: Vec<_> = damaged.into_iter().map(Option::unwrap).collect();
+    assert_eq!(shards, damaged);
+}
+
+#[test]
+fn test_reconstruct_alloc_error_handling() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut damaged: Vec<_> = shards.iter().cloned().map(Some).collect();
+    damaged[0] = None;
+    damaged[6] = None;
+    let expect = damaged.clone();
+
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_alloc(&mut damaged, |len| vec![0u8; len - 1])
+            .unwrap_err()
+    );
+    assert_eq!(expect, damaged);
+
+    damaged[1] = None;
+    damaged[2] = None;
+    damaged[3] = None;
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_alloc(&mut damaged, |_| -> Vec<u8> {
+            panic!("nothing is allocated")
+        })
+        .unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_data_sep() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut surviving: Vec<_> = shards.iter().map(|s| Some(&s[..])).collect();
+    surviving[0] = None;
+    surviving[3] = None;
+    surviving[6] = None;
+
+    let mut outputs = vec![vec![0xFFu8; 100]; 2];
+    r.reconstruct_data_sep(&surviving, &mut outputs).unwrap();
+    assert_eq!(shards[0], outputs[0]);
+    assert_eq!(shards[3], outputs[1]);
+
+    // nothing to do with all data shards present
+    let all_data: Vec<_> = shards
+        .iter()
+        .take(5)
+        .map(|s| Some(&s[..]))
+        .chain(vec![None; 3])
+        .collect();
+    r.reconstruct_data_sep(&all_data, &mut Vec::<Vec<u8>>::new())
+        .unwrap();
+}
+
+#[test]
+fn test_reconstruct_data_sep_error_handling() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut surviving: Vec<_> = shards.iter().map(|s| Some(&s[..])).collect();
+    surviving[1] = None;
+
+    assert_eq!(
+        Error::TooFewBufferShards,
+        r.reconstruct_data_sep(&surviving, &mut Vec::<Vec<u8>>::new())
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyBufferShards,
+        r.reconstruct_data_sep(&surviving, &mut vec![vec![0u8; 100]; 2])
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_data_sep(&surviving, &mut [vec![0u8; 99]])
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShards,
+        r.reconstruct_data_sep(&surviving[..7], &mut [vec![0u8; 100]])
+            .unwrap_err()
+    );
+
+    surviving[2] = None;
+    surviving[3] = None;
+    surviving[4] = None;
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_data_sep(&surviving, &mut vec![vec![0u8; 100]; 4])
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_verified() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut lost: Vec<_> = shards.iter().cloned().map(Some).collect();
+    lost[1] = None;
+    lost[6] = None;
+    r.reconstruct_verified(&mut lost).unwrap();
+    let lost: Vec<_> = lost.into_iter().map(Option::unwrap).collect();
+    assert_eq!(shards, lost);
+
+    // a corrupt shard used by the reconstruction
+    let mut corrupt: Vec<_> = shards.iter().cloned().map(Some).collect();
+    corrupt[0].as_mut().unwrap()[7] ^= 1;
+    corrupt[1] = None;
+    assert_eq!(
+        Error::InconsistentShards,
+        r.reconstruct_verified(&mut corrupt).unwrap_err()
+    );
+    let mut corrupt: Vec<_> = shards.iter().cloned().map(Some).collect();
+    corrupt[0].as_mut().unwrap()[7] ^= 1;
+    corrupt[1] = None;
+    r.reconstruct(&mut corrupt).unwrap();
+    assert!(corrupt[1].as_ref().unwrap() != &shards[1]);
+
+    // nothing to check against
+    corrupt[1] = None;
+    corrupt[5] = None;
+    corrupt[6] = None;
+    r.reconstruct_verified(&mut corrupt).unwrap();
+
+    let mut too_few: Vec<Option<Vec<u8>>> = vec![None; 8];
+    too_few[0] = Some(shards[0].clone());
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_verified(&mut too_few).unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_into() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    for missing in [vec![0, 3, 6], vec![5, 7], vec![1, 2, 4], vec![2]].iter() {
+        let surviving: Vec<_> = shards
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                if missing.contains(&i) {
+                    None
+                } else {
+                    Some(&s[..])
+                }
+            })
+            .collect();
+
+        let mut outputs = vec![vec![0xFFu8; 100]; missing.len()];
+        r.reconstruct_into(&surviving, &mut outputs).unwrap();
+        for (&i, output) in missing.iter().zip(outputs.iter()) {
+            assert_eq!(shards[i], *output);
+        }
+    }
+
+    let surviving: Vec<_> = shards.iter().map(|s| Some(&s[..])).collect();
+    r.reconstruct_into(&surviving, &mut Vec::<Vec<u8>>::new())
+        .unwrap();
+
+    let mut surviving = surviving;
+    surviving[1] = None;
+    surviving[6] = None;
+    assert_eq!(
+        Error::TooFewBufferShards,
+        r.reconstruct_into(&surviving, &mut [vec![0u8; 100]])
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyBufferShards,
+        r.reconstruct_into(&surviving, &mut vec![vec![0u8; 100]; 3])
+            .unwrap_err()
+    );
+    let mut outputs = vec![vec![0u8; 100], vec![0u8; 99]];
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_into(&surviving, &mut outputs).unwrap_err()
+    );
+    assert!(outputs[0].iter().all(|&x| x == 0));
+}
+
+#[test]
+fn test_reconstruct_data_gather() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+    let data = shards[..5].concat();
+
+    let mut surviving: Vec<_> = shards.iter().map(|s| Some(&s[..])).collect();
+    surviving[1] = None;
+    surviving[4] = None;
+    surviving[6] = None;
+
+    // the original length, as much as whole shards, or cut within a
+    // missing or a present shard
+    for &len in [500, 450, 350, 199, 100, 0].iter() {
+        let mut output = vec![0xFFu8; len];
+        r.reconstruct_data_gather(&surviving, &mut output).unwrap();
+        assert_eq!(&data[..len], &output[..]);
+    }
+
+    let all: Vec<_> = shards.iter().map(|s| Some(&s[..])).collect();
+    let mut output = vec![0u8; 500];
+    r.reconstruct_data_gather(&all, &mut output).unwrap();
+    assert_eq!(data, output);
+
+    let mut output = vec![0u8; 501];
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_data_gather(&surviving, &mut output)
+            .unwrap_err()
+    );
+    surviving[0] = None;
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_data_gather(&surviving, &mut output[..500])
+            .unwrap_err()
+    );
+    assert!(output.iter().all(|&x| x == 0));
+}
+
+#[test]
+fn test_reconstruct_indexed() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let pairs = vec![
+        (7, shards[7].clone()),
+        (2, shards[2].clone()),
+        (0, shards[0].clone()),
+        (5, shards[5].clone()),
+        (3, shards[3].clone()),
+    ];
+    assert_eq!(shards, r.reconstruct_indexed(pairs).unwrap());
+
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_indexed(vec![(1, shards[1].clone())])
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::InvalidIndex,
+        r.reconstruct_indexed(vec![(8, shards[1].clone())])
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_present() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut damaged = shards.clone();
+    let mut present = crate::ShardSet::full(8);
+    for &i in [1, 4, 7].iter() {
+        fill_random(&mut damaged[i]);
+        present.remove(i);
+    }
+
+    let mut data_only = damaged.clone();
+    r.reconstruct_data_present(&mut data_only, &present)
+        .unwrap();
+    assert_eq!(&shards[..5], &data_only[..5]);
+    assert_eq!(damaged[7], data_only[7]);
+
+    r.reconstruct_present(&mut damaged, &present).unwrap();
+    assert_eq!(shards, damaged);
+}
+
+#[test]
+fn test_reconstruct_present_error_handling() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+
+    assert_eq!(
+        Error::InvalidShardFlags,
+        r.reconstruct_present(&mut shards, &crate::ShardSet::full(7))
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShards,
+        r.reconstruct_present(&mut shards[..7], &crate::ShardSet::full(7))
+            .unwrap_err()
+    );
+
+    let mut present = crate::ShardSet::full(8);
+    for i in 0..4 {
+        present.remove(i);
+    }
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_present(&mut shards, &present).unwrap_err()
+    );
+
+    shards[0].pop();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_present(&mut shards, &crate::ShardSet::new(8).complement())
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_flags() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut damaged = shards.clone();
+    let mut present = [true; 8];
+    for &i in [0, 2, 6].iter() {
+        fill_random(&mut damaged[i]);
+        present[i] = false;
+    }
+
+    {
+        let mut data_only: Vec<&mut [u8]> = damaged.iter_mut().map(|x| &mut x[..]).collect();
+        r.reconstruct_data_flags(&mut data_only, &present).unwrap();
+    }
+    assert_eq!(&shards[..5], &damaged[..5]);
+    assert_ne!(shards[6], damaged[6]);
+
+    r.reconstruct_flags(&mut damaged, &present).unwrap();
+    assert_eq!(shards, damaged);
+}
+
+#[test]
+fn test_reconstruct_flags_error_handling() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+
+    assert_eq!(
+        Error::InvalidShardFlags,
+        r.reconstruct_flags(&mut shards, &[true; 7]).unwrap_err()
+    );
+    assert_eq!(
+        Error::InvalidShardFlags,
+        r.reconstruct_data_flags(&mut shards, &[true; 9])
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShards,
+        r.reconstruct_flags(&mut shards[..7], &[true; 8])
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_flags(
+            &mut shards,
+            &[false, true, false, true, false, true, false, true]
+        )
+        .unwrap_err()
+    );
+
+    shards[7].push(0);
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_flags(&mut shards, &[true; 8]).unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_lazy() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 7);
+    r.encode(&mut shards).unwrap();
+
+    // intact data shards are all that is requested
+    let mut requested = Vec::new();
+    let data = r
+        .reconstruct_data_lazy(|i| {
+            requested.push(i);
+            Some(&shards[i][..])
+        })
+        .unwrap();
+    assert_eq!(&shards[..4], &data[..]);
+    assert_eq!(vec![0, 1, 2, 3], requested);
+
+    // unavailable and malformed shards are replaced by the next ones
+    let mut requested = Vec::new();
+    let all = r
+        .reconstruct_lazy(|i| {
+            requested.push(i);
+            match i {
+                1 => None,
+                2 => Some(vec![0u8; 99]),
+                3 => Some(Vec::new()),
+                _ => Some(shards[i].clone()),
+            }
+        })
+        .unwrap();
+    assert_eq!(shards, all);
+    assert_eq!(vec![0, 1, 2, 3, 4, 5, 6], requested);
+
+    let mut requested = Vec::new();
+    r.reconstruct_data_lazy(|i| {
+        requested.push(i);
+        if i == 0 {
+            None
+        } else {
+            Some(&shards[i][..])
+        }
+    })
+    .unwrap();
+    assert_eq!(vec![0, 1, 2, 3, 4], requested);
+}
+
+#[test]
+fn test_reconstruct_lazy_error_handling() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 7);
+    r.encode(&mut shards).unwrap();
+
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_lazy(|i| if i % 3 == 0 {
+            Some(&shards[i][..])
+        } else {
+            None
+        })
+        .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_data_lazy(|_| None::<Vec<u8>>).unwrap_err()
+    );
+}
+
+#[test]
+fn test_encode_hashed() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    let r = ReedSolomon::new(6, 3).unwrap();
+
+    for &len in [1, 100, 4096, 10000].iter() {
+        let mut expect = make_random_shards!(len, 9);
+        r.encode(&mut expect).unwrap();
+
+        let mut shards = expect.clone();
+        for shard in shards[6..].iter_mut() {
+            fill_random(shard);
+        }
+
+        let mut seen = vec![Vec::new(); 9];
+        r.encode_hashed(&mut shards, |i, chunk| seen[i].extend_from_slice(chunk))
+            .unwrap();
+        assert_eq!(expect, shards);
+        assert_eq!(expect, seen);
+
+        let mut hashers = vec![DefaultHasher::new(); 9];
+        {
+            let (data, parity) = shards.split_at_mut(6);
+            r.encode_sep_hashed(data, parity, |i, chunk| hashers[i].write(chunk))
+                .unwrap();
+        }
+        assert_eq!(expect, shards);
+        for (shard, hasher) in expect.iter().zip(hashers.iter()) {
+            let mut whole = DefaultHasher::new();
+            whole.write(shard);
+            assert_eq!(whole.finish(), hasher.finish());
+        }
+    }
+}
+
+#[test]
+fn test_encode_hashed_error_handling() {
+    let r = ReedSolomon::new(6, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 9);
+
+    assert_eq!(
+        Error::TooFewShards,
+        r.encode_hashed(&mut shards[..8], |_, _| ()).unwrap_err()
+    );
+
+    let (data, parity) = shards.split_at_mut(6);
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_sep_hashed(&data[..5], parity, |_, _| ())
+            .unwrap_err()
+    );
+    parity[1].pop();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.encode_sep_hashed(data, parity, |_, _| ()).unwrap_err()
+    );
+}
+
+#[test]
+fn test_encode_sep_uninit() {
+    use std::mem::MaybeUninit;
+
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut expect = make_random_shards!(100, 13);
+    r.encode(&mut expect).unwrap();
+
+    let mut parity = vec![vec![MaybeUninit::<u8>::uninit(); 100]; 3];
+    let encoded = r.encode_sep_uninit(&expect[..10], &mut parity).unwrap();
+
+    assert_eq!(3, encoded.len());
+    for (shard, expect) in encoded.iter().zip(expect[10..].iter()) {
+        assert_eq!(&shard[..], &expect[..]);
+    }
+}
+
+#[test]
+fn test_encode_sep_uninit_error_handling() {
+    use std::mem::MaybeUninit;
+
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let data = make_random_shards!(100, 10);
+
+    let mut parity = vec![vec![MaybeUninit::<u8>::uninit(); 100]; 2];
+    assert_eq!(
+        Error::TooFewParityShards,
+        r.encode_sep_uninit(&data, &mut parity).unwrap_err()
+    );
+
+    let mut parity = vec![vec![MaybeUninit::<u8>::uninit(); 100]; 3];
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_sep_uninit(&data[..9], &mut parity).unwrap_err()
+    );
+
+    parity[2].pop();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.encode_sep_uninit(&data, &mut parity).unwrap_err()
+    );
+}
+
+#[test]
+fn test_verify_reuses_scratch_across_shard_sizes() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    // the pooled buffers must be resized and cleared between calls
+    for &size in [100, 10, 1000, 1, 100].iter() {
+        let mut shards = make_random_shards!(size, 8);
+        r.encode(&mut shards).unwrap();
+        assert!(r.verify(&shards).unwrap());
+
+        {
+            let mut shards = shards_to_option_shards(&shards);
+            shards[1] = None;
+            shards[3] = None;
+            assert_eq!((true, 1), r.verify_partial(&shards).unwrap());
+        }
+
+        shards[6][size - 1] ^= 1;
+        assert!(!r.verify(&shards).unwrap());
+        {
+            let mut shards = shards_to_option_shards(&shards);
+            shards[1] = None;
+            shards[3] = None;
+            assert_eq!((false, 1), r.verify_partial(&shards).unwrap());
+        }
+    }
+
+    // clones start with their own pool
+    let mut shards = make_random_shards!(50, 8);
+    r.clone().encode(&mut shards).unwrap();
+    assert!(r.clone().verify(&shards).unwrap());
+}
+
+#[test]
+fn test_verify_concurrently() {
+    let r = std::sync::Arc::new(ReedSolomon::new(4, 2).unwrap());
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let r = r.clone();
+            std::thread::spawn(move || {
+                for size in 1..50 {
+                    let mut shards = make_random_shards!(size, 6);
+                    r.encode(&mut shards).unwrap();
+                    assert!(r.verify(&shards).unwrap());
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn test_encode_par_matches_encode() {
+    let r = ReedSolomon::new(10, 6).unwrap();
+
+    for &size in [1, 100, 1000, 4097].iter() {
+        let mut expect = make_random_shards!(size, 16);
+        r.encode(&mut expect).unwrap();
+
+        for &(bytes_per_encode, thread_count, chunk_assignment) in [
+            (1, 1, ChunkAssignment::Interleaved),
+            (7, 3, ChunkAssignment::Interleaved),
+            (64, 16, ChunkAssignment::Interleaved),
+            (1000, 2, ChunkAssignment::Interleaved),
+            (32768, 8, ChunkAssignment::Interleaved),
+            (0, 0, ChunkAssignment::Interleaved),
+            (1, 1, ChunkAssignment::ByteRange),
+            (7, 3, ChunkAssignment::ByteRange),
+            (64, 16, ChunkAssignment::ByteRange),
+            (0, 0, ChunkAssignment::ByteRange),
+        ]
+        .iter()
+        {
+            let param = ParallelParam {
+                bytes_per_encode,
+                thread_count,
+                chunk_assignment,
+                min_bytes_per_thread: 1,
+                cache_chunk_len: 0,
+                thread_source: ThreadSource::Scoped,
+            };
+
+            let mut shards = expect.clone();
+            for shard in shards.iter_mut().skip(10) {
+                fill_random(shard);
+            }
+            r.encode_par(&mut shards, param).unwrap();
+            assert_eq!(expect, shards);
+
+            let mut parity = vec![vec![0u8; size]; 6];
+            r.encode_sep_par(&expect[..10], &mut parity, param).unwrap();
+            assert_eq!(&expect[10..], &parity[..]);
+        }
+    }
+}
+
+#[test]
+fn test_encode_par_cache_chunk_len() {
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    let mut expect = make_random_shards!(1000, 14);
+    r.encode(&mut expect).unwrap();
+
+    for &cache_chunk_len in [0, 1, 7, 64, 4096].iter() {
+        let param = ParallelParam {
+            bytes_per_encode: 100,
+            thread_count: 3,
+            chunk_assignment: ChunkAssignment::Interleaved,
+            min_bytes_per_thread: 1,
+            cache_chunk_len,
+            thread_source: ThreadSource::Scoped,
+        };
+
+        let mut shards = expect.clone();
+        for shard in shards.iter_mut().skip(10) {
+            fill_random(shard);
+        }
+        r.encode_par(&mut shards, param).unwrap();
+        assert_eq!(expect, shards);
+    }
+}
+
+#[test]
+fn test_encode_par_error_handling() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+    let param = ParallelParam::default();
+
+    let mut shards = make_random_shards!(10, 4);
+    assert_eq!(
+        Error::TooFewShards,
+        r.encode_par(&mut shards, param).unwrap_err()
+    );
+
+    let mut shards = make_random_shards!(10, 5);
+    shards[4] = vec![0u8; 9];
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.encode_par(&mut shards, param).unwrap_err()
+    );
+
+    let data = make_random_shards!(10, 3);
+    let mut parity = vec![vec![0u8; 10]; 3];
+    assert_eq!(
+        Error::TooManyParityShards,
+        r.encode_sep_par(&data, &mut parity, param).unwrap_err()
+    );
+}
+
+#[test]
+fn test_wide_code_beyond_inline_capacity() {
+    let r = ReedSolomon::new(100, 40).unwrap();
+
+    let mut shards = make_random_shards!(64, 140);
+    r.encode(&mut shards).unwrap();
+    assert!(r.verify(&shards).unwrap());
+
+    let mut option_shards = shards_to_option_shards(&shards);
+    for i in (0..140).step_by(4) {
+        option_shards[i] = None;
+    }
+    r.reconstruct(&mut option_shards).unwrap();
+    assert_eq!(shards, option_shards_into_shards(option_shards));
+}
+
+#[test]
+fn test_alloc_shards_par() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    for &chunk_assignment in [ChunkAssignment::Interleaved, ChunkAssignment::ByteRange].iter() {
+        let param = ParallelParam {
+            bytes_per_encode: 16,
+            thread_count: 3,
+            chunk_assignment,
+            min_bytes_per_thread: 1,
+            cache_chunk_len: 0,
+            thread_source: ThreadSource::Scoped,
+        };
+
+        for &size in [0, 1, 100, 1000].iter() {
+            let shards = r.alloc_shards_par(size, param);
+            assert_eq!(vec![vec![0u8; size]; 6], shards);
+        }
+
+        let mut shards = r.alloc_shards_par(1000, param);
+        for shard in shards.iter_mut().take(4) {
+            fill_random(shard);
+        }
+        r.encode_par(&mut shards, param).unwrap();
+        assert!(r.verify(&shards).unwrap());
+    }
+}
+
+#[test]
+fn test_set_pparam() {
+    let mut r = ReedSolomon::new(10, 4).unwrap();
+    assert_eq!(ParallelParam::default(), r.pparam());
+
+    let mut expect = make_random_shards!(1000, 14);
+    r.encode(&mut expect).unwrap();
+
+    let mut shards = shards_to_option_shards(&expect);
+    shards[0] = None;
+    shards[11] = None;
+    r.reconstruct(&mut shards).unwrap();
+    assert_eq!(1, r.stats().cache_misses);
+
+    let param = ParallelParam {
+        bytes_per_encode: 64,
+        thread_count: 3,
+        min_bytes_per_thread: 1,
+        thread_source: ThreadSource::Pool,
+        ..ParallelParam::default()
+    };
+    r.set_pparam(param);
+    assert_eq!(param, r.pparam());
+    assert_eq!(param, r.clone().pparam());
+
+    let mut shards = expect.clone();
+    for shard in shards.iter_mut().skip(10) {
+        fill_random(shard);
+    }
+    r.encode_par(&mut shards, None).unwrap();
+    assert_eq!(expect, shards);
+
+    // a per-call override
+    let mut parity = vec![vec![0u8; 1000]; 4];
+    r.encode_sep_par(&expect[..10], &mut parity, ParallelParam::new(100))
+        .unwrap();
+    assert_eq!(&expect[10..], &parity[..]);
+
+    let reports = r
+        .verify_batch(&[&expect[..]], VerifyPolicy::ShortCircuit, None)
+        .unwrap();
+    assert!(reports[0].valid);
+    assert_eq!(vec![vec![0u8; 10]; 14], r.alloc_shards_par(10, None));
+
+    // the decode matrix cached before is still there
+    let mut shards = shards_to_option_shards(&expect);
+    shards[0] = None;
+    shards[11] = None;
+    r.reconstruct(&mut shards).unwrap();
+    assert_eq!(1, r.stats().cache_misses);
+    assert_eq!(1, r.stats().cache_hits);
+}
+
+#[test]
+fn test_encode_par_thread_pool() {
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    let mut expect = make_random_shards!(1000, 14);
+    r.encode(&mut expect).unwrap();
+
+    for &chunk_assignment in [ChunkAssignment::Interleaved, ChunkAssignment::ByteRange].iter() {
+        let param = ParallelParam {
+            bytes_per_encode: 64,
+            thread_count: 4,
+            chunk_assignment,
+            min_bytes_per_thread: 1,
+            cache_chunk_len: 0,
+            thread_source: ThreadSource::Pool,
+        };
+
+        let mut shards = r.alloc_shards_par(1000, param);
+        assert_eq!(vec![vec![0u8; 1000]; 14], shards);
+        shards[..10].clone_from_slice(&expect[..10]);
+        r.encode_par(&mut shards, param).unwrap();
+        assert_eq!(expect, shards);
+
+        // calls from several threads share the pool
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..20 {
+                        let mut parity = vec![vec![0u8; 1000]; 4];
+                        r.encode_sep_par(&expect[..10], &mut parity, param).unwrap();
+                        assert_eq!(&expect[10..], &parity[..]);
+                    }
+                });
+            }
+        });
+    }
+}
+
+#[test]
+fn test_parallel_param_adapted() {
+    let param = ParallelParam::new(32768)
+        .with_thread_count(8)
+        .with_chunk_assignment(ChunkAssignment::Interleaved)
+        .with_min_bytes_per_thread(65536)
+        .with_cache_chunk_len(4096)
+        .with_thread_source(ThreadSource::Scoped);
+
+    // a small packet stays on the calling thread
+    let adapted = param.adapted(4, 1500);
+    assert_eq!(1, adapted.thread_count);
+    assert_eq!(32768, adapted.bytes_per_encode);
+
+    // threads are limited by the amount of work
+    let adapted = param.adapted(4, 65536);
+    assert_eq!(4, adapted.thread_count);
+    assert_eq!(32768, adapted.bytes_per_encode);
+
+    // pieces shrink so that every thread gets some
+    let large_pieces = ParallelParam {
+        bytes_per_encode: 1 << 20,
+        ..param
+    };
+    let adapted = large_pieces.adapted(1, 1 << 20);
+    assert_eq!(8, adapted.thread_count);
+    assert_eq!((1 << 20) / 16, adapted.bytes_per_encode);
+
+    // large work keeps the configured pieces
+    let adapted = param.adapted(16, 1 << 20);
+    assert_eq!(8, adapted.thread_count);
+    assert_eq!(32768, adapted.bytes_per_encode);
+}
+
+#[test]
+fn test_reconstruct_with_scratch() {
+    let r = ReedSolomon::new(8, 4).unwrap();
+    let mut scratch = galois_8::ReconstructScratch::new();
+
+    let mut shards = make_random_shards!(100, 12);
+    r.encode(&mut shards).unwrap();
+
+    // alternate between erasure patterns, and repeat each one
+    for &missing in [[0, 5, 9], [0, 5, 9], [1, 2, 11], [0, 5, 9], [8, 9, 10]].iter() {
+        let mut option_shards = shards_to_option_shards(&shards);
+        for &i in missing.iter() {
+            option_shards[i] = None;
+        }
+        r.reconstruct_with_scratch(&mut option_shards, &mut scratch)
+            .unwrap();
+        assert_eq!(shards, option_shards_into_shards(option_shards));
+
+        let mut option_shards = shards_to_option_shards(&shards);
+        for &i in missing.iter() {
+            option_shards[i] = None;
+        }
+        r.reconstruct_data_with_scratch(&mut option_shards, &mut scratch)
+            .unwrap();
+        for i in 0..8 {
+            assert_eq!(Some(&shards[i]), option_shards[i].as_ref());
+        }
+        for &i in missing.iter().filter(|&&i| i >= 8) {
+            assert_eq!(None, option_shards[i]);
+        }
+    }
+
+    // the same workspace with another codec of the same shape
+    let r2 = ReedSolomon::new_cauchy(8, 4).unwrap();
+    let mut shards2 = make_random_shards!(100, 12);
+    r2.encode(&mut shards2).unwrap();
+
+    let mut option_shards = shards_to_option_shards(&shards2);
+    option_shards[8] = None;
+    option_shards[9] = None;
+    option_shards[10] = None;
+    r2.reconstruct_with_scratch(&mut option_shards, &mut scratch)
+        .unwrap();
+    assert_eq!(shards2, option_shards_into_shards(option_shards));
+
+    // errors leave the shards untouched
+    let mut option_shards = shards_to_option_shards(&shards);
+    for shard in option_shards.iter_mut().take(5) {
+        *shard = None;
+    }
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_with_scratch(&mut option_shards, &mut scratch)
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShards,
+        r.reconstruct_with_scratch(&mut option_shards[..11], &mut scratch)
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_stats() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    assert_eq!(CodecStats::default(), r.stats());
+
+    let mut shards = make_random_shards!(100, 6);
+    r.encode(&mut shards).unwrap();
+    r.encode_par(&mut shards, ParallelParam::new(10)).unwrap();
+    assert_eq!(800, r.stats().bytes_encoded);
+
+    assert!(r.verify(&shards).unwrap());
+    let option_shards = shards_to_option_shards(&shards);
+    assert_eq!((true, 2), r.verify_partial(&option_shards).unwrap());
+    assert_eq!(2, r.stats().stripes_verified);
+
+    // nothing to reconstruct
+    let mut option_shards = shards_to_option_shards(&shards);
+    r.reconstruct(&mut option_shards).unwrap();
+    assert_eq!(0, r.stats().bytes_reconstructed);
+
+    option_shards[1] = None;
+    option_shards[5] = None;
+    r.reconstruct(&mut option_shards).unwrap();
+    assert_eq!(200, r.stats().bytes_reconstructed);
+
+    option_shards[1] = None;
+    option_shards[5] = None;
+    r.reconstruct_data(&mut option_shards).unwrap();
+    assert_eq!(300, r.stats().bytes_reconstructed);
+
+    // verify_partial looked up the identity of the pattern without missing shards
+    let stats = r.stats();
+    assert_eq!(1, stats.cache_misses);
+    assert_eq!(2, stats.cache_hits);
+
+    let mut scratch = galois_8::ReconstructScratch::new();
+    for _ in 0..2 {
+        option_shards[1] = None;
+        option_shards[5] = None;
+        r.reconstruct_with_scratch(&mut option_shards, &mut scratch)
+            .unwrap();
+    }
+    // from the tree, then from the workspace
+    assert_eq!(1, r.stats().cache_misses);
+    assert_eq!(4, r.stats().cache_hits);
+
+    // a clone has its own counters
+    let r2 = r.clone();
+    r2.encode(&mut shards).unwrap();
+    assert_eq!(800, r.stats().bytes_encoded);
+    assert_eq!(400, r2.stats().bytes_encoded);
+
+    r.reset_stats();
+    assert_eq!(CodecStats::default(), r.stats());
+}
+
+#[test]
+fn test_encode_sparse() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    for &i in [1, 3].iter() {
+        shards[i] = vec![0; 100];
+    }
+    let mut expect = shards.clone();
+    r.encode(&mut expect).unwrap();
+
+    fill_random(&mut shards[6]);
+    r.encode_sparse(&mut shards).unwrap();
+    assert_eq!(expect, shards);
+
+    // all zeros
+    let mut shards = vec![vec![1; 10]; 8];
+    for shard in shards.iter_mut().take(5) {
+        *shard = vec![0; 10];
+    }
+    r.encode_sparse(&mut shards).unwrap();
+    assert_eq!(vec![vec![0; 10]; 8], shards);
+}
+
+#[test]
+fn test_encode_sep_skipping() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    let mut shards = make_random_shards!(50, 6);
+    shards[2] = vec![0; 50];
+    r.encode(&mut shards).unwrap();
+
+    // the hinted shard is not read
+    let mut data = shards[..4].to_vec();
+    fill_random(&mut data[2]);
+    let mut parity = vec![vec![0; 50]; 2];
+    let mut zero = crate::ShardSet::new(4);
+    zero.insert(2);
+    r.encode_sep_skipping(&data, &mut parity, &zero).unwrap();
+    assert_eq!(&shards[4..], &parity[..]);
+
+    assert_eq!(
+        Error::InvalidShardFlags,
+        r.encode_sep_skipping(&data, &mut parity, &crate::ShardSet::new(6))
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_data_decode_matrix_same_as_inverse() {
+    for r in [
+        ReedSolomon::new(10, 4).unwrap(),
+        ReedSolomon::new_cauchy(10, 4).unwrap(),
+    ]
+    .iter()
+    {
+        for _ in 0..50 {
+            let mut invalid: Vec<usize> = (0..14).filter(|_| rand::random::<bool>()).collect();
+            invalid.truncate(4);
+            let valid: Vec<usize> = (0..14).filter(|i| !invalid.contains(i)).take(10).collect();
+
+            let mut sub_matrix = crate::matrix::Matrix::new(10, 10);
+            for (sub_row, &valid_row) in valid.iter().enumerate() {
+                for c in 0..10 {
+                    sub_matrix.set(sub_row, c, r.matrix.get(valid_row, c));
+                }
+            }
+
+            assert_eq!(
+                sub_matrix.invert().unwrap(),
+                *r.get_data_decode_matrix(&valid, &invalid)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_verify_with_policy() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    for &policy in [VerifyPolicy::ShortCircuit, VerifyPolicy::ConstantTime].iter() {
+        let report = r.verify_with_policy(&shards, policy).unwrap();
+        assert!(report.valid);
+        assert_eq!(
+            policy == VerifyPolicy::ConstantTime,
+            report.mismatched == Some(0)
+        );
+    }
+
+    shards[5][0] ^= 1;
+    shards[7][10] ^= 1;
+    shards[7][99] ^= 0xff;
+    let report = r
+        .verify_with_policy(&shards, VerifyPolicy::ShortCircuit)
+        .unwrap();
+    assert!(!report.valid);
+    assert_eq!(None, report.mismatched);
+    let report = r
+        .verify_with_policy(&shards, VerifyPolicy::ConstantTime)
+        .unwrap();
+    assert!(!report.valid);
+    assert_eq!(Some(3), report.mismatched);
+
+    // a corrupted data shard shows up in every parity shard
+    r.encode(&mut shards).unwrap();
+    shards[0][0] ^= 1;
+    let report = r
+        .verify_with_policy(&shards, VerifyPolicy::ConstantTime)
+        .unwrap();
+    assert_eq!(Some(3), report.mismatched);
+
+    assert_eq!(
+        Error::TooFewShards,
+        r.verify_with_policy(&shards[..7], VerifyPolicy::ConstantTime)
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_verify_batch() {
+    let r = ReedSolomon::new(6, 3).unwrap();
+
+    let mut stripes: Vec<Vec<Vec<u8>>> = (0..50)
+        .map(|i| {
+            let mut shards = make_random_shards!(100 + i * 7, 9);
+            r.encode(&mut shards).unwrap();
+            shards
+        })
+        .collect();
+    stripes[3][7][0] ^= 1;
+    stripes[20][0][5] ^= 2;
+    stripes[20][8][9] ^= 2;
+    stripes[49][2][100] ^= 0xff;
+
+    for &policy in [
+        VerifyPolicy::ShortCircuit,
+        VerifyPolicy::ConstantTime,
+        VerifyPolicy::Probabilistic { rounds: 8 },
+    ]
+    .iter()
+    {
+        for &(thread_count, thread_source) in [
+            (1, ThreadSource::Scoped),
+            (4, ThreadSource::Scoped),
+            (4, ThreadSource::Pool),
+            (100, ThreadSource::Pool),
+        ]
+        .iter()
+        {
+            let param = ParallelParam {
+                thread_count,
+                min_bytes_per_thread: 1,
+                thread_source,
+                ..ParallelParam::default()
+            };
+            let reports = r.verify_batch(&stripes, policy, param).unwrap();
+            assert_eq!(50, reports.len());
+            for (stripe, report) in stripes.iter().zip(reports.iter()) {
+                assert_eq!(
+                    r.verify_with_policy(stripe, policy).unwrap().valid,
+                    report.valid
+                );
+                if policy == VerifyPolicy::ConstantTime {
+                    assert_eq!(r.verify_with_policy(stripe, policy).unwrap(), *report);
+                }
+            }
+            let failed: Vec<usize> = (0..50).filter(|&i| !reports[i].valid).collect();
+            assert_eq!(vec![3, 20, 49], failed);
+        }
+    }
+
+    let empty: [Vec<Vec<u8>>; 0] = [];
+    let reports = r
+        .verify_batch(&empty, VerifyPolicy::ShortCircuit, ParallelParam::default())
+        .unwrap();
+    assert!(reports.is_empty());
+
+    stripes[30].pop();
+    assert_eq!(
+        Error::TooFewShards,
+        r.verify_batch(
+            &stripes,
+            VerifyPolicy::ShortCircuit,
+            ParallelParam::default()
+        )
+        .unwrap_err()
+    );
+}
+
+#[test]
+fn test_verify_probabilistic() {
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    let mut shards = make_random_shards!(1000, 14);
+    r.encode(&mut shards).unwrap();
+
+    for &rounds in [0, 1, 3, 8].iter() {
+        assert!(r.verify_probabilistic(&shards, rounds).unwrap());
+    }
+    let report = r
+        .verify_with_policy(&shards, VerifyPolicy::Probabilistic { rounds: 2 })
+        .unwrap();
+    assert!(report.valid);
+    assert_eq!(None, report.mismatched);
+
+    // with 8 rounds, a false accept is below 1 in 2^64
+    for &(i, j) in [(13, 999), (10, 0), (0, 500), (9, 1)].iter() {
+        let mut corrupt = shards.clone();
+        corrupt[i][j] ^= 0x40;
+        assert!(!r.verify_probabilistic(&corrupt, 8).unwrap());
+        assert!(r.verify_probabilistic(&corrupt, 0).unwrap());
+    }
+
+    assert_eq!(1.0, r.false_accept_probability(0));
+    assert_eq!(1.0 / 256.0, r.false_accept_probability(1));
+    assert_eq!(1.0 / 65536.0 / 65536.0, r.false_accept_probability(4));
+
+    assert_eq!(
+        Error::TooFewShards,
+        r.verify_probabilistic(&shards[..13], 1).unwrap_err()
+    );
+    shards[3].pop();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.verify_probabilistic(&shards, 1).unwrap_err()
+    );
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::executor::block_on;
+use object_store::memory::InMemory;
+use object_store::path::Path;
+use object_store::{Attribute, ObjectStore, PutPayload};
+
+use super::{fill_random, ReedSolomon};
+use crate::object_shards::{ObjectShards, PutError};
+use crate::Error;
+
+fn make_stripe(r: &ReedSolomon) -> Vec<Vec<u8>> {
+    let mut shards = vec![vec![0u8; 100]; r.total_shard_count()];
+    for shard in shards.iter_mut().take(r.data_shard_count()) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+    shards
+}
+
+#[test]
+fn test_object_shards_put_and_get() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let store = Arc::new(InMemory::new());
+    let shards = ObjectShards::new(&r, store.clone(), Path::from("backups/disk0"));
+    let expect = make_stripe(&r);
+
+    block_on(shards.put_stripe(3, &expect)).unwrap();
+    assert_eq!(Path::from("backups/disk0/3/5"), shards.shard_path(3, 5));
+    let object = block_on(store.get(&shards.shard_path(3, 5))).unwrap();
+    assert_eq!(
+        "5",
+        &**object
+            .attributes
+            .get(&Attribute::Metadata("rse-index".into()))
+            .unwrap()
+    );
+    assert_eq!(&expect[5][..], &block_on(object.bytes()).unwrap()[..]);
+
+    assert_eq!(
+        expect[..4].to_vec(),
+        block_on(shards.get_data(3, 2)).unwrap()
+    );
+
+    // whatever subset of the objects is left
+    block_on(store.delete(&shards.shard_path(3, 0))).unwrap();
+    block_on(store.delete(&shards.shard_path(3, 4))).unwrap();
+    assert_eq!(expect, block_on(shards.get_stripe(3, 2)).unwrap());
+
+    // a rebuilt shard is put back
+    block_on(shards.put_shard(3, 0, Bytes::from(expect[0].clone()))).unwrap();
+    block_on(store.delete(&shards.shard_path(3, 5))).unwrap();
+    assert_eq!(expect, block_on(shards.get_stripe(3, 1)).unwrap());
+
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        block_on(shards.get_data(4, 2)).unwrap_err()
+    );
+}
+
+#[test]
+fn test_object_shards_check_envelope() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+    let shards = ObjectShards::new(&r, Arc::clone(&store), Path::from("stripes"));
+    let expect = make_stripe(&r);
+    block_on(shards.put_stripe(0, &expect)).unwrap();
+
+    // an object without the metadata, and one of another shard
+    block_on(store.put(
+        &shards.shard_path(0, 1),
+        PutPayload::from(expect[1].clone()),
+    ))
+    .unwrap();
+    block_on(store.copy(&shards.shard_path(0, 2), &shards.shard_path(0, 3))).unwrap();
+    assert_eq!(expect, block_on(shards.get_stripe(0, 6)).unwrap());
+    block_on(store.delete(&shards.shard_path(0, 0))).unwrap();
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        block_on(shards.get_stripe(0, 6)).unwrap_err()
+    );
+
+    // the objects of a codec of another configuration
+    let other = ReedSolomon::new_cauchy(4, 2).unwrap();
+    let other_shards = ObjectShards::new(&other, store, Path::from("stripes"));
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        block_on(other_shards.get_data(0, 6)).unwrap_err()
+    );
+}
+
+#[test]
+fn test_object_shards_put_errors() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let shards = ObjectShards::new(&r, Arc::new(InMemory::new()), Path::from("stripes"));
+    let expect = make_stripe(&r);
+
+    match block_on(shards.put_stripe(0, &expect[..5])) {
+        Err(PutError::RSError(Error::TooFewShards)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    let mut uneven = expect.clone();
+    uneven[3].pop();
+    match block_on(shards.put_stripe(0, &uneven)) {
+        Err(PutError::RSError(Error::IncorrectShardSize)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match block_on(shards.put_shard(0, 6, Bytes::from(expect[0].clone()))) {
+        Err(PutError::RSError(Error::InvalidIndex)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        block_on(shards.get_data(0, 1)).unwrap_err()
+    );
+}
@@ -0,0 +1,152 @@
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::sink::SinkExt;
+use futures::stream::{self, StreamExt};
+
+use super::{fill_random, ReedSolomon};
+use crate::pipeline::SinkError;
+use crate::stream::Stripe;
+use crate::Error;
+
+#[test]
+fn test_encode_stream_encodes_each_chunk() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut chunks = vec![vec![0u8; 300], vec![0u8; 100]];
+    for chunk in chunks.iter_mut() {
+        fill_random(chunk);
+    }
+
+    let stripes: Vec<Stripe> = block_on(
+        r.encode_stream(stream::iter(chunks.clone()))
+            .map(Result::unwrap)
+            .collect(),
+    );
+
+    assert_eq!(2, stripes.len());
+    for (i, (stripe, chunk)) in stripes.iter().zip(chunks.iter()).enumerate() {
+        assert_eq!(i, stripe.index());
+        assert_eq!(chunk.len(), stripe.data_len());
+        assert!(r.verify(stripe.shards()).unwrap());
+
+        let mut joined = Vec::new();
+        r.join(&mut joined, stripe.shards(), chunk.len()).unwrap();
+        assert_eq!(chunk, &joined);
+    }
+}
+
+#[test]
+fn test_encode_stream_empty_chunk() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let chunks = vec![vec![], vec![1u8; 30]];
+    let results: Vec<_> = block_on(r.encode_stream(stream::iter(chunks)).collect());
+
+    assert_eq!(Some(&Error::EmptyShard), results[0].as_ref().err());
+    assert_eq!(0, results[1].as_ref().unwrap().index());
+}
+
+#[test]
+fn test_encode_sink_sends_full_stripes() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut shards = vec![vec![0u8; 50]; 7];
+    for shard in shards.iter_mut() {
+        fill_random(shard);
+    }
+
+    let (tx, rx) = mpsc::unbounded();
+    let mut sink = r.encode_sink(tx);
+    block_on(async {
+        for shard in shards.iter() {
+            sink.send(shard.clone()).await.unwrap();
+        }
+        sink.close().await.unwrap();
+    });
+
+    let stripes: Vec<Stripe> = block_on(rx.collect());
+
+    assert_eq!(3, stripes.len());
+    assert_eq!(150, stripes[0].data_len());
+    assert_eq!(50, stripes[2].data_len());
+    for (i, stripe) in stripes.iter().enumerate() {
+        assert_eq!(i, stripe.index());
+        assert!(r.verify(stripe.shards()).unwrap());
+    }
+
+    let data_shards: Vec<_> = stripes
+        .iter()
+        .flat_map(|stripe| stripe.data_shards().iter().cloned())
+        .collect();
+    assert_eq!(&shards[..], &data_shards[..7]);
+    assert!(data_shards[7..].iter().all(|s| s.iter().all(|&b| b == 0)));
+}
+
+#[test]
+fn test_encode_sink_rejects_bad_shards() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let (tx, _rx) = mpsc::unbounded();
+    let mut sink = r.encode_sink(tx);
+    block_on(async {
+        match sink.send(vec![]).await {
+            Err(SinkError::RSError(Error::EmptyShard)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        sink.send(vec![0u8; 10]).await.unwrap();
+        match sink.send(vec![0u8; 11]).await {
+            Err(SinkError::RSError(Error::IncorrectShardSize)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn test_encode_sink_inner_error() {
+    let r = ReedSolomon::new(2, 1).unwrap();
+
+    let (tx, rx) = mpsc::channel(1);
+    drop(rx);
+    let mut sink = r.encode_sink(tx);
+    block_on(async {
+        sink.send(vec![1u8; 10]).await.unwrap();
+        match sink.send(vec![2u8; 10]).await {
+            Err(SinkError::Sink(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn test_background_shard_by_shard_parity_ready_future() {
+    let r = std::sync::Arc::new(ReedSolomon::new(3, 2).unwrap());
+
+    let mut data = vec![vec![0u8; 100]; 3];
+    for shard in data.iter_mut() {
+        fill_random(shard);
+    }
+
+    let sbs = crate::galois_8::BackgroundShardByShard::new(std::sync::Arc::clone(&r), 100);
+    let ready = sbs.parity_ready_handle();
+    let (tx, rx) = mpsc::unbounded();
+    for (i, shard) in data.iter().enumerate() {
+        tx.unbounded_send((i, shard.clone())).unwrap();
+    }
+    drop(tx);
+
+    block_on(async {
+        // the receive loop never waits for the encoding
+        rx.for_each(|(i, shard)| {
+            sbs.submit(i, shard).unwrap();
+            async {}
+        })
+        .await;
+        ready.await.unwrap();
+    });
+
+    let mut parity = vec![vec![0u8; 100]; 2];
+    sbs.copy_parity(&mut parity).unwrap();
+    let mut shards = data;
+    shards.extend(parity);
+    assert!(r.verify(&shards).unwrap());
+}
@@ -0,0 +1,125 @@
+use super::ReedSolomon;
+use crate::placement::{Node, PlacementPolicy, Placer};
+use crate::Error;
+
+// `racks` racks of `per_rack` nodes, of weight 1
+fn cluster(racks: usize, per_rack: usize) -> Vec<Node> {
+    (0..racks * per_rack)
+        .map(|i| Node {
+            domain: 100 + i / per_rack,
+            weight: 1,
+        })
+        .collect()
+}
+
+#[test]
+fn test_placement_limits_domains() {
+    let r = ReedSolomon::new(6, 3).unwrap();
+
+    for &policy in [
+        PlacementPolicy::RoundRobin,
+        PlacementPolicy::RackAware,
+        PlacementPolicy::Weighted,
+    ]
+    .iter()
+    {
+        let placer = Placer::new(&r, cluster(5, 4), policy, 2).unwrap();
+        assert_eq!(policy, placer.policy());
+        for stripe in 0..200 {
+            let placement = placer.place(stripe);
+            assert_eq!(stripe, placement.stripe());
+            assert_eq!(9, placement.nodes().len());
+
+            let mut nodes = placement.nodes().to_vec();
+            nodes.sort_unstable();
+            nodes.dedup();
+            assert_eq!(9, nodes.len());
+
+            let mut per_domain = [0; 5];
+            for &node in placement.nodes() {
+                per_domain[placer.nodes()[node].domain - 100] += 1;
+            }
+            assert!(per_domain.iter().all(|&count| count <= 2));
+
+            assert_eq!(placement, placer.place(stripe));
+        }
+    }
+
+    assert_eq!(
+        Error::InvalidPlacement,
+        Placer::new(&r, cluster(4, 4), PlacementPolicy::RackAware, 2).unwrap_err()
+    );
+    assert_eq!(
+        Error::InvalidPlacement,
+        Placer::new(&r, cluster(1, 8), PlacementPolicy::RoundRobin, 9).unwrap_err()
+    );
+    // a rack of nodes of weight 0
+    let mut nodes = cluster(5, 2);
+    nodes[0].weight = 0;
+    nodes[1].weight = 0;
+    Placer::new(&r, nodes.clone(), PlacementPolicy::RoundRobin, 2).unwrap();
+    assert_eq!(
+        Error::InvalidPlacement,
+        Placer::new(&r, nodes, PlacementPolicy::Weighted, 2).unwrap_err()
+    );
+}
+
+#[test]
+fn test_placement_policies() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    // the shards are spread over all racks
+    let placer = Placer::new(&r, cluster(3, 4), PlacementPolicy::RackAware, 6).unwrap();
+    for stripe in 0..12 {
+        let mut per_domain = [0; 3];
+        for &node in placer.place(stripe).nodes() {
+            per_domain[placer.nodes()[node].domain - 100] += 1;
+        }
+        assert_eq!([2, 2, 2], per_domain);
+    }
+
+    let placer = Placer::new(&r, cluster(1, 8), PlacementPolicy::RoundRobin, 6).unwrap();
+    assert_eq!(&[0, 1, 2, 3, 4, 5], placer.place(0).nodes());
+    assert_eq!(&[3, 4, 5, 6, 7, 0], placer.place(3).nodes());
+
+    // a node of weight 3 is picked about 3 times as often
+    let mut nodes = cluster(60, 1);
+    nodes[0].weight = 3;
+    nodes[59].weight = 0;
+    let placer = Placer::new(&r, nodes, PlacementPolicy::Weighted, 1).unwrap();
+    let mut counts = [0; 60];
+    for stripe in 0..3000 {
+        for &node in placer.place(stripe).nodes() {
+            counts[node] += 1;
+        }
+    }
+    assert_eq!(0, counts[59]);
+    let mean = counts[1..59].iter().sum::<usize>() / 58;
+    assert!(counts[0] > mean * 2 && counts[0] < mean * 4);
+}
+
+#[test]
+fn test_placement_repair_plan() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let placer = Placer::new(&r, cluster(3, 3), PlacementPolicy::RackAware, 2).unwrap();
+    let placement = placer.place(7);
+
+    // losing a rack loses at most 2 shards
+    let rack: Vec<usize> = (0..9)
+        .filter(|&i| placer.nodes()[i].domain == 101)
+        .collect();
+    let present = placement.present_without(&rack);
+    assert_eq!(4, present.count());
+
+    let lost = (0..6).find(|&i| !present.contains(i)).unwrap();
+    assert!(rack.contains(&placement.node(lost).unwrap()));
+    assert_eq!(
+        Some(lost),
+        placement.shard_on(placement.node(lost).unwrap())
+    );
+    let plan = r.repair_plan(&present, lost).unwrap();
+    for &source in plan.sources() {
+        assert!(!rack.contains(&placement.node(source).unwrap()));
+    }
+    assert_eq!(None, placement.node(6));
+}
@@ -0,0 +1,46 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::pool;
+
+#[test]
+fn test_pool_for_each() {
+    let mut items: Vec<usize> = (0..10).collect();
+    pool::for_each(&mut items, &|x| *x *= 2);
+    assert_eq!((0..10).map(|x| x * 2).collect::<Vec<_>>(), items);
+
+    pool::for_each(&mut [] as &mut [usize], &|_| unreachable!());
+
+    // jobs calling back into the pool do not deadlock
+    let count = AtomicUsize::new(0);
+    let mut outer = [0; 8];
+    pool::for_each(&mut outer, &|_| {
+        let mut inner = [0; 8];
+        pool::for_each(&mut inner, &|_| {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+    });
+    assert_eq!(64, count.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_pool_for_each_panic() {
+    let done = AtomicUsize::new(0);
+    let mut items: Vec<usize> = (0..6).collect();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool::for_each(&mut items, &|x| {
+            if *x == 3 {
+                panic!("job 3");
+            }
+            done.fetch_add(1, Ordering::Relaxed);
+        })
+    }));
+
+    // the panic is resumed once the other jobs are done
+    assert_eq!("job 3", *result.unwrap_err().downcast::<&str>().unwrap());
+    assert_eq!(5, done.load(Ordering::Relaxed));
+
+    // the pool still works
+    pool::for_each(&mut items, &|x| *x += 1);
+    assert_eq!((1..7).collect::<Vec<_>>(), items);
+}
@@ -0,0 +1,136 @@
+use super::{fill_random, ReedSolomon};
+use crate::product::ProductCode;
+use crate::Error;
+
+fn encoded_grid(code: &ProductCode<crate::galois_8::Field>, len: usize) -> Vec<Vec<u8>> {
+    let mut cells = vec![vec![0u8; len]; code.cell_count()];
+    for r in 0..code.data_rows() {
+        for c in 0..code.data_columns() {
+            fill_random(&mut cells[r * code.columns() + c]);
+        }
+    }
+    code.encode(&mut cells).unwrap();
+    cells
+}
+
+#[test]
+fn test_product_code_encode() {
+    let code = ProductCode::new(
+        ReedSolomon::new(4, 2).unwrap(),
+        ReedSolomon::new(3, 2).unwrap(),
+    );
+    assert_eq!(5, code.rows());
+    assert_eq!(6, code.columns());
+    assert_eq!(30, code.cell_count());
+
+    let cells = encoded_grid(&code, 16);
+
+    // the parity rows are codewords of the row codec too
+    for row in cells.chunks(6) {
+        assert!(code.row_codec().verify(row).unwrap());
+    }
+    for c in 0..6 {
+        let column: Vec<_> = cells.iter().skip(c).step_by(6).collect();
+        assert!(code.column_codec().verify(&column).unwrap());
+    }
+
+    let mut cells = cells;
+    assert_eq!(
+        Error::TooFewShards,
+        code.encode(&mut cells[1..]).unwrap_err()
+    );
+}
+
+#[test]
+fn test_product_code_reconstruct() {
+    let code = ProductCode::new(
+        ReedSolomon::new(4, 2).unwrap(),
+        ReedSolomon::new(4, 2).unwrap(),
+    );
+    let expect = encoded_grid(&code, 20);
+
+    // rows 0 and 1 and column 5 miss more cells than their codec repairs,
+    // the repair of the other lines brings them back within reach
+    let missing = [0, 1, 2, 6, 7, 8, 11, 17, 23, 29];
+    let mut cells: Vec<_> = expect.iter().cloned().map(Some).collect();
+    for &i in &missing {
+        cells[i] = None;
+    }
+    assert!(code.row_codec().reconstruct(&mut cells[..6]).is_err());
+
+    let present: Vec<_> = cells.iter().map(Option::is_some).collect();
+    assert!(code.is_recoverable(&present).unwrap());
+
+    code.reconstruct(&mut cells).unwrap();
+    for (expect, cell) in expect.iter().zip(cells.iter()) {
+        assert_eq!(expect, cell.as_ref().unwrap());
+    }
+}
+
+#[test]
+fn test_product_code_unrecoverable() {
+    let code = ProductCode::new(
+        ReedSolomon::new(3, 1).unwrap(),
+        ReedSolomon::new(3, 1).unwrap(),
+    );
+    let expect = encoded_grid(&code, 8);
+
+    // a 2 by 2 square of missing cells is the smallest pattern stopping the
+    // repair
+    let mut cells: Vec<_> = expect.iter().cloned().map(Some).collect();
+    for &i in &[5, 6, 9, 10] {
+        cells[i] = None;
+    }
+    let present: Vec<_> = cells.iter().map(Option::is_some).collect();
+    assert!(!code.is_recoverable(&present).unwrap());
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        code.reconstruct(&mut cells).unwrap_err()
+    );
+    assert_eq!(12, cells.iter().filter(|cell| cell.is_some()).count());
+
+    // any 3 missing cells are recoverable
+    for a in 0..16 {
+        for b in a + 1..16 {
+            for c in b + 1..16 {
+                let mut cells: Vec<_> = expect.iter().cloned().map(Some).collect();
+                cells[a] = None;
+                cells[b] = None;
+                cells[c] = None;
+                code.reconstruct(&mut cells).unwrap();
+                assert_eq!(expect[a], *cells[a].as_ref().unwrap());
+                assert_eq!(expect[c], *cells[c].as_ref().unwrap());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_product_code_error_handling() {
+    let code = ProductCode::new(
+        ReedSolomon::new(2, 1).unwrap(),
+        ReedSolomon::new(2, 1).unwrap(),
+    );
+    let expect = encoded_grid(&code, 8);
+
+    let mut cells: Vec<_> = expect.iter().cloned().map(Some).collect();
+    assert_eq!(
+        Error::TooManyShards,
+        code.is_recoverable(&[true; 10]).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShards,
+        code.reconstruct(&mut cells[1..]).unwrap_err()
+    );
+
+    cells[0] = None;
+    cells[1] = Some(vec![0u8; 7]);
+    assert_eq!(
+        Error::IncorrectShardSize,
+        code.reconstruct(&mut cells).unwrap_err()
+    );
+    assert!(cells[0].is_none());
+
+    let mut cells = vec![Some(Vec::<u8>::new()); 9];
+    assert_eq!(Error::EmptyShard, code.reconstruct(&mut cells).unwrap_err());
+}
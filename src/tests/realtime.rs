@@ -0,0 +1,149 @@
+use super::{encoded_shards, fill_random, ReedSolomon};
+use crate::realtime::RealTimeCodec;
+use crate::Error;
+
+#[test]
+fn test_realtime_pattern_count() {
+    let rt = RealTimeCodec::new(ReedSolomon::new(10, 4).unwrap(), 4);
+    assert_eq!(4, rt.max_erasures());
+    assert_eq!(1001, rt.pattern_count());
+
+    let rt = RealTimeCodec::new(ReedSolomon::new(3, 2).unwrap(), 9);
+    assert_eq!(2, rt.max_erasures());
+    assert_eq!(10, rt.pattern_count());
+
+    let rt = RealTimeCodec::new(ReedSolomon::new(3, 2).unwrap(), 0);
+    assert_eq!(1, rt.pattern_count());
+}
+
+#[test]
+fn test_realtime_reconstruct_all_patterns() {
+    let rt = RealTimeCodec::new(ReedSolomon::new(5, 3).unwrap(), 3);
+    let expect = encoded_shards(rt.codec(), 64);
+
+    // every pattern of at most 3 missing shards out of 8
+    for mask in 0u32..256 {
+        if mask.count_ones() > 3 {
+            continue;
+        }
+
+        let mut shards: Vec<_> = expect
+            .iter()
+            .enumerate()
+            .map(|(i, shard)| {
+                let present = mask & (1 << i) == 0;
+                let shard = if present {
+                    shard.clone()
+                } else {
+                    vec![0u8; 64]
+                };
+                (shard, present)
+            })
+            .collect();
+
+        rt.reconstruct_data(&mut shards).unwrap();
+        for (i, (shard, _)) in shards.iter().enumerate().take(5) {
+            assert_eq!(expect[i], *shard);
+        }
+
+        rt.reconstruct(&mut shards).unwrap();
+        for (i, (shard, present)) in shards.iter().enumerate() {
+            assert_eq!(expect[i], *shard);
+            assert_eq!(mask & (1 << i) == 0, *present);
+        }
+    }
+}
+
+#[test]
+fn test_realtime_matches_codec() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let expect = encoded_shards(&r, 100);
+    let rt = RealTimeCodec::new(r, 2);
+
+    let mut parity = vec![vec![0u8; 100]; 2];
+    rt.encode_sep(&expect[..4], &mut parity).unwrap();
+    assert_eq!(&expect[4..], &parity[..]);
+
+    let mut buffer = vec![vec![0u8; 100]; 2];
+    assert!(rt.verify_with_buffer(&expect, &mut buffer).unwrap());
+
+    let stats = rt.codec().stats();
+    let mut shards: Vec<_> = expect.iter().map(|shard| (shard.clone(), true)).collect();
+    shards[0] = (vec![0u8; 100], false);
+    shards[5] = (vec![0u8; 100], false);
+    rt.reconstruct(&mut shards).unwrap();
+
+    // no decode matrix is looked up in the cache of the codec
+    let after = rt.codec().stats();
+    assert_eq!(stats.cache_hits, after.cache_hits);
+    assert_eq!(stats.cache_misses, after.cache_misses);
+    assert_eq!(stats.bytes_reconstructed + 200, after.bytes_reconstructed);
+}
+
+#[test]
+fn test_realtime_error_handling() {
+    let rt = RealTimeCodec::new(ReedSolomon::new(4, 3).unwrap(), 1);
+    let expect = encoded_shards(rt.codec(), 10);
+
+    let mut shards: Vec<_> = expect.iter().map(|shard| (shard.clone(), true)).collect();
+    shards[1].1 = false;
+    shards[2].1 = false;
+    shards[1].0 = vec![1u8; 10];
+    assert_eq!(
+        Error::ErasurePatternNotPrecomputed,
+        rt.reconstruct(&mut shards).unwrap_err()
+    );
+    // untouched on error
+    assert_eq!(vec![1u8; 10], shards[1].0);
+
+    // the missing parity shards come from the data shards
+    shards[1].1 = true;
+    shards[1].0 = expect[1].clone();
+    shards[5].1 = false;
+    shards[6].1 = false;
+    shards[5].0 = vec![0u8; 10];
+    rt.reconstruct(&mut shards).unwrap();
+    for (i, (shard, _)) in shards.iter().enumerate() {
+        assert_eq!(expect[i], *shard);
+    }
+
+    shards[0].0 = vec![0u8; 9];
+    assert_eq!(
+        Error::IncorrectShardSize,
+        rt.reconstruct(&mut shards).unwrap_err()
+    );
+
+    let mut shards: Vec<_> = expect.iter().map(|shard| (shard.clone(), false)).collect();
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        rt.reconstruct(&mut shards).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShards,
+        rt.reconstruct(&mut shards[1..]).unwrap_err()
+    );
+
+    let mut shards = vec![(Vec::<u8>::new(), true); 7];
+    assert_eq!(Error::EmptyShard, rt.reconstruct(&mut shards).unwrap_err());
+}
+
+#[test]
+fn test_realtime_galois_16() {
+    let r = crate::ReedSolomon::<crate::galois_16::Field>::new(3, 2).unwrap();
+    let mut expect = vec![vec![[0u8; 2]; 8]; 5];
+    for shard in expect.iter_mut().take(3) {
+        for x in shard.iter_mut() {
+            fill_random(x);
+        }
+    }
+    r.encode(&mut expect).unwrap();
+
+    let rt = RealTimeCodec::new(r, 2);
+    let mut shards: Vec<_> = expect.iter().map(|shard| (shard.clone(), true)).collect();
+    shards[0] = (vec![[0u8; 2]; 8], false);
+    shards[2] = (vec![[0u8; 2]; 8], false);
+    rt.reconstruct(&mut shards).unwrap();
+    for (i, (shard, _)) in shards.iter().enumerate() {
+        assert_eq!(expect[i], *shard);
+    }
+}
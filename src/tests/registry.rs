@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::thread;
+
+use crate::galois_8;
+use crate::registry::{CodecKey, CodecRegistry};
+use crate::{Error, MatrixKind};
+
+#[test]
+fn test_registry_shares_codecs() {
+    let registry = CodecRegistry::<galois_8::Field>::new(4);
+
+    let a = registry.get(CodecKey::new(10, 4)).unwrap();
+    let b = registry.get(CodecKey::new(10, 4)).unwrap();
+    let c = registry.get(CodecKey::new_cauchy(10, 4)).unwrap();
+
+    assert!(Arc::ptr_eq(&a, &b));
+    assert!(!Arc::ptr_eq(&a, &c));
+    assert_eq!(MatrixKind::Cauchy, c.matrix_kind());
+    assert_eq!(10, a.data_shard_count());
+    assert_eq!(4, a.parity_shard_count());
+    assert_eq!(2, registry.len());
+}
+
+#[test]
+fn test_registry_evicts_least_recently_used() {
+    let registry = CodecRegistry::<galois_8::Field>::new(2);
+
+    let first = registry.get(CodecKey::new(2, 1)).unwrap();
+    registry.get(CodecKey::new(3, 1)).unwrap();
+    // makes (3, 1) the least recently used
+    registry.get(CodecKey::new(2, 1)).unwrap();
+    registry.get(CodecKey::new(4, 1)).unwrap();
+
+    assert_eq!(2, registry.len());
+    assert!(Arc::ptr_eq(
+        &first,
+        &registry.get(CodecKey::new(2, 1)).unwrap()
+    ));
+    assert!(registry.evict(&CodecKey::new(4, 1)));
+    assert!(!registry.evict(&CodecKey::new(3, 1)));
+
+    registry.clear();
+    assert!(registry.is_empty());
+    // evicted codecs stay usable
+    assert_eq!(2, first.data_shard_count());
+}
+
+#[test]
+fn test_registry_concurrent_get() {
+    let registry = Arc::new(CodecRegistry::<galois_8::Field>::new(8));
+
+    let codecs: Vec<_> = (0..8)
+        .map(|_| {
+            let registry = Arc::clone(&registry);
+            thread::spawn(move || registry.get(CodecKey::new(6, 3)).unwrap())
+        })
+        .map(|handle| handle.join().unwrap())
+        .collect();
+
+    assert_eq!(1, registry.len());
+    let cached = registry.get(CodecKey::new(6, 3)).unwrap();
+    // all threads but those racing the first insertion share the cached codec
+    assert!(codecs.iter().any(|codec| Arc::ptr_eq(codec, &cached)));
+}
+
+#[test]
+fn test_registry_error_handling() {
+    let registry = CodecRegistry::<galois_8::Field>::new(0);
+
+    assert_eq!(1, registry.capacity());
+    assert_eq!(
+        Error::TooFewDataShards,
+        registry.get(CodecKey::new(0, 1)).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyShards,
+        registry.get(CodecKey::new_cauchy(200, 100)).unwrap_err()
+    );
+    assert!(registry.is_empty());
+}
@@ -0,0 +1,258 @@
+use super::{fill_random, ReedSolomon};
+use crate::galois_16;
+use crate::{Error, ShardSet};
+
+fn make_shards(len: usize, count: usize) -> Vec<Vec<u8>> {
+    let mut shards = vec![vec![0u8; len]; count];
+    for shard in shards.iter_mut() {
+        fill_random(shard);
+    }
+    shards
+}
+
+#[test]
+fn test_repair_plan_repairs_every_shard() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_shards(100, 8);
+    r.encode(&mut shards).unwrap();
+
+    let mut present = ShardSet::full(8);
+    present.remove(1);
+    present.remove(6);
+
+    for lost in 0..8 {
+        let plan = r.repair_plan(&present, lost).unwrap();
+        assert_eq!(lost, plan.lost());
+        assert_eq!(5, plan.sources().len());
+        assert!(!plan.sources().contains(&lost));
+        assert!(plan.sources().iter().all(|&i| present.contains(i)));
+
+        let partials: Vec<_> = plan
+            .sources()
+            .iter()
+            .map(|&i| plan.partial_repair(i, &shards[i]).unwrap())
+            .collect();
+
+        assert_eq!(shards[lost], plan.combine(&partials).unwrap());
+    }
+}
+
+#[test]
+fn test_repair_plan_combine_in_groups() {
+    let r = ReedSolomon::new(6, 3).unwrap();
+
+    let mut shards = make_shards(50, 9);
+    r.encode(&mut shards).unwrap();
+
+    let mut present = ShardSet::full(9);
+    present.remove(0);
+    present.remove(4);
+
+    let plan = r.repair_plan(&present, 0).unwrap();
+    assert_eq!(&[1, 2, 3, 5, 6, 7], plan.sources());
+    assert_eq!(None, plan.coefficient(0));
+    assert_eq!(None, plan.coefficient(8));
+
+    let partials: Vec<_> = plan
+        .sources()
+        .iter()
+        .map(|&i| plan.partial_repair(i, &shards[i]).unwrap())
+        .collect();
+
+    let left = plan.combine(&partials[..2]).unwrap();
+    let right = plan.combine(&partials[2..]).unwrap();
+
+    assert_eq!(shards[0], plan.combine(&[left, right]).unwrap());
+}
+
+#[test]
+fn test_repair_plan_matches_reconstruct_galois_16() {
+    let r = galois_16::ReedSolomon::new(4, 2).unwrap();
+
+    let mut shards = vec![vec![[0u8; 2]; 10]; 6];
+    for shard in shards.iter_mut() {
+        for elem in shard.iter_mut() {
+            fill_random(elem);
+        }
+    }
+    r.encode(&mut shards).unwrap();
+
+    let present = ShardSet::from(&[true, false, true, true, true, true][..]);
+    let plan = r.repair_plan(&present, 5).unwrap();
+
+    let partials: Vec<_> = plan
+        .sources()
+        .iter()
+        .map(|&i| plan.partial_repair(i, &shards[i]).unwrap())
+        .collect();
+
+    assert_eq!(shards[5], plan.combine(&partials).unwrap());
+}
+
+#[test]
+fn test_repair_plan_error_handling() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    assert_eq!(
+        Error::InvalidShardFlags,
+        r.repair_plan(&ShardSet::full(4), 0).unwrap_err()
+    );
+    assert_eq!(
+        Error::InvalidIndex,
+        r.repair_plan(&ShardSet::full(5), 5).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.repair_plan(&ShardSet::from(&[true, true, false, true, false][..]), 0)
+            .unwrap_err()
+    );
+
+    let plan = r.repair_plan(&ShardSet::full(5), 4).unwrap();
+    assert_eq!(&[0, 1, 2], plan.sources());
+
+    assert_eq!(
+        Error::InvalidIndex,
+        plan.partial_repair(3, &[1, 2, 3]).unwrap_err()
+    );
+    assert_eq!(Error::EmptyShard, plan.partial_repair(0, &[]).unwrap_err());
+
+    let empty: [Vec<u8>; 0] = [];
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        plan.combine(&empty).unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        plan.combine(&[vec![1, 2], vec![1]]).unwrap_err()
+    );
+    assert_eq!(
+        Error::EmptyShard,
+        plan.combine(&[vec![], vec![]]).unwrap_err()
+    );
+}
+
+fn checksum(shard: &[u8]) -> u64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    let mut hasher = DefaultHasher::new();
+    hasher.write(shard);
+    hasher.finish()
+}
+
+#[test]
+fn test_scrub_and_repair() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut shards = make_shards(100, 8);
+    r.encode(&mut shards).unwrap();
+    let checksums: Vec<u64> = shards.iter().map(|shard| checksum(shard)).collect();
+
+    let mut option_shards: Vec<_> = shards.iter().cloned().map(Some).collect();
+    assert!(r
+        .scrub_and_repair(&mut option_shards, &checksums, checksum)
+        .unwrap()
+        .is_empty());
+
+    option_shards[1] = None;
+    option_shards[3].as_mut().unwrap()[50] ^= 1;
+    option_shards[7].as_mut().unwrap()[0] ^= 0x80;
+    assert_eq!(
+        vec![1, 3, 7],
+        r.scrub_and_repair(&mut option_shards, &checksums, checksum)
+            .unwrap()
+    );
+    let repaired: Vec<_> = option_shards.into_iter().map(Option::unwrap).collect();
+    assert_eq!(shards, repaired);
+}
+
+#[test]
+fn test_scrub_and_repair_errors() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut shards = make_shards(10, 5);
+    r.encode(&mut shards).unwrap();
+    let checksums: Vec<u64> = shards.iter().map(|shard| checksum(shard)).collect();
+
+    let mut option_shards: Vec<_> = shards.iter().cloned().map(Some).collect();
+    assert_eq!(
+        Error::TooFewShards,
+        r.scrub_and_repair(&mut option_shards, &checksums[..4], checksum)
+            .unwrap_err()
+    );
+
+    // too many bad shards, which are left as they are
+    option_shards[0] = None;
+    option_shards[2].as_mut().unwrap()[0] ^= 1;
+    option_shards[4].as_mut().unwrap()[0] ^= 1;
+    let before = option_shards.clone();
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.scrub_and_repair(&mut option_shards, &checksums, checksum)
+            .unwrap_err()
+    );
+    assert_eq!(before, option_shards);
+
+    // corrupted shards of the wrong length are repaired
+    let mut option_shards: Vec<_> = shards.iter().cloned().map(Some).collect();
+    option_shards[1].as_mut().unwrap().push(0);
+    option_shards[3].as_mut().unwrap().pop();
+    assert_eq!(
+        vec![1, 3],
+        r.scrub_and_repair(&mut option_shards, &checksums, checksum)
+            .unwrap()
+    );
+    let repaired: Vec<_> = option_shards.into_iter().map(Option::unwrap).collect();
+    assert_eq!(shards, repaired);
+
+    // but shards of the wrong length matching their checksum are not
+    let mut checksums = checksums;
+    let mut option_shards: Vec<_> = shards.iter().cloned().map(Some).collect();
+    option_shards[0] = None;
+    option_shards[1].as_mut().unwrap().push(0);
+    checksums[1] = checksum(option_shards[1].as_ref().unwrap());
+    let before = option_shards.clone();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.scrub_and_repair(&mut option_shards, &checksums, checksum)
+            .unwrap_err()
+    );
+    assert_eq!(before, option_shards);
+}
+
+#[test]
+fn test_reconstruct_checked() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    let mut shards = make_shards(50, 6);
+    r.encode(&mut shards).unwrap();
+    let checksums: Vec<u64> = shards.iter().map(|shard| checksum(shard)).collect();
+
+    let mut option_shards: Vec<_> = shards.iter().cloned().map(Some).collect();
+    option_shards[0] = None;
+    option_shards[5] = None;
+    r.reconstruct_checked(&mut option_shards, &checksums, checksum)
+        .unwrap();
+    let reconstructed: Vec<_> = option_shards.into_iter().map(Option::unwrap).collect();
+    assert_eq!(shards, reconstructed);
+
+    // reconstructed from a silently corrupted shard
+    let mut option_shards: Vec<_> = shards.iter().cloned().map(Some).collect();
+    option_shards[0] = None;
+    option_shards[5] = None;
+    option_shards[2].as_mut().unwrap()[10] ^= 1;
+    let before = option_shards.clone();
+    assert_eq!(
+        Error::ChecksumMismatch,
+        r.reconstruct_checked(&mut option_shards, &checksums, checksum)
+            .unwrap_err()
+    );
+    assert_eq!(before, option_shards);
+
+    assert_eq!(
+        Error::TooManyShards,
+        r.reconstruct_checked(&mut option_shards, &[0u64; 7], checksum)
+            .unwrap_err()
+    );
+}
@@ -0,0 +1,67 @@
+use crate::ShardSet;
+
+#[test]
+fn test_shard_set_insert_remove() {
+    let mut set = ShardSet::new(300);
+    assert_eq!(300, set.shard_count());
+    assert_eq!(0, set.count());
+
+    assert!(set.insert(0));
+    assert!(set.insert(63));
+    assert!(set.insert(64));
+    assert!(set.insert(299));
+    assert!(!set.insert(64));
+    assert_eq!(4, set.count());
+
+    assert!(set.contains(63));
+    assert!(!set.contains(62));
+    assert!(!set.contains(300));
+
+    assert!(set.remove(63));
+    assert!(!set.remove(63));
+    assert!(!set.remove(1000));
+    assert_eq!(vec![0, 64, 299], set.iter().collect::<Vec<_>>());
+}
+
+#[test]
+#[should_panic]
+fn test_shard_set_insert_out_of_range() {
+    ShardSet::new(10).insert(10);
+}
+
+#[test]
+fn test_shard_set_full_and_complement() {
+    for &count in [0, 1, 13, 64, 65, 256].iter() {
+        let full = ShardSet::full(count);
+        assert_eq!(count, full.count());
+        assert_eq!(ShardSet::new(count), full.complement());
+        assert_eq!(full, ShardSet::new(count).complement());
+    }
+
+    let mut set = ShardSet::new(70);
+    set.insert(3);
+    set.insert(68);
+    let complement = set.complement();
+    assert_eq!(68, complement.count());
+    assert!(!complement.contains(3));
+    assert!(!complement.contains(68));
+    assert!(complement.contains(69));
+}
+
+#[test]
+fn test_shard_set_flags() {
+    let flags = [true, false, false, true, true, false];
+
+    let set = ShardSet::from(&flags[..]);
+    assert_eq!(6, set.shard_count());
+    assert_eq!(vec![0, 3, 4], set.iter().collect::<Vec<_>>());
+    assert_eq!(flags.to_vec(), set.to_flags());
+
+    let collected: ShardSet = flags.iter().cloned().collect();
+    assert_eq!(set, collected);
+
+    let long: Vec<bool> = (0..200).map(|i| i % 3 == 0).collect();
+    let collected: ShardSet = long.iter().cloned().collect();
+    assert_eq!(ShardSet::from(&long[..]), collected);
+    assert_eq!(long, collected.to_flags());
+}
@@ -0,0 +1,162 @@
+use crate::sim::{LossModel, SimReport, Simulation};
+use crate::Error;
+
+#[test]
+fn test_sim_new_error_handling() {
+    let model = LossModel::Uniform { loss_prob: 0.1 };
+    assert_eq!(
+        Error::TooFewDataShards,
+        Simulation::new(0, 2, model).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewParityShards,
+        Simulation::new(4, 0, model).unwrap_err()
+    );
+
+    for &model in [
+        LossModel::Uniform { loss_prob: -0.1 },
+        LossModel::Uniform { loss_prob: 1.5 },
+        LossModel::GilbertElliott {
+            good_to_bad: 0.1,
+            bad_to_good: 0.5,
+            loss_good: 0.0,
+            loss_bad: 2.0,
+        },
+        LossModel::NodeFailure {
+            shards_per_node: 0,
+            failure_prob: 0.1,
+        },
+    ]
+    .iter()
+    {
+        assert_eq!(
+            Error::InvalidLossModel,
+            Simulation::new(4, 2, model).unwrap_err()
+        );
+    }
+}
+
+#[test]
+fn test_sim_extremes() {
+    let mut sim = Simulation::new(4, 2, LossModel::Uniform { loss_prob: 0.0 }).unwrap();
+    assert_eq!(
+        SimReport {
+            trials: 100,
+            recovered: 100,
+            shards_lost: 0,
+            max_lost: 0,
+        },
+        sim.run(100)
+    );
+
+    let mut sim = Simulation::new(4, 2, LossModel::Uniform { loss_prob: 1.0 }).unwrap();
+    let report = sim.run(100);
+    assert_eq!(0, report.recovered);
+    assert_eq!(6, report.max_lost);
+    assert_eq!(6.0, report.mean_lost());
+
+    assert_eq!(1.0, SimReport::default().recovery_rate());
+}
+
+#[test]
+fn test_sim_uniform_matches_binomial() {
+    let mut sim = Simulation::new(4, 2, LossModel::Uniform { loss_prob: 0.1 })
+        .unwrap()
+        .with_seed(42);
+
+    // P(at most 2 of 6 lost) = 0.9^6 + 6 * 0.1 * 0.9^5 + 15 * 0.01 * 0.9^4
+    let report = sim.run(50_000);
+    assert!((report.recovery_rate() - 0.98415).abs() < 0.005);
+    assert!((report.mean_lost() - 0.6).abs() < 0.02);
+}
+
+#[test]
+fn test_sim_seed_is_reproducible() {
+    let model = LossModel::GilbertElliott {
+        good_to_bad: 0.05,
+        bad_to_good: 0.3,
+        loss_good: 0.01,
+        loss_bad: 0.8,
+    };
+
+    let a = Simulation::new(10, 4, model)
+        .unwrap()
+        .with_seed(7)
+        .run(1000);
+    let b = Simulation::new(10, 4, model)
+        .unwrap()
+        .with_seed(7)
+        .run(1000);
+    let c = Simulation::new(10, 4, model)
+        .unwrap()
+        .with_seed(8)
+        .run(1000);
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn test_sim_bursts_lose_more_stripes() {
+    // same mean loss rate of about 10%
+    let uniform = LossModel::Uniform { loss_prob: 0.1 };
+    let bursty = LossModel::GilbertElliott {
+        good_to_bad: 0.025,
+        bad_to_good: 0.2,
+        loss_good: 0.0,
+        loss_bad: 0.9,
+    };
+
+    let uniform = Simulation::new(10, 4, uniform).unwrap().run(20_000);
+    let bursty = Simulation::new(10, 4, bursty).unwrap().run(20_000);
+
+    assert!((uniform.mean_lost() - bursty.mean_lost()).abs() < 0.3);
+    assert!(bursty.recovery_rate() < uniform.recovery_rate());
+}
+
+#[test]
+fn test_sim_node_failure() {
+    // 3 shards per node, a single failed node is recoverable
+    let model = LossModel::NodeFailure {
+        shards_per_node: 3,
+        failure_prob: 0.2,
+    };
+    let mut sim = Simulation::new(6, 3, model).unwrap();
+
+    for _ in 0..100 {
+        let present = sim.next_stripe();
+        let lost = 9 - present.count();
+        assert_eq!(0, lost % 3);
+        for node in 0..3 {
+            let on_node = (node * 3..node * 3 + 3)
+                .filter(|&i| present.contains(i))
+                .count();
+            assert!(on_node == 0 || on_node == 3);
+        }
+    }
+
+    // recovered iff at most one of three nodes failed
+    let report = sim.run(50_000);
+    let expect = 0.8f64.powi(3) + 3.0 * 0.2 * 0.8f64.powi(2);
+    assert!((report.recovery_rate() - expect).abs() < 0.01);
+}
+
+#[test]
+fn test_sim_apply() {
+    let mut sim = Simulation::new(3, 2, LossModel::Uniform { loss_prob: 0.3 }).unwrap();
+
+    for _ in 0..100 {
+        let mut shards: Vec<_> = (0..5).map(Some).collect();
+        let recoverable = sim.apply(&mut shards).unwrap();
+
+        let lost = shards.iter().filter(|s| s.is_none()).count();
+        assert_eq!(lost <= 2, recoverable);
+        for (i, shard) in shards.iter().enumerate() {
+            assert!(shard.is_none() || *shard == Some(i));
+        }
+    }
+
+    let mut shards = vec![Some(0); 4];
+    assert_eq!(Error::TooFewShards, sim.apply(&mut shards).unwrap_err());
+    let mut shards = vec![Some(0); 6];
+    assert_eq!(Error::TooManyShards, sim.apply(&mut shards).unwrap_err());
+}
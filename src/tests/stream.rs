@@ -0,0 +1,876 @@
+use std::io::{self, Cursor, Read, Write};
+
+use super::{fill_random, ReedSolomon};
+use crate::stream::{RepairCheckpoint, ShardReaders, ShardTransform, ShardWriters, WriteError};
+use crate::{Error, ParallelParam};
+
+struct FailingReader;
+
+impl Read for FailingReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::Other.into())
+    }
+}
+
+#[test]
+fn test_stripes_empty_shard() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    assert_eq!(Error::EmptyShard, r.stripes(&[0u8; 10][..], 0).unwrap_err());
+}
+
+#[test]
+fn test_stripes_empty_reader() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    assert_eq!(0, r.stripes(&[][..], 10).unwrap().count());
+}
+
+#[test]
+fn test_stripes_encode_correctly() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    let mut input = vec![0u8; 4 * 100 * 3 + 123];
+    fill_random(&mut input);
+
+    let stripes: Vec<_> = r
+        .stripes(&input[..], 100)
+        .unwrap()
+        .collect::<io::Result<_>>()
+        .unwrap();
+
+    assert_eq!(4, stripes.len());
+
+    let mut output = Vec::new();
+    for (i, stripe) in stripes.iter().enumerate() {
+        assert_eq!(i, stripe.index());
+        assert_eq!(100, stripe.shard_len());
+        assert_eq!(6, stripe.shards().len());
+        assert!(r.verify(stripe.shards()).unwrap());
+
+        for shard in stripe.data_shards() {
+            output.extend_from_slice(shard);
+        }
+        output.truncate(i * 400 + stripe.data_len());
+    }
+
+    assert_eq!(123, stripes[3].data_len());
+    assert_eq!(input, output);
+
+    // padding of the final stripe is zeroed
+    assert!(stripes[3].data_shards()[1][23..].iter().all(|&x| x == 0));
+    assert!(stripes[3].data_shards()[2].iter().all(|&x| x == 0));
+}
+
+#[test]
+fn test_stripes_exact_multiple() {
+    let r = ReedSolomon::new(2, 1).unwrap();
+
+    let input = [1u8; 2 * 8 * 2];
+
+    let stripes: Vec<_> = r
+        .stripes(&input[..], 8)
+        .unwrap()
+        .collect::<io::Result<_>>()
+        .unwrap();
+
+    assert_eq!(2, stripes.len());
+    assert_eq!(16, stripes[1].data_len());
+}
+
+#[test]
+fn test_stripes_stop_after_error() {
+    let r = ReedSolomon::new(2, 1).unwrap();
+
+    let mut stripes = r.stripes(FailingReader, 8).unwrap();
+
+    assert!(stripes.next().unwrap().is_err());
+    assert!(stripes.next().is_none());
+}
+
+struct FailingWriter;
+
+impl Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::Other.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_shard_writers_write_each_shard_to_its_destination() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut input = vec![0u8; 3 * 10 * 2];
+    fill_random(&mut input);
+
+    let mut writers = ShardWriters::new(&r, vec![Vec::new(); 5]).unwrap();
+
+    let mut expect = vec![Vec::new(); 5];
+    for stripe in r.stripes(&input[..], 10).unwrap() {
+        let stripe = stripe.unwrap();
+        writers.write_stripe(&stripe).unwrap();
+
+        for (i, shard) in stripe.shards().iter().enumerate() {
+            expect[i].extend_from_slice(shard);
+        }
+    }
+    writers.flush().unwrap();
+
+    assert!(writers.failed_indices().is_empty());
+    assert_eq!(expect, writers.into_inner());
+}
+
+#[test]
+fn test_shard_writers_error_handling() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    assert_eq!(
+        Error::TooFewShards,
+        ShardWriters::new(&r, vec![Vec::<u8>::new(); 4]).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyShards,
+        ShardWriters::new(&r, vec![Vec::<u8>::new(); 6]).unwrap_err()
+    );
+
+    let mut writers = ShardWriters::new(&r, vec![Vec::<u8>::new(); 5]).unwrap();
+
+    match writers.write_shards(&[[0u8; 4]; 4]) {
+        Err(WriteError::RSError(Error::TooFewShards)) => {}
+        _ => panic!(),
+    }
+    match writers.write_shards(&[vec![0u8; 4], vec![0; 4], vec![0; 3], vec![0; 4], vec![0; 4]]) {
+        Err(WriteError::RSError(Error::IncorrectShardSize)) => {}
+        _ => panic!(),
+    }
+
+    // nothing is written on shape errors
+    assert!(writers.into_inner().iter().all(|w| w.is_empty()));
+}
+
+#[test]
+fn test_shard_writers_report_failed_destinations() {
+    let r = ReedSolomon::new(2, 2).unwrap();
+
+    let destinations: Vec<Box<dyn Write>> = vec![
+        Box::new(Vec::new()),
+        Box::new(FailingWriter),
+        Box::new(Vec::new()),
+        Box::new(FailingWriter),
+    ];
+    let mut writers = ShardWriters::new(&r, destinations).unwrap();
+
+    match writers.write_shards(&[[1u8; 4]; 4]) {
+        Err(WriteError::Io(errors)) => {
+            let failed: Vec<_> = errors.iter().map(|&(i, _)| i).collect();
+            assert_eq!(vec![1, 3], failed);
+        }
+        _ => panic!(),
+    }
+    assert_eq!(vec![1, 3], writers.failed_indices());
+
+    // failed destinations are skipped afterwards
+    writers.write_shards(&[[2u8; 4]; 4]).unwrap();
+    writers.flush().unwrap();
+}
+
+#[test]
+fn test_write_error_into_io_error() {
+    let r = ReedSolomon::new(2, 2).unwrap();
+
+    let write = |destinations: Vec<Box<dyn Write>>, shards: &[[u8; 4]]| -> io::Result<()> {
+        let mut writers = ShardWriters::new(&r, destinations)?;
+        writers.write_shards(shards)?;
+        Ok(())
+    };
+    let destinations = || -> Vec<Box<dyn Write>> {
+        vec![
+            Box::new(Vec::new()),
+            Box::new(FailingWriter),
+            Box::new(Vec::new()),
+            Box::new(FailingWriter),
+        ]
+    };
+
+    let e = write(destinations(), &[[1u8; 4]; 3]).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+    assert_eq!(
+        Error::TooFewShards,
+        *e.into_inner().unwrap().downcast::<Error>().unwrap()
+    );
+
+    // all failed destinations are kept
+    let e = write(destinations(), &[[1u8; 4]; 4]).unwrap_err();
+    assert_eq!(io::ErrorKind::Other, e.kind());
+    match *e.into_inner().unwrap().downcast::<WriteError>().unwrap() {
+        WriteError::Io(errors) => assert_eq!(2, errors.len()),
+        _ => panic!(),
+    }
+
+    let mut destinations = destinations();
+    destinations[3] = Box::new(Vec::new());
+    let e = write(destinations, &[[1u8; 4]; 4]).unwrap_err();
+    assert_eq!(io::ErrorKind::Other, e.kind());
+    assert!(e.into_inner().is_none());
+}
+
+#[test]
+fn test_encode_to_writers() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+
+    let mut shards = vec![vec![0u8; 1000]; 7];
+    for shard in shards.iter_mut().take(4) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+
+    for &window_len in [1, 7, 100, 999, 1000, 4096].iter() {
+        let mut writers = vec![Vec::new(); 3];
+
+        r.encode_to_writers(&shards[..4], &mut writers, window_len)
+            .unwrap();
+
+        assert_eq!(&shards[4..], &writers[..]);
+    }
+}
+
+#[test]
+fn test_encode_to_writers_error_handling() {
+    let r = ReedSolomon::new(2, 2).unwrap();
+
+    let data = vec![vec![1u8; 10]; 2];
+
+    let error_of = |e: io::Error| -> Error {
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        *e.into_inner().unwrap().downcast::<Error>().unwrap()
+    };
+
+    let e = r
+        .encode_to_writers(&data, &mut vec![Vec::new(); 2], 0)
+        .unwrap_err();
+    assert_eq!(Error::EmptyShard, error_of(e));
+
+    let e = r
+        .encode_to_writers(&data[..1], &mut vec![Vec::new(); 2], 4)
+        .unwrap_err();
+    assert_eq!(Error::TooFewDataShards, error_of(e));
+
+    let e = r
+        .encode_to_writers(&data, &mut vec![Vec::new(); 3], 4)
+        .unwrap_err();
+    assert_eq!(Error::TooManyParityShards, error_of(e));
+
+    let e = r
+        .encode_to_writers(&[vec![1u8; 10], vec![1u8; 9]], &mut vec![Vec::new(); 2], 4)
+        .unwrap_err();
+    assert_eq!(Error::IncorrectShardSize, error_of(e));
+}
+
+#[test]
+fn test_reconstruct_windowed() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+
+    let mut shards = vec![vec![0u8; 1000]; 7];
+    for shard in shards.iter_mut().take(4) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+
+    for &window_len in [1, 7, 100, 999, 1000, 4096].iter() {
+        let mut readers: Vec<_> = shards
+            .iter()
+            .map(|s| Some(Cursor::new(s.clone())))
+            .collect();
+        readers[0] = None;
+        readers[5] = None;
+
+        let mut writers: Vec<Option<Vec<u8>>> = vec![None; 7];
+        writers[0] = Some(Vec::new());
+        writers[5] = Some(Vec::new());
+
+        let shard_len = r
+            .reconstruct_windowed(&mut readers, &mut writers, window_len)
+            .unwrap();
+
+        assert_eq!(1000, shard_len);
+        assert_eq!(Some(&shards[0]), writers[0].as_ref());
+        assert_eq!(Some(&shards[5]), writers[5].as_ref());
+
+        // shards beyond the first 4 present ones are not read
+        assert_eq!(0, readers[6].as_ref().unwrap().position());
+    }
+}
+
+#[test]
+fn test_reconstruct_resumable() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+
+    let mut shards = vec![vec![0u8; 1000]; 7];
+    for shard in shards.iter_mut().take(4) {
+        fill_random(shard);
+    }
+    r.encode(&mut shards).unwrap();
+
+    let readers = |missing: &[usize]| -> Vec<Option<Cursor<Vec<u8>>>> {
+        shards
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                if missing.contains(&i) {
+                    None
+                } else {
+                    Some(Cursor::new(s.clone()))
+                }
+            })
+            .collect()
+    };
+    let mut writers: Vec<Option<Cursor<Vec<u8>>>> = vec![None; 7];
+    writers[1] = Some(Cursor::new(Vec::new()));
+    writers[6] = Some(Cursor::new(Vec::new()));
+
+    // interrupt the repair once 300 bytes are written
+    let mut stored = Vec::new();
+    let e = r
+        .reconstruct_resumable(
+            &mut readers(&[1, 6]),
+            &mut writers,
+            128,
+            None,
+            |checkpoint| {
+                stored = checkpoint.to_bytes();
+                if checkpoint.offset() >= 300 {
+                    Err(io::ErrorKind::Interrupted.into())
+                } else {
+                    Ok(())
+                }
+            },
+        )
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::Interrupted, e.kind());
+
+    let checkpoint = RepairCheckpoint::from_bytes(&stored).unwrap();
+    assert_eq!(384, checkpoint.offset());
+    assert_eq!(&[1, 6], checkpoint.wanted());
+    assert_eq!(
+        &shards[1][..384],
+        &writers[1].as_ref().unwrap().get_ref()[..]
+    );
+
+    // garbage past the checkpoint is overwritten, and another shard can be
+    // read in place of shard 0
+    writers[1]
+        .as_mut()
+        .unwrap()
+        .get_mut()
+        .extend_from_slice(&[0xff; 50]);
+    let mut count = 0;
+    let shard_len = r
+        .reconstruct_resumable(
+            &mut readers(&[0, 1, 6]),
+            &mut writers,
+            128,
+            Some(&checkpoint),
+            |_| {
+                count += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+    assert_eq!(1000, shard_len);
+    assert_eq!(5, count);
+    assert_eq!(&shards[1], writers[1].as_ref().unwrap().get_ref());
+    assert_eq!(&shards[6], writers[6].as_ref().unwrap().get_ref());
+
+    let error_of = |e: io::Error| -> Error {
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        *e.into_inner().unwrap().downcast::<Error>().unwrap()
+    };
+    writers[6] = None;
+    let e = r
+        .reconstruct_resumable(
+            &mut readers(&[1]),
+            &mut writers,
+            128,
+            Some(&checkpoint),
+            |_| Ok(()),
+        )
+        .unwrap_err();
+    assert_eq!(Error::InvalidCheckpoint, error_of(e));
+
+    let r = ReedSolomon::new(5, 2).unwrap();
+    let e = r
+        .reconstruct_resumable(
+            &mut readers(&[1, 6]),
+            &mut writers,
+            128,
+            Some(&checkpoint),
+            |_| Ok(()),
+        )
+        .unwrap_err();
+    assert_eq!(Error::InvalidCheckpoint, error_of(e));
+
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        RepairCheckpoint::from_bytes(&stored[..stored.len() - 1])
+            .unwrap_err()
+            .kind()
+    );
+    let mut corrupt = stored.clone();
+    corrupt[19] = 7;
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        RepairCheckpoint::from_bytes(&corrupt).unwrap_err().kind()
+    );
+}
+
+#[test]
+fn test_reconstruct_windowed_error_handling() {
+    let r = ReedSolomon::new(2, 2).unwrap();
+
+    let shard = vec![1u8; 10];
+    let readers = || -> Vec<Option<Cursor<Vec<u8>>>> {
+        vec![
+            None,
+            Some(Cursor::new(shard.clone())),
+            Some(Cursor::new(shard.clone())),
+            None,
+        ]
+    };
+    let writers = || -> Vec<Option<Vec<u8>>> { vec![Some(Vec::new()), None, None, None] };
+
+    let error_of = |e: io::Error| -> Error {
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        *e.into_inner().unwrap().downcast::<Error>().unwrap()
+    };
+
+    let e = r
+        .reconstruct_windowed(&mut readers(), &mut writers(), 0)
+        .unwrap_err();
+    assert_eq!(Error::EmptyShard, error_of(e));
+
+    let e = r
+        .reconstruct_windowed(&mut readers()[..3], &mut writers(), 4)
+        .unwrap_err();
+    assert_eq!(Error::TooFewShards, error_of(e));
+
+    let mut w = writers();
+    w[1] = Some(Vec::new());
+    let e = r
+        .reconstruct_windowed(&mut readers(), &mut w, 4)
+        .unwrap_err();
+    assert_eq!(Error::InvalidShardFlags, error_of(e));
+
+    let mut rd = readers();
+    rd[2] = None;
+    let e = r
+        .reconstruct_windowed(&mut rd, &mut writers(), 4)
+        .unwrap_err();
+    assert_eq!(Error::TooFewShardsPresent, error_of(e));
+
+    let mut rd = readers();
+    rd[2] = Some(Cursor::new(vec![1u8; 9]));
+    let e = r
+        .reconstruct_windowed(&mut rd, &mut writers(), 4)
+        .unwrap_err();
+    assert_eq!(Error::IncorrectShardSize, error_of(e));
+}
+
+#[test]
+fn test_split_join() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    for &len in [1, 3, 4, 5, 100, 101, 103].iter() {
+        let mut data = vec![0u8; len];
+        fill_random(&mut data);
+
+        let mut shards = r.split(&data).unwrap();
+        assert_eq!(4, shards.len());
+        for shard in shards.iter() {
+            assert_eq!(len.div_ceil(4), shard.len());
+        }
+
+        let mut joined = Vec::new();
+        r.join(&mut joined, &shards, len).unwrap();
+        assert_eq!(data, joined);
+
+        // parity shards are ignored
+        r.encode_append(&mut shards).unwrap();
+        let mut joined = Vec::new();
+        r.join(&mut joined, &shards, len).unwrap();
+        assert_eq!(data, joined);
+    }
+}
+
+#[test]
+fn test_split_join_error_handling() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    assert_eq!(Error::EmptyShard, r.split(&[]).unwrap_err());
+
+    let error_of = |e: io::Error| -> Error {
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        *e.into_inner().unwrap().downcast::<Error>().unwrap()
+    };
+
+    let shards = r.split(&[1u8; 10]).unwrap();
+    let mut out = Vec::new();
+
+    let e = r.join(&mut out, &shards[..3], 10).unwrap_err();
+    assert_eq!(Error::TooFewDataShards, error_of(e));
+
+    let e = r.join(&mut out, &shards, 13).unwrap_err();
+    assert_eq!(Error::IncorrectShardSize, error_of(e));
+
+    let mut uneven = shards.clone();
+    uneven[2].push(0);
+    let e = r.join(&mut out, &uneven, 10).unwrap_err();
+    assert_eq!(Error::IncorrectShardSize, error_of(e));
+
+    assert!(out.is_empty());
+
+    r.join(&mut out, &shards, 0).unwrap();
+    assert!(out.is_empty());
+}
+
+/// Run-length encoding of bytes, then XOR with a key depending on the shard.
+struct RleXor;
+
+impl ShardTransform for RleXor {
+    fn store(&mut self, index: usize, shard: &[u8]) -> io::Result<Vec<u8>> {
+        let mut stored = Vec::new();
+        for &byte in shard.iter() {
+            match stored.len() {
+                len if len >= 2 && stored[len - 1] == byte && stored[len - 2] < 255 => {
+                    stored[len - 2] += 1
+                }
+                _ => stored.extend_from_slice(&[1, byte]),
+            }
+        }
+        Ok(stored.into_iter().map(|b| b ^ index as u8).collect())
+    }
+
+    fn load(&mut self, index: usize, stored: &[u8]) -> io::Result<Vec<u8>> {
+        if !stored.len().is_multiple_of(2) {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let mut shard = Vec::new();
+        for pair in stored.chunks(2) {
+            let count = pair[0] ^ index as u8;
+            shard.extend(std::iter::repeat_n(pair[1] ^ index as u8, count as usize));
+        }
+        Ok(shard)
+    }
+}
+
+#[test]
+fn test_shard_transform_round_trip() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    // compressible data
+    let mut data = vec![0u8; 3000];
+    fill_random(&mut data[..500]);
+
+    let stripes: Vec<_> = r
+        .stripes(&data[..], 250)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+    let mut writers = ShardWriters::new(&r, vec![Vec::new(); 5]).unwrap();
+    writers.set_transform(Box::new(RleXor));
+    for stripe in stripes.iter() {
+        writers.write_stripe(stripe).unwrap();
+    }
+    let stored = writers.into_inner();
+    // the zeros are compressed
+    assert!(stored[2].len() < 4 * 250);
+
+    let readers: Vec<_> = stored
+        .iter()
+        .enumerate()
+        .map(|(i, s)| if i == 0 || i == 3 { None } else { Some(&s[..]) })
+        .collect();
+    let mut readers = ShardReaders::new(&r, readers, Box::new(RleXor)).unwrap();
+
+    for stripe in stripes.iter() {
+        let mut shards = readers.read_stripe().unwrap();
+        assert!(shards[0].is_none());
+        r.reconstruct(&mut shards).unwrap();
+
+        let shards: Vec<_> = shards.into_iter().map(Option::unwrap).collect();
+        assert_eq!(stripe.shards(), &shards[..]);
+    }
+    assert!(readers.read_stripe().is_none());
+    assert_eq!(vec![0, 3], readers.failed_indices());
+}
+
+#[test]
+fn test_shard_readers_fail_on_corrupt_frames() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut writers = ShardWriters::new(&r, vec![Vec::new(); 5]).unwrap();
+    writers.set_transform(Box::new(RleXor));
+    let mut shards = vec![vec![7u8; 10]; 5];
+    r.encode(&mut shards).unwrap();
+    writers.write_shards(&shards).unwrap();
+    writers.write_shards(&shards).unwrap();
+
+    let mut stored = writers.into_inner();
+    // an odd length fails to load
+    stored[1][0] = 1;
+    // a truncated frame fails to read
+    let len = stored[4].len();
+    stored[4].truncate(len - 1);
+
+    let readers: Vec<_> = stored.iter().map(|s| Some(&s[..])).collect();
+    let mut readers = ShardReaders::new(&r, readers, Box::new(RleXor)).unwrap();
+
+    let first = readers.read_stripe().unwrap();
+    assert!(first[1].is_none());
+    assert_eq!(Some(&shards[4]), first[4].as_ref());
+    assert_eq!(vec![1], readers.failed_indices());
+
+    let second = readers.read_stripe().unwrap();
+    assert!(second[1].is_none());
+    assert!(second[4].is_none());
+    assert_eq!(Some(&shards[0]), second[0].as_ref());
+    assert_eq!(vec![1, 4], readers.failed_indices());
+
+    assert!(readers.read_stripe().is_none());
+
+    assert_eq!(
+        Error::TooFewShards,
+        ShardReaders::new(&r, vec![Some(&[][..]); 4], Box::new(RleXor)).unwrap_err()
+    );
+}
+
+#[test]
+fn test_shard_writers_transform_failure() {
+    struct Failing;
+
+    impl ShardTransform for Failing {
+        fn store(&mut self, index: usize, shard: &[u8]) -> io::Result<Vec<u8>> {
+            if index == 2 {
+                Err(io::ErrorKind::Other.into())
+            } else {
+                Ok(shard.to_vec())
+            }
+        }
+
+        fn load(&mut self, _index: usize, stored: &[u8]) -> io::Result<Vec<u8>> {
+            Ok(stored.to_vec())
+        }
+    }
+
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut writers = ShardWriters::new(&r, vec![Vec::new(); 5]).unwrap();
+    writers.set_transform(Box::new(Failing));
+
+    match writers.write_shards(&vec![vec![1u8; 10]; 5]) {
+        Err(WriteError::Io(errors)) => {
+            assert_eq!(1, errors.len());
+            assert_eq!(2, errors[0].0);
+        }
+        _ => panic!("expected a write error"),
+    }
+    assert_eq!(vec![2], writers.failed_indices());
+
+    let stored = writers.into_inner();
+    assert!(stored[2].is_empty());
+    assert_eq!(8 + 10, stored[0].len());
+}
+
+#[test]
+fn test_encode_decode_stream_exact_length() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+
+    for &input_len in [0, 1, 399, 400, 4 * 100 * 3 + 123].iter() {
+        let mut input = vec![0u8; input_len];
+        fill_random(&mut input);
+
+        let mut writers = vec![Vec::new(); 6];
+        let written = r.encode_stream_to(&input[..], &mut writers, 100).unwrap();
+        assert_eq!(input_len as u64, written);
+
+        let mut readers: Vec<_> = writers.iter().map(|w| Some(&w[..])).collect();
+        readers[1] = None;
+        readers[4] = None;
+
+        let mut output = Vec::new();
+        let read = r.decode_stream_from(&mut readers, &mut output).unwrap();
+
+        assert_eq!(input_len as u64, read);
+        assert_eq!(input, output);
+    }
+}
+
+#[test]
+fn test_decode_stream_skips_bad_readers() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut input = vec![0u8; 1000];
+    fill_random(&mut input);
+
+    let mut writers = vec![Vec::new(); 5];
+    r.encode_stream_to(&input[..], &mut writers, 64).unwrap();
+
+    // swapped destinations do not match their position
+    writers.swap(0, 1);
+    // truncated destination
+    let len = writers[2].len();
+    writers[2].truncate(len - 10);
+
+    let mut readers: Vec<_> = writers.iter().map(|w| Some(&w[..])).collect();
+    readers.push(None);
+    let mut output = Vec::new();
+    assert_eq!(
+        io::ErrorKind::InvalidInput,
+        r.decode_stream_from(&mut readers, &mut output)
+            .unwrap_err()
+            .kind()
+    );
+
+    let mut readers: Vec<_> = writers.iter().map(|w| Some(&w[..])).collect();
+    let mut output = Vec::new();
+    let e = r.decode_stream_from(&mut readers, &mut output).unwrap_err();
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        *e.into_inner().unwrap().downcast::<Error>().unwrap()
+    );
+    assert!(readers[0].is_none());
+    assert!(readers[1].is_none());
+    assert!(readers[2].is_none());
+
+    writers.swap(0, 1);
+    let mut readers: Vec<_> = writers.iter().map(|w| Some(&w[..])).collect();
+    let mut output = Vec::new();
+    r.decode_stream_from(&mut readers, &mut output).unwrap();
+    assert_eq!(input, output);
+    assert!(readers[2].is_none());
+}
+
+#[test]
+fn test_decode_stream_drops_readers_disagreeing_with_most() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let mut input = vec![0u8; 1000];
+    fill_random(&mut input);
+
+    let mut writers = vec![Vec::new(); 5];
+    r.encode_stream_to(&input[..], &mut writers, 64).unwrap();
+    // the stream header ends with the shard length, and is followed by
+    // the length of the first stripe
+    let header_len = 20;
+
+    // a wrong length of the first stripe of shard 0
+    let mut corrupted = writers.clone();
+    corrupted[0][header_len] ^= 1;
+    let mut readers: Vec<_> = corrupted.iter().map(|w| Some(&w[..])).collect();
+    let mut output = Vec::new();
+    r.decode_stream_from(&mut readers, &mut output).unwrap();
+    assert_eq!(input, output);
+    assert!(readers[0].is_none());
+    assert!(readers[1..].iter().all(Option::is_some));
+
+    // a wrong shard length in the header of shard 0
+    let mut corrupted = writers.clone();
+    corrupted[0][header_len - 4] ^= 1;
+    let mut readers: Vec<_> = corrupted.iter().map(|w| Some(&w[..])).collect();
+    let mut output = Vec::new();
+    r.decode_stream_from(&mut readers, &mut output).unwrap();
+    assert_eq!(input, output);
+    assert!(readers[0].is_none());
+    assert!(readers[1..].iter().all(Option::is_some));
+}
+
+#[test]
+fn test_encode_stream_to_error_handling() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+
+    let error_of = |e: io::Error| -> Error {
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        *e.into_inner().unwrap().downcast::<Error>().unwrap()
+    };
+
+    let e = r
+        .encode_stream_to(&[1u8; 10][..], &mut vec![Vec::new(); 4], 4)
+        .unwrap_err();
+    assert_eq!(Error::TooFewShards, error_of(e));
+
+    let e = r
+        .encode_stream_to(&[1u8; 10][..], &mut vec![Vec::new(); 5], 0)
+        .unwrap_err();
+    assert_eq!(Error::EmptyShard, error_of(e));
+}
+
+#[test]
+fn test_encode_stream_to_par_matches_serial() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+    let mut param = ParallelParam::new(16);
+    param.min_bytes_per_thread = 0;
+
+    for &input_len in &[0, 1, 191, 192, 193, 2000] {
+        let mut input = vec![0u8; input_len];
+        fill_random(&mut input);
+
+        let mut expect = vec![Vec::new(); 5];
+        r.encode_stream_to(&input[..], &mut expect, 64).unwrap();
+
+        let mut writers = vec![Vec::new(); 5];
+        let len = r
+            .encode_stream_to_par(&input[..], &mut writers, 64, param)
+            .unwrap();
+        assert_eq!(input_len as u64, len);
+        assert_eq!(expect, writers);
+    }
+}
+
+/// Reader failing once `len` bytes are read.
+struct ShortReader {
+    len: usize,
+}
+
+impl Read for ShortReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.len == 0 {
+            return Err(io::ErrorKind::Other.into());
+        }
+        let n = buf.len().min(self.len);
+        self.len -= n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_encode_stream_to_par_error_handling() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+    let param = ParallelParam::new(16);
+
+    let e = r
+        .encode_stream_to_par(&[1u8; 10][..], &mut vec![Vec::new(); 4], 4, param)
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+    let e = r
+        .encode_stream_to_par(&[1u8; 10][..], &mut vec![Vec::new(); 5], 0, param)
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+    // the stripes read before the error are written
+    let mut writers = vec![Vec::new(); 5];
+    let e = r
+        .encode_stream_to_par(ShortReader { len: 1000 }, &mut writers, 64, param)
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::Other, e.kind());
+    assert!(writers[0].len() > 5 * 64);
+
+    let mut writers: Vec<_> = (0..5).map(|_| FailingWriter).collect();
+    let e = r
+        .encode_stream_to_par(&[1u8; 100_000][..], &mut writers, 64, param)
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::Other, e.kind());
+}
@@ -0,0 +1,87 @@
+use std::io::{Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{fill_random, ReedSolomon};
+use crate::throttle::{Rate, Throttle, Throttled};
+use crate::Error;
+
+#[test]
+fn test_throttle_bytes_per_sec() {
+    let throttle = Throttle::new(Rate::BytesPerSec(10_000)).unwrap();
+    let mut data = vec![0u8; 3_000];
+    fill_random(&mut data);
+
+    // the first 1000 bytes are the burst, the other 2000 take 200ms
+    let start = Instant::now();
+    let mut copy = Vec::new();
+    Throttled::new(&data[..], &throttle)
+        .read_to_end(&mut copy)
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(190));
+    assert_eq!(data, copy);
+
+    let start = Instant::now();
+    let mut writer = Throttled::new(Vec::new(), &throttle);
+    writer.write_all(&data[..500]).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(40));
+    assert_eq!(&data[..500], &writer.into_inner()[..]);
+}
+
+#[test]
+fn test_throttle_shared_between_threads() {
+    let throttle = Throttle::new(Rate::BytesPerSec(20_000)).unwrap();
+
+    let start = Instant::now();
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..5 {
+                    throttle.pace(250);
+                }
+            });
+        }
+    });
+    // 5000 bytes, of which 2000 are the burst
+    assert!(start.elapsed() >= Duration::from_millis(140));
+}
+
+#[test]
+fn test_throttle_duty_cycle() {
+    let throttle = Throttle::new(Rate::DutyCycle(0.25)).unwrap();
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    let start = Instant::now();
+    let mut work = Duration::from_secs(0);
+    for _ in 0..4 {
+        let mut shards = vec![vec![0u8; 10_000]; 14];
+        for shard in &mut shards[..10] {
+            fill_random(shard);
+        }
+        let stripe = Instant::now();
+        r.encode(&mut shards).unwrap();
+        thread::sleep(Duration::from_millis(5));
+        work += stripe.elapsed();
+        throttle.pace(140_000);
+    }
+    assert!(start.elapsed() >= work * 4 - Duration::from_millis(5));
+}
+
+#[test]
+fn test_throttle_invalid_rate() {
+    for &rate in &[
+        Rate::BytesPerSec(0),
+        Rate::DutyCycle(0.0),
+        Rate::DutyCycle(-0.5),
+        Rate::DutyCycle(1.5),
+        Rate::DutyCycle(f64::NAN),
+    ] {
+        assert_eq!(Error::InvalidRate, Throttle::new(rate).unwrap_err());
+    }
+
+    let throttle = Throttle::new(Rate::DutyCycle(1.0)).unwrap();
+    assert_eq!(Rate::DutyCycle(1.0), throttle.rate());
+    let start = Instant::now();
+    throttle.pace(1 << 30);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,79 @@
+use super::{fill_random, ReedSolomon};
+use crate::galois_16;
+use crate::tiny::TinyCodec;
+use crate::Error;
+
+#[test]
+fn test_tiny_encode() {
+    for r in [
+        ReedSolomon::new(1, 1).unwrap(),
+        ReedSolomon::new(5, 3).unwrap(),
+        ReedSolomon::new_cauchy(10, 4).unwrap(),
+    ] {
+        let tiny = TinyCodec::new(r.clone());
+        for &len in [1, 7, 64, 255, 1000].iter() {
+            let mut expect = vec![vec![0u8; len]; r.total_shard_count()];
+            for shard in expect.iter_mut().take(r.data_shard_count()) {
+                fill_random(shard);
+            }
+            r.encode(&mut expect).unwrap();
+
+            let mut shards = expect.clone();
+            for shard in shards.iter_mut().skip(r.data_shard_count()) {
+                fill_random(shard);
+            }
+            tiny.encode(&mut shards).unwrap();
+            assert_eq!(expect, shards);
+
+            let mut parity = vec![vec![0xFFu8; len]; r.parity_shard_count()];
+            tiny.encode_sep(&expect[..r.data_shard_count()], &mut parity)
+                .unwrap();
+            assert_eq!(&expect[r.data_shard_count()..], &parity[..]);
+        }
+    }
+
+    let r = galois_16::ReedSolomon::new(3, 2).unwrap();
+    let tiny = TinyCodec::new(r.clone());
+    let mut expect = vec![vec![[0u8; 2]; 10]; 5];
+    for (i, x) in expect.iter_mut().take(3).flatten().enumerate() {
+        *x = [i as u8, (i * 7) as u8];
+    }
+    r.encode(&mut expect).unwrap();
+    let mut shards = expect.clone();
+    shards[3] = vec![[0u8; 2]; 10];
+    tiny.encode(&mut shards).unwrap();
+    assert_eq!(expect, shards);
+}
+
+#[test]
+fn test_tiny_encode_errors() {
+    let tiny = TinyCodec::new(ReedSolomon::new(3, 2).unwrap());
+    assert_eq!(3, tiny.codec().data_shard_count());
+
+    let mut shards = vec![vec![0u8; 16]; 5];
+    assert_eq!(
+        Error::TooFewShards,
+        tiny.encode(&mut shards[1..]).unwrap_err()
+    );
+    shards[4].pop();
+    assert_eq!(
+        Error::IncorrectShardSize,
+        tiny.encode(&mut shards).unwrap_err()
+    );
+    let mut empty = vec![vec![0u8; 0]; 5];
+    assert_eq!(Error::EmptyShard, tiny.encode(&mut empty).unwrap_err());
+
+    let (data, parity) = shards.split_at_mut(3);
+    assert_eq!(
+        Error::TooFewDataShards,
+        tiny.encode_sep(&data[1..], parity).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewParityShards,
+        tiny.encode_sep(data, &mut parity[1..]).unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        tiny.encode_sep(data, parity).unwrap_err()
+    );
+}
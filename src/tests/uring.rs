@@ -0,0 +1,176 @@
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+
+use super::{fill_random, ReedSolomon};
+use crate::stream::WriteError;
+use crate::uring::UringShardFiles;
+use crate::Error;
+
+fn shard_paths(name: &str, count: usize) -> Vec<PathBuf> {
+    (0..count)
+        .map(|i| {
+            std::env::temp_dir().join(format!(
+                "reed-solomon-erasure-uring-{}-{}-{}",
+                name,
+                std::process::id(),
+                i
+            ))
+        })
+        .collect()
+}
+
+fn open(paths: &[PathBuf]) -> Vec<Option<File>> {
+    paths
+        .iter()
+        .map(|path| {
+            Some(
+                OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(path)
+                    .unwrap(),
+            )
+        })
+        .collect()
+}
+
+// io_uring may be disabled, e.g. by the seccomp policy of a container
+fn uring_files(
+    r: &ReedSolomon,
+    files: Vec<Option<File>>,
+    shard_len: usize,
+) -> Option<UringShardFiles> {
+    match UringShardFiles::new(r, files, shard_len) {
+        Ok(files) => Some(files),
+        Err(ref e) if e.kind() != io::ErrorKind::InvalidInput => None,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+#[test]
+fn test_uring_shard_files_round_trip() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let paths = shard_paths("round-trip", 6);
+
+    let mut input = vec![0u8; 1000];
+    fill_random(&mut input);
+    let stripes: Vec<_> = r
+        .stripes(&input[..], 64)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(4, stripes.len());
+
+    let mut files = match uring_files(&r, open(&paths), 64) {
+        Some(files) => files,
+        None => return,
+    };
+    for stripe in stripes.iter() {
+        files.write_stripe(stripe).unwrap();
+    }
+    assert_eq!(4, files.next_stripe());
+    for path in paths.iter() {
+        assert_eq!(256, fs::metadata(path).unwrap().len());
+    }
+
+    // lose a data shard and a parity shard
+    let mut files = open(&paths);
+    files[1] = None;
+    files[5] = None;
+    let mut files = uring_files(&r, files, 64).unwrap();
+    for stripe in stripes.iter() {
+        let mut shards = files.read_stripe().unwrap();
+        assert!(shards[1].is_none() && shards[5].is_none());
+        r.reconstruct(&mut shards).unwrap();
+        let shards: Vec<_> = shards.into_iter().map(Option::unwrap).collect();
+        assert_eq!(stripe.shards(), &shards[..]);
+    }
+    assert!(files.read_stripe().is_none());
+    assert_eq!(vec![1, 5], files.failed_indices());
+
+    // repair a single stripe in place
+    files.seek_stripe(2);
+    let mut shards = files.read_stripe().unwrap();
+    r.reconstruct(&mut shards).unwrap();
+    let shards: Vec<_> = shards.into_iter().map(Option::unwrap).collect();
+    let mut files = uring_files(&r, open(&paths), 64).unwrap();
+    files.seek_stripe(2);
+    files.write_shards(&shards).unwrap();
+    files.seek_stripe(2);
+    let shards: Vec<_> = files
+        .read_stripe()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect();
+    assert_eq!(stripes[2].shards(), &shards[..]);
+
+    for path in paths.iter() {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_uring_shard_files_failures() {
+    let r = ReedSolomon::new(3, 2).unwrap();
+    let paths = shard_paths("failures", 5);
+
+    let err = UringShardFiles::new(&r, open(&paths[..4]), 16).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+    let err = UringShardFiles::new(&r, open(&paths), 0).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+    let mut files = open(&paths);
+    // a read only file fails to be written to
+    files[3] = Some(File::open(&paths[3]).unwrap());
+    let mut files = match uring_files(&r, files, 16) {
+        Some(files) => files,
+        None => return,
+    };
+
+    let shards = vec![vec![7u8; 16]; 5];
+    match files.write_shards(&shards[1..]) {
+        Err(WriteError::RSError(Error::TooFewShards)) => {}
+        other => panic!("{:?}", other),
+    }
+    match files.write_shards(&[
+        &shards[0][..],
+        &shards[1],
+        &shards[2],
+        &shards[3],
+        &shards[4][1..],
+    ]) {
+        Err(WriteError::RSError(Error::IncorrectShardSize)) => {}
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(0, files.next_stripe());
+
+    match files.write_shards(&shards) {
+        Err(WriteError::Io(errors)) => {
+            assert_eq!(1, errors.len());
+            assert_eq!(3, errors[0].0);
+        }
+        other => panic!("{:?}", other),
+    }
+    files.write_shards(&shards).unwrap();
+    assert_eq!(vec![3], files.failed_indices());
+
+    // shard 4 ends within the second stripe
+    let files = files.into_inner();
+    files[4].as_ref().unwrap().set_len(24).unwrap();
+    let mut files = uring_files(&r, files, 16).unwrap();
+    let shards = files.read_stripe().unwrap();
+    assert_eq!(Some(vec![7u8; 16]), shards[4]);
+    let shards = files.read_stripe().unwrap();
+    assert!(shards[3].is_none() && shards[4].is_none());
+    assert_eq!(Some(vec![7u8; 16]), shards[0]);
+    assert_eq!(vec![3, 4], files.failed_indices());
+    assert!(files.read_stripe().is_none());
+
+    for path in paths.iter() {
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use std::io;
+
+use super::ReedSolomon;
+use crate::vectors::TestVector;
+use crate::{Error, MatrixKind};
+
+#[test]
+fn test_vector_generate_check() {
+    for &(data, parity, len) in [(1, 1, 1), (3, 2, 4), (10, 4, 100), (17, 3, 33)].iter() {
+        for r in [
+            ReedSolomon::new(data, parity).unwrap(),
+            ReedSolomon::new_cauchy(data, parity).unwrap(),
+        ]
+        .iter()
+        {
+            let v = TestVector::generate(r, len, 42).unwrap();
+            assert_eq!(r.matrix_kind(), v.matrix_kind());
+            assert_eq!(data + parity, v.shards().len());
+            assert_eq!(data + parity, v.matrix().len());
+            assert_eq!(3, v.erasures().len());
+            assert!(v.erasures().iter().all(|e| e.len() == parity));
+            assert!(v.check().unwrap());
+
+            assert_eq!(v, TestVector::generate(r, len, 42).unwrap());
+            assert_ne!(v, TestVector::generate(r, len, 43).unwrap());
+        }
+    }
+
+    let r = ReedSolomon::new(3, 2).unwrap();
+    assert_eq!(
+        Error::EmptyShard,
+        TestVector::generate(&r, 0, 1).unwrap_err()
+    );
+}
+
+#[test]
+fn test_vector_golden_json() {
+    // pinned output, changes here break compatibility with exported vectors
+    let r = ReedSolomon::new(3, 2).unwrap();
+    assert_eq!(
+        concat!(
+            "{\"field\": \"GF(2^8)\", \"polynomial\": \"0x11d\", \"matrix_kind\": \"vandermonde\", ",
+            "\"data_shards\": 3, \"parity_shards\": 2, \"shard_len\": 4, ",
+            "\"matrix\": [\"010000\", \"000100\", \"000001\", \"010101\", \"0f0806\"], ",
+            "\"shards\": [\"f3658d19\", \"02487fb3\", \"9e42905a\", \"6f6f62f0\", \"5bc200b7\"], ",
+            "\"erasures\": [[0, 1], [3, 4], [1, 4]]}"
+        ),
+        TestVector::generate(&r, 4, 1).unwrap().to_json()
+    );
+
+    let r = ReedSolomon::new_cauchy(3, 2).unwrap();
+    assert_eq!(
+        concat!(
+            "{\"field\": \"GF(2^8)\", \"polynomial\": \"0x11d\", \"matrix_kind\": \"cauchy\", ",
+            "\"data_shards\": 3, \"parity_shards\": 2, \"shard_len\": 4, ",
+            "\"matrix\": [\"010000\", \"000100\", \"000001\", \"f48e01\", \"47a77a\"], ",
+            "\"shards\": [\"f3658d19\", \"02487fb3\", \"9e42905a\", \"ce455a71\", \"68a2162c\"], ",
+            "\"erasures\": [[0, 1], [3, 4], [1, 4]]}"
+        ),
+        TestVector::generate(&r, 4, 1).unwrap().to_json()
+    );
+}
+
+#[test]
+fn test_vector_bytes_round_trip() {
+    let r = ReedSolomon::new_cauchy(5, 3).unwrap();
+    let v = TestVector::generate(&r, 20, 7).unwrap();
+
+    let bytes = v.to_bytes();
+    assert_eq!(b"RSTV", &bytes[..4]);
+    assert_eq!(
+        4 + 1 + 1 + 2 + 2 + 4 + 8 * 5 + 8 * 20 + 2 + 3 * (2 + 3 * 2),
+        bytes.len()
+    );
+
+    let parsed = TestVector::from_bytes(&bytes).unwrap();
+    assert_eq!(v, parsed);
+    assert_eq!(MatrixKind::Cauchy, parsed.matrix_kind());
+    assert!(parsed.check().unwrap());
+
+    for len in [0, 3, 10, bytes.len() - 1].iter() {
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            TestVector::from_bytes(&bytes[..*len]).unwrap_err().kind()
+        );
+    }
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(TestVector::from_bytes(&trailing).is_err());
+
+    let mut bad_version = bytes.clone();
+    bad_version[4] = 2;
+    assert!(TestVector::from_bytes(&bad_version).is_err());
+}
+
+#[test]
+fn test_vector_check_detects_mismatch() {
+    let r = ReedSolomon::new(4, 2).unwrap();
+    let bytes = TestVector::generate(&r, 8, 3).unwrap().to_bytes();
+
+    // a parity byte
+    let mut tampered = bytes.clone();
+    let parity_offset = 16 + 6 * 4 + 4 * 8;
+    tampered[parity_offset] ^= 1;
+    assert!(!TestVector::from_bytes(&tampered).unwrap().check().unwrap());
+
+    // a matrix coefficient
+    let mut tampered = bytes.clone();
+    tampered[16 + 4 * 4] ^= 1;
+    assert!(!TestVector::from_bytes(&tampered).unwrap().check().unwrap());
+
+    // the matrix kind
+    let mut tampered = bytes;
+    tampered[5] = 1;
+    assert!(!TestVector::from_bytes(&tampered).unwrap().check().unwrap());
+}
@@ -0,0 +1,164 @@
+//! Rate limiting of background jobs, such as scrubbing and re-encoding.
+//!
+//! Bulk jobs run as fast as the disks and the CPU allow, starving the
+//! requests served meanwhile. A `Throttle` slows such a job down to a
+//! number of bytes per second, or to a fraction of the time, by putting
+//! the thread doing the work to sleep.
+//!
+//! `Throttled` wraps the readers and writers given to the streaming
+//! adapters of the `stream` module, e.g. `ReedSolomon::stripes` or
+//! `ReedSolomon::reconstruct_windowed`, pacing every byte moved. Jobs
+//! working on whole stripes in memory, e.g. with
+//! `ReedSolomon::scrub_and_repair`, call `Throttle::pace` with the size of
+//! every stripe done instead.
+//!
+//! A throttle can be shared by all the threads of a job, or by several
+//! jobs, to cap them together.
+
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+/// The pace a `Throttle` holds a job to.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Rate {
+    /// At most this many bytes per second, with bursts of up to a tenth of
+    /// a second of work.
+    BytesPerSec(u64),
+    /// Working at most this fraction of the time, in `(0, 1]`, sleeping
+    /// the rest of the time.
+    ///
+    /// The work time is the time between two calls to `Throttle::pace`, so
+    /// this is meant for a throttle used by a single thread.
+    DutyCycle(f64),
+}
+
+#[derive(Debug)]
+struct State {
+    // bytes which may be moved without waiting, negative when in debt
+    available: f64,
+    // when `available` was last updated, or the last call returned
+    last: Instant,
+}
+
+/// Limits the pace of a job, see the module documentation.
+#[derive(Debug)]
+pub struct Throttle {
+    rate: Rate,
+    state: Mutex<State>,
+}
+
+impl Throttle {
+    /// Creates a throttle holding jobs to `rate`.
+    ///
+    /// Returns `Error::InvalidRate` for a rate of `0` bytes per second, or
+    /// a duty cycle not within `(0, 1]`.
+    pub fn new(rate: Rate) -> Result<Throttle, Error> {
+        let valid = match rate {
+            Rate::BytesPerSec(bytes) => bytes > 0,
+            Rate::DutyCycle(fraction) => fraction > 0.0 && fraction <= 1.0,
+        };
+        if !valid {
+            return Err(Error::InvalidRate);
+        }
+
+        let available = match rate {
+            Rate::BytesPerSec(bytes) => Self::burst(bytes),
+            Rate::DutyCycle(_) => 0.0,
+        };
+
+        Ok(Throttle {
+            rate,
+            state: Mutex::new(State {
+                available,
+                last: Instant::now(),
+            }),
+        })
+    }
+
+    pub fn rate(&self) -> Rate {
+        self.rate
+    }
+
+    /// Accounts for `bytes` of work just done, sleeping as long as needed
+    /// to stay within the rate.
+    ///
+    /// The lock of the throttle is not held while sleeping.
+    pub fn pace(&self, bytes: u64) {
+        let delay = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+
+            match self.rate {
+                Rate::BytesPerSec(rate) => {
+                    let rate = rate as f64;
+                    let refill = now.duration_since(state.last).as_secs_f64() * rate;
+                    state.available = (state.available + refill).min(Self::burst(rate as u64));
+                    state.last = now;
+                    state.available -= bytes as f64;
+
+                    if state.available < 0.0 {
+                        Duration::from_secs_f64(-state.available / rate)
+                    } else {
+                        Duration::from_secs(0)
+                    }
+                }
+                Rate::DutyCycle(fraction) => {
+                    let work = now.duration_since(state.last);
+                    let delay = work.mul_f64((1.0 - fraction) / fraction);
+                    state.last = now + delay;
+                    delay
+                }
+            }
+        };
+
+        if delay > Duration::from_secs(0) {
+            thread::sleep(delay);
+        }
+    }
+
+    fn burst(bytes_per_sec: u64) -> f64 {
+        (bytes_per_sec as f64 / 10.0).max(1.0)
+    }
+}
+
+/// Reader or writer pacing the bytes it moves with a `Throttle`.
+#[derive(Debug)]
+pub struct Throttled<'a, T> {
+    inner: T,
+    throttle: &'a Throttle,
+}
+
+impl<'a, T> Throttled<'a, T> {
+    pub fn new(inner: T, throttle: &'a Throttle) -> Throttled<'a, T> {
+        Throttled { inner, throttle }
+    }
+
+    /// Consumes the adapter, returning the wrapped reader or writer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<'a, R: Read> Read for Throttled<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.throttle.pace(read as u64);
+        Ok(read)
+    }
+}
+
+impl<'a, W: Write> Write for Throttled<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.throttle.pace(written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
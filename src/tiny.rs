@@ -0,0 +1,133 @@
+//! Encoding of tiny shards, e.g. the packets of a voice or game stream.
+//!
+//! For shards of a few hundred bytes, the set up of `ReedSolomon::encode`
+//! costs about as much as the coding itself: collecting the data shards,
+//! going through the coefficient matrix, and splitting the shards into
+//! chunks, to then hand out a few bytes at a time to the SIMD routines.
+//!
+//! `TinyCodec` instead compiles the parity rows of a codec upfront into the
+//! list of the data shards and coefficients each parity shard sums, leaving
+//! out the zero coefficients. Encoding then only walks the lists, in plain
+//! loops the compiler vectorizes, and never allocates.
+//!
+//! For larger shards, encode with the codec itself, which codes with SIMD
+//! routines with the `simd-accel` feature, and spreads the work over
+//! threads with the `_par` methods.
+
+use crate::{Error, Field, ReedSolomon};
+
+/// Codec encoding tiny shards with the parity rows compiled upfront.
+#[derive(Debug)]
+pub struct TinyCodec<F: Field> {
+    codec: ReedSolomon<F>,
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    total_shard_count: usize,
+    // the data shard index and coefficient of every non-zero coefficient of
+    // the parity rows, row after row
+    terms: Vec<(usize, F::Elem)>,
+    // `terms[row_ends[r - 1]..row_ends[r]]` are the terms of parity row `r`
+    row_ends: Vec<usize>,
+}
+
+impl<F: Field> TinyCodec<F> {
+    /// Compiles the parity rows of `codec`.
+    pub fn new(codec: ReedSolomon<F>) -> TinyCodec<F> {
+        let mut terms = Vec::new();
+        let mut row_ends = Vec::with_capacity(codec.parity_shard_count);
+        for row in codec.get_parity_rows() {
+            for (i, &c) in row.iter().enumerate() {
+                if c != F::zero() {
+                    terms.push((i, c));
+                }
+            }
+            row_ends.push(terms.len());
+        }
+
+        TinyCodec {
+            data_shard_count: codec.data_shard_count,
+            parity_shard_count: codec.parity_shard_count,
+            total_shard_count: codec.total_shard_count,
+            codec,
+            terms,
+            row_ends,
+        }
+    }
+
+    /// Returns the codec, for the operations other than encoding.
+    pub fn codec(&self) -> &ReedSolomon<F> {
+        &self.codec
+    }
+
+    /// Constructs the parity shards, as `ReedSolomon::encode`.
+    pub fn encode<T, U>(&self, mut shards: T) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        let slices: &mut [U] = shards.as_mut();
+
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let (data, parity) = slices.split_at_mut(self.data_shard_count);
+
+        self.encode_unchecked(data, parity);
+
+        Ok(())
+    }
+
+    /// Constructs the parity shards from separate data shards, as
+    /// `ReedSolomon::encode_sep`.
+    pub fn encode_sep<T, U>(&self, data: &[T], parity: &mut [U]) -> Result<(), Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
+    {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+
+        self.encode_unchecked(data, parity);
+
+        Ok(())
+    }
+
+    fn encode_unchecked<T, U>(&self, data: &[T], parity: &mut [U])
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsMut<[F::Elem]>,
+    {
+        let mut start = 0;
+        for (output, &end) in parity.iter_mut().zip(self.row_ends.iter()) {
+            let output = output.as_mut();
+            let terms = &self.terms[start..end];
+            start = end;
+
+            let (&(i, c), rest) = match terms.split_first() {
+                Some(first) => first,
+                None => {
+                    output.iter_mut().for_each(|x| *x = F::zero());
+                    continue;
+                }
+            };
+            for (x, &y) in output.iter_mut().zip(data[i].as_ref()) {
+                *x = F::mul(c, y);
+            }
+            for &(i, c) in rest {
+                let input = data[i].as_ref();
+                if c == F::one() {
+                    for (x, &y) in output.iter_mut().zip(input) {
+                        *x = F::add(*x, y);
+                    }
+                } else {
+                    for (x, &y) in output.iter_mut().zip(input) {
+                        *x = F::add(*x, F::mul(c, y));
+                    }
+                }
+            }
+        }
+
+        self.codec.count_encoded(data);
+    }
+}
@@ -0,0 +1,639 @@
+//! Shard file IO through io_uring, on Linux with the `io-uring` feature.
+//!
+//! Repairing shards stored on fast disks spends much of its time in
+//! syscalls when every shard of every stripe is read or written with its
+//! own call. `UringShardFiles` reads or writes all the shards of a stripe
+//! with a single batch of io_uring submissions, one file per shard.
+//!
+//! Shard `i` of stripe `s` is stored at offset `s * shard_len` of file
+//! `i`, as written by `ShardWriters` without a transform, so the stripes of
+//! `ReedSolomon::stripes` can be written with `UringShardFiles::write_stripe`
+//! and read back with `UringShardFiles::read_stripe`, ready for
+//! `ReedSolomon::reconstruct`.
+//!
+//! The kernel must be 5.6 or later. `UringShardFiles::new` fails if
+//! io_uring is unavailable, e.g. disabled by a seccomp policy, in which case
+//! the `std::io` adapters of the `stream` module are the fallback.
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::stream::{Stripe, WriteError, WriteResult};
+use crate::{Error, Field, ReedSolomon};
+
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x800_0000;
+const IORING_OFF_SQES: libc::off_t = 0x1000_0000;
+
+const IORING_ENTER_GETEVENTS: libc::c_uint = 1;
+
+const IORING_OP_READ: u8 = 22;
+const IORING_OP_WRITE: u8 = 23;
+
+/// Maximum number of submission queue entries of a ring.
+const MAX_ENTRIES: u32 = 256;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqRingOffsets,
+    cq_off: CqRingOffsets,
+}
+
+#[repr(C)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    rw_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+/// A mapping of one of the regions shared with the kernel.
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn new(fd: RawFd, len: usize, offset: libc::off_t) -> io::Result<Mmap> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Mmap { ptr, len })
+    }
+
+    fn at<T>(&self, offset: u32) -> *mut T {
+        unsafe { (self.ptr as *mut u8).add(offset as usize) as *mut T }
+    }
+
+    fn atomic(&self, offset: u32) -> &AtomicU32 {
+        unsafe { &*self.at::<AtomicU32>(offset) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+/// A read or write of a whole buffer, resubmitted until done.
+struct Op {
+    fd: RawFd,
+    write: bool,
+    buf: *mut u8,
+    len: usize,
+    offset: u64,
+    done: usize,
+    result: Option<io::Result<usize>>,
+}
+
+/// A submission and a completion queue shared with the kernel.
+struct Ring {
+    fd: RawFd,
+    sq: Mmap,
+    cq: Mmap,
+    sqes: Mmap,
+    params: Params,
+    // set once `io_uring_enter` failed for good, the submission queue may
+    // then hold entries of a past run
+    broken: bool,
+}
+
+impl Ring {
+    fn new(entries: u32) -> io::Result<Ring> {
+        let mut params = Params::default();
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                entries as libc::c_long,
+                &mut params as *mut Params,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = fd as RawFd;
+
+        let maps = (|| {
+            let sq_len = params.sq_off.array as usize + params.sq_entries as usize * 4;
+            let cq_len = params.cq_off.cqes as usize
+                + params.cq_entries as usize * std::mem::size_of::<Cqe>();
+            let sqes_len = params.sq_entries as usize * std::mem::size_of::<Sqe>();
+            Ok((
+                Mmap::new(fd, sq_len, IORING_OFF_SQ_RING)?,
+                Mmap::new(fd, cq_len, IORING_OFF_CQ_RING)?,
+                Mmap::new(fd, sqes_len, IORING_OFF_SQES)?,
+            ))
+        })();
+        let (sq, cq, sqes) = match maps {
+            Ok(maps) => maps,
+            Err(e) => {
+                unsafe { libc::close(fd) };
+                return Err(e);
+            }
+        };
+
+        Ok(Ring {
+            fd,
+            sq,
+            cq,
+            sqes,
+            params,
+            broken: false,
+        })
+    }
+
+    /// Runs `ops` to completion, with as many in flight at once as the
+    /// ring holds. The buffers of `ops` must stay valid until this returns.
+    ///
+    /// On an error of `io_uring_enter` other than `EINTR`, `EAGAIN` or
+    /// `EBUSY`, waits for the kernel to complete the operations it took
+    /// before returning the error, and the ring is unusable from then on.
+    /// If even waiting fails, the process aborts, as the kernel could still
+    /// access the buffers once they are released.
+    fn run(&mut self, ops: &mut [Op]) -> io::Result<()> {
+        if self.broken {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "io_uring ring unusable after an earlier failure",
+            ));
+        }
+
+        let sq_off = &self.params.sq_off;
+        let entries = self.params.sq_entries as usize;
+        let sq_mask = unsafe { *self.sq.at::<u32>(sq_off.ring_mask) };
+        let array = self.sq.at::<u32>(sq_off.array);
+        let sqes = self.sqes.at::<Sqe>(0);
+
+        let mut queue: Vec<usize> = (0..ops.len()).rev().collect();
+        // queued in the ring or taken by the kernel
+        let mut in_flight = 0;
+
+        while !queue.is_empty() || in_flight > 0 {
+            let mut tail = self.sq.atomic(sq_off.tail).load(Ordering::Relaxed);
+            while in_flight < entries {
+                let i = match queue.pop() {
+                    Some(i) => i,
+                    None => break,
+                };
+                let op = &ops[i];
+                let slot = tail & sq_mask;
+                unsafe {
+                    ptr::write(
+                        sqes.add(slot as usize),
+                        Sqe {
+                            opcode: if op.write {
+                                IORING_OP_WRITE
+                            } else {
+                                IORING_OP_READ
+                            },
+                            flags: 0,
+                            ioprio: 0,
+                            fd: op.fd,
+                            off: op.offset + op.done as u64,
+                            addr: op.buf.add(op.done) as u64,
+                            len: (op.len - op.done).min(u32::MAX as usize) as u32,
+                            rw_flags: 0,
+                            user_data: i as u64,
+                            buf_index: 0,
+                            personality: 0,
+                            splice_fd_in: 0,
+                            addr3: 0,
+                            pad: 0,
+                        },
+                    );
+                    *array.add(slot as usize) = slot;
+                }
+                tail = tail.wrapping_add(1);
+                in_flight += 1;
+            }
+            self.sq.atomic(sq_off.tail).store(tail, Ordering::Release);
+
+            // the entries the kernel did not take stay in the ring, and are
+            // submitted again on the next round
+            let unsubmitted = self.unsubmitted();
+            if let Err(e) = self.enter(unsubmitted, 1) {
+                if !Self::is_transient(&e) {
+                    self.broken = true;
+                    self.wait_taken(ops, in_flight);
+                    return Err(e);
+                }
+                if in_flight == unsubmitted as usize {
+                    // nothing to wait for, the kernel is short of resources
+                    std::thread::yield_now();
+                }
+            }
+
+            in_flight -= self.reap(ops, &mut queue);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of entries of the submission queue the kernel
+    /// has not taken yet.
+    fn unsubmitted(&self) -> u32 {
+        let sq_off = &self.params.sq_off;
+        let tail = self.sq.atomic(sq_off.tail).load(Ordering::Relaxed);
+        let head = self.sq.atomic(sq_off.head).load(Ordering::Acquire);
+        tail.wrapping_sub(head)
+    }
+
+    /// Submits `to_submit` entries and waits for `min_complete` completions.
+    fn enter(&self, to_submit: u32, min_complete: u32) -> io::Result<()> {
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_enter,
+                self.fd as libc::c_long,
+                to_submit as libc::c_long,
+                min_complete as libc::c_long,
+                IORING_ENTER_GETEVENTS as libc::c_long,
+                ptr::null::<libc::sigset_t>(),
+                0 as libc::c_long,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Whether `io_uring_enter` may succeed if called again: interrupted,
+    /// short of resources, or with too many completions pending.
+    fn is_transient(e: &io::Error) -> bool {
+        matches!(
+            e.raw_os_error(),
+            Some(libc::EINTR) | Some(libc::EAGAIN) | Some(libc::EBUSY)
+        )
+    }
+
+    /// Waits until the kernel has completed all operations it took of the
+    /// `in_flight` ones, after a failure. The operations are left without a
+    /// result if they still needed resubmitting.
+    fn wait_taken(&self, ops: &mut [Op], mut in_flight: usize) {
+        let mut resubmit = Vec::new();
+        loop {
+            in_flight -= self.reap(ops, &mut resubmit);
+            if in_flight <= self.unsubmitted() as usize {
+                return;
+            }
+            match self.enter(0, 1) {
+                Ok(()) => {}
+                Err(ref e) if Self::is_transient(e) => {}
+                // the kernel may still access the buffers of `ops`
+                Err(_) => std::process::abort(),
+            }
+        }
+    }
+
+    /// Handles the completions posted, returning their number. Operations
+    /// which moved part of their buffer, or were interrupted, are pushed to
+    /// `queue` to be submitted again.
+    fn reap(&self, ops: &mut [Op], queue: &mut Vec<usize>) -> usize {
+        let cq_off = &self.params.cq_off;
+        let cq_mask = unsafe { *self.cq.at::<u32>(cq_off.ring_mask) };
+        let cqes = self.cq.at::<Cqe>(cq_off.cqes);
+
+        let head_atomic = self.cq.atomic(cq_off.head);
+        let mut head = head_atomic.load(Ordering::Relaxed);
+        let cq_tail = self.cq.atomic(cq_off.tail).load(Ordering::Acquire);
+        let mut reaped = 0;
+        while head != cq_tail {
+            let cqe = unsafe { ptr::read(cqes.add((head & cq_mask) as usize)) };
+            head = head.wrapping_add(1);
+            reaped += 1;
+
+            let i = cqe.user_data as usize;
+            let op = &mut ops[i];
+            if cqe.res < 0 {
+                let e = io::Error::from_raw_os_error(-cqe.res);
+                match e.kind() {
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock => queue.push(i),
+                    _ => op.result = Some(Err(e)),
+                }
+            } else if cqe.res == 0 {
+                op.result = Some(if op.write {
+                    Err(io::ErrorKind::WriteZero.into())
+                } else {
+                    Ok(op.done)
+                });
+            } else {
+                op.done += cqe.res as usize;
+                if op.done < op.len {
+                    queue.push(i);
+                } else {
+                    op.result = Some(Ok(op.done));
+                }
+            }
+        }
+        head_atomic.store(head, Ordering::Release);
+
+        reaped
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// Reads and writes the stripes of shard files, one file per shard, with
+/// a batch of io_uring submissions per stripe.
+///
+/// A file which fails is reported once, in the error of the write during
+/// which it failed, or as a missing shard of the read, and is skipped by
+/// all subsequent reads and writes, as with `ShardWriters`.
+pub struct UringShardFiles {
+    ring: Ring,
+    files: Vec<Option<File>>,
+    shard_len: usize,
+    next_stripe: u64,
+}
+
+impl std::fmt::Debug for UringShardFiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        f.debug_struct("UringShardFiles")
+            .field("files", &self.files)
+            .field("shard_len", &self.shard_len)
+            .field("next_stripe", &self.next_stripe)
+            .finish()
+    }
+}
+
+impl UringShardFiles {
+    /// Creates the reader and writer of `files`, one per shard of `codec`,
+    /// `None` marking the missing shards, holding shards of `shard_len`
+    /// bytes.
+    ///
+    /// Returns an error of kind `InvalidInput` wrapping
+    /// `Error::TooFewShards`, `Error::TooManyShards` or `Error::EmptyShard`
+    /// if the files or `shard_len` do not fit `codec`, or the error of
+    /// setting up the ring.
+    pub fn new<F: Field>(
+        codec: &ReedSolomon<F>,
+        files: Vec<Option<File>>,
+        shard_len: usize,
+    ) -> io::Result<UringShardFiles> {
+        let check = || {
+            check_piece_count!(all => codec, &files[..]);
+            if shard_len == 0 {
+                return Err(Error::EmptyShard);
+            }
+            Ok(())
+        };
+        check()?;
+
+        let entries = (files.len() as u32).next_power_of_two().min(MAX_ENTRIES);
+
+        Ok(UringShardFiles {
+            ring: Ring::new(entries)?,
+            files,
+            shard_len,
+            next_stripe: 0,
+        })
+    }
+
+    /// Returns the length of a shard, the bytes of each file per stripe.
+    pub fn shard_len(&self) -> usize {
+        self.shard_len
+    }
+
+    /// Returns the index of the stripe the next read or write is at.
+    pub fn next_stripe(&self) -> u64 {
+        self.next_stripe
+    }
+
+    /// Moves to stripe `index`, e.g. to repair a range of stripes.
+    pub fn seek_stripe(&mut self, index: u64) {
+        self.next_stripe = index;
+    }
+
+    /// Writes the shards of a stripe, its shard `i` to file `i`.
+    pub fn write_stripe(&mut self, stripe: &Stripe) -> WriteResult {
+        self.write_shards(stripe.shards())
+    }
+
+    /// Writes `shards` at the next stripe, shard `i` to file `i`, then
+    /// moves to the following stripe.
+    ///
+    /// Missing and failed files are skipped. Returns `WriteError::Io` with
+    /// the files which failed during this call.
+    ///
+    /// Returns `WriteError::RSError` if the number of shards does not match
+    /// the number of files, or a shard is not `shard_len` bytes long,
+    /// nothing being written then.
+    pub fn write_shards<T: AsRef<[u8]>>(&mut self, shards: &[T]) -> WriteResult {
+        if shards.len() < self.files.len() {
+            return Err(WriteError::RSError(Error::TooFewShards));
+        }
+        if shards.len() > self.files.len() {
+            return Err(WriteError::RSError(Error::TooManyShards));
+        }
+        if shards
+            .iter()
+            .any(|shard| shard.as_ref().len() != self.shard_len)
+        {
+            return Err(WriteError::RSError(Error::IncorrectShardSize));
+        }
+
+        let offset = self.next_stripe * self.shard_len as u64;
+        let mut indices = Vec::with_capacity(self.files.len());
+        let mut ops = Vec::with_capacity(self.files.len());
+        for (i, (file, shard)) in self.files.iter().zip(shards.iter()).enumerate() {
+            if let Some(file) = file {
+                indices.push(i);
+                ops.push(Op {
+                    fd: file.as_raw_fd(),
+                    write: true,
+                    // only read from by the kernel
+                    buf: shard.as_ref().as_ptr() as *mut u8,
+                    len: self.shard_len,
+                    offset,
+                    done: 0,
+                    result: None,
+                });
+            }
+        }
+
+        let failed = self.finish(&indices, &mut ops);
+        self.next_stripe += 1;
+
+        let failed: Vec<(usize, io::Error)> = failed
+            .into_iter()
+            .map(|(i, result)| {
+                (
+                    i,
+                    result
+                        .err()
+                        .unwrap_or_else(|| io::ErrorKind::WriteZero.into()),
+                )
+            })
+            .collect();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(WriteError::Io(failed))
+        }
+    }
+
+    /// Reads the next stripe, then moves to the following stripe.
+    ///
+    /// Returns `None` once no file holds the stripe. Otherwise returns the
+    /// shards of the stripe, `None` marking the shards which are missing,
+    /// ready to be passed to `ReedSolomon::reconstruct`.
+    ///
+    /// A file which ends before the stripe only misses its shard, a file
+    /// ending within the shard or failing to read is marked failed.
+    pub fn read_stripe(&mut self) -> Option<Vec<Option<Vec<u8>>>> {
+        let offset = self.next_stripe * self.shard_len as u64;
+        let mut shards: Vec<Option<Vec<u8>>> = self
+            .files
+            .iter()
+            .map(|file| file.as_ref().map(|_| vec![0u8; self.shard_len]))
+            .collect();
+
+        let mut indices = Vec::with_capacity(self.files.len());
+        let mut ops = Vec::with_capacity(self.files.len());
+        for (i, (file, shard)) in self.files.iter().zip(shards.iter_mut()).enumerate() {
+            if let (Some(file), Some(shard)) = (file, shard) {
+                indices.push(i);
+                ops.push(Op {
+                    fd: file.as_raw_fd(),
+                    write: false,
+                    buf: shard.as_mut_ptr(),
+                    len: self.shard_len,
+                    offset,
+                    done: 0,
+                    result: None,
+                });
+            }
+        }
+
+        for (i, _) in self.finish(&indices, &mut ops) {
+            shards[i] = None;
+        }
+        self.next_stripe += 1;
+
+        if shards.iter().any(Option::is_some) {
+            Some(shards)
+        } else {
+            None
+        }
+    }
+
+    /// Runs `ops`, the operations on the files at `indices`, marking
+    /// failed the files whose operation failed or was cut short.
+    ///
+    /// Returns the files whose operation did not move a whole shard, along
+    /// with its result. A read at the end of a file returns `Ok(0)` and
+    /// does not fail the file.
+    fn finish(&mut self, indices: &[usize], ops: &mut [Op]) -> Vec<(usize, io::Result<usize>)> {
+        let ring_error = self.ring.run(ops).err();
+
+        let mut failed = Vec::new();
+        for (&i, op) in indices.iter().zip(ops.iter_mut()) {
+            let result = match (op.result.take(), &ring_error) {
+                (Some(result), _) => result,
+                (None, Some(e)) => Err(io::Error::new(e.kind(), e.to_string())),
+                (None, None) => unreachable!("all operations run to completion; qed"),
+            };
+            match result {
+                Ok(len) if len == self.shard_len => continue,
+                Ok(0) if !op.write => {}
+                _ => self.files[i] = None,
+            }
+            failed.push((i, result));
+        }
+
+        failed
+    }
+
+    /// Returns the indices of the files which are missing or have failed.
+    pub fn failed_indices(&self) -> Vec<usize> {
+        self.files
+            .iter()
+            .enumerate()
+            .filter(|&(_, file)| file.is_none())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Consumes the reader and writer, returning the files, `None` marking
+    /// the missing and failed ones.
+    pub fn into_inner(self) -> Vec<Option<File>> {
+        self.files
+    }
+}
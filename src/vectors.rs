@@ -0,0 +1,342 @@
+//! Golden test vectors of the GF(2^8) codecs.
+//!
+//! A `TestVector` records the encoding matrix of a codec, a stripe of
+//! pseudo-random data shards along with the parity shards computed from
+//! them, and a few erasure patterns which must reconstruct to the same
+//! stripe. Vectors exported by one version of this crate can be checked
+//! with `TestVector::check` by later versions, or by other
+//! implementations, to assert bit-compatibility.
+//!
+//! The field is GF(2^8) with the generating polynomial
+//! x^8 + x^4 + x^3 + x^2 + 1 (`0x11d`).
+//!
+//! # Binary form
+//!
+//! All integers are little-endian.
+//!
+//! | Field             | Size                                 |
+//! |-------------------|--------------------------------------|
+//! | magic `b"RSTV"`   | 4                                    |
+//! | version, `1`      | 1                                    |
+//! | matrix kind       | 1, `0` Vandermonde, `1` Cauchy       |
+//! | data shards `k`   | 2                                    |
+//! | parity shards `m` | 2                                    |
+//! | shard length `n`  | 4                                    |
+//! | matrix            | `(k + m) * k`, row-major             |
+//! | shards            | `(k + m) * n`, data shards first     |
+//! | erasure count `e` | 2                                    |
+//! | erasures          | `e` times a 2 byte count then as many 2 byte shard indices |
+//!
+//! # JSON form
+//!
+//! An object with the keys `field`, `polynomial`, `matrix_kind`
+//! (`"vandermonde"` or `"cauchy"`), `data_shards`, `parity_shards`,
+//! `shard_len`, `matrix` (rows as hex strings), `shards` (hex strings)
+//! and `erasures` (arrays of shard indices).
+
+use std::convert::TryInto;
+use std::io;
+
+use crate::galois_8::ReedSolomon;
+use crate::sim::next_random;
+use crate::{Error, MatrixKind};
+
+const MAGIC: &[u8; 4] = b"RSTV";
+
+const VERSION: u8 = 1;
+
+/// A golden test vector of a GF(2^8) codec.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TestVector {
+    matrix_kind: MatrixKind,
+    data_shards: usize,
+    parity_shards: usize,
+    matrix: Vec<Vec<u8>>,
+    shards: Vec<Vec<u8>>,
+    erasures: Vec<Vec<usize>>,
+}
+
+impl TestVector {
+    /// Generates the test vector of `codec` for shards of `shard_len`
+    /// bytes, with data shards derived from `seed`.
+    ///
+    /// The erasure patterns lose the first `m` shards, the last `m` shards,
+    /// and `m` shards picked from `seed`, where `m` is the number of parity
+    /// shards.
+    ///
+    /// Returns `Error::EmptyShard` if `shard_len == 0`.
+    pub fn generate(codec: &ReedSolomon, shard_len: usize, seed: u64) -> Result<TestVector, Error> {
+        if shard_len == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        let data_shards = codec.data_shard_count();
+        let parity_shards = codec.parity_shard_count();
+        let total_shards = codec.total_shard_count();
+
+        // any non-zero state will do
+        let mut state = seed ^ 0x853C_49E6_748F_EA9B;
+        if state == 0 {
+            state = 1;
+        }
+
+        let mut shards = vec![vec![0u8; shard_len]; total_shards];
+        for shard in shards.iter_mut().take(data_shards) {
+            for x in shard.iter_mut() {
+                *x = (next_random(&mut state) >> 56) as u8;
+            }
+        }
+        codec.encode(&mut shards)?;
+
+        let mut picked: Vec<usize> = (0..total_shards).collect();
+        for i in 0..parity_shards {
+            let j = i + next_random(&mut state) as usize % (total_shards - i);
+            picked.swap(i, j);
+        }
+        picked.truncate(parity_shards);
+        picked.sort_unstable();
+
+        let erasures = vec![
+            (0..parity_shards).collect(),
+            (data_shards..total_shards).collect(),
+            picked,
+        ];
+
+        Ok(TestVector {
+            matrix_kind: codec.matrix_kind(),
+            data_shards,
+            parity_shards,
+            matrix: (0..total_shards)
+                .map(|r| codec.matrix.get_row(r).to_vec())
+                .collect(),
+            shards,
+            erasures,
+        })
+    }
+
+    pub fn matrix_kind(&self) -> MatrixKind {
+        self.matrix_kind
+    }
+
+    pub fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    pub fn parity_shards(&self) -> usize {
+        self.parity_shards
+    }
+
+    pub fn matrix(&self) -> &[Vec<u8>] {
+        &self.matrix
+    }
+
+    pub fn shards(&self) -> &[Vec<u8>] {
+        &self.shards
+    }
+
+    pub fn erasures(&self) -> &[Vec<usize>] {
+        &self.erasures
+    }
+
+    /// Creates the codec the vector was generated with.
+    ///
+    /// Returns the errors of `ReedSolomon::new` for invalid shard counts.
+    pub fn codec(&self) -> Result<ReedSolomon, Error> {
+        match self.matrix_kind {
+            MatrixKind::Vandermonde => ReedSolomon::new(self.data_shards, self.parity_shards),
+            MatrixKind::Cauchy => ReedSolomon::new_cauchy(self.data_shards, self.parity_shards),
+        }
+    }
+
+    /// Checks that this crate reproduces the vector: the same encoding
+    /// matrix, the same parity shards, and the same stripe from every
+    /// erasure pattern.
+    ///
+    /// Returns the errors of `ReedSolomon::new` for invalid shard counts,
+    /// and of `ReedSolomon::reconstruct` for invalid shards or erasures.
+    pub fn check(&self) -> Result<bool, Error> {
+        let codec = self.codec()?;
+
+        let matrix_matches = self
+            .matrix
+            .iter()
+            .enumerate()
+            .all(|(r, row)| codec.matrix.get_row(r) == &row[..]);
+        if !matrix_matches || !codec.verify(&self.shards)? {
+            return Ok(false);
+        }
+
+        for erasure in self.erasures.iter() {
+            let mut shards: Vec<_> = self.shards.iter().cloned().map(Some).collect();
+            for &i in erasure.iter() {
+                if i >= shards.len() {
+                    return Err(Error::InvalidIndex);
+                }
+                shards[i] = None;
+            }
+
+            codec.reconstruct(&mut shards)?;
+
+            if shards
+                .into_iter()
+                .map(Option::unwrap)
+                .ne(self.shards.iter().cloned())
+            {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the binary form of the vector.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(match self.matrix_kind {
+            MatrixKind::Vandermonde => 0,
+            MatrixKind::Cauchy => 1,
+        });
+        bytes.extend_from_slice(&(self.data_shards as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.parity_shards as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.shards[0].len() as u32).to_le_bytes());
+        for row in self.matrix.iter() {
+            bytes.extend_from_slice(row);
+        }
+        for shard in self.shards.iter() {
+            bytes.extend_from_slice(shard);
+        }
+        bytes.extend_from_slice(&(self.erasures.len() as u16).to_le_bytes());
+        for erasure in self.erasures.iter() {
+            bytes.extend_from_slice(&(erasure.len() as u16).to_le_bytes());
+            for &i in erasure.iter() {
+                bytes.extend_from_slice(&(i as u16).to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    /// Parses the binary form of a vector.
+    ///
+    /// Returns an error of kind `InvalidData` if `bytes` is not a vector
+    /// of a supported version.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<TestVector> {
+        let mut reader = ByteReader { bytes };
+
+        if reader.take(4)? != MAGIC || reader.take(1)?[0] != VERSION {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let matrix_kind = match reader.take(1)?[0] {
+            0 => MatrixKind::Vandermonde,
+            1 => MatrixKind::Cauchy,
+            _ => return Err(io::ErrorKind::InvalidData.into()),
+        };
+        let data_shards = reader.u16()?;
+        let parity_shards = reader.u16()?;
+        let shard_len = reader.u32()?;
+        if data_shards == 0 || shard_len == 0 {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let total_shards = data_shards + parity_shards;
+
+        let mut matrix = Vec::with_capacity(total_shards);
+        for _ in 0..total_shards {
+            matrix.push(reader.take(data_shards)?.to_vec());
+        }
+        let mut shards = Vec::with_capacity(total_shards);
+        for _ in 0..total_shards {
+            shards.push(reader.take(shard_len)?.to_vec());
+        }
+        let erasure_count = reader.u16()?;
+        let mut erasures = Vec::with_capacity(erasure_count);
+        for _ in 0..erasure_count {
+            let len = reader.u16()?;
+            let mut erasure = Vec::with_capacity(len);
+            for _ in 0..len {
+                erasure.push(reader.u16()?);
+            }
+            erasures.push(erasure);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+
+        Ok(TestVector {
+            matrix_kind,
+            data_shards,
+            parity_shards,
+            matrix,
+            shards,
+            erasures,
+        })
+    }
+
+    /// Returns the JSON form of the vector.
+    pub fn to_json(&self) -> String {
+        fn hex(bytes: &[u8]) -> String {
+            bytes.iter().map(|b| format!("{:02x}", b)).collect()
+        }
+        fn hex_list(rows: &[Vec<u8>]) -> String {
+            let rows: Vec<_> = rows.iter().map(|row| format!("\"{}\"", hex(row))).collect();
+            format!("[{}]", rows.join(", "))
+        }
+
+        let erasures: Vec<_> = self
+            .erasures
+            .iter()
+            .map(|erasure| format!("{:?}", erasure))
+            .collect();
+
+        format!(
+            concat!(
+                "{{\"field\": \"GF(2^8)\", \"polynomial\": \"0x11d\", ",
+                "\"matrix_kind\": \"{}\", \"data_shards\": {}, \"parity_shards\": {}, ",
+                "\"shard_len\": {}, \"matrix\": {}, \"shards\": {}, \"erasures\": [{}]}}"
+            ),
+            match self.matrix_kind {
+                MatrixKind::Vandermonde => "vandermonde",
+                MatrixKind::Cauchy => "cauchy",
+            },
+            self.data_shards,
+            self.parity_shards,
+            self.shards[0].len(),
+            hex_list(&self.matrix),
+            hex_list(&self.shards),
+            erasures.join(", ")
+        )
+    }
+}
+
+/// Reads the fields of the binary form, failing on truncated input.
+pub(crate) struct ByteReader<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u16(&mut self) -> io::Result<usize> {
+        let bytes = self.take(2)?.try_into().expect("2 bytes taken; qed");
+        Ok(u16::from_le_bytes(bytes) as usize)
+    }
+
+    pub(crate) fn u32(&mut self) -> io::Result<usize> {
+        let bytes = self.take(4)?.try_into().expect("4 bytes taken; qed");
+        Ok(u32::from_le_bytes(bytes) as usize)
+    }
+
+    pub(crate) fn u64(&mut self) -> io::Result<u64> {
+        let bytes = self.take(8)?.try_into().expect("8 bytes taken; qed");
+        Ok(u64::from_le_bytes(bytes))
+    }
+}